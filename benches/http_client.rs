@@ -0,0 +1,67 @@
+//! Compares rapid successive requests sent with the shared `HttpClient` against requests sent with a new client each time.
+//! Requests go to a stubbed Chat Completions API on localhost, so the difference is the cost of opening a new connection per request.
+
+use allms::llm::{LLMModel, OpenAIModels};
+use allms::{CompletionsOptions, HttpClient};
+use criterion::{criterion_group, criterion_main, Criterion};
+use serde_json::json;
+use tokio::runtime::Runtime;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn bench_successive_requests(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let server = runtime.block_on(async {
+        let server = MockServer::start().await;
+        let response = json!({
+            "id": "chatcmpl-123",
+            "object": "chat.completion",
+            "created": 1677652288,
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "{\"answer\": \"42\"}"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 9, "completion_tokens": 12, "total_tokens": 21}
+        });
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&response))
+            .mount(&server)
+            .await;
+        server
+    });
+    std::env::set_var("OPENAI_API_URL", server.uri());
+
+    let model = OpenAIModels::Gpt4o;
+    let body = model.get_body(
+        "instructions",
+        &json!({"type": "object"}),
+        false,
+        &1000,
+        &0.0,
+        &CompletionsOptions::default(),
+    );
+
+    let mut group = c.benchmark_group("successive_requests");
+    let shared_client = HttpClient::default();
+    group.bench_function("shared_client", |b| {
+        b.iter(|| {
+            runtime
+                .block_on(model.call_api(&shared_client, "test-key", &body, false))
+                .unwrap()
+        })
+    });
+    group.bench_function("new_client", |b| {
+        b.iter(|| {
+            let client = HttpClient::new(reqwest::Client::new());
+            runtime
+                .block_on(model.call_api(&client, "test-key", &body, false))
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_successive_requests);
+criterion_main!(benches);
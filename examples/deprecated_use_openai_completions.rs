@@ -1,3 +1,6 @@
+//The example shows the deprecated API on purpose
+#![allow(deprecated)]
+
 use allms::OpenAI;
 use allms::OpenAIModels;
 use schemars::JsonSchema;
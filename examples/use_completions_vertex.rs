@@ -1,9 +1,12 @@
 use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
-use yup_oauth2::{read_service_account_key, ServiceAccountAuthenticator};
+use std::path::PathBuf;
 
-use allms::{llm::GoogleModels, Completions};
+use allms::{
+    llm::{GeminiProVertexConfig, GoogleModels, VertexAuthMethod},
+    Completions,
+};
 
 #[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
 struct TranslationResponse {
@@ -25,24 +28,13 @@ async fn main() {
     let model = GoogleModels::Gemini2_0ProExpVertex;
 
     // To authenticate Google Vertex AI we need to use a key associated with a GCP service account with correct permissions
-    // Load your service account key from a file or an environment variable
-    let service_account_key = read_service_account_key("secrets/gcp_sa_key.json")
-        .await
-        .unwrap();
-
-    // Authenticate with your service account
-    let auth = ServiceAccountAuthenticator::builder(service_account_key)
-        .build()
-        .await
-        .unwrap();
-    let google_token = auth
-        .token(&["https://www.googleapis.com/auth/cloud-platform"])
-        .await
-        .unwrap();
-    let google_token_str = &google_token.token().unwrap();
+    // The config exchanges the service account key for an OAuth2 access token and caches it until it is about to expire
+    let vertex_config = GeminiProVertexConfig::new(VertexAuthMethod::ServiceAccountJson(
+        PathBuf::from("secrets/gcp_sa_key.json"),
+    ));
 
     // **Pre-requisite**: GeminiPro request through Vertex AI require `GOOGLE_PROJECT_ID` environment variable defined
-    let gemini_completion = Completions::new(model, google_token_str, None, None);
+    let gemini_completion = Completions::new(model, "", None, None).vertex_config(vertex_config);
 
     match gemini_completion
         .get_answer::<TranslationResponse>(instructions)
@@ -1,6 +1,11 @@
 pub mod openai;
 
+pub use crate::enums::{OpenAIAssistantRole, OpenAIRunStatus};
 pub use openai::{
-    OpenAIAssistant, OpenAIAssistantResource, OpenAIAssistantVersion, OpenAIFile,
-    OpenAIVectorStore, OpenAIVectorStoreFileCounts, OpenAIVectorStoreStatus,
+    AssistantId, AssistantMessage, AssistantTool, CodeInterpreterCall, CodeInterpreterOutput,
+    FileBatchId, FileBatchStatus, FileId, FileInfo, FilePurpose, MessageId, OpenAIAssistant,
+    OpenAIAssistantResource, OpenAIAssistantVersion, OpenAIAssistantsClient, OpenAIFile,
+    OpenAIFileObject, OpenAIFilesClient, OpenAIVectorStore, OpenAIVectorStoreClient,
+    OpenAIVectorStoreFileBatchStatus, OpenAIVectorStoreFileCounts, OpenAIVectorStoreStatus, RunId,
+    RunStatus, ThreadId, VectorStoreId,
 };
@@ -1,11 +1,23 @@
 pub mod openai_api_version;
 pub mod openai_assistant;
+pub mod openai_assistants_client;
 pub mod openai_file;
+pub mod openai_files_client;
 pub mod openai_vector_store;
+pub mod openai_vector_store_client;
 
 pub use openai_api_version::{OpenAIAssistantResource, OpenAIAssistantVersion};
 pub use openai_assistant::OpenAIAssistant;
-pub use openai_file::OpenAIFile;
+pub use openai_assistants_client::{
+    AssistantId, AssistantMessage, AssistantTool, CodeInterpreterCall, CodeInterpreterOutput,
+    MessageId, OpenAIAssistantsClient, RunId, RunStatus, ThreadId,
+};
+pub use openai_file::{OpenAIFile, OpenAIFileObject};
+pub use openai_files_client::{FileId, FileInfo, FilePurpose, OpenAIFilesClient};
 pub use openai_vector_store::{
-    OpenAIVectorStore, OpenAIVectorStoreFileCounts, OpenAIVectorStoreStatus,
+    OpenAIVectorStore, OpenAIVectorStoreFileBatchStatus, OpenAIVectorStoreFileCounts,
+    OpenAIVectorStoreStatus,
+};
+pub use openai_vector_store_client::{
+    FileBatchId, FileBatchStatus, OpenAIVectorStoreClient, VectorStoreId,
 };
@@ -5,6 +5,8 @@ use serde_json::{json, Value};
 use std::str::FromStr;
 
 use crate::constants::{DEFAULT_AZURE_VERSION, OPENAI_API_URL};
+use crate::http_client::HttpClient;
+use crate::utils::get_openai_headers;
 
 #[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq)]
 pub enum OpenAIAssistantVersion {
@@ -16,8 +18,17 @@ pub enum OpenAIAssistantVersion {
 
 impl OpenAIAssistantVersion {
     pub(crate) fn get_endpoint(&self, resource: &OpenAIAssistantResource) -> String {
+        self.get_endpoint_with_url(&OPENAI_API_URL, resource)
+    }
+
+    // Same as `get_endpoint` but allows the base API url to be overwritten
+    pub(crate) fn get_endpoint_with_url(
+        &self,
+        api_url: &str,
+        resource: &OpenAIAssistantResource,
+    ) -> String {
         //OpenAI documentation: https://platform.openai.com/docs/models/model-endpoint-compatibility
-        let trimmed_api_url = (*OPENAI_API_URL).trim_end_matches('/');
+        let trimmed_api_url = api_url.trim_end_matches('/');
         let base_url = match self {
             OpenAIAssistantVersion::V1 | OpenAIAssistantVersion::V2 => {
                 format!("{trimmed_api_url}/v1")
@@ -32,7 +43,17 @@ impl OpenAIAssistantVersion {
             OpenAIAssistantResource::Assistant { assistant_id } => {
                 format!("{base_url}/assistants/{assistant_id}")
             }
+            OpenAIAssistantResource::AssistantFiles { assistant_id } => {
+                format!("{base_url}/assistants/{assistant_id}/files")
+            }
+            OpenAIAssistantResource::AssistantFile {
+                assistant_id,
+                file_id,
+            } => format!("{base_url}/assistants/{assistant_id}/files/{file_id}"),
             OpenAIAssistantResource::Threads => format!("{base_url}/threads"),
+            OpenAIAssistantResource::Thread { thread_id } => {
+                format!("{base_url}/threads/{thread_id}")
+            }
             OpenAIAssistantResource::Messages { thread_id } => {
                 format!("{base_url}/threads/{thread_id}/messages")
             }
@@ -42,6 +63,9 @@ impl OpenAIAssistantVersion {
             OpenAIAssistantResource::Run { thread_id, run_id } => {
                 format!("{base_url}/threads/{thread_id}/runs/{run_id}")
             }
+            OpenAIAssistantResource::RunSteps { thread_id, run_id } => {
+                format!("{base_url}/threads/{thread_id}/runs/{run_id}/steps")
+            }
             OpenAIAssistantResource::Files => format!("{base_url}/files"),
             OpenAIAssistantResource::File { file_id } => format!("{base_url}/files/{file_id}"),
             OpenAIAssistantResource::VectorStores => format!("{base_url}/vector_stores"),
@@ -51,6 +75,10 @@ impl OpenAIAssistantVersion {
             OpenAIAssistantResource::VectorStoreFileBatches { vector_store_id } => {
                 format!("{base_url}/vector_stores/{vector_store_id}/file_batches")
             }
+            OpenAIAssistantResource::VectorStoreFileBatch {
+                vector_store_id,
+                batch_id,
+            } => format!("{base_url}/vector_stores/{vector_store_id}/file_batches/{batch_id}"),
         };
 
         // Add Azure version suffix if needed
@@ -84,6 +112,7 @@ impl OpenAIAssistantVersion {
                     );
                 };
                 headers.insert("OpenAI-Beta", HeaderValue::from_static("assistants=v1"));
+                headers.extend(get_openai_headers(&HttpClient::default()));
             }
             OpenAIAssistantVersion::V2 => {
                 // Try to create the header value from the bearer token
@@ -96,6 +125,7 @@ impl OpenAIAssistantVersion {
                     );
                 };
                 headers.insert("OpenAI-Beta", HeaderValue::from_static("assistants=v2"));
+                headers.extend(get_openai_headers(&HttpClient::default()));
             }
             OpenAIAssistantVersion::Azure | OpenAIAssistantVersion::AzureVersion { .. } => {
                 // Try to create the header value from the bearer token
@@ -193,16 +223,50 @@ impl FromStr for OpenAIAssistantVersion {
 #[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq)]
 pub enum OpenAIAssistantResource {
     Assistants,
-    Assistant { assistant_id: String },
+    Assistant {
+        assistant_id: String,
+    },
+    //Only available in Assistants API v1
+    AssistantFiles {
+        assistant_id: String,
+    },
+    AssistantFile {
+        assistant_id: String,
+        file_id: String,
+    },
     Threads,
-    Messages { thread_id: String },
-    Runs { thread_id: String },
-    Run { thread_id: String, run_id: String },
+    Thread {
+        thread_id: String,
+    },
+    Messages {
+        thread_id: String,
+    },
+    Runs {
+        thread_id: String,
+    },
+    Run {
+        thread_id: String,
+        run_id: String,
+    },
+    RunSteps {
+        thread_id: String,
+        run_id: String,
+    },
     Files,
-    File { file_id: String },
+    File {
+        file_id: String,
+    },
     VectorStores,
-    VectorStore { vector_store_id: String },
-    VectorStoreFileBatches { vector_store_id: String },
+    VectorStore {
+        vector_store_id: String,
+    },
+    VectorStoreFileBatches {
+        vector_store_id: String,
+    },
+    VectorStoreFileBatch {
+        vector_store_id: String,
+        batch_id: String,
+    },
 }
 
 #[cfg(test)]
@@ -220,6 +284,31 @@ mod tests {
         assert_eq!(version.get_endpoint(&resource), expected_url);
     }
 
+    #[test]
+    fn test_v1_assistant_file_endpoint() {
+        let version = OpenAIAssistantVersion::V1;
+        let resource = OpenAIAssistantResource::AssistantFile {
+            assistant_id: "asst_1".to_string(),
+            file_id: "file_1".to_string(),
+        };
+        let expected_url = format!("{}/v1/assistants/asst_1/files/file_1", OPENAI_API_URL);
+        assert_eq!(version.get_endpoint(&resource), expected_url);
+    }
+
+    #[test]
+    fn test_v2_vector_store_file_batch_endpoint() {
+        let version = OpenAIAssistantVersion::V2;
+        let resource = OpenAIAssistantResource::VectorStoreFileBatch {
+            vector_store_id: "vs_1".to_string(),
+            batch_id: "vsfb_1".to_string(),
+        };
+        let expected_url = format!(
+            "{}/v1/vector_stores/vs_1/file_batches/vsfb_1",
+            OPENAI_API_URL
+        );
+        assert_eq!(version.get_endpoint(&resource), expected_url);
+    }
+
     #[test]
     fn test_azure_assistant_endpoint() {
         let version = OpenAIAssistantVersion::AzureVersion {
@@ -271,6 +360,18 @@ mod tests {
         assert_eq!(version.get_endpoint(&resource), expected_url);
     }
 
+    #[test]
+    fn test_v2_thread_endpoint_with_url() {
+        let version = OpenAIAssistantVersion::V2;
+        let resource = OpenAIAssistantResource::Thread {
+            thread_id: "xyz".to_string(),
+        };
+        assert_eq!(
+            version.get_endpoint_with_url("http://localhost:8080/", &resource),
+            "http://localhost:8080/v1/threads/xyz"
+        );
+    }
+
     #[test]
     fn test_v1_run_endpoint() {
         let version = OpenAIAssistantVersion::V1;
@@ -12,14 +12,14 @@ use tokio::time;
 use tokio::time::timeout;
 
 use crate::assistants::{OpenAIAssistantResource, OpenAIAssistantVersion, OpenAIVectorStore};
-use crate::constants::OPENAI_ASSISTANT_INSTRUCTIONS;
+use crate::constants::{OPENAI_API_URL, OPENAI_ASSISTANT_INSTRUCTIONS};
 use crate::domain::{
-    AllmsError, OpenAIAssistantResp, OpenAIMessageListResp, OpenAIMessageResp, OpenAIRunResp,
-    OpenAIThreadResp,
+    AllmsError, OpenAIAssistantResp, OpenAIDeleteResp, OpenAIMessageListResp, OpenAIMessageResp,
+    OpenAIRunResp, OpenAIThreadResp,
 };
 use crate::enums::{OpenAIAssistantRole, OpenAIRunStatus};
 use crate::llm_models::{LLMModel, OpenAIModels};
-use crate::utils::get_type_schema;
+use crate::utils::{get_type_schema, redact_secrets};
 
 /// [OpenAI Docs](https://platform.openai.com/docs/assistants/overview)
 ///
@@ -40,6 +40,8 @@ pub struct OpenAIAssistant {
     version: OpenAIAssistantVersion,
     vector_store: Option<OpenAIVectorStore>,
     temperature: f32,
+    api_url: String,
+    delete_files: bool,
 }
 
 impl OpenAIAssistant {
@@ -63,6 +65,8 @@ impl OpenAIAssistant {
             // Defaulting to V1 for now
             version: OpenAIAssistantVersion::V1,
             vector_store: None,
+            api_url: OPENAI_API_URL.to_string(),
+            delete_files: false,
         }
     }
 
@@ -102,14 +106,35 @@ impl OpenAIAssistant {
         self
     }
 
+    ///
+    /// This method can be used to overwrite the base url of the API, e.g. when requests are sent via a proxy
+    /// Default is the value of `OPENAI_API_URL` environment variable or `https://api.openai.com`
+    ///
+    pub fn api_url(mut self, api_url: &str) -> Self {
+        self.api_url = api_url.to_string();
+        self
+    }
+
+    ///
+    /// This method turns on deleting of the files passed to `get_answer` once the run processing them is finished
+    /// OpenAI charges for the storage of uploaded files so they should not be kept longer than needed
+    ///
+    pub fn delete_files_after_run(mut self) -> Self {
+        self.delete_files = true;
+        self
+    }
+
+    // This function returns the version-specific url of the resource
+    fn get_endpoint(&self, resource: &OpenAIAssistantResource) -> String {
+        self.version.get_endpoint_with_url(&self.api_url, resource)
+    }
+
     /*
      * This function creates an Assistant and updates the ID of the OpenAIAssistant struct
      */
     async fn create_assistant(&mut self) -> Result<()> {
         //Get the assistant API url
-        let assistant_url = self
-            .version
-            .get_endpoint(&OpenAIAssistantResource::Assistants);
+        let assistant_url = self.get_endpoint(&OpenAIAssistantResource::Assistants);
 
         //Get the version-specific header
         let version_headers = self.version.get_headers(&self.api_key);
@@ -144,7 +169,8 @@ impl OpenAIAssistant {
         if self.debug {
             info!(
                 "[debug] OpenAI Assistant API response: [{}] {:#?}",
-                &response_status, &response_text
+                &response_status,
+                redact_secrets(&response_text)
             );
         }
 
@@ -249,7 +275,7 @@ impl OpenAIAssistant {
         let operation_timeout = Duration::from_secs(600); // Timeout for the whole operation
         let poll_interval = Duration::from_secs(10);
 
-        let _result = timeout(operation_timeout, async {
+        let run_result = timeout(operation_timeout, async {
             let mut interval = time::interval(poll_interval);
             loop {
                 interval.tick().await; // Wait for the next interval tick
@@ -273,7 +299,13 @@ impl OpenAIAssistant {
                 }
             }
         })
-        .await?;
+        .await;
+
+        //Files are no longer needed once the run is finished so they can be deleted if requested
+        if self.delete_files {
+            self.delete_files(file_ids).await?;
+        }
+        let _result = run_result?;
 
         //Step 5: Get all messages posted on the thread. This should now include response from the Assistant
         let messages = self.get_message_thread().await?;
@@ -405,7 +437,7 @@ impl OpenAIAssistant {
      */
     async fn create_thread(&mut self, body: &serde_json::Value) -> Result<()> {
         //Get version-specific URL
-        let thread_url = self.version.get_endpoint(&OpenAIAssistantResource::Threads);
+        let thread_url = self.get_endpoint(&OpenAIAssistantResource::Threads);
 
         //Get version-specific headers
         let version_headers = self.version.get_headers(&self.api_key);
@@ -426,7 +458,8 @@ impl OpenAIAssistant {
         if self.debug {
             info!(
                 "[debug] OpenAI Threads API response: [{}] {:#?}",
-                &response_status, &response_text
+                &response_status,
+                redact_secrets(&response_text)
             );
         }
 
@@ -461,7 +494,7 @@ impl OpenAIAssistant {
         let messages_resource = OpenAIAssistantResource::Messages {
             thread_id: self.thread_id.clone().unwrap_or_default(),
         };
-        let message_url = self.version.get_endpoint(&messages_resource);
+        let message_url = self.get_endpoint(&messages_resource);
 
         //Get version-specific headers
         let version_headers = self.version.get_headers(&self.api_key);
@@ -482,7 +515,8 @@ impl OpenAIAssistant {
         if self.debug {
             info!(
                 "[debug] OpenAI Messages API response: [{}] {:#?}",
-                &response_status, &response_text
+                &response_status,
+                redact_secrets(&response_text)
             );
         }
 
@@ -514,7 +548,7 @@ impl OpenAIAssistant {
         let message_resource = OpenAIAssistantResource::Messages {
             thread_id: self.thread_id.clone().unwrap_or_default(),
         };
-        let message_url = self.version.get_endpoint(&message_resource);
+        let message_url = self.get_endpoint(&message_resource);
 
         //Get version-specific headers
         let version_headers = self.version.get_headers(&self.api_key);
@@ -534,7 +568,8 @@ impl OpenAIAssistant {
         if self.debug {
             info!(
                 "[debug] OpenAI Messages API response: [{}] {:#?}",
-                &response_status, &response_text
+                &response_status,
+                redact_secrets(&response_text)
             );
         }
 
@@ -572,7 +607,7 @@ impl OpenAIAssistant {
 
         //Get version-specific URL
         let run_resource = OpenAIAssistantResource::Runs { thread_id };
-        let run_url = self.version.get_endpoint(&run_resource);
+        let run_url = self.get_endpoint(&run_resource);
 
         //Get version-specific headers
         let version_headers = self.version.get_headers(&self.api_key);
@@ -597,7 +632,8 @@ impl OpenAIAssistant {
         if self.debug {
             info!(
                 "[debug] OpenAI Messages API response: [{}] {:#?}",
-                &response_status, &response_text
+                &response_status,
+                redact_secrets(&response_text)
             );
         }
 
@@ -638,7 +674,7 @@ impl OpenAIAssistant {
 
         //Get version-specific URL
         let run_resource = OpenAIAssistantResource::Run { thread_id, run_id };
-        let run_url = self.version.get_endpoint(&run_resource);
+        let run_url = self.get_endpoint(&run_resource);
 
         //Get version-specific headers
         let version_headers = self.version.get_headers(&self.api_key);
@@ -654,7 +690,8 @@ impl OpenAIAssistant {
         if self.debug {
             info!(
                 "[debug] OpenAI Run status API response: [{}] {:#?}",
-                &response_status, &response_text
+                &response_status,
+                redact_secrets(&response_text)
             );
         }
 
@@ -720,7 +757,7 @@ impl OpenAIAssistant {
         let assistant_resource = OpenAIAssistantResource::Assistant {
             assistant_id: self.id.clone().unwrap_or_default(),
         };
-        let assistant_url = self.version.get_endpoint(&assistant_resource);
+        let assistant_url = self.get_endpoint(&assistant_resource);
 
         //Get version-specific headers
         let version_headers = self.version.get_headers(&self.api_key);
@@ -749,7 +786,8 @@ impl OpenAIAssistant {
         if self.debug {
             info!(
                 "[debug] OpenAI Vector Store Attach API response: [{}] {:#?}",
-                &response_status, &response_text
+                &response_status,
+                redact_secrets(&response_text)
             );
         }
 
@@ -770,4 +808,245 @@ impl OpenAIAssistant {
             })
             .map(|_| Ok(()))?
     }
+
+    ///
+    /// This method deletes the thread and the Assistant. Files and Vector Stores are not deleted.
+    ///
+    pub async fn delete(&self) -> Result<()> {
+        if let Some(thread_id) = &self.thread_id {
+            let thread_resource = OpenAIAssistantResource::Thread {
+                thread_id: thread_id.to_string(),
+            };
+            self.delete_resource(&thread_resource).await?;
+        }
+        if let Some(assistant_id) = &self.id {
+            let assistant_resource = OpenAIAssistantResource::Assistant {
+                assistant_id: assistant_id.to_string(),
+            };
+            self.delete_resource(&assistant_resource).await?;
+        }
+        Ok(())
+    }
+
+    /*
+     * This function deletes files uploaded to OpenAI
+     */
+    async fn delete_files(&self, file_ids: &[String]) -> Result<()> {
+        for file_id in file_ids {
+            let file_resource = OpenAIAssistantResource::File {
+                file_id: file_id.to_string(),
+            };
+            self.delete_resource(&file_resource).await?;
+        }
+        Ok(())
+    }
+
+    /*
+     * This function deletes an Assistants API resource (assistant, thread or file)
+     */
+    async fn delete_resource(&self, resource: &OpenAIAssistantResource) -> Result<()> {
+        //Get version-specific URL
+        let delete_url = self.get_endpoint(resource);
+
+        //Get version-specific headers
+        let version_headers = self.version.get_headers(&self.api_key);
+
+        //Make the API call
+        let client = Client::new();
+
+        let response = client
+            .delete(delete_url)
+            .headers(version_headers)
+            .send()
+            .await?;
+
+        let response_status = response.status();
+        let response_text = response.text().await?;
+
+        if self.debug {
+            info!(
+                "[debug] OpenAI Delete API response: [{}] {:#?}",
+                &response_status,
+                redact_secrets(&response_text)
+            );
+        }
+
+        //Check if the resource was successfully deleted
+        serde_json::from_str::<OpenAIDeleteResp>(&response_text)
+            .map_err(|error| {
+                let error = AllmsError {
+                    crate_name: "allms".to_string(),
+                    module: "assistants::openai_assistant".to_string(),
+                    error_message: format!("Delete API response serialization error: {}", error),
+                    error_detail: response_text,
+                };
+                error!("{:?}", error);
+                anyhow!("{:?}", error)
+            })
+            .and_then(|response| match response.deleted {
+                true => Ok(()),
+                false => Err(anyhow!(
+                    "[OpenAI][Assistants] Unable to delete {}: {}",
+                    response.object,
+                    response.id
+                )),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use schemars::JsonSchema;
+    use serde::{Deserialize, Serialize};
+    use serde_json::{json, Value};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use crate::assistants::{OpenAIAssistant, OpenAIAssistantVersion};
+    use crate::llm_models::OpenAIModels;
+
+    #[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, PartialEq)]
+    struct Answer {
+        answer: String,
+    }
+
+    async fn mount_json(server: &MockServer, http_method: &str, url_path: &str, body: Value) {
+        Mock::given(method(http_method))
+            .and(path(url_path))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(server)
+            .await;
+    }
+
+    async fn mount_assistant_lifecycle(server: &MockServer) {
+        mount_json(
+            server,
+            "POST",
+            "/v1/assistants",
+            json!({
+                "id": "asst_1",
+                "object": "assistant",
+                "created_at": 1699009709,
+                "model": "gpt-4o",
+                "tools": []
+            }),
+        )
+        .await;
+        mount_json(
+            server,
+            "POST",
+            "/v1/threads",
+            json!({"id": "thread_1", "object": "thread", "created_at": 1699012949}),
+        )
+        .await;
+        mount_json(
+            server,
+            "POST",
+            "/v1/threads/thread_1/messages",
+            json!({
+                "id": "msg_1",
+                "object": "thread.message",
+                "created_at": 1699017614,
+                "thread_id": "thread_1",
+                "role": "user",
+                "content": [{"type": "text", "text": {"value": "message"}}]
+            }),
+        )
+        .await;
+        mount_json(
+            server,
+            "POST",
+            "/v1/threads/thread_1/runs",
+            json!({"id": "run_1", "object": "thread.run", "created_at": 1699063290, "status": "queued"}),
+        )
+        .await;
+        mount_json(
+            server,
+            "GET",
+            "/v1/threads/thread_1/runs/run_1",
+            json!({"id": "run_1", "object": "thread.run", "created_at": 1699063290, "status": "completed"}),
+        )
+        .await;
+        mount_json(
+            server,
+            "GET",
+            "/v1/threads/thread_1/messages",
+            json!({
+                "object": "list",
+                "data": [{
+                    "id": "msg_2",
+                    "object": "thread.message",
+                    "created_at": 1699017700,
+                    "thread_id": "thread_1",
+                    "role": "assistant",
+                    "content": [{"type": "text", "text": {"value": "{\"answer\": \"42\"}"}}]
+                }]
+            }),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_assistant_thread_run_lifecycle() {
+        let server = MockServer::start().await;
+        mount_assistant_lifecycle(&server).await;
+
+        let answer = OpenAIAssistant::new(OpenAIModels::Gpt4o, "key")
+            .version(OpenAIAssistantVersion::V2)
+            .api_url(&server.uri())
+            .get_answer::<Answer>("What is the answer?", &[])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            answer,
+            Answer {
+                answer: "42".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_assistant_deletes_files_after_run() {
+        let server = MockServer::start().await;
+        mount_assistant_lifecycle(&server).await;
+        Mock::given(method("DELETE"))
+            .and(path("/v1/files/file_1"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({"id": "file_1", "object": "file", "deleted": true})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/v1/threads/thread_1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                json!({"id": "thread_1", "object": "thread.deleted", "deleted": true}),
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/v1/assistants/asst_1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                json!({"id": "asst_1", "object": "assistant.deleted", "deleted": true}),
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut assistant = OpenAIAssistant::new(OpenAIModels::Gpt4o, "key")
+            .version(OpenAIAssistantVersion::V2)
+            .api_url(&server.uri())
+            .delete_files_after_run();
+        let answer = assistant
+            .get_answer::<Answer>("What is the answer?", &["file_1".to_string()])
+            .await
+            .unwrap();
+        assistant.delete().await.unwrap();
+
+        assert_eq!(answer.answer, "42");
+        // Expectations on the mocks are verified when the server is dropped
+    }
 }
@@ -0,0 +1,738 @@
+use anyhow::{anyhow, Result};
+use log::{error, info};
+use reqwest::{Client, RequestBuilder};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::assistants::{FileId, OpenAIAssistantResource, OpenAIAssistantVersion};
+use crate::constants::OPENAI_API_URL;
+use crate::domain::{
+    AllmsError, OpenAIAssistantFileResp, OpenAIAssistantResp, OpenAICodeInterpreterCall,
+    OpenAICodeInterpreterOutput, OpenAIDeleteResp, OpenAIMessageListResp, OpenAIMessageResp,
+    OpenAIRunResp, OpenAIRunStepListResp, OpenAIThreadResp,
+};
+use crate::enums::{OpenAIAssistantRole, OpenAIRunStatus};
+use crate::llm_models::{LLMModel, OpenAIModels};
+use crate::utils::redact_secrets;
+
+pub type RunStatus = OpenAIRunStatus;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq, Hash)]
+pub struct AssistantId(pub String);
+
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ThreadId(pub String);
+
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq, Hash)]
+pub struct MessageId(pub String);
+
+/// Runs are scoped to a thread so the ID of the thread is kept with the ID of the run
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq, Hash)]
+pub struct RunId {
+    pub thread_id: ThreadId,
+    pub id: String,
+}
+
+/// Tools available to an Assistant
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub enum AssistantTool {
+    CodeInterpreter,
+    // Sent as `retrieval` in Assistants API v1
+    FileSearch,
+    Function {
+        name: String,
+        description: Option<String>,
+        parameters: Value,
+    },
+}
+
+impl AssistantTool {
+    fn get_payload(&self, version: &OpenAIAssistantVersion) -> Value {
+        match self {
+            AssistantTool::CodeInterpreter => json!({"type": "code_interpreter"}),
+            AssistantTool::FileSearch => match version {
+                OpenAIAssistantVersion::V1 => json!({"type": "retrieval"}),
+                _ => json!({"type": "file_search"}),
+            },
+            AssistantTool::Function {
+                name,
+                description,
+                parameters,
+            } => json!({
+                "type": "function",
+                "function": {
+                    "name": name,
+                    "description": description,
+                    "parameters": parameters,
+                },
+            }),
+        }
+    }
+}
+
+/// Message posted to a thread, with the text of all its content parts
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct AssistantMessage {
+    pub id: MessageId,
+    pub role: OpenAIAssistantRole,
+    pub content: String,
+}
+
+impl From<OpenAIMessageResp> for AssistantMessage {
+    fn from(message: OpenAIMessageResp) -> Self {
+        AssistantMessage {
+            id: MessageId(message.id),
+            role: message.role,
+            content: message
+                .content
+                .into_iter()
+                .filter_map(|content| content.text)
+                .map(|text| text.value)
+                .collect(),
+        }
+    }
+}
+
+/// Python code executed by the Code Interpreter tool during a Run
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct CodeInterpreterCall {
+    pub input: String,
+    pub outputs: Vec<CodeInterpreterOutput>,
+}
+
+/// Output of the code, either the text logs or an image saved as a file
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub enum CodeInterpreterOutput {
+    Logs(String),
+    Image(FileId),
+}
+
+impl From<OpenAICodeInterpreterCall> for CodeInterpreterCall {
+    fn from(call: OpenAICodeInterpreterCall) -> Self {
+        CodeInterpreterCall {
+            input: call.input,
+            outputs: call
+                .outputs
+                .into_iter()
+                .map(|output| match output {
+                    OpenAICodeInterpreterOutput::Logs { logs } => CodeInterpreterOutput::Logs(logs),
+                    OpenAICodeInterpreterOutput::Image { image } => {
+                        CodeInterpreterOutput::Image(FileId(image.file_id))
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// [OpenAI Docs](https://platform.openai.com/docs/api-reference/assistants)
+///
+/// Low-level client of the Assistants API. Unlike `OpenAIAssistant`, which orchestrates a complete run to get an answer,
+/// it exposes the individual steps so that assistants, threads and runs can be managed by the caller.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OpenAIAssistantsClient {
+    api_key: String,
+    version: OpenAIAssistantVersion,
+    api_url: String,
+    debug: bool,
+}
+
+impl OpenAIAssistantsClient {
+    /// Constructor
+    pub fn new(open_ai_key: &str) -> Self {
+        OpenAIAssistantsClient {
+            api_key: open_ai_key.to_string(),
+            version: OpenAIAssistantVersion::V2,
+            api_url: OPENAI_API_URL.to_string(),
+            debug: false,
+        }
+    }
+
+    ///
+    /// This method can be used to turn on debug mode
+    ///
+    pub fn debug(mut self) -> Self {
+        self.debug = true;
+        self
+    }
+
+    ///
+    /// This method can be used to set the version of Assistants API Beta
+    /// Current default is V2
+    ///
+    pub fn version(mut self, version: OpenAIAssistantVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    ///
+    /// This method can be used to overwrite the base url of the API, e.g. when requests are sent via a proxy
+    /// Default is the value of `OPENAI_API_URL` environment variable or `https://api.openai.com`
+    ///
+    pub fn api_url(mut self, api_url: &str) -> Self {
+        self.api_url = api_url.to_string();
+        self
+    }
+
+    ///
+    /// This method creates an Assistant with the provided instructions and tools
+    ///
+    pub async fn create_assistant(
+        &self,
+        instructions: &str,
+        model: OpenAIModels,
+        tools: &[AssistantTool],
+    ) -> Result<AssistantId> {
+        let mut body = json!({
+            "instructions": instructions,
+            "model": model.as_str(),
+        });
+        if !tools.is_empty() {
+            body["tools"] = tools
+                .iter()
+                .map(|tool| tool.get_payload(&self.version))
+                .collect();
+        }
+
+        let request = Client::new()
+            .post(self.get_endpoint(&OpenAIAssistantResource::Assistants))
+            .json(&body);
+        let assistant: OpenAIAssistantResp = self.send(request, "Assistant").await?;
+
+        Ok(AssistantId(assistant.id))
+    }
+
+    ///
+    /// This method creates an empty Thread
+    ///
+    pub async fn create_thread(&self) -> Result<ThreadId> {
+        let request = Client::new()
+            .post(self.get_endpoint(&OpenAIAssistantResource::Threads))
+            .json(&json!({}));
+        let thread: OpenAIThreadResp = self.send(request, "Thread").await?;
+
+        Ok(ThreadId(thread.id))
+    }
+
+    ///
+    /// This method adds a user message to the Thread
+    ///
+    pub async fn add_message(&self, thread_id: &ThreadId, content: &str) -> Result<MessageId> {
+        let messages_resource = OpenAIAssistantResource::Messages {
+            thread_id: thread_id.0.clone(),
+        };
+        let request = Client::new()
+            .post(self.get_endpoint(&messages_resource))
+            .json(&json!({
+                "role": "user",
+                "content": content,
+            }));
+        let message: OpenAIMessageResp = self.send(request, "Messages").await?;
+
+        Ok(MessageId(message.id))
+    }
+
+    ///
+    /// This method starts a Run of the Assistant on the Thread. Its progress can be checked with `poll_run`
+    ///
+    pub async fn run_thread(
+        &self,
+        thread_id: &ThreadId,
+        assistant_id: &AssistantId,
+    ) -> Result<RunId> {
+        let runs_resource = OpenAIAssistantResource::Runs {
+            thread_id: thread_id.0.clone(),
+        };
+        let request = Client::new()
+            .post(self.get_endpoint(&runs_resource))
+            .json(&json!({
+                "assistant_id": assistant_id.0,
+            }));
+        let run: OpenAIRunResp = self.send(request, "Run").await?;
+
+        Ok(RunId {
+            thread_id: thread_id.clone(),
+            id: run.id,
+        })
+    }
+
+    ///
+    /// This method returns the current status of the Run
+    ///
+    pub async fn poll_run(&self, run_id: &RunId) -> Result<RunStatus> {
+        let run_resource = OpenAIAssistantResource::Run {
+            thread_id: run_id.thread_id.0.clone(),
+            run_id: run_id.id.clone(),
+        };
+        let request = Client::new().get(self.get_endpoint(&run_resource));
+        let run: OpenAIRunResp = self.send(request, "Run status").await?;
+
+        Ok(run.status)
+    }
+
+    ///
+    /// This method returns the code executed by the Code Interpreter tool during the Run, in order of execution, with its outputs
+    /// Images generated by the code can be downloaded using the returned file IDs
+    ///
+    pub async fn get_code_interpreter_calls(
+        &self,
+        run_id: &RunId,
+    ) -> Result<Vec<CodeInterpreterCall>> {
+        let steps_resource = OpenAIAssistantResource::RunSteps {
+            thread_id: run_id.thread_id.0.clone(),
+            run_id: run_id.id.clone(),
+        };
+        let request = Client::new()
+            .get(self.get_endpoint(&steps_resource))
+            .query(&[("order", "asc")]);
+        let steps: OpenAIRunStepListResp = self.send(request, "Run Steps").await?;
+
+        Ok(steps
+            .data
+            .into_iter()
+            .flat_map(|step| step.step_details.tool_calls)
+            .filter_map(|tool_call| tool_call.code_interpreter)
+            .map(CodeInterpreterCall::from)
+            .collect())
+    }
+
+    ///
+    /// This method lists the messages of the Thread, most recent first
+    ///
+    pub async fn list_messages(&self, thread_id: &ThreadId) -> Result<Vec<AssistantMessage>> {
+        let messages_resource = OpenAIAssistantResource::Messages {
+            thread_id: thread_id.0.clone(),
+        };
+        let request = Client::new().get(self.get_endpoint(&messages_resource));
+        let messages: OpenAIMessageListResp = self.send(request, "Messages").await?;
+
+        Ok(messages
+            .data
+            .into_iter()
+            .map(AssistantMessage::from)
+            .collect())
+    }
+
+    ///
+    /// This method attaches an uploaded file to the Assistant
+    /// In Assistants API v2 files are attached to the Code Interpreter tool, which needs to be enabled for the Assistant
+    ///
+    pub async fn attach_file_to_assistant(
+        &self,
+        assistant_id: &AssistantId,
+        file_id: &FileId,
+    ) -> Result<()> {
+        match self.version {
+            //Docs: https://platform.openai.com/docs/api-reference/assistants-v1/createAssistantFile
+            OpenAIAssistantVersion::V1 => {
+                let files_resource = OpenAIAssistantResource::AssistantFiles {
+                    assistant_id: assistant_id.0.clone(),
+                };
+                let request = Client::new()
+                    .post(self.get_endpoint(&files_resource))
+                    .json(&json!({
+                        "file_id": file_id.0,
+                    }));
+                let _assistant_file: OpenAIAssistantFileResp =
+                    self.send(request, "Assistant Files").await?;
+                Ok(())
+            }
+            _ => {
+                let mut file_ids = self.get_code_interpreter_file_ids(assistant_id).await?;
+                if !file_ids.contains(&file_id.0) {
+                    file_ids.push(file_id.0.clone());
+                }
+                self.update_code_interpreter_file_ids(assistant_id, &file_ids)
+                    .await
+            }
+        }
+    }
+
+    ///
+    /// This method detaches a file from the Assistant. The file itself is not deleted.
+    ///
+    pub async fn detach_file_from_assistant(
+        &self,
+        assistant_id: &AssistantId,
+        file_id: &FileId,
+    ) -> Result<()> {
+        match self.version {
+            //Docs: https://platform.openai.com/docs/api-reference/assistants-v1/deleteAssistantFile
+            OpenAIAssistantVersion::V1 => {
+                let file_resource = OpenAIAssistantResource::AssistantFile {
+                    assistant_id: assistant_id.0.clone(),
+                    file_id: file_id.0.clone(),
+                };
+                let request = Client::new().delete(self.get_endpoint(&file_resource));
+                let response: OpenAIDeleteResp = self.send(request, "Assistant Files").await?;
+                match response.deleted {
+                    true => Ok(()),
+                    false => Err(anyhow!(
+                        "[OpenAI][Assistants] Unable to detach file {} from the Assistant",
+                        file_id.0
+                    )),
+                }
+            }
+            _ => {
+                let mut file_ids = self.get_code_interpreter_file_ids(assistant_id).await?;
+                file_ids.retain(|attached_file_id| attached_file_id != &file_id.0);
+                self.update_code_interpreter_file_ids(assistant_id, &file_ids)
+                    .await
+            }
+        }
+    }
+
+    // This function returns the IDs of the files available to the Code Interpreter tool of the Assistant
+    async fn get_code_interpreter_file_ids(
+        &self,
+        assistant_id: &AssistantId,
+    ) -> Result<Vec<String>> {
+        let assistant_resource = OpenAIAssistantResource::Assistant {
+            assistant_id: assistant_id.0.clone(),
+        };
+        let request = Client::new().get(self.get_endpoint(&assistant_resource));
+        let assistant: OpenAIAssistantResp = self.send(request, "Assistant").await?;
+
+        Ok(assistant
+            .tool_resources
+            .and_then(|tool_resources| {
+                serde_json::from_value(tool_resources["code_interpreter"]["file_ids"].clone()).ok()
+            })
+            .unwrap_or_default())
+    }
+
+    // This function replaces the files available to the Code Interpreter tool of the Assistant
+    async fn update_code_interpreter_file_ids(
+        &self,
+        assistant_id: &AssistantId,
+        file_ids: &[String],
+    ) -> Result<()> {
+        let assistant_resource = OpenAIAssistantResource::Assistant {
+            assistant_id: assistant_id.0.clone(),
+        };
+        let request = Client::new()
+            .post(self.get_endpoint(&assistant_resource))
+            .json(&json!({
+                "tool_resources": {
+                    "code_interpreter": {
+                        "file_ids": file_ids,
+                    },
+                },
+            }));
+        let _assistant: OpenAIAssistantResp = self.send(request, "Assistant").await?;
+        Ok(())
+    }
+
+    // This function returns the version-specific url of the resource
+    fn get_endpoint(&self, resource: &OpenAIAssistantResource) -> String {
+        self.version.get_endpoint_with_url(&self.api_url, resource)
+    }
+
+    // This function sends the request with version-specific headers and deserializes the response
+    async fn send<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+        api_name: &str,
+    ) -> Result<T> {
+        let response = request
+            .headers(self.version.get_headers(&self.api_key))
+            .send()
+            .await?;
+
+        let response_status = response.status();
+        let response_text = response.text().await?;
+
+        if self.debug {
+            info!(
+                "[debug] OpenAI {} API response: [{}] {:#?}",
+                api_name,
+                &response_status,
+                redact_secrets(&response_text)
+            );
+        }
+
+        serde_json::from_str(&response_text).map_err(|error| {
+            let error = AllmsError {
+                crate_name: "allms".to_string(),
+                module: "assistants::openai_assistants_client".to_string(),
+                error_message: format!("{} API response serialization error: {}", api_name, error),
+                error_detail: response_text,
+            };
+            error!("{:?}", error);
+            anyhow!("{:?}", error)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use wiremock::matchers::{body_partial_json, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::{
+        AssistantTool, CodeInterpreterCall, CodeInterpreterOutput, OpenAIAssistantsClient,
+    };
+    use crate::assistants::{
+        AssistantId, FileId, MessageId, OpenAIAssistantVersion, RunId, RunStatus, ThreadId,
+    };
+    use crate::enums::OpenAIAssistantRole;
+    use crate::llm_models::OpenAIModels;
+
+    async fn mount_json(server: &MockServer, http_method: &str, url_path: &str, body: Value) {
+        Mock::given(method(http_method))
+            .and(path(url_path))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(server)
+            .await;
+    }
+
+    #[test]
+    fn test_tool_payloads() {
+        let function = AssistantTool::Function {
+            name: "get_weather".to_string(),
+            description: None,
+            parameters: json!({"type": "object", "properties": {}}),
+        };
+        assert_eq!(
+            function.get_payload(&OpenAIAssistantVersion::V2),
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "get_weather",
+                    "description": null,
+                    "parameters": {"type": "object", "properties": {}},
+                },
+            })
+        );
+        assert_eq!(
+            AssistantTool::FileSearch.get_payload(&OpenAIAssistantVersion::V1),
+            json!({"type": "retrieval"})
+        );
+        assert_eq!(
+            AssistantTool::FileSearch.get_payload(&OpenAIAssistantVersion::V2),
+            json!({"type": "file_search"})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_code_interpreter_calls() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/threads/thread_1/runs/run_1/steps"))
+            .and(query_param("order", "asc"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "object": "list",
+                "data": [
+                    {
+                        "id": "step_1",
+                        "object": "thread.run.step",
+                        "type": "tool_calls",
+                        "step_details": {
+                            "type": "tool_calls",
+                            "tool_calls": [{
+                                "id": "call_1",
+                                "type": "code_interpreter",
+                                "code_interpreter": {
+                                    "input": "print(2 + 2)",
+                                    "outputs": [
+                                        {"type": "logs", "logs": "4"},
+                                        {"type": "image", "image": {"file_id": "file_1"}}
+                                    ]
+                                }
+                            }]
+                        }
+                    },
+                    {
+                        "id": "step_2",
+                        "object": "thread.run.step",
+                        "type": "message_creation",
+                        "step_details": {
+                            "type": "message_creation",
+                            "message_creation": {"message_id": "msg_1"}
+                        }
+                    }
+                ]
+            })))
+            .mount(&server)
+            .await;
+
+        let client = OpenAIAssistantsClient::new("key").api_url(&server.uri());
+        let run_id = RunId {
+            thread_id: ThreadId("thread_1".to_string()),
+            id: "run_1".to_string(),
+        };
+        let calls = client.get_code_interpreter_calls(&run_id).await.unwrap();
+
+        assert_eq!(
+            calls,
+            vec![CodeInterpreterCall {
+                input: "print(2 + 2)".to_string(),
+                outputs: vec![
+                    CodeInterpreterOutput::Logs("4".to_string()),
+                    CodeInterpreterOutput::Image(FileId("file_1".to_string())),
+                ],
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_attach_and_detach_file() {
+        let server = MockServer::start().await;
+        let assistant = |file_ids: Value| {
+            json!({
+                "id": "asst_1",
+                "object": "assistant",
+                "created_at": 1699009709,
+                "model": "gpt-4o",
+                "tools": [{"type": "code_interpreter"}],
+                "tool_resources": {"code_interpreter": {"file_ids": file_ids}}
+            })
+        };
+        mount_json(
+            &server,
+            "GET",
+            "/v1/assistants/asst_1",
+            assistant(json!(["file_1"])),
+        )
+        .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/assistants/asst_1"))
+            .and(body_partial_json(json!({
+                "tool_resources": {"code_interpreter": {"file_ids": ["file_1", "file_2"]}}
+            })))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(assistant(json!(["file_1", "file_2"]))),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/assistants/asst_1"))
+            .and(body_partial_json(json!({
+                "tool_resources": {"code_interpreter": {"file_ids": []}}
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(assistant(json!([]))))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = OpenAIAssistantsClient::new("key").api_url(&server.uri());
+        let assistant_id = AssistantId("asst_1".to_string());
+        client
+            .attach_file_to_assistant(&assistant_id, &FileId("file_2".to_string()))
+            .await
+            .unwrap();
+        client
+            .detach_file_from_assistant(&assistant_id, &FileId("file_1".to_string()))
+            .await
+            .unwrap();
+        // Expectations on the mocks are verified when the server is dropped
+    }
+
+    #[tokio::test]
+    async fn test_assistants_client_lifecycle() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/assistants"))
+            .and(body_partial_json(json!({
+                "model": "gpt-4o",
+                "tools": [{"type": "code_interpreter"}],
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "asst_1",
+                "object": "assistant",
+                "created_at": 1699009709,
+                "model": "gpt-4o",
+                "tools": [{"type": "code_interpreter"}]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        mount_json(
+            &server,
+            "POST",
+            "/v1/threads",
+            json!({"id": "thread_1", "object": "thread", "created_at": 1699012949}),
+        )
+        .await;
+        mount_json(
+            &server,
+            "POST",
+            "/v1/threads/thread_1/messages",
+            json!({
+                "id": "msg_1",
+                "object": "thread.message",
+                "created_at": 1699017614,
+                "thread_id": "thread_1",
+                "role": "user",
+                "content": [{"type": "text", "text": {"value": "What is 6 x 7?"}}]
+            }),
+        )
+        .await;
+        mount_json(
+            &server,
+            "POST",
+            "/v1/threads/thread_1/runs",
+            json!({"id": "run_1", "object": "thread.run", "created_at": 1699063290, "status": "queued"}),
+        )
+        .await;
+        mount_json(
+            &server,
+            "GET",
+            "/v1/threads/thread_1/runs/run_1",
+            json!({"id": "run_1", "object": "thread.run", "created_at": 1699063290, "status": "completed"}),
+        )
+        .await;
+        mount_json(
+            &server,
+            "GET",
+            "/v1/threads/thread_1/messages",
+            json!({
+                "object": "list",
+                "data": [{
+                    "id": "msg_2",
+                    "object": "thread.message",
+                    "created_at": 1699017700,
+                    "thread_id": "thread_1",
+                    "role": "assistant",
+                    "content": [{"type": "text", "text": {"value": "42"}}]
+                }]
+            }),
+        )
+        .await;
+
+        let client = OpenAIAssistantsClient::new("key").api_url(&server.uri());
+        let assistant_id = client
+            .create_assistant(
+                "You are a calculator",
+                OpenAIModels::Gpt4o,
+                &[AssistantTool::CodeInterpreter],
+            )
+            .await
+            .unwrap();
+        assert_eq!(assistant_id, AssistantId("asst_1".to_string()));
+
+        let thread_id = client.create_thread().await.unwrap();
+        assert_eq!(thread_id, ThreadId("thread_1".to_string()));
+
+        let message_id = client
+            .add_message(&thread_id, "What is 6 x 7?")
+            .await
+            .unwrap();
+        assert_eq!(message_id, MessageId("msg_1".to_string()));
+
+        let run_id = client.run_thread(&thread_id, &assistant_id).await.unwrap();
+        assert_eq!(run_id.id, "run_1");
+        assert_eq!(
+            client.poll_run(&run_id).await.unwrap(),
+            RunStatus::Completed
+        );
+
+        let messages = client.list_messages(&thread_id).await.unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].role, OpenAIAssistantRole::Assistant);
+        assert_eq!(messages[0].content, "42");
+    }
+}
@@ -6,6 +6,7 @@ use std::path::Path;
 
 use crate::assistants::{OpenAIAssistantResource, OpenAIAssistantVersion};
 use crate::domain::AllmsError;
+use crate::utils::redact_secrets;
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct OpenAIFile {
@@ -13,6 +14,7 @@ pub struct OpenAIFile {
     debug: bool,
     api_key: String,
     version: OpenAIAssistantVersion,
+    purpose: String,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -20,6 +22,22 @@ pub struct OpenAIFileResp {
     id: String,
 }
 
+/// File object returned by OpenAI Files API
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OpenAIFileObject {
+    pub id: String,
+    pub object: String,
+    pub bytes: usize,
+    pub created_at: u64,
+    pub filename: String,
+    pub purpose: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct OpenAIFileListResp {
+    data: Vec<OpenAIFileObject>,
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct OpenAIDFileDeleteResp {
     id: String,
@@ -35,6 +53,7 @@ impl OpenAIFile {
             debug: false,
             api_key: open_ai_key.to_string(),
             version: OpenAIAssistantVersion::V1, // Default to V1
+            purpose: "assistants".to_string(),
         }
     }
 
@@ -61,7 +80,16 @@ impl OpenAIFile {
     }
 
     ///
-    /// This function uploads a file to OpenAI and assigns it for use with Assistant API
+    /// This method can be used to set the intended purpose of the uploaded file, e.g. `fine-tune` or `batch`
+    /// Default is `assistants`
+    ///
+    pub fn purpose(mut self, purpose: &str) -> Self {
+        self.purpose = purpose.to_string();
+        self
+    }
+
+    ///
+    /// This function uploads a file to OpenAI and assigns it for use with the API matching the purpose
     ///
     pub async fn upload(mut self, file_name: &str, file_bytes: Vec<u8>) -> Result<Self> {
         let files_url = self.version.get_endpoint(&OpenAIAssistantResource::Files);
@@ -70,51 +98,7 @@ impl OpenAIFile {
         let mut version_headers = self.version.get_headers(&self.api_key);
         version_headers.remove(header::CONTENT_TYPE);
 
-        // Determine MIME type based on file extension
-        // OpenAI documentation: https://platform.openai.com/docs/assistants/tools/supported-files
-        let mime_type = match Path::new(file_name)
-            .extension()
-            .and_then(std::ffi::OsStr::to_str)
-        {
-            Some("pdf") => "application/pdf",
-            Some("json") => "application/json",
-            Some("txt") => "text/plain",
-            Some("html") => "text/html",
-            Some("c") => "text/x-c",
-            Some("cpp") => "text/x-c++",
-            Some("docx") => {
-                "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
-            }
-            Some("java") => "text/x-java",
-            Some("md") => "text/markdown",
-            Some("php") => "text/x-php",
-            Some("pptx") => {
-                "application/vnd.openxmlformats-officedocument.presentationml.presentation"
-            }
-            Some("py") => "text/x-python",
-            Some("rb") => "text/x-ruby",
-            Some("tex") => "text/x-tex",
-            //The below are currently only supported for Code Interpreter but NOT Retrieval
-            Some("css") => "text/css",
-            Some("jpeg") | Some("jpg") => "image/jpeg",
-            Some("js") => "text/javascript",
-            Some("gif") => "image/gif",
-            Some("png") => "image/png",
-            Some("tar") => "application/x-tar",
-            Some("ts") => "application/typescript",
-            Some("xlsx") => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
-            Some("xml") => "application/xml",
-            Some("zip") => "application/zip",
-            _ => anyhow::bail!("Unsupported file type"),
-        };
-
-        let form = multipart::Form::new().text("purpose", "assistants").part(
-            "file",
-            multipart::Part::bytes(file_bytes)
-                .file_name(file_name.to_string())
-                .mime_str(mime_type)
-                .context("Failed to set MIME type")?,
-        );
+        let form = get_upload_form(file_name, file_bytes, &self.purpose)?;
 
         //Make the API call
         let client = Client::new();
@@ -132,7 +116,8 @@ impl OpenAIFile {
         if self.debug {
             info!(
                 "[debug] OpenAI Files status API response: [{}] {:#?}",
-                &response_status, &response_text
+                &response_status,
+                redact_secrets(&response_text)
             );
         }
 
@@ -154,15 +139,15 @@ impl OpenAIFile {
         Ok(self)
     }
 
-    /*
-     * This function deletes a file from OpenAI
-     */
-    pub async fn delete(&self) -> Result<()> {
+    ///
+    /// This function retrieves the details of the file from OpenAI
+    ///
+    pub async fn retrieve(&self) -> Result<OpenAIFileObject> {
         let file_id = if let Some(id) = &self.id {
             id
         } else {
             return Err(anyhow!(
-                "[OpenAI][File API] Unable to delete file without an ID."
+                "[OpenAI][File API] Unable to retrieve file without an ID."
             ));
         };
 
@@ -176,7 +161,7 @@ impl OpenAIFile {
         let client = Client::new();
 
         let response = client
-            .delete(files_url)
+            .get(files_url)
             .headers(version_headers)
             .send()
             .await?;
@@ -186,19 +171,51 @@ impl OpenAIFile {
 
         if self.debug {
             info!(
-                "[debug] OpenAI Files status API response: [{}] {:#?}",
-                &response_status, &response_text
+                "[debug] OpenAI Files retrieve API response: [{}] {:#?}",
+                &response_status,
+                redact_secrets(&response_text)
             );
         }
 
-        //Check if the file was successfully deleted
-        serde_json::from_str::<OpenAIDFileDeleteResp>(&response_text)
+        parse_file_object(&response_text)
+    }
+
+    ///
+    /// This function lists files uploaded to OpenAI. If a purpose is provided only files with that purpose are returned, e.g. `fine-tune`
+    ///
+    pub async fn list(&self, purpose: Option<&str>) -> Result<Vec<OpenAIFileObject>> {
+        let files_url = self.version.get_endpoint(&OpenAIAssistantResource::Files);
+        let version_headers = self.version.get_headers(&self.api_key);
+
+        //Make the API call
+        let client = Client::new();
+
+        let request = client.get(files_url).headers(version_headers);
+        let request = match purpose {
+            Some(purpose) => request.query(&[("purpose", purpose)]),
+            None => request,
+        };
+        let response = request.send().await?;
+
+        let response_status = response.status();
+        let response_text = response.text().await?;
+
+        if self.debug {
+            info!(
+                "[debug] OpenAI Files list API response: [{}] {:#?}",
+                &response_status,
+                redact_secrets(&response_text)
+            );
+        }
+
+        serde_json::from_str::<OpenAIFileListResp>(&response_text)
+            .map(|response| response.data)
             .map_err(|error| {
                 let error = AllmsError {
                     crate_name: "allms".to_string(),
                     module: "assistants::openai_file".to_string(),
                     error_message: format!(
-                        "Files Delete API response serialization error: {}",
+                        "Files List API response serialization error: {}",
                         error
                     ),
                     error_detail: response_text,
@@ -206,9 +223,191 @@ impl OpenAIFile {
                 error!("{:?}", error);
                 anyhow!("{:?}", error)
             })
-            .and_then(|response| match response.deleted {
-                true => Ok(()),
-                false => Err(anyhow!("[OpenAIAssistant] Failed to delete the file.")),
-            })
+    }
+
+    /*
+     * This function deletes a file from OpenAI
+     */
+    pub async fn delete(&self) -> Result<()> {
+        let file_id = if let Some(id) = &self.id {
+            id
+        } else {
+            return Err(anyhow!(
+                "[OpenAI][File API] Unable to delete file without an ID."
+            ));
+        };
+
+        let files_resource = OpenAIAssistantResource::File {
+            file_id: file_id.to_string(),
+        };
+        let files_url = self.version.get_endpoint(&files_resource);
+        let version_headers = self.version.get_headers(&self.api_key);
+
+        //Make the API call
+        let client = Client::new();
+
+        let response = client
+            .delete(files_url)
+            .headers(version_headers)
+            .send()
+            .await?;
+
+        let response_status = response.status();
+        let response_text = response.text().await?;
+
+        if self.debug {
+            info!(
+                "[debug] OpenAI Files status API response: [{}] {:#?}",
+                &response_status,
+                redact_secrets(&response_text)
+            );
+        }
+
+        //Check if the file was successfully deleted
+        parse_delete_response(&response_text)
+    }
+}
+
+// This function prepares the multipart form with the file and its purpose
+pub(super) fn get_upload_form(
+    file_name: &str,
+    file_bytes: Vec<u8>,
+    purpose: &str,
+) -> Result<multipart::Form> {
+    // Determine MIME type based on file extension
+    // OpenAI documentation: https://platform.openai.com/docs/assistants/tools/supported-files
+    let mime_type = match Path::new(file_name)
+        .extension()
+        .and_then(std::ffi::OsStr::to_str)
+    {
+        Some("pdf") => "application/pdf",
+        Some("json") => "application/json",
+        //Used for fine-tuning and batch files
+        Some("jsonl") => "application/jsonl",
+        Some("txt") => "text/plain",
+        Some("html") => "text/html",
+        Some("c") => "text/x-c",
+        Some("cpp") => "text/x-c++",
+        Some("docx") => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        Some("java") => "text/x-java",
+        Some("md") => "text/markdown",
+        Some("php") => "text/x-php",
+        Some("pptx") => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        Some("py") => "text/x-python",
+        Some("rb") => "text/x-ruby",
+        Some("tex") => "text/x-tex",
+        //The below are currently only supported for Code Interpreter but NOT Retrieval
+        Some("css") => "text/css",
+        Some("jpeg") | Some("jpg") => "image/jpeg",
+        Some("js") => "text/javascript",
+        Some("gif") => "image/gif",
+        Some("png") => "image/png",
+        Some("tar") => "application/x-tar",
+        Some("ts") => "application/typescript",
+        Some("xlsx") => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        Some("xml") => "application/xml",
+        Some("zip") => "application/zip",
+        _ => anyhow::bail!("Unsupported file type"),
+    };
+
+    let form = multipart::Form::new()
+        .text("purpose", purpose.to_string())
+        .part(
+            "file",
+            multipart::Part::bytes(file_bytes)
+                .file_name(file_name.to_string())
+                .mime_str(mime_type)
+                .context("Failed to set MIME type")?,
+        );
+
+    Ok(form)
+}
+
+pub(super) fn parse_file_object(response_text: &str) -> Result<OpenAIFileObject> {
+    serde_json::from_str::<OpenAIFileObject>(response_text).map_err(|error| {
+        let error = AllmsError {
+            crate_name: "allms".to_string(),
+            module: "assistants::openai_file".to_string(),
+            error_message: format!("Files API response serialization error: {}", error),
+            error_detail: response_text.to_string(),
+        };
+        error!("{:?}", error);
+        anyhow!("{:?}", error)
+    })
+}
+
+pub(super) fn parse_delete_response(response_text: &str) -> Result<()> {
+    serde_json::from_str::<OpenAIDFileDeleteResp>(response_text)
+        .map_err(|error| {
+            let error = AllmsError {
+                crate_name: "allms".to_string(),
+                module: "assistants::openai_file".to_string(),
+                error_message: format!("Files Delete API response serialization error: {}", error),
+                error_detail: response_text.to_string(),
+            };
+            error!("{:?}", error);
+            anyhow!("{:?}", error)
+        })
+        .and_then(|response| match response.deleted {
+            true => Ok(()),
+            false => Err(anyhow!("[OpenAIAssistant] Failed to delete the file.")),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_upload_form_is_multipart() {
+        let form = get_upload_form("training.jsonl", b"{}".to_vec(), "fine-tune").unwrap();
+        let boundary = form.boundary().to_string();
+
+        let request = Client::new()
+            .post("https://api.openai.com/v1/files")
+            .multipart(form)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            request.headers().get(header::CONTENT_TYPE).unwrap(),
+            &format!("multipart/form-data; boundary={}", boundary)
+        );
+    }
+
+    #[test]
+    fn test_upload_form_rejects_unsupported_file_type() {
+        assert!(get_upload_form("archive.rar", vec![0u8], "assistants").is_err());
+    }
+
+    #[test]
+    fn test_parse_file_object() {
+        let response = json!({
+            "id": "file-abc123",
+            "object": "file",
+            "bytes": 120000,
+            "created_at": 1677610602,
+            "filename": "salesOverview.pdf",
+            "purpose": "assistants"
+        });
+
+        let file_object = parse_file_object(&response.to_string()).unwrap();
+
+        assert_eq!(file_object.id, "file-abc123");
+        assert_eq!(file_object.bytes, 120000);
+        assert_eq!(file_object.filename, "salesOverview.pdf");
+        assert_eq!(file_object.purpose, "assistants");
+    }
+
+    #[test]
+    fn test_parse_delete_response() {
+        let deleted = json!({"id": "file-abc123", "object": "file", "deleted": true});
+        let not_deleted = json!({"id": "file-abc123", "object": "file", "deleted": false});
+
+        assert!(parse_delete_response(&deleted.to_string()).is_ok());
+        assert!(parse_delete_response(&not_deleted.to_string()).is_err());
+        assert!(parse_delete_response("{}").is_err());
     }
 }
@@ -0,0 +1,237 @@
+use anyhow::{anyhow, Result};
+use log::{error, info};
+use reqwest::{header, Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::assistants::openai::openai_file::{
+    get_upload_form, parse_delete_response, parse_file_object,
+};
+use crate::assistants::{OpenAIAssistantResource, OpenAIAssistantVersion, OpenAIFileObject};
+use crate::constants::OPENAI_API_URL;
+use crate::domain::AllmsError;
+use crate::utils::redact_secrets;
+
+pub type FileInfo = OpenAIFileObject;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq, Hash)]
+pub struct FileId(pub String);
+
+/// Intended use of an uploaded file
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq)]
+pub enum FilePurpose {
+    Assistants,
+    Batch,
+    FineTune,
+    Vision,
+}
+
+impl FilePurpose {
+    pub fn as_str(&self) -> &str {
+        match self {
+            FilePurpose::Assistants => "assistants",
+            FilePurpose::Batch => "batch",
+            FilePurpose::FineTune => "fine-tune",
+            FilePurpose::Vision => "vision",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct OpenAIFileListResp {
+    data: Vec<OpenAIFileObject>,
+}
+
+/// [OpenAI Docs](https://platform.openai.com/docs/api-reference/files)
+///
+/// Client of the Files API. Files used by Assistants can be attached with `OpenAIAssistantsClient::attach_file_to_assistant`.
+/// OpenAI charges for the storage of uploaded files so they should be deleted once no longer needed.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OpenAIFilesClient {
+    api_key: String,
+    version: OpenAIAssistantVersion,
+    api_url: String,
+    debug: bool,
+}
+
+impl OpenAIFilesClient {
+    /// Constructor
+    pub fn new(open_ai_key: &str) -> Self {
+        OpenAIFilesClient {
+            api_key: open_ai_key.to_string(),
+            version: OpenAIAssistantVersion::V1,
+            api_url: OPENAI_API_URL.to_string(),
+            debug: false,
+        }
+    }
+
+    ///
+    /// This method can be used to turn on debug mode
+    ///
+    pub fn debug(mut self) -> Self {
+        self.debug = true;
+        self
+    }
+
+    ///
+    /// This method can be used to set the API version, e.g. to use Azure OpenAI
+    /// The Files API is not versioned so V2 is treated as V1
+    ///
+    pub fn version(mut self, version: OpenAIAssistantVersion) -> Self {
+        self.version = match version {
+            OpenAIAssistantVersion::V2 => OpenAIAssistantVersion::V1,
+            _ => version,
+        };
+        self
+    }
+
+    ///
+    /// This method can be used to overwrite the base url of the API, e.g. when requests are sent via a proxy
+    /// Default is the value of `OPENAI_API_URL` environment variable or `https://api.openai.com`
+    ///
+    pub fn api_url(mut self, api_url: &str) -> Self {
+        self.api_url = api_url.to_string();
+        self
+    }
+
+    ///
+    /// This method uploads a file to OpenAI for use with the API matching the purpose
+    ///
+    pub async fn upload_file(
+        &self,
+        content: &[u8],
+        filename: &str,
+        purpose: FilePurpose,
+    ) -> Result<FileId> {
+        let form = get_upload_form(filename, content.to_vec(), purpose.as_str())?;
+        let request = Client::new()
+            .post(self.get_endpoint(&OpenAIAssistantResource::Files))
+            .multipart(form);
+
+        let response_text = self.send(request, "Files upload").await?;
+        parse_file_object(&response_text).map(|file| FileId(file.id))
+    }
+
+    ///
+    /// This method deletes the file from OpenAI
+    ///
+    pub async fn delete_file(&self, id: &FileId) -> Result<()> {
+        let file_resource = OpenAIAssistantResource::File {
+            file_id: id.0.clone(),
+        };
+        let request = Client::new().delete(self.get_endpoint(&file_resource));
+
+        let response_text = self.send(request, "Files delete").await?;
+        parse_delete_response(&response_text)
+    }
+
+    ///
+    /// This method lists all files uploaded to OpenAI
+    ///
+    pub async fn list_files(&self) -> Result<Vec<FileInfo>> {
+        let request = Client::new().get(self.get_endpoint(&OpenAIAssistantResource::Files));
+
+        let response_text = self.send(request, "Files list").await?;
+        serde_json::from_str::<OpenAIFileListResp>(&response_text)
+            .map(|response| response.data)
+            .map_err(|error| {
+                let error = AllmsError {
+                    crate_name: "allms".to_string(),
+                    module: "assistants::openai_files_client".to_string(),
+                    error_message: format!(
+                        "Files List API response serialization error: {}",
+                        error
+                    ),
+                    error_detail: response_text,
+                };
+                error!("{:?}", error);
+                anyhow!("{:?}", error)
+            })
+    }
+
+    // This function returns the version-specific url of the resource
+    fn get_endpoint(&self, resource: &OpenAIAssistantResource) -> String {
+        self.version.get_endpoint_with_url(&self.api_url, resource)
+    }
+
+    // This function sends the request with version-specific headers and returns the response text
+    async fn send(&self, request: RequestBuilder, api_name: &str) -> Result<String> {
+        // Uploads send a form so content type is set by the multipart method
+        let mut version_headers = self.version.get_headers(&self.api_key);
+        version_headers.remove(header::CONTENT_TYPE);
+
+        let response = request.headers(version_headers).send().await?;
+
+        let response_status = response.status();
+        let response_text = response.text().await?;
+
+        if self.debug {
+            info!(
+                "[debug] OpenAI {} API response: [{}] {:#?}",
+                api_name,
+                &response_status,
+                redact_secrets(&response_text)
+            );
+        }
+
+        Ok(response_text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::matchers::{header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::{FileId, FilePurpose, OpenAIFilesClient};
+
+    #[tokio::test]
+    async fn test_files_client() {
+        let server = MockServer::start().await;
+        let file_object = json!({
+            "id": "file-abc123",
+            "object": "file",
+            "bytes": 2,
+            "created_at": 1677610602,
+            "filename": "training.jsonl",
+            "purpose": "fine-tune"
+        });
+        Mock::given(method("POST"))
+            .and(path("/v1/files"))
+            .and(header_exists("content-type"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(file_object.clone()))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/files"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({"object": "list", "data": [file_object]})),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/v1/files/file-abc123"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({"id": "file-abc123", "object": "file", "deleted": true})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = OpenAIFilesClient::new("key").api_url(&server.uri());
+        let file_id = client
+            .upload_file(b"{}", "training.jsonl", FilePurpose::FineTune)
+            .await
+            .unwrap();
+        assert_eq!(file_id, FileId("file-abc123".to_string()));
+
+        let files = client.list_files().await.unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].purpose, "fine-tune");
+
+        client.delete_file(&file_id).await.unwrap();
+    }
+}
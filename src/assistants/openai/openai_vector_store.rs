@@ -6,6 +6,7 @@ use serde_json::json;
 
 use crate::assistants::{OpenAIAssistantResource, OpenAIAssistantVersion};
 use crate::domain::AllmsError;
+use crate::utils::redact_secrets;
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct OpenAIVectorStore {
@@ -86,7 +87,8 @@ impl OpenAIVectorStore {
         if self.debug {
             info!(
                 "[allms][OpenAI][VectorStore][debug] VectorStore Create API response: [{}] {:#?}",
-                &response_status, &response_text
+                &response_status,
+                redact_secrets(&response_text)
             );
         }
 
@@ -169,7 +171,7 @@ impl OpenAIVectorStore {
         if self.debug {
             info!(
                 "[allms][OpenAI][VectorStore][debug] VectorStore Batch Upload API response: [{}] {:#?}",
-                &response_status, &response_text
+                &response_status, redact_secrets(&response_text)
             );
         }
 
@@ -224,7 +226,8 @@ impl OpenAIVectorStore {
         if self.debug {
             info!(
                 "[allms][OpenAI][VectorStore][debug] VectorStore Status API response: [{}] {:#?}",
-                &response_status, &response_text
+                &response_status,
+                redact_secrets(&response_text)
             );
         }
 
@@ -279,7 +282,8 @@ impl OpenAIVectorStore {
         if self.debug {
             info!(
                 "[allms][OpenAI][VectorStore][debug] VectorStore Status API response: [{}] {:#?}",
-                &response_status, &response_text
+                &response_status,
+                redact_secrets(&response_text)
             );
         }
 
@@ -334,7 +338,8 @@ impl OpenAIVectorStore {
         if self.debug {
             info!(
                 "[allms][OpenAI][VectorStore][debug] VectorStore Delete API response: [{}] {:#?}",
-                &response_status, &response_text
+                &response_status,
+                redact_secrets(&response_text)
             );
         }
 
@@ -368,14 +373,14 @@ impl OpenAIVectorStore {
 *
 ******************************************************************************************/
 #[derive(Deserialize, Serialize, Debug, Clone)]
-struct OpenAIVectorStoreResp {
-    id: String,
+pub(super) struct OpenAIVectorStoreResp {
+    pub(super) id: String,
     name: String,
-    status: OpenAIVectorStoreStatus,
+    pub(super) status: OpenAIVectorStoreStatus,
     created_at: i64,
     expires_at: Option<i64>,
     last_active_at: Option<i64>,
-    file_counts: OpenAIVectorStoreFileCounts,
+    pub(super) file_counts: OpenAIVectorStoreFileCounts,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -398,15 +403,15 @@ pub enum OpenAIVectorStoreStatus {
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
-struct OpenAIVectorStoreFileBatchResp {
-    id: String,
-    vector_store_id: String,
-    status: OpenAIVectorStoreFileBatchStatus,
+pub(super) struct OpenAIVectorStoreFileBatchResp {
+    pub(super) id: String,
+    pub(super) vector_store_id: String,
+    pub(super) status: OpenAIVectorStoreFileBatchStatus,
     created_at: i64,
-    file_counts: OpenAIVectorStoreFileCounts,
+    pub(super) file_counts: OpenAIVectorStoreFileCounts,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq)]
 pub enum OpenAIVectorStoreFileBatchStatus {
     #[serde(rename(deserialize = "in_progress", serialize = "in_progress"))]
     InProgress,
@@ -419,7 +424,7 @@ pub enum OpenAIVectorStoreFileBatchStatus {
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
-struct OpenAIVectorStoreDeleteResp {
+pub(super) struct OpenAIVectorStoreDeleteResp {
     id: String,
-    deleted: bool,
+    pub(super) deleted: bool,
 }
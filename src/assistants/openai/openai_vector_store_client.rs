@@ -0,0 +1,319 @@
+use anyhow::{anyhow, Result};
+use log::{error, info, warn};
+use reqwest::{Client, RequestBuilder};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::assistants::openai::openai_vector_store::{
+    OpenAIVectorStoreDeleteResp, OpenAIVectorStoreFileBatchResp, OpenAIVectorStoreFileBatchStatus,
+    OpenAIVectorStoreResp,
+};
+use crate::assistants::{FileId, OpenAIAssistantResource, OpenAIAssistantVersion};
+use crate::constants::OPENAI_API_URL;
+use crate::domain::AllmsError;
+use crate::utils::redact_secrets;
+
+pub type FileBatchStatus = OpenAIVectorStoreFileBatchStatus;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq, Hash)]
+pub struct VectorStoreId(pub String);
+
+/// File batches are scoped to a vector store so the ID of the store is kept with the ID of the batch
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq, Hash)]
+pub struct FileBatchId {
+    pub vector_store_id: VectorStoreId,
+    pub id: String,
+}
+
+/// [OpenAI Docs](https://platform.openai.com/docs/api-reference/vector-stores)
+///
+/// Client of the Vector Stores API used by the `file_search` tool of Assistants API v2. Unlike `OpenAIVectorStore`,
+/// it exposes the individual steps so that stores and file batches can be managed by the caller.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OpenAIVectorStoreClient {
+    api_key: String,
+    version: OpenAIAssistantVersion,
+    api_url: String,
+    debug: bool,
+}
+
+impl OpenAIVectorStoreClient {
+    /// Constructor
+    pub fn new(open_ai_key: &str) -> Self {
+        OpenAIVectorStoreClient {
+            api_key: open_ai_key.to_string(),
+            version: OpenAIAssistantVersion::V2,
+            api_url: OPENAI_API_URL.to_string(),
+            debug: false,
+        }
+    }
+
+    ///
+    /// This method can be used to turn on debug mode
+    ///
+    pub fn debug(mut self) -> Self {
+        self.debug = true;
+        self
+    }
+
+    ///
+    /// This method can be used to set the version of Assistants API Beta, e.g. to use Azure OpenAI
+    /// Vector Stores are only available in V2 so V1 is treated as V2
+    ///
+    pub fn version(mut self, version: OpenAIAssistantVersion) -> Self {
+        self.version = match version {
+            OpenAIAssistantVersion::V1 => OpenAIAssistantVersion::V2,
+            _ => version,
+        };
+        self
+    }
+
+    ///
+    /// This method can be used to overwrite the base url of the API, e.g. when requests are sent via a proxy
+    /// Default is the value of `OPENAI_API_URL` environment variable or `https://api.openai.com`
+    ///
+    pub fn api_url(mut self, api_url: &str) -> Self {
+        self.api_url = api_url.to_string();
+        self
+    }
+
+    ///
+    /// This method creates a Vector Store including the provided files, which are processed in the background
+    ///
+    pub async fn create_vector_store(
+        &self,
+        name: &str,
+        file_ids: &[FileId],
+    ) -> Result<VectorStoreId> {
+        let file_ids: Vec<&str> = file_ids.iter().map(|file_id| file_id.0.as_str()).collect();
+        let request = Client::new()
+            .post(self.get_endpoint(&OpenAIAssistantResource::VectorStores))
+            .json(&json!({
+                "name": name,
+                "file_ids": file_ids,
+            }));
+        let vector_store: OpenAIVectorStoreResp = self.send(request, "VectorStore Create").await?;
+
+        Ok(VectorStoreId(vector_store.id))
+    }
+
+    ///
+    /// This method adds files to an existing Vector Store as a single batch
+    /// The files are processed in the background and can be searched once `poll_file_batch_status` returns `Completed`
+    ///
+    pub async fn add_file_to_vector_store(
+        &self,
+        vector_store_id: &VectorStoreId,
+        file_ids: &[FileId],
+    ) -> Result<FileBatchId> {
+        let file_ids: Vec<&str> = file_ids.iter().map(|file_id| file_id.0.as_str()).collect();
+        let batches_resource = OpenAIAssistantResource::VectorStoreFileBatches {
+            vector_store_id: vector_store_id.0.clone(),
+        };
+        let request = Client::new()
+            .post(self.get_endpoint(&batches_resource))
+            .json(&json!({ "file_ids": file_ids }));
+        let batch: OpenAIVectorStoreFileBatchResp =
+            self.send(request, "VectorStore File Batch").await?;
+
+        Ok(FileBatchId {
+            vector_store_id: VectorStoreId(batch.vector_store_id),
+            id: batch.id,
+        })
+    }
+
+    ///
+    /// This method returns the current status of the file batch
+    /// Batches which are not yet `Completed` should be polled again after a delay
+    ///
+    pub async fn poll_file_batch_status(&self, batch_id: &FileBatchId) -> Result<FileBatchStatus> {
+        let batch_resource = OpenAIAssistantResource::VectorStoreFileBatch {
+            vector_store_id: batch_id.vector_store_id.0.clone(),
+            batch_id: batch_id.id.clone(),
+        };
+        let request = Client::new().get(self.get_endpoint(&batch_resource));
+        let batch: OpenAIVectorStoreFileBatchResp =
+            self.send(request, "VectorStore File Batch status").await?;
+
+        if batch.file_counts.failed > 0 {
+            warn!(
+                "[allms][OpenAI][VectorStore] {} files of batch {} failed to process",
+                batch.file_counts.failed, batch.id
+            );
+        }
+
+        Ok(batch.status)
+    }
+
+    ///
+    /// This method deletes the Vector Store. The files it contains are not deleted.
+    ///
+    pub async fn delete_vector_store(&self, vector_store_id: &VectorStoreId) -> Result<()> {
+        let vector_store_resource = OpenAIAssistantResource::VectorStore {
+            vector_store_id: vector_store_id.0.clone(),
+        };
+        let request = Client::new().delete(self.get_endpoint(&vector_store_resource));
+        let response: OpenAIVectorStoreDeleteResp =
+            self.send(request, "VectorStore Delete").await?;
+
+        match response.deleted {
+            true => Ok(()),
+            false => Err(anyhow!(
+                "[OpenAI][VectorStore] Unable to delete Vector Store {}",
+                vector_store_id.0
+            )),
+        }
+    }
+
+    // This function returns the version-specific url of the resource
+    fn get_endpoint(&self, resource: &OpenAIAssistantResource) -> String {
+        self.version.get_endpoint_with_url(&self.api_url, resource)
+    }
+
+    // This function sends the request with version-specific headers and deserializes the response
+    async fn send<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+        api_name: &str,
+    ) -> Result<T> {
+        let response = request
+            .headers(self.version.get_headers(&self.api_key))
+            .send()
+            .await?;
+
+        let response_status = response.status();
+        let response_text = response.text().await?;
+
+        if self.debug {
+            info!(
+                "[debug] OpenAI {} API response: [{}] {:#?}",
+                api_name,
+                &response_status,
+                redact_secrets(&response_text)
+            );
+        }
+
+        serde_json::from_str(&response_text).map_err(|error| {
+            let error = AllmsError {
+                crate_name: "allms".to_string(),
+                module: "assistants::openai_vector_store_client".to_string(),
+                error_message: format!("{} API response serialization error: {}", api_name, error),
+                error_detail: response_text,
+            };
+            error!("{:?}", error);
+            anyhow!("{:?}", error)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::{FileBatchId, FileBatchStatus, OpenAIVectorStoreClient, VectorStoreId};
+    use crate::assistants::{FileId, OpenAIAssistantVersion};
+
+    fn file_batch(status: &str) -> Value {
+        json!({
+            "id": "vsfb_123",
+            "object": "vector_store.file_batch",
+            "vector_store_id": "vs_abc123",
+            "status": status,
+            "created_at": 1699061776,
+            "file_counts": {
+                "in_progress": 0,
+                "completed": 2,
+                "failed": 0,
+                "cancelled": 0,
+                "total": 2
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_vector_store_client() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/vector_stores"))
+            .and(header("OpenAI-Beta", "assistants=v2"))
+            .and(body_partial_json(
+                json!({"name": "Docs", "file_ids": ["file-1"]}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "vs_abc123",
+                "object": "vector_store",
+                "name": "Docs",
+                "status": "in_progress",
+                "created_at": 1699061776,
+                "expires_at": null,
+                "last_active_at": 1699061776,
+                "file_counts": {
+                    "in_progress": 1,
+                    "completed": 0,
+                    "failed": 0,
+                    "cancelled": 0,
+                    "total": 1
+                }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/vector_stores/vs_abc123/file_batches"))
+            .and(header("OpenAI-Beta", "assistants=v2"))
+            .and(body_partial_json(json!({"file_ids": ["file-2", "file-3"]})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(file_batch("in_progress")))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1/vector_stores/vs_abc123/file_batches/vsfb_123"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(file_batch("completed")))
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/v1/vector_stores/vs_abc123"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "vs_abc123",
+                "object": "vector_store.deleted",
+                "deleted": true
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        // V1 does not support Vector Stores so the V2 endpoints are used
+        let client = OpenAIVectorStoreClient::new("key")
+            .version(OpenAIAssistantVersion::V1)
+            .api_url(&server.uri());
+
+        let vector_store_id = client
+            .create_vector_store("Docs", &[FileId("file-1".to_string())])
+            .await
+            .unwrap();
+        assert_eq!(vector_store_id, VectorStoreId("vs_abc123".to_string()));
+
+        let batch_id = client
+            .add_file_to_vector_store(
+                &vector_store_id,
+                &[FileId("file-2".to_string()), FileId("file-3".to_string())],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            batch_id,
+            FileBatchId {
+                vector_store_id: vector_store_id.clone(),
+                id: "vsfb_123".to_string(),
+            }
+        );
+
+        let status = client.poll_file_batch_status(&batch_id).await.unwrap();
+        assert_eq!(status, FileBatchStatus::Completed);
+
+        client.delete_vector_store(&vector_store_id).await.unwrap();
+    }
+}
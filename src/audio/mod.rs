@@ -0,0 +1,5 @@
+pub mod openai;
+
+pub use openai::{
+    OpenAISpeech, OpenAISpeechModels, OpenAITranscription, OpenAITranscriptionModels, OpenAIVoice,
+};
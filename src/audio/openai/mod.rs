@@ -0,0 +1,5 @@
+pub mod openai_speech;
+pub mod openai_transcription;
+
+pub use openai_speech::{OpenAISpeech, OpenAISpeechModels, OpenAIVoice};
+pub use openai_transcription::{OpenAITranscription, OpenAITranscriptionModels};
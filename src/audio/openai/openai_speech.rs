@@ -0,0 +1,234 @@
+use anyhow::{anyhow, Result};
+use log::{error, info};
+use reqwest::{header, Client, Request};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::constants::OPENAI_API_URL;
+use crate::domain::AllmsError;
+use crate::http_client::HttpClient;
+use crate::utils::get_openai_headers;
+
+// Range of the speed accepted by the Speech API
+const SPEED_RANGE: (f32, f32) = (0.25, 4.0);
+
+/// [OpenAI Docs](https://platform.openai.com/docs/api-reference/audio/createSpeech)
+///
+/// The Speech API generates audio from the input text.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OpenAISpeech {
+    model: OpenAISpeechModels,
+    api_key: String,
+    voice: OpenAIVoice,
+    response_format: Option<String>,
+    speed: Option<f32>,
+    debug: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq, Hash)]
+pub enum OpenAISpeechModels {
+    Tts1,
+    Tts1Hd,
+    Gpt4oMiniTts,
+}
+
+impl OpenAISpeechModels {
+    pub fn as_str(&self) -> &str {
+        match self {
+            OpenAISpeechModels::Tts1 => "tts-1",
+            OpenAISpeechModels::Tts1Hd => "tts-1-hd",
+            OpenAISpeechModels::Gpt4oMiniTts => "gpt-4o-mini-tts",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OpenAIVoice {
+    Alloy,
+    Echo,
+    Fable,
+    Onyx,
+    Nova,
+    Shimmer,
+}
+
+impl OpenAISpeech {
+    /// Constructor
+    pub fn new(model: OpenAISpeechModels, open_ai_key: &str) -> Self {
+        OpenAISpeech {
+            model,
+            api_key: open_ai_key.to_string(),
+            voice: OpenAIVoice::Alloy,
+            response_format: None,
+            speed: None,
+            debug: false,
+        }
+    }
+
+    ///
+    /// This method can be used to turn on debug mode
+    ///
+    pub fn debug(mut self) -> Self {
+        self.debug = true;
+        self
+    }
+
+    ///
+    /// This method can be used to set the voice used to generate the audio
+    /// Default is `Alloy`
+    ///
+    pub fn voice(mut self, voice: OpenAIVoice) -> Self {
+        self.voice = voice;
+        self
+    }
+
+    ///
+    /// This method can be used to set the format of the audio, e.g. `mp3`, `opus`, `aac`, `flac`, `wav` or `pcm`
+    /// Default is `mp3`
+    ///
+    pub fn response_format(mut self, response_format: &str) -> Self {
+        self.response_format = Some(response_format.to_string());
+        self
+    }
+
+    ///
+    /// This method can be used to set the speed of the generated audio. Values range from 0.25 to 4.0
+    /// Out-of-range values are rejected by `generate` before the API is called
+    ///
+    pub fn speed(mut self, speed: f32) -> Self {
+        self.speed = Some(speed);
+        self
+    }
+
+    ///
+    /// This method can be used to set the speed of the generated audio
+    /// The speed is validated against the 0.25-4.0 range accepted by the API
+    ///
+    pub fn try_speed(self, speed: f32) -> Result<Self> {
+        validate_speed(speed)?;
+        Ok(self.speed(speed))
+    }
+
+    ///
+    /// This method generates audio for the provided text and returns the raw audio bytes
+    ///
+    pub async fn generate(&self, text: &str) -> Result<Vec<u8>> {
+        //Make the API call
+        let client = Client::new();
+        let request = self.build_request(&client, text)?;
+
+        let response = client.execute(request).await?;
+        let response_status = response.status();
+
+        if !response_status.is_success() {
+            let response_text = response.text().await?;
+            let error = AllmsError {
+                crate_name: "allms".to_string(),
+                module: "audio::openai_speech".to_string(),
+                error_message: format!("Speech API error: {}", response_status),
+                error_detail: response_text,
+            };
+            error!("{:?}", error);
+            return Err(anyhow!("{:?}", error));
+        }
+
+        let audio_bytes = response.bytes().await?;
+
+        if self.debug {
+            info!(
+                "[allms][OpenAI][Speech][debug] Speech API response: [{}] {} bytes",
+                &response_status,
+                audio_bytes.len()
+            );
+        }
+
+        Ok(audio_bytes.to_vec())
+    }
+
+    // This function prepares the Speech API request
+    fn build_request(&self, client: &Client, text: &str) -> Result<Request> {
+        let speech_url = format!(
+            "{OPENAI_API_URL}/v1/audio/speech",
+            OPENAI_API_URL = *OPENAI_API_URL
+        );
+
+        let mut body = json!({
+            "model": self.model.as_str(),
+            "input": text,
+            "voice": self.voice,
+        });
+        if let Some(response_format) = &self.response_format {
+            body["response_format"] = json!(response_format);
+        }
+        if let Some(speed) = self.speed {
+            validate_speed(speed)?;
+            body["speed"] = json!(speed);
+        }
+
+        Ok(client
+            .post(speech_url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .headers(get_openai_headers(&HttpClient::default()))
+            .bearer_auth(&self.api_key)
+            .json(&body)
+            .build()?)
+    }
+}
+
+fn validate_speed(speed: f32) -> Result<()> {
+    let (min, max) = SPEED_RANGE;
+    if (min..=max).contains(&speed) {
+        return Ok(());
+    }
+
+    let error = AllmsError {
+        crate_name: "allms".to_string(),
+        module: "audio::openai_speech".to_string(),
+        error_message: "Speed is outside of the range accepted by the Speech API".to_string(),
+        error_detail: format!("Speed {} is not within {}-{}", speed, min, max),
+    };
+    error!("{:?}", error);
+    Err(anyhow!("{:?}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_speech_request_voice() {
+        let speech = OpenAISpeech::new(OpenAISpeechModels::Tts1, "key")
+            .voice(OpenAIVoice::Nova)
+            .response_format("wav");
+
+        let request = speech.build_request(&Client::new(), "Hello!").unwrap();
+        let body: serde_json::Value = serde_json::from_slice(
+            request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .unwrap_or_default(),
+        )
+        .unwrap();
+
+        assert!(request.url().as_str().ends_with("/v1/audio/speech"));
+        assert_eq!(body["model"], "tts-1");
+        assert_eq!(body["input"], "Hello!");
+        assert_eq!(body["voice"], "nova");
+        assert_eq!(body["response_format"], "wav");
+        assert!(body.get("speed").is_none());
+    }
+
+    #[test]
+    fn test_speech_speed_validation() {
+        let speech = OpenAISpeech::new(OpenAISpeechModels::Tts1Hd, "key");
+
+        assert!(speech.clone().try_speed(0.25).is_ok());
+        assert!(speech.clone().try_speed(4.0).is_ok());
+        assert!(speech.clone().try_speed(0.1).is_err());
+        assert!(speech.clone().try_speed(f32::NAN).is_err());
+
+        let speech = speech.speed(5.0);
+        assert!(speech.build_request(&Client::new(), "Hello!").is_err());
+    }
+}
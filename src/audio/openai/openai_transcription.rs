@@ -0,0 +1,260 @@
+use anyhow::{anyhow, Result};
+use log::{error, info};
+use reqwest::{multipart, Client, Request};
+use serde::{Deserialize, Serialize};
+
+use crate::constants::OPENAI_API_URL;
+use crate::domain::AllmsError;
+use crate::http_client::HttpClient;
+use crate::utils::{get_openai_headers, redact_secrets};
+
+/// [OpenAI Docs](https://platform.openai.com/docs/api-reference/audio/createTranscription)
+///
+/// The Transcriptions API transcribes audio into the input language.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OpenAITranscription {
+    model: OpenAITranscriptionModels,
+    api_key: String,
+    language: Option<String>,
+    prompt: Option<String>,
+    response_format: Option<String>,
+    mime_type: Option<String>,
+    debug: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq, Hash)]
+pub enum OpenAITranscriptionModels {
+    Whisper1,
+    Gpt4oTranscribe,
+    Gpt4oMiniTranscribe,
+}
+
+impl OpenAITranscriptionModels {
+    pub fn as_str(&self) -> &str {
+        match self {
+            OpenAITranscriptionModels::Whisper1 => "whisper-1",
+            OpenAITranscriptionModels::Gpt4oTranscribe => "gpt-4o-transcribe",
+            OpenAITranscriptionModels::Gpt4oMiniTranscribe => "gpt-4o-mini-transcribe",
+        }
+    }
+}
+
+impl OpenAITranscription {
+    /// Constructor
+    pub fn new(model: OpenAITranscriptionModels, open_ai_key: &str) -> Self {
+        OpenAITranscription {
+            model,
+            api_key: open_ai_key.to_string(),
+            language: None,
+            prompt: None,
+            response_format: None,
+            mime_type: None,
+            debug: false,
+        }
+    }
+
+    ///
+    /// This method can be used to turn on debug mode
+    ///
+    pub fn debug(mut self) -> Self {
+        self.debug = true;
+        self
+    }
+
+    ///
+    /// This method can be used to set the language of the input audio in ISO-639-1 format, e.g. `en`
+    ///
+    pub fn language(mut self, language: &str) -> Self {
+        self.language = Some(language.to_string());
+        self
+    }
+
+    ///
+    /// This method can be used to provide a text guiding the model's style or continuing a previous audio segment
+    ///
+    pub fn prompt(mut self, prompt: &str) -> Self {
+        self.prompt = Some(prompt.to_string());
+        self
+    }
+
+    ///
+    /// This method can be used to set the format of the transcript, e.g. `json`, `text`, `srt` or `vtt`
+    /// Default is `json`. Subtitle formats (`srt`, `vtt`) are supported by `whisper-1` only
+    ///
+    pub fn response_format(mut self, response_format: &str) -> Self {
+        self.response_format = Some(response_format.to_string());
+        self
+    }
+
+    ///
+    /// This method can be used to set the MIME type of the audio, e.g. `audio/mpeg` or `audio/wav`
+    /// If not set the format is detected by the API based on the file name
+    ///
+    pub fn mime_type(mut self, mime_type: &str) -> Self {
+        self.mime_type = Some(mime_type.to_string());
+        self
+    }
+
+    ///
+    /// This method transcribes the provided audio file and returns the text
+    /// For `json` and `verbose_json` response formats the text of the transcript is returned, for other formats the response as-is
+    ///
+    pub async fn transcribe(&self, file_name: &str, audio_bytes: Vec<u8>) -> Result<String> {
+        //Make the API call
+        let client = Client::new();
+        let request = self.build_request(&client, file_name, audio_bytes)?;
+
+        let response = client.execute(request).await?;
+
+        let response_status = response.status();
+        let response_text = response.text().await?;
+
+        if self.debug {
+            info!(
+                "[allms][OpenAI][Transcriptions][debug] Transcriptions API response: [{}] {:#?}",
+                &response_status,
+                redact_secrets(&response_text)
+            );
+        }
+
+        if !response_status.is_success() {
+            let error = AllmsError {
+                crate_name: "allms".to_string(),
+                module: "audio::openai_transcription".to_string(),
+                error_message: format!("Transcriptions API error: {}", response_status),
+                error_detail: response_text,
+            };
+            error!("{:?}", error);
+            return Err(anyhow!("{:?}", error));
+        }
+
+        match self.get_response_format() {
+            "json" | "verbose_json" => parse_transcription_response(&response_text),
+            _ => Ok(response_text),
+        }
+    }
+
+    // This function returns the requested response format, `json` by default
+    fn get_response_format(&self) -> &str {
+        self.response_format.as_deref().unwrap_or("json")
+    }
+
+    // This function prepares the Transcriptions API request
+    // This API sends a form so content type (including the boundary) is set by multipart method
+    fn build_request(
+        &self,
+        client: &Client,
+        file_name: &str,
+        audio_bytes: Vec<u8>,
+    ) -> Result<Request> {
+        let transcriptions_url = format!(
+            "{OPENAI_API_URL}/v1/audio/transcriptions",
+            OPENAI_API_URL = *OPENAI_API_URL
+        );
+
+        let mut file = multipart::Part::bytes(audio_bytes).file_name(file_name.to_string());
+        if let Some(mime_type) = &self.mime_type {
+            file = file.mime_str(mime_type)?;
+        }
+
+        let mut form = multipart::Form::new()
+            .text("model", self.model.as_str().to_string())
+            .text("response_format", self.get_response_format().to_string())
+            .part("file", file);
+        if let Some(language) = &self.language {
+            form = form.text("language", language.to_string());
+        }
+        if let Some(prompt) = &self.prompt {
+            form = form.text("prompt", prompt.to_string());
+        }
+
+        Ok(client
+            .post(transcriptions_url)
+            .headers(get_openai_headers(&HttpClient::default()))
+            .bearer_auth(&self.api_key)
+            .multipart(form)
+            .build()?)
+    }
+}
+
+/******************************************************************************************
+*
+* API Response serialization / deserialization structs
+*
+******************************************************************************************/
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct OpenAITranscriptionResp {
+    text: String,
+}
+
+fn parse_transcription_response(response_text: &str) -> Result<String> {
+    serde_json::from_str::<OpenAITranscriptionResp>(response_text)
+        .map(|response| response.text)
+        .map_err(|error| {
+            let error = AllmsError {
+                crate_name: "allms".to_string(),
+                module: "audio::openai_transcription".to_string(),
+                error_message: format!(
+                    "Transcriptions API response serialization error: {}",
+                    error
+                ),
+                error_detail: response_text.to_string(),
+            };
+            error!("{:?}", error);
+            anyhow!("{:?}", error)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header;
+
+    use super::*;
+
+    #[test]
+    fn test_transcription_request_is_multipart() {
+        let transcription =
+            OpenAITranscription::new(OpenAITranscriptionModels::Whisper1, "key").language("en");
+
+        let request = transcription
+            .build_request(&Client::new(), "speech.mp3", vec![0u8; 16])
+            .unwrap();
+        let content_type = request
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+
+        assert!(request.url().as_str().ends_with("/v1/audio/transcriptions"));
+        assert!(content_type.starts_with("multipart/form-data; boundary="));
+        assert!(content_type.len() > "multipart/form-data; boundary=".len());
+    }
+
+    #[test]
+    fn test_parse_transcription_response() {
+        let response = r#"{"text": "Imagine the wildest idea that you've ever had."}"#;
+
+        assert_eq!(
+            parse_transcription_response(response).unwrap(),
+            "Imagine the wildest idea that you've ever had."
+        );
+        assert!(parse_transcription_response(r#"{"error": {}}"#).is_err());
+    }
+
+    #[test]
+    fn test_transcription_response_format_and_mime_type() {
+        let transcription = OpenAITranscription::new(OpenAITranscriptionModels::Whisper1, "key");
+        assert_eq!(transcription.get_response_format(), "json");
+
+        let transcription = transcription.response_format("srt").mime_type("audio/mpeg");
+        assert_eq!(transcription.get_response_format(), "srt");
+        assert!(transcription
+            .build_request(&Client::new(), "speech.mp3", vec![0u8; 16])
+            .is_ok());
+
+        let transcription = transcription.mime_type("not a mime type");
+        assert!(transcription
+            .build_request(&Client::new(), "speech.mp3", vec![0u8; 16])
+            .is_err());
+    }
+}
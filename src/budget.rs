@@ -0,0 +1,148 @@
+use std::sync::{Arc, Mutex};
+
+use crate::domain::{BudgetExceeded, CompletionsUsage, TokenCost};
+
+/// Running total of the spend of API calls in USD, with an optional budget.
+///
+/// The tracker is cheap to clone and all clones share the same total, so a single tracker can be attached to any number of requests and checked or reset by the caller.
+/// Once the recorded spend reaches the budget, further requests fail with a `BudgetExceeded` error before they are sent.
+/// The cost of each call is calculated from the token usage reported in its response, or its upper bound if not reported, and `LLMModel::cost_per_1k_tokens`.
+/// Requests to models without prices fail when a budget is set, as it can't be enforced.
+#[derive(Debug, Clone, Default)]
+pub struct BudgetTracker {
+    state: Arc<Mutex<BudgetState>>,
+}
+
+#[derive(Debug, Default)]
+struct BudgetState {
+    spent: f64,
+    budget: Option<f64>,
+}
+
+impl BudgetTracker {
+    /// Constructor of a tracker without a budget, only recording the spend
+    pub fn new() -> Self {
+        BudgetTracker::default()
+    }
+
+    /// Constructor of a tracker with a budget in USD
+    pub fn with_budget(budget: f64) -> Self {
+        let tracker = BudgetTracker::default();
+        tracker.set_budget(Some(budget));
+        tracker
+    }
+
+    ///
+    /// This method sets or removes the budget in USD
+    ///
+    pub fn set_budget(&self, budget: Option<f64>) {
+        self.state().budget = budget;
+    }
+
+    ///
+    /// This method returns the budget in USD, if set
+    ///
+    pub fn budget(&self) -> Option<f64> {
+        self.state().budget
+    }
+
+    ///
+    /// This method returns the total spend recorded in USD
+    ///
+    pub fn spent(&self) -> f64 {
+        self.state().spent
+    }
+
+    ///
+    /// This method returns how much of the budget remains in USD, if a budget is set
+    ///
+    pub fn remaining(&self) -> Option<f64> {
+        let state = self.state();
+        state.budget.map(|budget| (budget - state.spent).max(0.0))
+    }
+
+    ///
+    /// This method returns a `BudgetExceeded` error if the recorded spend reached the budget
+    ///
+    pub fn check(&self) -> Result<(), BudgetExceeded> {
+        let state = self.state();
+        match state.budget {
+            Some(budget) if state.spent >= budget => Err(BudgetExceeded {
+                spent: state.spent,
+                budget,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    ///
+    /// This method adds the cost in USD to the recorded spend, e.g. of calls made outside of the crate
+    ///
+    pub fn record(&self, cost: f64) {
+        self.state().spent += cost;
+    }
+
+    ///
+    /// This method adds the cost of the token usage of a call at the provided prices and returns it
+    ///
+    pub fn record_usage(&self, usage: &CompletionsUsage, cost: &TokenCost) -> f64 {
+        let cost = cost.get_cost(usage);
+        self.record(cost);
+        cost
+    }
+
+    ///
+    /// This method resets the recorded spend to zero. The budget is kept.
+    ///
+    pub fn reset(&self) {
+        self.state().spent = 0.0;
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, BudgetState> {
+        self.state.lock().unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::{CompletionsUsage, TokenCost};
+
+    use super::BudgetTracker;
+
+    #[test]
+    fn test_budget_tracker() {
+        let tracker = BudgetTracker::with_budget(0.05);
+        let usage = CompletionsUsage {
+            input_tokens: 2_000,
+            output_tokens: 1_000,
+            ..Default::default()
+        };
+        let cost = TokenCost {
+            input: 0.0025,
+            output: 0.01,
+        };
+
+        assert!((tracker.record_usage(&usage, &cost) - 0.015).abs() < 1e-12);
+        // Clones share the same total
+        tracker.clone().record_usage(&usage, &cost);
+        assert!((tracker.spent() - 0.03).abs() < 1e-12);
+        assert!((tracker.remaining().unwrap() - 0.02).abs() < 1e-12);
+        assert!(tracker.check().is_ok());
+
+        tracker.record(0.02);
+        let exceeded = tracker.check().unwrap_err();
+        assert_eq!(exceeded.budget, 0.05);
+        assert_eq!(tracker.remaining(), Some(0.0));
+
+        tracker.reset();
+        assert_eq!(tracker.spent(), 0.0);
+        assert_eq!(tracker.budget(), Some(0.05));
+        assert!(tracker.check().is_ok());
+
+        // Without a budget the spend is only recorded
+        let tracker = BudgetTracker::new();
+        tracker.record(100.0);
+        assert!(tracker.check().is_ok());
+        assert_eq!(tracker.remaining(), None);
+    }
+}
@@ -0,0 +1,192 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use crate::utils::to_canonical_string;
+
+///This trait defines a storage for raw API responses that can be attached to `Completions` to avoid repeated calls with identical requests
+///Implement it to back the cache with an external store (e.g. Redis)
+#[async_trait(?Send)]
+pub trait ResponseCache {
+    ///Returns the cached response for the key if one exists
+    async fn get(&self, key: &str) -> Result<Option<String>>;
+    ///Stores the response under the key
+    async fn set(&self, key: &str, response: &str) -> Result<()>;
+}
+
+///Builds the cache key for a request. The key is based on the model ID (see `LLMModel::id`) and the fully-resolved body (including temperature, schema, etc.)
+///The body is serialized with sorted keys and hashed with SHA-256 so that identical requests produce the same key across processes and crate versions
+pub fn cache_key(model: &str, body: &Value) -> String {
+    let body_hash = Sha256::digest(to_canonical_string(body).as_bytes());
+    let body_hash: String = body_hash
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("{}:{}", model, body_hash)
+}
+
+///In-memory cache evicting the least recently used responses once the capacity is reached
+pub struct InMemoryLruCache {
+    capacity: usize,
+    entries: Mutex<LruEntries>,
+}
+
+#[derive(Default)]
+struct LruEntries {
+    values: HashMap<String, String>,
+    //Keys ordered from least to most recently used
+    order: VecDeque<String>,
+}
+
+impl LruEntries {
+    fn touch(&mut self, key: &str) {
+        if let Some(position) = self.order.iter().position(|item| item == key) {
+            self.order.remove(position);
+        }
+        self.order.push_back(key.to_string());
+    }
+}
+
+impl InMemoryLruCache {
+    /// Constructor
+    pub fn new(capacity: usize) -> Self {
+        InMemoryLruCache {
+            capacity: capacity.max(1),
+            entries: Mutex::new(LruEntries::default()),
+        }
+    }
+
+    ///Returns the number of cached responses
+    pub fn len(&self) -> usize {
+        self.entries
+            .lock()
+            .map(|entries| entries.values.len())
+            .unwrap_or_default()
+    }
+
+    ///Returns true if no responses are cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[async_trait(?Send)]
+impl ResponseCache for InMemoryLruCache {
+    async fn get(&self, key: &str) -> Result<Option<String>> {
+        let mut entries = self
+            .entries
+            .lock()
+            .map_err(|_| anyhow!("[allms][Cache] Cache lock poisoned"))?;
+        let response = entries.values.get(key).cloned();
+        if response.is_some() {
+            entries.touch(key);
+        }
+        Ok(response)
+    }
+
+    async fn set(&self, key: &str, response: &str) -> Result<()> {
+        let mut entries = self
+            .entries
+            .lock()
+            .map_err(|_| anyhow!("[allms][Cache] Cache lock poisoned"))?;
+        entries.values.insert(key.to_string(), response.to_string());
+        entries.touch(key);
+
+        //Evict least recently used responses
+        while entries.values.len() > self.capacity {
+            if let Some(oldest_key) = entries.order.pop_front() {
+                entries.values.remove(&oldest_key);
+            } else {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::cache::{cache_key, InMemoryLruCache, ResponseCache};
+    use crate::domain::CompletionsOptions;
+    use crate::llm_models::{LLMModel, OpenAIModels};
+
+    #[test]
+    fn test_cache_key_depends_on_temperature() {
+        let model = OpenAIModels::Gpt4o;
+        let schema = json!({"type": "object"});
+        let options = CompletionsOptions::default();
+        let body_a = model.get_body("instructions", &schema, false, &1000, &0.0, &options);
+        let body_b = model.get_body("instructions", &schema, false, &1000, &0.0, &options);
+        let body_c = model.get_body("instructions", &schema, false, &1000, &0.5, &options);
+
+        assert_eq!(
+            cache_key(model.as_str(), &body_a),
+            cache_key(model.as_str(), &body_b)
+        );
+        assert_ne!(
+            cache_key(model.as_str(), &body_a),
+            cache_key(model.as_str(), &body_c)
+        );
+    }
+
+    #[test]
+    fn test_cache_key_is_stable() {
+        // Persistent caches rely on the key not changing between runs
+        let body = json!({"temperature": 0.0, "messages": [{"role": "user", "content": "hi"}]});
+        let reordered_body =
+            json!({"messages": [{"content": "hi", "role": "user"}], "temperature": 0.0});
+
+        assert_eq!(
+            cache_key("gpt-4o", &body),
+            "gpt-4o:e54061badcc1ef782b9287ba2c7d13b0d425af56bd0c1bbbb15c9f85cb5d8ab1"
+        );
+        assert_eq!(
+            cache_key("gpt-4o", &body),
+            cache_key("gpt-4o", &reordered_body)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lru_cache_hit_and_miss() {
+        let model = OpenAIModels::Gpt4o;
+        let schema = json!({"type": "object"});
+        let options = CompletionsOptions::default();
+        let cache = InMemoryLruCache::new(10);
+
+        let body = model.get_body("instructions", &schema, false, &1000, &0.0, &options);
+        cache
+            .set(&cache_key(model.as_str(), &body), "response")
+            .await
+            .unwrap();
+
+        let same_body = model.get_body("instructions", &schema, false, &1000, &0.0, &options);
+        let hit = cache.get(&cache_key(model.as_str(), &same_body)).await;
+        assert_eq!(hit.unwrap(), Some("response".to_string()));
+
+        let other_temp_body = model.get_body("instructions", &schema, false, &1000, &0.5, &options);
+        let miss = cache
+            .get(&cache_key(model.as_str(), &other_temp_body))
+            .await;
+        assert_eq!(miss.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_lru_cache_evicts_least_recently_used() {
+        let cache = InMemoryLruCache::new(2);
+        cache.set("a", "1").await.unwrap();
+        cache.set("b", "2").await.unwrap();
+        // Using `a` makes `b` the least recently used entry
+        assert!(cache.get("a").await.unwrap().is_some());
+        cache.set("c", "3").await.unwrap();
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("a").await.unwrap().is_some());
+        assert!(cache.get("b").await.unwrap().is_none());
+        assert!(cache.get("c").await.unwrap().is_some());
+    }
+}
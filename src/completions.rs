@@ -1,24 +1,56 @@
 use anyhow::{anyhow, Result};
+use futures::future::try_join_all;
 use log::{error, info, warn};
+use reqwest::Client;
 use schemars::JsonSchema;
 use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Instant;
 
-use crate::domain::{AllmsError, OpenAIDataResponse};
-use crate::llm_models::LLMModel;
-use crate::utils::{get_tokenizer, get_type_schema};
+use crate::budget::BudgetTracker;
+use crate::cache::{cache_key, ResponseCache};
+use crate::constants::PLAIN_TEXT_BASE_INSTRUCTIONS;
+use crate::domain::{
+    AllmsError, ApiResponse, CompletionsOptions, CompletionsRequest, CompletionsResponse,
+    CompletionsUsage, ContextLengthExceeded, Document, DryRun, EmptyResponse, FileData,
+    FinishReason, GroundingTool, Message, OpenAIDataResponse, OutputStrictness, Temperature,
+    TemperaturePolicy, TruncationPolicy,
+};
+use crate::hooks::{RequestEvent, RequestHooks, ResponseEvent};
+use crate::http_client::HttpClient;
+use crate::llm_models::{GeminiProVertexConfig, LLMModel, TextStream};
+use crate::utils::{
+    get_tokenizer, get_type_schema, redact_secrets, repair_json, schema_to_description,
+    truncate_tokens, validate_json_schema, validate_output,
+};
 
 /// Completions APIs take a list of messages as input and return a model-generated message as output.
 /// Although the Completions format is designed to make multi-turn conversations easy,
 /// it’s just as useful for single-turn tasks without any conversation.
+#[derive(Clone)]
 pub struct Completions<T: LLMModel> {
     model: T,
     //For prompt & response
     max_tokens: usize,
     temperature: f32,
+    temperature_policy: TemperaturePolicy,
+    truncation_policy: TruncationPolicy,
     input_json: Option<String>,
     debug: bool,
+    dry_run: bool,
     function_call: bool,
     api_key: String,
+    cache: Option<Arc<dyn ResponseCache>>,
+    validate_output: bool,
+    parse_retries: usize,
+    correct_invalid_json: bool,
+    max_continuations: usize,
+    hooks: RequestHooks,
+    http_client: HttpClient,
+    budget: Option<BudgetTracker>,
+    vertex_config: Option<GeminiProVertexConfig>,
+    options: CompletionsOptions,
 }
 
 impl<T: LLMModel> Completions<T> {
@@ -38,12 +70,78 @@ impl<T: LLMModel> Completions<T> {
             function_call: model.function_call_default(),
             model,
             temperature,
+            temperature_policy: TemperaturePolicy::default(),
+            truncation_policy: TruncationPolicy::default(),
             input_json: None,
             debug: false,
+            dry_run: false,
             api_key: api_key.to_string(),
+            cache: None,
+            validate_output: false,
+            parse_retries: 0,
+            correct_invalid_json: false,
+            max_continuations: 0,
+            hooks: RequestHooks::default(),
+            http_client: HttpClient::default(),
+            budget: None,
+            vertex_config: None,
+            options: CompletionsOptions::default(),
         }
     }
 
+    ///
+    /// Constructor restoring the settings of a persisted request
+    /// Use `get_answer` with the instructions of the request to replay it. The model needs to be of the same provider as the one used originally.
+    ///
+    pub fn from_request(request: &CompletionsRequest, api_key: &str) -> Result<Self> {
+        let model = T::try_from_str(&request.model)
+            .ok_or_else(|| anyhow!("[allms] Unknown model in request: {}", request.model))?;
+        Ok(Completions {
+            model,
+            max_tokens: request.max_tokens,
+            temperature: request.temperature,
+            temperature_policy: TemperaturePolicy::default(),
+            truncation_policy: TruncationPolicy::default(),
+            input_json: request.input_json.clone(),
+            debug: false,
+            dry_run: request.dry_run,
+            function_call: request.function_call,
+            api_key: api_key.to_string(),
+            cache: None,
+            validate_output: false,
+            parse_retries: 0,
+            correct_invalid_json: false,
+            max_continuations: 0,
+            hooks: RequestHooks::default(),
+            http_client: HttpClient::default(),
+            budget: None,
+            vertex_config: None,
+            options: request.options.clone(),
+        })
+    }
+
+    ///
+    /// This method returns a serializable snapshot of the request that would be sent for the instructions and output type.
+    /// The snapshot can be persisted and replayed later with `Completions::from_request`.
+    ///
+    pub fn to_request<U: JsonSchema + DeserializeOwned>(
+        &self,
+        instructions: &str,
+    ) -> Result<CompletionsRequest> {
+        let schema = get_type_schema::<U>()?;
+        Ok(CompletionsRequest {
+            model: self.model.id(),
+            instructions: instructions.to_string(),
+            json_schema: serde_json::from_str(&schema)?,
+            input_json: self.input_json.clone(),
+            max_tokens: self.max_tokens,
+            temperature: self.temperature,
+            function_call: self.function_call,
+            dry_run: self.dry_run,
+            options: self.options.clone(),
+        })
+    }
+
     ///
     /// This function turns on debug mode which will info! the prompt to log when executing it.
     ///
@@ -52,6 +150,15 @@ impl<T: LLMModel> Completions<T> {
         self
     }
 
+    ///
+    /// This method turns on/off dry run mode. The request body is built and validated as usual and logged with info!, but no request is sent.
+    /// All methods sending a request, including `get_text` and `stream_tokens`, return a `DryRun` error carrying the body instead.
+    ///
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
     ///
     /// This function turns on/off function calling mode when interacting with OpenAI API.
     ///
@@ -60,6 +167,270 @@ impl<T: LLMModel> Completions<T> {
         self
     }
 
+    ///
+    /// This method can be used to replace the default base (system) instructions sent to the model with each prompt
+    ///
+    pub fn base_instructions(mut self, base_instructions: &str) -> Self {
+        self.options.custom_base_instructions = Some(base_instructions.to_string());
+        self
+    }
+
+    ///
+    /// This method sets the previous turns of the conversation. They are sent to the model in order, ahead of the instructions passed to `get_answer`.
+    ///
+    pub fn history(mut self, history: &[Message]) -> Self {
+        self.options.history = history.to_vec();
+        self
+    }
+
+    ///
+    /// This method sets labelled examples (input, expected output) for in-context learning. They are sent as user/assistant turns ahead of the history.
+    /// Examples that do not fit in the tokens left after the prompt are dropped, starting with the last one.
+    ///
+    pub fn few_shot_examples(mut self, examples: &[(&str, &str)]) -> Self {
+        self.options.few_shot_examples = examples
+            .iter()
+            .map(|(input, output)| (input.to_string(), output.to_string()))
+            .collect();
+        self
+    }
+
+    ///
+    /// This method requests log probabilities of the response tokens, each with up to `top_logprobs` most likely alternatives (0-20).
+    /// Only supported by models for which `supports_logprobs` returns true. The probabilities are returned in `CompletionsResponse::logprobs`.
+    ///
+    pub fn logprobs(mut self, top_logprobs: u8) -> Self {
+        self.options.logprobs = Some(top_logprobs);
+        self
+    }
+
+    ///
+    /// This method sets the ID of the end-user on whose behalf the request is sent, e.g. a hashed user ID in multi-tenant applications.
+    /// It is sent as the `user` field of OpenAI requests to help OpenAI detect abuse, and ignored by other providers.
+    ///
+    pub fn end_user_id(mut self, end_user_id: &str) -> Self {
+        self.options.end_user_id = Some(end_user_id.to_string());
+        self
+    }
+
+    ///
+    /// This method sets a callback invoked before each API call with the model and the size of the request body.
+    /// Together with `on_response` it can be used to push metrics to any sink without depending on a logging framework.
+    ///
+    pub fn on_request<F: Fn(&RequestEvent) + 'static>(mut self, on_request: F) -> Self {
+        self.hooks.on_request = Some(Arc::new(on_request));
+        self
+    }
+
+    ///
+    /// This method sets a callback invoked after each API call with the model, the size of the request body, the error if the call failed, the latency and the token usage.
+    /// Responses read from the cache do not trigger the callbacks.
+    ///
+    pub fn on_response<F: Fn(&ResponseEvent) + 'static>(mut self, on_response: F) -> Self {
+        self.hooks.on_response = Some(Arc::new(on_response));
+        self
+    }
+
+    ///
+    /// This method sets the tracker recording the cost of each API call, based on the reported token usage and `cost_per_1k_tokens` of the model.
+    /// Once the spend recorded by the tracker reaches its budget, API calls fail with a `BudgetExceeded` error instead of being sent. Streamed responses are not tracked.
+    ///
+    pub fn budget(mut self, budget: BudgetTracker) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    ///
+    /// This method sets the HTTP client used to send the requests so that its connections are reused across calls
+    /// By default a client shared by all `Completions` is used. `LLMClient` shares a client built with its settings between all of its requests.
+    ///
+    pub fn http_client(mut self, client: Client) -> Self {
+        self.http_client = self.http_client.client(client);
+        self
+    }
+
+    ///
+    /// This method sets the Google Vertex AI config used to authenticate the requests instead of the API key
+    /// OAuth2 access tokens are obtained with the config before each call and reused until they are about to expire.
+    ///
+    pub fn vertex_config(mut self, config: GeminiProVertexConfig) -> Self {
+        self.vertex_config = Some(config);
+        self
+    }
+
+    ///
+    /// This method makes request bodies of at least the provided size be compressed with gzip to reduce upload latency of large prompts
+    /// Only applied for providers accepting compressed requests (OpenAI and Anthropic)
+    ///
+    pub fn compress_requests_above_bytes(mut self, bytes: usize) -> Self {
+        self.http_client = self.http_client.compress_requests_above_bytes(bytes);
+        self
+    }
+
+    ///
+    /// This method sets how many times requests rejected with 429 Too Many Requests are sent again after the wait indicated by the API
+    /// Defaults to the `RATE_LIMIT_RETRIES` environment variable, or no retries if it is not set
+    ///
+    pub fn rate_limit_retries(mut self, retries: usize) -> Self {
+        self.http_client = self.http_client.rate_limit_retries(retries);
+        self
+    }
+
+    ///
+    /// This method sets the Anthropic API version sent in the `anthropic-version` header
+    /// Defaults to the `ANTHROPIC_API_VERSION` environment variable, or `2023-06-01` if it is not set
+    ///
+    pub fn anthropic_version(mut self, version: &str) -> Self {
+        self.http_client = self.http_client.anthropic_version(version);
+        self
+    }
+
+    ///
+    /// This method enables Anthropic beta features sent in the `anthropic-beta` header, e.g. `output-128k-2025-02-19`
+    /// Defaults to the `ANTHROPIC_BETA` environment variable
+    ///
+    pub fn anthropic_beta(mut self, beta: &str) -> Self {
+        self.http_client = self.http_client.anthropic_beta(beta);
+        self
+    }
+
+    ///
+    /// This method sets the OpenAI organization sent in the `OpenAI-Organization` header
+    /// Defaults to the `OPENAI_ORG_ID` environment variable
+    ///
+    pub fn openai_organization(mut self, organization: &str) -> Self {
+        self.http_client = self.http_client.openai_organization(organization);
+        self
+    }
+
+    ///
+    /// This method sets the OpenAI project sent in the `OpenAI-Project` header, e.g. for project-scoped keys
+    /// Defaults to the `OPENAI_PROJECT_ID` environment variable
+    ///
+    pub fn openai_project(mut self, project: &str) -> Self {
+        self.http_client = self.http_client.openai_project(project);
+        self
+    }
+
+    ///
+    /// This method lets the model ground its answer in live web results, e.g. `GroundingTool { web_search: true, ..Default::default() }`.
+    /// Only supported by models for which `supports_grounding` returns true. The sources are returned in `CompletionsResponse::grounding_citations`.
+    /// Gemini models do not accept search tools together with function calling, so grounding is only sent with `function_calling(false)`.
+    ///
+    pub fn grounding(mut self, grounding: GroundingTool) -> Self {
+        self.options.grounding = Some(grounding);
+        self
+    }
+
+    ///
+    /// This method sets files uploaded to the provider (e.g. with `GeminiFile::upload`) that are sent to the model along with the instructions.
+    /// Only supported by models for which `supports_file_data` returns true.
+    ///
+    pub fn files(mut self, files: &[FileData]) -> Self {
+        self.options.files = files.to_vec();
+        self
+    }
+
+    ///
+    /// This method sets PDF documents, e.g. created with `Document::pdf`, that are sent inline to the model along with the instructions.
+    /// Only supported by models for which `supports_pdf_documents` returns true.
+    ///
+    pub fn documents(mut self, documents: &[Document]) -> Self {
+        self.options.documents = documents.to_vec();
+        self
+    }
+
+    ///
+    /// This method enables extended thinking, letting the model use up to `budget_tokens` (at least 1024 and less than the max tokens left for the response once the prompt is counted) to reason before answering.
+    /// Only supported by models for which `supports_thinking` returns true. The reasoning is returned in `CompletionsResponse::thinking`.
+    ///
+    pub fn thinking_budget_tokens(mut self, budget_tokens: u32) -> Self {
+        self.options.thinking_budget_tokens = Some(budget_tokens);
+        self
+    }
+
+    ///
+    /// This method turns on/off validation of the model output against the JSON schema of the expected type.
+    /// It catches outputs that deserialize but violate constraints serde does not enforce (e.g. hallucinated enum values caught by `#[serde(other)]`, or `range` attributes).
+    /// Violations are returned as a `SchemaMismatch` error.
+    ///
+    pub fn validate_output(mut self, validate_output: bool) -> Self {
+        self.validate_output = validate_output;
+        self
+    }
+
+    ///
+    /// This method sets how many times the request is sent again if the response cannot be deserialized into the expected type (e.g. malformed Json).
+    /// Retries always call the API, even if the first response was read from the cache. By default the request is not retried.
+    ///
+    pub fn parse_retries(mut self, parse_retries: usize) -> Self {
+        self.parse_retries = parse_retries;
+        self
+    }
+
+    ///
+    /// This method turns on/off correction of invalid Json on retry. Instead of sending the same request again, each retry sends the prompt augmented with the invalid output and the error, asking the model to correct it.
+    /// Requires `parse_retries` to be set. The number of retries is reported in `CompletionsResponse::retries`.
+    ///
+    pub fn correct_invalid_json(mut self, correct_invalid_json: bool) -> Self {
+        self.correct_invalid_json = correct_invalid_json;
+        self
+    }
+
+    ///
+    /// This method turns on/off prepending a natural-language description of the output schema to the instructions, e.g. `name (string, required)`.
+    /// It helps models that do not fully follow complex schemas. See `schema_to_description`.
+    ///
+    pub fn prepend_schema_description(mut self, prepend_schema_description: bool) -> Self {
+        self.options.prepend_schema_description = prepend_schema_description;
+        self
+    }
+
+    ///
+    /// This method turns on/off streaming the API response, which is aggregated into a single response, e.g. to avoid gateway timeouts for long outputs.
+    /// Defaults to the `ANTHROPIC_STREAMING` environment variable for Anthropic models and `GOOGLE_VERTEX_STREAMING` for Vertex AI models.
+    ///
+    pub fn streaming(mut self, streaming: bool) -> Self {
+        self.options.streaming = Some(streaming);
+        self
+    }
+
+    ///
+    /// This method sets how strictly the base instructions ask the model to format its Json output.
+    /// `OutputStrictness::Strict` asks for minified Json only, `OutputStrictness::Lenient` allows markdown code fences. Ignored if custom base instructions are set.
+    ///
+    pub fn output_strictness(mut self, output_strictness: OutputStrictness) -> Self {
+        self.options.output_strictness = output_strictness;
+        self
+    }
+
+    ///
+    /// This method sets how many follow-up requests can be sent to continue a response cut off by the max tokens limit (`FinishReason::Length`).
+    /// The model is asked to continue where it stopped and the fragments are joined before the response is deserialized. By default truncated responses are not continued.
+    ///
+    pub fn continue_truncated(mut self, max_continuations: usize) -> Self {
+        self.max_continuations = max_continuations;
+        self
+    }
+
+    ///
+    /// This method turns on/off prompt caching of the static part of the prompt (base instructions & output schema).
+    /// Currently supported by Anthropic models only. Cache usage is reported in `CompletionsResponse::usage`.
+    ///
+    pub fn prompt_caching(mut self, prompt_caching: bool) -> Self {
+        self.options.prompt_caching = prompt_caching;
+        self
+    }
+
+    ///
+    /// This method attaches a response cache. Requests with a body identical to a previously successful one are answered from the cache without calling the API.
+    /// Only responses from which data could be extracted are cached, so failed calls are always retried against the API.
+    ///
+    pub fn cache(mut self, cache: Arc<dyn ResponseCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
     ///
     /// This method can be used to define the model temperature used by the Assistant
     /// This method accepts % target of the acceptable range for the model
@@ -69,6 +440,23 @@ impl<T: LLMModel> Completions<T> {
         self
     }
 
+    ///
+    /// This method can be used to define the model temperature used by the Assistant
+    /// The temperature is validated against the range accepted by the model, e.g. 0-2 for OpenAI or 0-1 for Anthropic
+    ///
+    pub fn try_temperature(self, temp: f32) -> Result<Self> {
+        let temperature = Temperature::new(temp, &self.model)?;
+        Ok(self.validated_temperature(temperature))
+    }
+
+    ///
+    /// This method can be used to define the model temperature used by the Assistant using a value already validated with `Temperature::new`
+    ///
+    pub fn validated_temperature(mut self, temperature: Temperature) -> Self {
+        self.temperature = temperature.value();
+        self
+    }
+
     ///
     /// This method can be used to define the model temperature used by the Assistant
     /// Using this method the temperature can be set directly without any validation of the range accepted by the model
@@ -76,9 +464,231 @@ impl<T: LLMModel> Completions<T> {
     ///
     pub fn temperature_unchecked(mut self, temp: f32) -> Self {
         self.temperature = temp;
+        self.temperature_policy = TemperaturePolicy::Unchecked;
+        self
+    }
+
+    ///
+    /// This method defines how instructions exceeding the tokens allocated for the prompt are handled before the request is sent, e.g. for summarization of long logs.
+    /// By default the request fails with a `ContextLengthExceeded` error instead of silently dropping parts of the instructions.
+    ///
+    pub fn truncation_policy(mut self, truncation_policy: TruncationPolicy) -> Self {
+        self.truncation_policy = truncation_policy;
+        self
+    }
+
+    ///
+    /// This method defines how a temperature outside of the range accepted by the model is handled before the request is sent
+    /// By default the request fails with an error instead of sending a value the API would reject
+    ///
+    pub fn temperature_policy(mut self, temperature_policy: TemperaturePolicy) -> Self {
+        self.temperature_policy = temperature_policy;
         self
     }
 
+    //This function checks that log probabilities, if requested, are supported by the model and within the accepted range
+    fn check_logprobs(&self) -> Result<()> {
+        let top_logprobs = match self.options.logprobs {
+            Some(top_logprobs) => top_logprobs,
+            None => return Ok(()),
+        };
+
+        let error_detail = if !self.model.supports_logprobs() {
+            format!("{} does not support log probabilities", self.model.as_str())
+        } else if top_logprobs > 20 {
+            format!("top_logprobs {} is not within 0-20", top_logprobs)
+        } else {
+            return Ok(());
+        };
+
+        let error = AllmsError {
+            crate_name: "allms".to_string(),
+            module: format!("assistants::completions::{}", self.model.as_str()),
+            error_message: "Invalid log probabilities request".to_string(),
+            error_detail,
+        };
+        error!("{:?}", error);
+        Err(anyhow!("{:?}", error))
+    }
+
+    // The thinking budget is checked against the tokens left for the response once the prompt is counted, as that is the max tokens sent to the API
+    fn check_model_options(&self, response_tokens: usize) -> Result<()> {
+        let error_detail = if self.options.web_search() && !self.model.supports_grounding() {
+            format!(
+                "{} does not support grounding with web search",
+                self.model.as_str()
+            )
+        } else if !self.options.files.is_empty() && !self.model.supports_file_data() {
+            format!("{} does not support files", self.model.as_str())
+        } else if !self.options.documents.is_empty() && !self.model.supports_pdf_documents() {
+            format!("{} does not support PDF documents", self.model.as_str())
+        } else if let Some(budget_tokens) = self.options.thinking_budget_tokens {
+            if !self.model.supports_thinking() {
+                format!("{} does not support extended thinking", self.model.as_str())
+            } else if budget_tokens < 1024 || budget_tokens as usize >= response_tokens {
+                format!(
+                        "Thinking budget of {} tokens needs to be at least 1024 and less than the tokens left for the response ({})",
+                        budget_tokens, response_tokens
+                    )
+            } else {
+                return Ok(());
+            }
+        } else {
+            return Ok(());
+        };
+
+        let error = AllmsError {
+            crate_name: "allms".to_string(),
+            module: format!("assistants::completions::{}", self.model.as_str()),
+            error_message: "Invalid request options".to_string(),
+            error_detail,
+        };
+        error!("{:?}", error);
+        Err(anyhow!("{:?}", error))
+    }
+
+    // If the prompt does not fit within the allocated tokens, the instructions are truncated as defined by the truncation policy
+    // The truncated prompt takes up to half of the allocated tokens, leaving the rest for the response
+    // `other_tokens` are the tokens of the prompt without the instructions, i.e. base instructions, input Json and schema
+    fn truncate_instructions(
+        &self,
+        instructions: &str,
+        prompt_tokens: usize,
+        other_tokens: usize,
+    ) -> Result<Option<String>> {
+        let max_tokens = self.max_tokens.min(self.model.context_window_tokens());
+        if self.truncation_policy == TruncationPolicy::Error || prompt_tokens < max_tokens {
+            return Ok(None);
+        }
+
+        let bpe = get_tokenizer(&self.model)?;
+        let instructions_tokens = bpe.encode_ordinary(instructions).len();
+        let budget = (max_tokens / 2).saturating_sub(other_tokens);
+        //Without room for the instructions the request fails with `ContextLengthExceeded`
+        if budget == 0 {
+            return Ok(None);
+        }
+
+        warn!(
+            "[allms] Truncating instructions from {} to {} tokens ({:?})",
+            instructions_tokens, budget, self.truncation_policy
+        );
+        Ok(Some(truncate_tokens(
+            &bpe,
+            instructions,
+            budget,
+            &self.truncation_policy,
+        )))
+    }
+
+    // Few-shot examples may use up to half of the allocated tokens (capped by the context window of the model) together with the prompt
+    // The examples that do not fit are dropped, starting with the last one. Returns the options with the remaining examples and the tokens they use
+    fn fit_few_shot_examples(&self, prompt_tokens: usize) -> Result<(CompletionsOptions, usize)> {
+        let mut options = self.options.clone();
+        if options.few_shot_examples.is_empty() {
+            return Ok((options, 0));
+        }
+
+        let bpe = get_tokenizer(&self.model)?;
+        let max_tokens = self.max_tokens.min(self.model.context_window_tokens());
+        let budget = (max_tokens / 2).saturating_sub(prompt_tokens);
+        let mut examples_tokens = 0;
+        let mut fitting_examples = 0;
+        for (input, output) in &options.few_shot_examples {
+            let tokens = bpe.encode_ordinary(input).len() + bpe.encode_ordinary(output).len();
+            if examples_tokens + tokens > budget {
+                break;
+            }
+            examples_tokens += tokens;
+            fitting_examples += 1;
+        }
+
+        if fitting_examples < options.few_shot_examples.len() {
+            warn!(
+                "[allms] Sending {} of {} few-shot examples, the others do not fit in the {} tokens left for examples",
+                fitting_examples,
+                options.few_shot_examples.len(),
+                budget
+            );
+            options.few_shot_examples.truncate(fitting_examples);
+        }
+        Ok((options, examples_tokens))
+    }
+
+    // This function builds the prompt sent to the model for the instructions and the input data
+    fn get_prompt(&self, instructions: &str, json_schema: &Value) -> String {
+        //The schema description is prepended to the instructions if requested
+        let instructions = match self.options.prepend_schema_description {
+            true => format!("{}\n\n{}", schema_to_description(json_schema), instructions),
+            false => instructions.to_string(),
+        };
+        format!(
+            "Instructions:
+            {instructions}
+
+            Input data:
+            {input_json}
+            
+            Respond ONLY with the data portion of a valid Json object. No schema definition required. No other words.", 
+            instructions = instructions,
+            input_json = self.input_json.clone().unwrap_or_default(),
+        )
+    }
+
+    // Prompt and response need to fit both within the allocated max tokens and the context window of the model
+    fn get_response_tokens(&self, prompt_tokens: usize) -> Result<usize> {
+        let context = self.model.context_window_tokens();
+        if prompt_tokens >= self.max_tokens || self.max_tokens > context {
+            let error = ContextLengthExceeded {
+                prompt: prompt_tokens,
+                max: self.max_tokens,
+                context,
+            };
+            error!("{}", error);
+            return Err(error.into());
+        }
+        Ok(self.max_tokens - prompt_tokens)
+    }
+
+    //This function applies the temperature policy to the temperature set for the request
+    fn get_checked_temperature(&self) -> Result<f32> {
+        let (min, max) = self.model.get_temperature_range();
+        if self.temperature_policy == TemperaturePolicy::Unchecked
+            || (min..=max).contains(&self.temperature)
+        {
+            return Ok(self.temperature);
+        }
+
+        match self.temperature_policy {
+            TemperaturePolicy::Clamp if !self.temperature.is_nan() => {
+                let temperature = self.temperature.clamp(min, max);
+                warn!(
+                    "[allms] Temperature {} clamped to {} for {}",
+                    self.temperature,
+                    temperature,
+                    self.model.as_str()
+                );
+                Ok(temperature)
+            }
+            _ => {
+                let error = AllmsError {
+                    crate_name: "allms".to_string(),
+                    module: format!("assistants::completions::{}", self.model.as_str()),
+                    error_message: format!(
+                        "Temperature is outside of the range accepted by {}",
+                        self.model.as_str()
+                    ),
+                    error_detail: format!(
+                        "Temperature {} is not within {}-{}",
+                        self.temperature, min, max
+                    ),
+                };
+                error!("{:?}", error);
+                Err(anyhow!("{:?}", error))
+            }
+        }
+    }
+
     ///
     /// This method can be used to provide values that will be used as context for the prompt.
     /// Using this function you can provide multiple input values by calling it multiple times. New values will be appended with the category name
@@ -112,26 +722,28 @@ impl<T: LLMModel> Completions<T> {
     pub fn check_prompt_tokens<U: JsonSchema + DeserializeOwned>(
         &self,
         instructions: &str,
+    ) -> Result<usize> {
+        let prompt_tokens = self.count_prompt_tokens::<U>(instructions)?;
+
+        //Assuming another 5% overhead for json formatting
+        Ok((prompt_tokens as f64 * 1.05) as usize)
+    }
+
+    // This function counts the tokens of the prompt built for the instructions, including the base instructions and the schema
+    fn count_prompt_tokens<U: JsonSchema + DeserializeOwned>(
+        &self,
+        instructions: &str,
     ) -> Result<usize> {
         //Output schema is extracted from the type parameter
         let schema = get_type_schema::<U>()?;
 
-        let prompt = format!(
-            "Instructions:
-            {instructions}
-
-            Input data:
-            {input_json}
-            
-            Respond ONLY with the data portion of a valid Json object. No schema definition required. No other words.", 
-            instructions = instructions,
-            input_json = self.input_json.clone().unwrap_or_default(),
-        );
+        let prompt = self.get_prompt(instructions, &serde_json::from_str(&schema)?);
 
         let full_prompt = format!(
             "{}{}{}",
             //Base (system) instructions
-            self.model.get_base_instructions(Some(self.function_call)),
+            self.model
+                .get_body_base_instructions(self.function_call, &self.options),
             //Instructions & context data
             prompt,
             //Output schema
@@ -139,11 +751,34 @@ impl<T: LLMModel> Completions<T> {
         );
 
         //Check how many tokens are required for prompt
+        self.count_text_tokens(&full_prompt)
+    }
+
+    // This function counts the tokens of the text with the tokenizer of the model
+    fn count_text_tokens(&self, text: &str) -> Result<usize> {
         let bpe = get_tokenizer(&self.model)?;
-        let prompt_tokens = bpe.encode_with_special_tokens(&full_prompt).len();
+        Ok(bpe.encode_with_special_tokens(text).len())
+    }
 
-        //Assuming another 5% overhead for json formatting
-        Ok((prompt_tokens as f64 * 1.05) as usize)
+    ///
+    /// This method returns an upper-bound estimate of the cost of the request in USD, e.g. to reject requests exceeding a budget before sending them.
+    /// The estimated prompt tokens are priced as input and all tokens remaining for the response within `max_tokens` as output, using `cost_per_1k_tokens` of the model.
+    ///
+    pub fn estimate_cost<U: JsonSchema + DeserializeOwned>(
+        &self,
+        instructions: &str,
+    ) -> Result<f64> {
+        let cost = self.model.cost_per_1k_tokens().ok_or_else(|| {
+            anyhow!(
+                "[allms] Token prices of {} are unknown",
+                self.model.as_str()
+            )
+        })?;
+
+        let prompt_tokens = self.check_prompt_tokens::<U>(instructions)?;
+        let response_tokens = self.get_response_tokens(prompt_tokens)?;
+
+        Ok((prompt_tokens as f64 * cost.input + response_tokens as f64 * cost.output) / 1000.0)
     }
 
     ///
@@ -155,52 +790,287 @@ impl<T: LLMModel> Completions<T> {
         self,
         instructions: &str,
     ) -> Result<U> {
+        self.get_response::<U>(instructions)
+            .await
+            .map(|response| response.data)
+    }
+
+    ///
+    /// This method works the same way as `get_answer` but in addition to the deserialized data it returns metadata of the response, such as token usage.
+    ///
+    pub async fn get_response<U: JsonSchema + DeserializeOwned>(
+        self,
+        instructions: &str,
+    ) -> Result<CompletionsResponse<U>> {
+        let model = self.model.as_str().to_string();
+        let response = self.get_choices_response::<U>(instructions).await?;
+        let data = response
+            .data
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("[allms] No completion returned by {}", model))?;
+        Ok(CompletionsResponse {
+            data,
+            usage: response.usage,
+            citations: response.citations,
+            logprobs: response.logprobs,
+            raw_response: response.raw_response,
+            retries: response.retries,
+            finish_reason: response.finish_reason,
+            grounding_citations: response.grounding_citations,
+            thinking: response.thinking,
+            rate_limit_status: response.rate_limit_status,
+            provider: response.provider,
+        })
+    }
+
+    ///
+    /// This method sends the instructions as a plain-text prompt and returns the text of the answer without enforcing an output schema.
+    /// Unless custom base instructions are set, the Json-specific base instructions are replaced with general assistant instructions.
+    /// The request goes through the same cache as `get_answer`.
+    ///
+    pub async fn get_text(mut self, instructions: &str) -> Result<String> {
+        self.set_plain_text();
+        let model_body = self.get_text_body(instructions)?;
+
+        if self.debug {
+            info!("[debug] Model body: {:#?}", model_body);
+        }
+
+        if self.dry_run {
+            return Err(self.dry_run_error(model_body));
+        }
+
+        let (response, is_cached) = self.get_response_text(&model_body, true).await?;
+        let response_text = response.text;
+        let text = self
+            .get_response_data(&response_text)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("[allms] No completion returned by {}", self.model.as_str()))?;
+
+        if !is_cached {
+            self.cache_response(&model_body, &response_text).await;
+        }
+
+        Ok(text)
+    }
+
+    ///
+    /// This method works the same way as `get_text` but returns the text of the answer in chunks as they are generated, e.g. to forward them to a chunked HTTP response or a WebSocket.
+    /// Only supported by models for which `supports_streaming` returns true. Streamed responses are not cached.
+    ///
+    pub async fn stream_tokens(mut self, instructions: &str) -> Result<TextStream> {
+        if !self.model.supports_streaming() {
+            return Err(anyhow!(
+                "[allms] Streaming is not supported by {}",
+                self.model.as_str()
+            ));
+        }
+
+        self.set_plain_text();
+        let model_body = self.get_text_body(instructions)?;
+
+        if self.debug {
+            info!("[debug] Model body: {:#?}", model_body);
+        }
+
+        if self.dry_run {
+            return Err(self.dry_run_error(model_body));
+        }
+
+        self.stream_model_api(&model_body).await
+    }
+
+    // With the `tracing` feature enabled opening the stream is wrapped in a span recording the model, endpoint, body size and the duration until the response starts
+    // The HTTP status and token usage are not known for streamed responses so they are not recorded
+    async fn stream_model_api(&self, model_body: &Value) -> Result<TextStream> {
+        #[cfg(feature = "tracing")]
+        {
+            use tracing::{field, Instrument};
+
+            let span = tracing::info_span!(
+                "allms.stream_api",
+                model = self.model.as_str(),
+                provider = %self.model.get_provider(),
+                endpoint = self.model.get_endpoint(),
+                body_size_bytes = model_body.to_string().len(),
+                duration_ms = field::Empty,
+            );
+
+            let start = Instant::now();
+            let stream = self.open_stream(model_body).instrument(span.clone()).await;
+            span.record("duration_ms", start.elapsed().as_millis() as u64);
+            stream
+        }
+
+        #[cfg(not(feature = "tracing"))]
+        {
+            self.open_stream(model_body).await
+        }
+    }
+
+    async fn open_stream(&self, model_body: &Value) -> Result<TextStream> {
+        let api_key = self.get_api_key().await?;
+        self.model
+            .stream_api(&self.http_client, &api_key, model_body, self.debug)
+            .await
+    }
+
+    // Plain text is returned as message content rather than function arguments, and the Json-specific base instructions are replaced unless custom ones are set
+    fn set_plain_text(&mut self) {
+        self.function_call = false;
+        if self.options.custom_base_instructions.is_none() {
+            self.options.custom_base_instructions = Some(PLAIN_TEXT_BASE_INSTRUCTIONS.to_string());
+        }
+    }
+
+    // This function builds the API body of a plain-text request, without an output schema or Json formatting instructions
+    fn get_text_body(&self, instructions: &str) -> Result<Value> {
+        let get_prompt = |instructions: &str| match &self.input_json {
+            Some(input_json) => format!("{instructions}\n\n{input_json}"),
+            None => instructions.to_string(),
+        };
+
+        //Validate how many tokens remain for the response (and how many are used for prompt)
+        let base_instructions = self
+            .model
+            .get_body_base_instructions(self.function_call, &self.options);
+        let count_prompt_tokens = |prompt: &str| {
+            get_tokenizer(&self.model)
+                .map(|bpe| {
+                    bpe.encode_with_special_tokens(&format!("{base_instructions}{prompt}"))
+                        .len()
+                })
+                .unwrap_or_default()
+        };
+        let mut prompt = get_prompt(instructions);
+        let mut prompt_tokens = count_prompt_tokens(&prompt);
+
+        //Oversized instructions are truncated if allowed by the truncation policy
+        if let Some(truncated_instructions) = self.truncate_instructions(
+            instructions,
+            prompt_tokens,
+            count_prompt_tokens(&get_prompt("")),
+        )? {
+            prompt = get_prompt(&truncated_instructions);
+            prompt_tokens = count_prompt_tokens(&prompt);
+        }
+        //Few-shot examples are limited to the ones that fit in the remaining tokens
+        let (options, examples_tokens) = self.fit_few_shot_examples(prompt_tokens)?;
+        let response_tokens = self.get_response_tokens(prompt_tokens + examples_tokens)?;
+
+        //Validate the temperature against the range accepted by the model before sending the request
+        let temperature = self.get_checked_temperature()?;
+
+        //Grounding, files, documents and extended thinking can only be requested from models that support them
+        self.check_model_options(response_tokens)?;
+
+        //Build the API body with an empty output schema, which models leave out of the prompt
+        Ok(self.model.get_body(
+            &prompt,
+            &json!({}),
+            self.function_call,
+            &response_tokens,
+            &temperature,
+            &options,
+        ))
+    }
+
+    ///
+    /// This method works the same way as `get_answer` but requests `n` candidate completions for the prompt, e.g. for diversity sampling.
+    /// Models supporting the `n` parameter (see `supports_multiple_choices`) return all completions in a single request.
+    /// For other models `n` requests are sent in parallel, bypassing the response cache so that each of them yields a new completion.
+    ///
+    pub async fn get_answers<U: JsonSchema + DeserializeOwned>(
+        mut self,
+        instructions: &str,
+        n: u8,
+    ) -> Result<Vec<U>>
+    where
+        T: Clone,
+    {
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+
+        if self.model.supports_multiple_choices() {
+            self.options.n = Some(n);
+            return self
+                .get_choices_response::<U>(instructions)
+                .await
+                .map(|response| response.data);
+        }
+
+        self.cache = None;
+        let requests = (0..n).map(|_| self.clone().get_answer::<U>(instructions));
+        try_join_all(requests).await
+    }
+
+    // This function sends the request and deserializes each of the completions returned by the model
+    async fn get_choices_response<U: JsonSchema + DeserializeOwned>(
+        self,
+        instructions: &str,
+    ) -> Result<CompletionsResponse<Vec<U>>> {
         //Output schema is extracted from the type parameter
         let schema = get_type_schema::<U>()?;
         let json_schema = serde_json::from_str(&schema)?;
 
-        let prompt = format!(
-            "Instructions:
-            {instructions}
-
-            Input data:
-            {input_json}
-            
-            Respond ONLY with the data portion of a valid Json object. No schema definition required. No other words.", 
-            instructions = instructions,
-            input_json = self.input_json.clone().unwrap_or_default(),
-        );
-
         //Validate how many tokens remain for the response (and how many are used for prompt)
-        let prompt_tokens = self
+        let mut prompt_tokens = self
             .check_prompt_tokens::<U>(instructions)
             .unwrap_or_default();
 
-        if prompt_tokens >= self.max_tokens {
-            return Err(anyhow!(
-                "The provided prompt requires more tokens than allocated."
-            ));
+        //Oversized instructions are truncated if allowed by the truncation policy
+        let truncated_instructions = self.truncate_instructions(
+            instructions,
+            prompt_tokens,
+            self.count_prompt_tokens::<U>("")?,
+        )?;
+        if let Some(truncated_instructions) = &truncated_instructions {
+            prompt_tokens = self
+                .check_prompt_tokens::<U>(truncated_instructions)
+                .unwrap_or_default();
         }
-        let response_tokens = self.max_tokens - prompt_tokens;
+        let instructions = truncated_instructions.as_deref().unwrap_or(instructions);
+
+        //Few-shot examples are limited to the ones that fit in the remaining tokens
+        let (options, examples_tokens) = self.fit_few_shot_examples(prompt_tokens)?;
+        prompt_tokens += examples_tokens;
+
+        let response_tokens = self.get_response_tokens(prompt_tokens)?;
+
+        let prompt = self.get_prompt(instructions, &json_schema);
 
         //Throw a warning if after processing the prompt there might be not enough tokens for response
         //This assumes response will be similar size as input. Because this is not always correct this is a warning and not an error
         if prompt_tokens * 2 >= self.max_tokens {
             warn!(
                 "{} tokens remaining for response: {} allocated, {} used for prompt",
-                response_tokens.to_string(),
-                self.max_tokens.to_string(),
-                prompt_tokens.to_string(),
+                response_tokens, self.max_tokens, prompt_tokens,
             );
         };
 
+        //Validate the temperature against the range accepted by the model before sending the request
+        let temperature = self.get_checked_temperature()?;
+
+        //Reject schemas the API would fail on with a less descriptive error
+        validate_json_schema(&json_schema)?;
+
+        //Log probabilities can only be requested from models that report them
+        self.check_logprobs()?;
+
+        //Grounding, files, documents and extended thinking can only be requested from models that support them
+        self.check_model_options(response_tokens)?;
+
         //Build the API body depending on the used model
         let model_body = self.model.get_body(
             &prompt,
             &json_schema,
             self.function_call,
             &response_tokens,
-            &self.temperature,
+            &temperature,
+            &options,
         );
 
         //Display debug info if requested
@@ -208,21 +1078,242 @@ impl<T: LLMModel> Completions<T> {
             info!("[debug] Model body: {:#?}", model_body);
             info!(
                 "[debug] Prompt accounts for approx {} tokens, leaving {} tokens for answer.",
-                prompt_tokens.to_string(),
-                response_tokens.to_string(),
+                prompt_tokens, response_tokens,
             );
         }
 
-        let response_text = self
-            .model
-            .call_api(&self.api_key, &model_body, self.debug)
-            .await?;
+        //In dry run mode the validated body is only logged
+        if self.dry_run {
+            return Err(self.dry_run_error(model_body));
+        }
 
-        //Extract data from the returned response text based on the used model
-        let response_string = self
+        //Check if the response for this exact request is already cached
+        let mut use_cache = true;
+        let mut retries = 0;
+        //Body of the retry asking the model to correct invalid Json, if requested
+        let mut correction_body: Option<Value> = None;
+        loop {
+            //Retries always call the API as the cached response is only used once
+            let request_body = correction_body.as_ref().unwrap_or(&model_body);
+            let (first_response, is_cached) =
+                self.get_response_text(request_body, use_cache).await?;
+            use_cache = false;
+            let first_response_text = first_response.text;
+            //The status is only reported if the last response was returned by the API rather than the cache
+            let mut rate_limit_status = first_response.rate_limit_status;
+            //Usage is summed across the response and its continuations
+            let mut usage = self.model.get_usage(&first_response_text);
+
+            //Extract data from the returned response text based on the used model
+            let mut response_strings = self.get_response_data(&first_response_text)?;
+
+            //Responses cut off by the max tokens limit are continued in follow-up requests, if requested
+            //Multiple choices are not continued as the follow-up would only return one of them
+            let mut response_text = first_response_text.clone();
+            let mut continuations = 0;
+            while continuations < self.max_continuations
+                && response_strings.len() == 1
+                && self.model.get_finish_reason(&response_text) == Some(FinishReason::Length)
+            {
+                continuations += 1;
+                warn!(
+                    "[allms] Response of {} was cut off by the max tokens limit, continuing ({}/{})",
+                    self.model.as_str(),
+                    continuations,
+                    self.max_continuations,
+                );
+                let continuation_prompt = get_continuation_prompt(&prompt, &response_strings[0]);
+                //The partial output repeated in the continuation prompt leaves fewer tokens for the response
+                let continuation_tokens = prompt_tokens
+                    + self
+                        .count_text_tokens(&continuation_prompt)?
+                        .saturating_sub(self.count_text_tokens(&prompt)?);
+                let continuation_response_tokens = self.get_response_tokens(continuation_tokens)?;
+                let continuation_body = self.model.get_body(
+                    &continuation_prompt,
+                    &json_schema,
+                    self.function_call,
+                    &continuation_response_tokens,
+                    &temperature,
+                    &options,
+                );
+                let (continuation_response, _) =
+                    self.get_response_text(&continuation_body, false).await?;
+                usage = match (usage, self.model.get_usage(&continuation_response.text)) {
+                    (Some(mut usage), Some(continuation_usage)) => {
+                        usage += continuation_usage;
+                        Some(usage)
+                    }
+                    (usage, continuation_usage) => usage.or(continuation_usage),
+                };
+                let continuation = self
+                    .get_response_data(&continuation_response.text)?
+                    .join("");
+                response_strings[0] = join_continuation(&response_strings[0], &continuation);
+                response_text = continuation_response.text;
+                rate_limit_status = continuation_response.rate_limit_status;
+            }
+
+            //Deserialize each of the string responses into the expected output type
+            let data = match response_strings
+                .iter()
+                .map(|response_string| {
+                    self.deserialize_data::<U>(
+                        response_string.clone(),
+                        &response_text,
+                        &json_schema,
+                    )
+                })
+                .collect::<Result<Vec<U>>>()
+            {
+                Ok(data) => data,
+                //Models occasionally return malformed Json, which usually succeeds when the request is sent again
+                Err(error) if retries < self.parse_retries => {
+                    retries += 1;
+                    warn!(
+                        "[allms] Unable to deserialize the response of {}, retrying ({}/{}): {}",
+                        self.model.as_str(),
+                        retries,
+                        self.parse_retries,
+                        error
+                    );
+                    if self.correct_invalid_json {
+                        let correction_prompt =
+                            get_correction_prompt(&prompt, &response_strings.join("\n"), &error);
+                        correction_body = Some(self.model.get_body(
+                            &correction_prompt,
+                            &json_schema,
+                            self.function_call,
+                            &response_tokens,
+                            &temperature,
+                            &options,
+                        ));
+                    }
+                    continue;
+                }
+                Err(error) => return Err(error),
+            };
+
+            //Store the response for subsequent identical requests
+            //A truncated response is cached as returned by the model and continued again when read from the cache
+            if !is_cached {
+                self.cache_response(&model_body, &first_response_text).await;
+            }
+
+            //The remaining metadata is taken from the last fragment, e.g. the finish reason tells how the joined response ended
+            let citations = self.model.get_citations(&response_text);
+            let logprobs = self.model.get_logprobs(&response_text);
+            let finish_reason = self.model.get_finish_reason(&response_text);
+            let grounding_citations = self.model.get_grounding_citations(&response_text);
+            let thinking = self.model.get_thinking(&response_text);
+            let raw_response = self.debug.then(|| response_text.clone());
+
+            return Ok(CompletionsResponse {
+                data,
+                usage,
+                citations,
+                logprobs,
+                raw_response,
+                retries,
+                finish_reason,
+                grounding_citations,
+                thinking,
+                rate_limit_status,
+                provider: self.model.get_provider(),
+            });
+        }
+    }
+
+    // This function logs the body that would be sent in dry run mode and returns it in a `DryRun` error
+    fn dry_run_error(&self, model_body: Value) -> anyhow::Error {
+        info!(
+            "[allms][dry run] Request body for {}: {}",
+            self.model.as_str(),
+            model_body
+        );
+        DryRun {
+            model: self.model.as_str().to_string(),
+            body: model_body,
+        }
+        .into()
+    }
+
+    // This function returns the response for the body, read from the cache if allowed and available, and if it was cached
+    async fn get_response_text(
+        &self,
+        model_body: &Value,
+        use_cache: bool,
+    ) -> Result<(ApiResponse, bool)> {
+        let cached_response = match (&self.cache, use_cache) {
+            (Some(cache), true) => {
+                let request_cache_key = cache_key(&self.model.id(), model_body);
+                let cached_response = cache.get(&request_cache_key).await.unwrap_or_else(|error| {
+                    warn!("[allms][Cache] Unable to read from cache: {:?}", error);
+                    None
+                });
+                if self.debug && cached_response.is_some() {
+                    info!("[debug] Response found in cache: {}", request_cache_key);
+                }
+                cached_response
+            }
+            _ => None,
+        };
+        let is_cached = cached_response.is_some();
+
+        let response = match cached_response {
+            Some(response_text) => ApiResponse {
+                text: response_text,
+                ..Default::default()
+            },
+            None => {
+                #[cfg(feature = "metrics")]
+                let start = Instant::now();
+                let response = self.call_api(model_body).await;
+                #[cfg(feature = "metrics")]
+                crate::metrics::observe_request(
+                    self.model.as_str(),
+                    start.elapsed(),
+                    response.as_ref(),
+                );
+                response?
+            }
+        };
+
+        //Errors returned by the API are reported with their HTTP status rather than as responses that can't be deserialized
+        if !response.is_success() {
+            let error = AllmsError {
+                crate_name: "allms".to_string(),
+                module: format!("assistants::completions::{}", self.model.as_str()),
+                error_message: format!(
+                    "Completions API returned HTTP status {}",
+                    response.status.unwrap_or_default()
+                ),
+                error_detail: response.text,
+            };
+            error!("{:?}", error);
+            return Err(anyhow!("{:?}", error));
+        }
+
+        if self.debug {
+            info!(
+                "[debug] Raw API response: {}",
+                redact_secrets(&response.text)
+            );
+        }
+
+        Ok((response, is_cached))
+    }
+
+    // This function extracts the data of each completion from the response text based on the used model
+    fn get_response_data(&self, response_text: &str) -> Result<Vec<String>> {
+        let response_strings = self
             .model
-            .get_data(&response_text, self.function_call)
+            .get_data_choices(response_text, self.function_call)
             .map_err(|error| {
+                //Cached responses are always valid so the failure is reported for the API call
+                #[cfg(feature = "metrics")]
+                crate::metrics::observe_error(self.model.as_str(), "invalid_response");
+
                 let error = AllmsError {
                     crate_name: "allms".to_string(),
                     module: format!("assistants::completions::{}", self.model.as_str()),
@@ -237,27 +1328,81 @@ impl<T: LLMModel> Completions<T> {
             })?;
 
         if self.debug {
-            info!("[debug] Completions response data: {}", response_string);
+            for response_string in &response_strings {
+                info!("[debug] Completions response data: {}", response_string);
+            }
+        }
+
+        //An empty answer is reported as such rather than as a deserialization error
+        if response_strings.is_empty()
+            || response_strings
+                .iter()
+                .any(|response_string| response_string.trim().is_empty())
+        {
+            #[cfg(feature = "metrics")]
+            crate::metrics::observe_error(self.model.as_str(), "empty_response");
+
+            let error = EmptyResponse {
+                model: self.model.as_str().to_string(),
+                finish_reason: self.model.get_finish_reason(response_text),
+            };
+            warn!("{}", error);
+            return Err(error.into());
+        }
+
+        Ok(response_strings)
+    }
+
+    // This function stores the response for subsequent identical requests
+    async fn cache_response(&self, model_body: &Value, response_text: &str) {
+        if let Some(cache) = &self.cache {
+            let request_cache_key = cache_key(&self.model.id(), model_body);
+            if let Err(error) = cache.set(&request_cache_key, response_text).await {
+                warn!("[allms][Cache] Unable to write to cache: {:?}", error);
+            }
         }
-        //Deserialize the string response into the expected output type
+    }
+
+    // This function deserializes the data extracted from the response into the expected output type
+    // If requested the output is validated against the JSON schema of the expected type first
+    fn deserialize_data<U: JsonSchema + DeserializeOwned>(
+        &self,
+        response_string: String,
+        response_text: &str,
+        json_schema: &Value,
+    ) -> Result<U> {
         let response_deser: anyhow::Result<U, anyhow::Error> =
-            serde_json::from_str(&response_string).map_err(|error| {
-                let error = AllmsError {
-                    crate_name: "allms".to_string(),
-                    module: format!("assistants::completions::{}", self.model.as_str()),
-                    error_message: format!(
-                        "Completions API response serialization error: {}",
-                        error
-                    ),
-                    error_detail: response_string,
-                };
-                error!("{:?}", error);
-                anyhow!("{:?}", error)
-            });
+            serde_json::from_str(&response_string)
+                .or_else(|error| {
+                    //Almost-valid Json (e.g. with a trailing comma or cut off by `max_tokens`) is repaired before giving up
+                    let repaired = repair_json(&response_string).and_then(|value| {
+                        serde_json::from_value(value).map_err(|error| error.into())
+                    });
+                    if repaired.is_ok() {
+                        warn!(
+                            "[allms] Repaired malformed Json in the response of {}",
+                            self.model.as_str()
+                        );
+                    }
+                    repaired.map_err(|_| error)
+                })
+                .map_err(|error| {
+                    let error = AllmsError {
+                        crate_name: "allms".to_string(),
+                        module: format!("assistants::completions::{}", self.model.as_str()),
+                        error_message: format!(
+                            "Completions API response serialization error: {}",
+                            error
+                        ),
+                        error_detail: response_string.clone(),
+                    };
+                    error!("{:?}", error);
+                    anyhow!("{:?}", error)
+                });
         // Sometimes openai responds with a json object that has a data property. If that's the case, we need to extract the data property and deserialize that.
         // TODO: This is OpenAI specific and should be implemented within the model.
         if let Err(_e) = response_deser {
-            let response_deser: OpenAIDataResponse<U> = serde_json::from_str(&response_text)
+            let response_deser: OpenAIDataResponse<U> = serde_json::from_str(response_text)
                 .map_err(|error| {
                     let error = AllmsError {
                         crate_name: "allms".to_string(),
@@ -266,14 +1411,1609 @@ impl<T: LLMModel> Completions<T> {
                             "Completions API response serialization error: {}",
                             error
                         ),
-                        error_detail: response_text,
+                        error_detail: response_text.to_string(),
                     };
                     error!("{:?}", error);
                     anyhow!("{:?}", error)
                 })?;
+            if self.validate_output {
+                let output: Value = serde_json::from_str(response_text)?;
+                validate_output(json_schema, &output["data"])?;
+            }
             Ok(response_deser.data)
         } else {
-            Ok(response_deser.unwrap())
+            if self.validate_output {
+                let output = repair_json(&response_string)?;
+                validate_output(json_schema, &output)?;
+            }
+            response_deser
+        }
+    }
+
+    // This function makes the API call of the model, rejecting it if the budget is exceeded and recording its cost if a budget tracker is set
+    async fn call_api(&self, model_body: &Value) -> Result<ApiResponse> {
+        let budget = match &self.budget {
+            Some(budget) => budget,
+            None => return self.call_api_with_hooks(model_body).await,
+        };
+        budget.check()?;
+
+        //A budget can't be enforced without the prices of the model, so the call is not sent
+        let cost = match (self.model.cost_per_1k_tokens(), budget.budget()) {
+            (Some(cost), _) => cost,
+            (None, Some(_)) => {
+                return Err(anyhow!(
+                    "[allms] Unable to enforce the budget: token prices of {} are unknown",
+                    self.model.as_str()
+                ))
+            }
+            (None, None) => {
+                warn!(
+                    "[allms] Unable to record the cost of the call of {}: token prices are unknown",
+                    self.model.as_str()
+                );
+                return self.call_api_with_hooks(model_body).await;
+            }
+        };
+
+        let response = self.call_api_with_hooks(model_body).await?;
+        //If the usage is not reported the upper bound of the cost is recorded, with the whole body as input and the max tokens as output
+        let usage = match self.model.get_usage(&response.text) {
+            Some(usage) => usage,
+            None => {
+                warn!(
+                    "[allms] Token usage of the call of {} is unknown, recording the upper bound of its cost",
+                    self.model.as_str()
+                );
+                let bpe = get_tokenizer(&self.model)?;
+                CompletionsUsage {
+                    input_tokens: bpe
+                        .encode_with_special_tokens(&model_body.to_string())
+                        .len(),
+                    output_tokens: self.max_tokens,
+                    ..Default::default()
+                }
+            }
+        };
+        budget.record_usage(&usage, &cost);
+        Ok(response)
+    }
+
+    // This function makes the API call of the model, invoking the request callbacks if set
+    async fn call_api_with_hooks(&self, model_body: &Value) -> Result<ApiResponse> {
+        if self.hooks.is_empty() {
+            return self.call_model_api(model_body).await;
+        }
+
+        let model = self.model.as_str().to_string();
+        let body_size = model_body.to_string().len();
+        if let Some(on_request) = &self.hooks.on_request {
+            on_request(&RequestEvent {
+                model: model.clone(),
+                body_size,
+            });
+        }
+
+        let start = Instant::now();
+        let response = self.call_model_api(model_body).await;
+
+        if let Some(on_response) = &self.hooks.on_response {
+            let (status, error) = match &response {
+                Ok(response) if response.is_success() => (response.status, None),
+                Ok(response) => (
+                    response.status,
+                    Some(format!(
+                        "HTTP status {}: {}",
+                        response.status.unwrap_or_default(),
+                        response.text
+                    )),
+                ),
+                Err(error) => (
+                    error
+                        .downcast_ref::<reqwest::Error>()
+                        .and_then(|error| error.status())
+                        .map(|status| status.as_u16()),
+                    Some(error.to_string()),
+                ),
+            };
+            on_response(&ResponseEvent {
+                model,
+                body_size,
+                status,
+                error,
+                latency: start.elapsed(),
+                usage: response
+                    .as_ref()
+                    .ok()
+                    .and_then(|response| self.model.get_usage(&response.text)),
+            });
+        }
+        response
+    }
+
+    // With the `tracing` feature enabled the call is wrapped in a span recording the model, endpoint, body size, duration, status and token usage
+    async fn call_model_api(&self, model_body: &Value) -> Result<ApiResponse> {
+        #[cfg(feature = "tracing")]
+        {
+            use tracing::{field, Instrument};
+
+            let span = tracing::info_span!(
+                "allms.call_api",
+                model = self.model.as_str(),
+                provider = %self.model.get_provider(),
+                endpoint = self.model.get_endpoint(),
+                body_size_bytes = model_body.to_string().len(),
+                input_tokens = field::Empty,
+                output_tokens = field::Empty,
+                duration_ms = field::Empty,
+                status = field::Empty,
+            );
+
+            let start = Instant::now();
+            let response = self.send_request(model_body).instrument(span.clone()).await;
+            span.record("duration_ms", start.elapsed().as_millis() as u64);
+
+            //Requests that failed without a response have no HTTP status
+            if let Ok(response) = &response {
+                if let Some(status) = response.status {
+                    span.record("status", status);
+                }
+                if self.debug {
+                    span.in_scope(|| {
+                        tracing::debug!(
+                            response_size_bytes = response.text.len(),
+                            "allms.call_api response"
+                        )
+                    });
+                }
+                if let Some(usage) = self.model.get_usage(&response.text) {
+                    span.record("input_tokens", usage.input_tokens);
+                    span.record("output_tokens", usage.output_tokens);
+                }
+            }
+            response
+        }
+
+        #[cfg(not(feature = "tracing"))]
+        {
+            self.send_request(model_body).await
+        }
+    }
+
+    // Requests are sent with the shared HTTP client and compressed if configured
+    async fn send_request(&self, model_body: &Value) -> Result<ApiResponse> {
+        let api_key = self.get_api_key().await?;
+        self.model
+            .call_api(&self.http_client, &api_key, model_body, self.debug)
+            .await
+    }
+
+    // Vertex AI access tokens expire, so with a Vertex config the token is obtained (or taken from its cache) for each call
+    async fn get_api_key(&self) -> Result<String> {
+        match &self.vertex_config {
+            Some(config) => config.access_token().await,
+            None => Ok(self.api_key.clone()),
         }
     }
+
+    ///
+    /// This method returns the number of input tokens of the request `get_answer` would send for the instructions, as counted by the token counting API of the provider (see `LLMModel::count_body_tokens_api`), e.g. the Anthropic Token Counting API
+    /// The count includes the base instructions, the input data and the output schema, so it matches the billed input tokens. Models without such an API return an error.
+    ///
+    pub async fn count_tokens_api<U: JsonSchema + DeserializeOwned>(
+        &self,
+        instructions: &str,
+    ) -> Result<usize> {
+        let body = self.get_count_tokens_body::<U>(instructions)?;
+        let api_key = self.get_api_key().await?;
+        self.model
+            .count_body_tokens_api(&self.http_client, &api_key, &body)
+            .await
+    }
+
+    // The body is built from the same prompt and settings as the body of `get_answer`
+    fn get_count_tokens_body<U: JsonSchema + DeserializeOwned>(
+        &self,
+        instructions: &str,
+    ) -> Result<Value> {
+        let json_schema: Value = serde_json::from_str(&get_type_schema::<U>()?)?;
+        let prompt = self.get_prompt(instructions, &json_schema);
+        Ok(self.model.get_body(
+            &prompt,
+            &json_schema,
+            self.function_call,
+            &self.max_tokens,
+            &self.temperature,
+            &self.options,
+        ))
+    }
+}
+
+// The original prompt is sent again with the invalid output and the error, asking the model to correct it
+fn get_correction_prompt(prompt: &str, invalid_output: &str, error: &anyhow::Error) -> String {
+    format!(
+        "{prompt}
+
+        Your previous response could not be parsed:
+        {invalid_output}
+
+        Error: {error}
+
+        Correct the response. Respond ONLY with the data portion of a valid Json object matching the schema. No other words."
+    )
+}
+
+// The original prompt is sent again with the truncated output, asking the model to continue it
+fn get_continuation_prompt(prompt: &str, partial_output: &str) -> String {
+    format!(
+        "{prompt}
+
+        Your previous response was cut off by the length limit:
+        {partial_output}
+
+        Continue the response exactly where it stopped. Respond ONLY with the remaining characters of the Json object, without repeating the beginning. No other words."
+    )
+}
+
+// Continuations are appended to the partial output. Code fences and a beginning repeated by the model (at least 8 characters) are removed first
+fn join_continuation(partial_output: &str, continuation: &str) -> String {
+    const MIN_OVERLAP: usize = 8;
+
+    // Whitespace is only trimmed next to code fences as it can be part of a cut off string value
+    let partial_output = match partial_output.trim_end().strip_suffix("```") {
+        Some(unfenced) => unfenced.trim_end(),
+        None => partial_output,
+    };
+    let continuation = match continuation.trim_start().strip_prefix("```") {
+        Some(fenced) => fenced.strip_prefix("json").unwrap_or(fenced).trim_start(),
+        None => continuation,
+    };
+    let continuation = match continuation.trim_end().strip_suffix("```") {
+        Some(unfenced) => unfenced.trim_end(),
+        None => continuation,
+    };
+
+    // Longest suffix of the partial output the continuation starts with
+    let overlap = (MIN_OVERLAP..=partial_output.len().min(continuation.len()))
+        .rev()
+        .find(|&length| {
+            partial_output.is_char_boundary(partial_output.len() - length)
+                && continuation.is_char_boundary(length)
+                && partial_output[partial_output.len() - length..] == continuation[..length]
+        })
+        .unwrap_or(0);
+
+    format!("{}{}", partial_output, &continuation[overlap..])
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use async_trait::async_trait;
+    use futures::TryStreamExt;
+    use schemars::JsonSchema;
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+    use std::sync::{Arc, Mutex};
+
+    use crate::cache::ResponseCache;
+    use crate::constants::PLAIN_TEXT_BASE_INSTRUCTIONS;
+    use crate::domain::{
+        BudgetExceeded, CompletionsOptions, CompletionsRequest, CompletionsUsage,
+        ContextLengthExceeded, Document, DryRun, EmptyResponse, FinishReason, GroundingTool,
+        LLMProvider, Message, RateLimitStatus, SchemaMismatch, Temperature, TemperaturePolicy,
+        TruncationPolicy,
+    };
+    use crate::llm_models::{
+        AnthropicModels, DeepSeekModels, GeminiProVertexConfig, GoogleModels, LLMModel,
+        LLMModelEnum, MistralModels, MockModel, MockResponse, OpenAIModels, PerplexityModels,
+        VertexAuthMethod,
+    };
+    use crate::{BudgetTracker, Completions, InMemoryLruCache};
+
+    use super::join_continuation;
+
+    #[derive(Deserialize, JsonSchema, Debug)]
+    struct TestResponse {
+        answer: String,
+    }
+
+    // Cache that answers every request with a canned response and records the requested keys
+    struct RecordingCache {
+        keys: Mutex<Vec<String>>,
+    }
+
+    #[async_trait(?Send)]
+    impl ResponseCache for RecordingCache {
+        async fn get(&self, key: &str) -> Result<Option<String>> {
+            self.keys.lock().unwrap().push(key.to_string());
+            let response = json!({
+                "choices": [{
+                    "message": {
+                        "role": "assistant",
+                        "function_call": {
+                            "name": "analyze_data",
+                            "arguments": "{\"answer\": \"cached\"}"
+                        }
+                    }
+                }]
+            });
+            Ok(Some(response.to_string()))
+        }
+
+        async fn set(&self, _key: &str, _response: &str) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    // Cache returning a malformed response and recording the responses written to it
+    struct MalformedCache {
+        stored: Mutex<Vec<String>>,
+    }
+
+    #[async_trait(?Send)]
+    impl ResponseCache for MalformedCache {
+        async fn get(&self, _key: &str) -> Result<Option<String>> {
+            Ok(Some(r#"{"answer": "#.to_string()))
+        }
+
+        async fn set(&self, _key: &str, response: &str) -> Result<()> {
+            self.stored.lock().unwrap().push(response.to_string());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_correct_invalid_json() {
+        // Sending the same request again does not help
+        let model = MockModel::new("The answer is mocked");
+        assert!(Completions::new(model.clone(), "", None, None)
+            .parse_retries(2)
+            .get_answer::<TestResponse>("Say something")
+            .await
+            .is_err());
+        assert_eq!(model.calls.len(), 3);
+
+        let model = MockModel::default()
+            .responses(vec!["The answer is mocked", r#"{"answer": "corrected"}"#]);
+        let response = Completions::new(model.clone(), "", None, None)
+            .parse_retries(2)
+            .correct_invalid_json(true)
+            .get_response::<TestResponse>("Say something")
+            .await
+            .unwrap();
+        assert_eq!(response.data.answer, "corrected");
+        assert_eq!(response.retries, 1);
+        // The invalid response is sent back to be corrected
+        let correction = model.calls.get(1).unwrap().body.to_string();
+        assert!(correction.contains("could not be parsed"));
+        assert!(correction.contains("The answer is mocked"));
+    }
+
+    #[tokio::test]
+    async fn test_budget() {
+        // $0.03 for input and $0.03 for output tokens at Claude 3.5 Sonnet prices
+        let model = || {
+            MockModel::default()
+                .prices(
+                    AnthropicModels::Claude3_5Sonnet
+                        .cost_per_1k_tokens()
+                        .unwrap(),
+                )
+                .responses(vec![MockResponse::new(r#"{"answer": "mocked"}"#).usage(
+                    CompletionsUsage {
+                        input_tokens: 10_000,
+                        output_tokens: 2_000,
+                        ..Default::default()
+                    },
+                )])
+        };
+        let budget = BudgetTracker::with_budget(0.1);
+
+        for _ in 0..2 {
+            let answer = Completions::new(model(), "", None, None)
+                .budget(budget.clone())
+                .get_answer::<TestResponse>("Say something")
+                .await
+                .unwrap();
+            assert_eq!(answer.answer, "mocked");
+        }
+        assert!((budget.spent() - 0.12).abs() < 1e-9);
+
+        let error = Completions::new(model(), "", None, None)
+            .budget(budget.clone())
+            .get_answer::<TestResponse>("Say something")
+            .await
+            .unwrap_err();
+        let exceeded = error.downcast_ref::<BudgetExceeded>().unwrap();
+        assert_eq!(exceeded.budget, 0.1);
+        assert!((exceeded.spent - 0.12).abs() < 1e-9);
+        assert!((budget.spent() - 0.12).abs() < 1e-9);
+
+        budget.reset();
+        assert!(Completions::new(model(), "", None, None)
+            .budget(budget.clone())
+            .get_answer::<TestResponse>("Say something")
+            .await
+            .is_ok());
+
+        // Without usage in the response the upper bound of the cost is recorded
+        let budget = BudgetTracker::with_budget(1.0);
+        let model = MockModel::new(r#"{"answer": "mocked"}"#)
+            .prices(OpenAIModels::Gpt4o.cost_per_1k_tokens().unwrap());
+        assert!(Completions::new(model, "", Some(1_000), None)
+            .budget(budget.clone())
+            .get_answer::<TestResponse>("Say something")
+            .await
+            .is_ok());
+        // 1,000 output tokens at $0.01 per 1k tokens, and the input tokens of the body
+        assert!(budget.spent() > 0.01);
+
+        // A budget can't be enforced for models without prices
+        let error = Completions::new(MockModel::new(r#"{"answer": "mocked"}"#), "", None, None)
+            .budget(BudgetTracker::with_budget(1.0))
+            .get_answer::<TestResponse>("Say something")
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("Unable to enforce the budget"));
+    }
+
+    #[tokio::test]
+    async fn test_empty_response() {
+        let model = MockModel::default().responses(vec![
+            MockResponse::new(" \n").finish_reason(FinishReason::ContentFilter)
+        ]);
+
+        let error = Completions::new(model.clone(), "", None, None)
+            .get_answer::<TestResponse>("Say something")
+            .await
+            .unwrap_err();
+        let empty_response = error.downcast_ref::<EmptyResponse>().unwrap();
+        assert_eq!(empty_response.model, "mock");
+        // The finish reason tells why the answer is empty
+        assert_eq!(
+            empty_response.finish_reason,
+            Some(FinishReason::ContentFilter)
+        );
+
+        let error = Completions::new(model, "", None, None)
+            .get_text("Say something")
+            .await
+            .unwrap_err();
+        assert!(error.downcast_ref::<EmptyResponse>().is_some());
+    }
+
+    #[test]
+    fn test_empty_provider_responses() {
+        let responses: Vec<(Box<dyn LLMModel>, Value)> = vec![
+            (
+                Box::new(OpenAIModels::Gpt4o),
+                json!({"choices": [{"index": 0, "message": {"role": "assistant", "content": ""}, "finish_reason": "content_filter"}]}),
+            ),
+            (
+                Box::new(OpenAIModels::O1),
+                json!({"choices": [{"index": 0, "message": {"role": "assistant", "content": null}, "finish_reason": "content_filter"}]}),
+            ),
+            (
+                Box::new(AnthropicModels::Claude3_5Sonnet),
+                json!({"id": "msg_1", "type": "message", "role": "assistant", "content": [], "model": "claude-3-5-sonnet-latest", "stop_reason": "end_turn", "stop_sequence": null, "usage": {"input_tokens": 10, "output_tokens": 0}}),
+            ),
+            (
+                Box::new(GoogleModels::Gemini2_0Flash),
+                json!({"candidates": [{"finishReason": "SAFETY"}]}),
+            ),
+            (
+                Box::new(GoogleModels::Gemini2_0Flash),
+                json!({"candidates": [{"content": {"role": "model", "parts": [{"text": " \n"}]}, "finishReason": "STOP"}]}),
+            ),
+            (
+                Box::new(MistralModels::MistralSmall),
+                json!({"choices": [{"index": 0, "message": {"role": "assistant", "content": "  "}, "finish_reason": "stop"}]}),
+            ),
+            (
+                Box::new(PerplexityModels::Sonar),
+                json!({"choices": [{"index": 0, "message": {"role": "assistant", "content": ""}, "finish_reason": "stop"}]}),
+            ),
+            (
+                Box::new(DeepSeekModels::DeepSeekChat),
+                json!({"choices": [{"index": 0, "message": {"role": "assistant", "content": "\n"}, "finish_reason": "stop"}]}),
+            ),
+        ];
+
+        // Empty answers are extracted as blank data, which `Completions` reports as `EmptyResponse` rather than a deserialization error
+        for (model, response) in responses {
+            let data = model
+                .get_data_choices(&response.to_string(), model.function_call_default())
+                .unwrap_or_else(|error| {
+                    panic!("Unexpected error for {}: {}", model.as_str(), error)
+                });
+            assert!(
+                data.iter().all(|data| data.trim().is_empty()),
+                "Unexpected data for {}: {:?}",
+                model.as_str(),
+                data
+            );
+        }
+
+        assert_eq!(
+            GoogleModels::Gemini2_0Flash.get_finish_reason(
+                &json!({"candidates": [{"finishReason": "SAFETY"}]}).to_string()
+            ),
+            Some(FinishReason::ContentFilter)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_status() {
+        let model =
+            MockModel::new(r#"{"answer": "throttled"}"#).rate_limit_status(RateLimitStatus {
+                remaining_requests: Some(9),
+                remaining_tokens: Some(1_000),
+                ..Default::default()
+            });
+        let response = Completions::new(model, "", None, None)
+            .get_response::<TestResponse>("Say something")
+            .await
+            .unwrap();
+        let rate_limit_status = response.rate_limit_status.unwrap();
+        assert_eq!(rate_limit_status.remaining_requests, Some(9));
+        assert_eq!(rate_limit_status.remaining_tokens, Some(1_000));
+
+        // Cached responses do not report the current status
+        let cache = Arc::new(RecordingCache {
+            keys: Mutex::new(Vec::new()),
+        });
+        let response = Completions::new(OpenAIModels::Gpt4o, "", None, None)
+            .cache(cache)
+            .get_response::<TestResponse>("Say something")
+            .await
+            .unwrap();
+        assert_eq!(response.data.answer, "cached");
+        assert!(response.rate_limit_status.is_none());
+    }
+
+    #[derive(Deserialize, JsonSchema, Debug)]
+    struct CountedResponse {
+        answer: String,
+        count: u32,
+    }
+
+    #[tokio::test]
+    async fn test_continue_truncated() {
+        let model = || {
+            MockModel::default().responses(vec![
+                MockResponse::new(r#"{"answer": "mocked", "count"#)
+                    .finish_reason(FinishReason::Length)
+                    .usage(CompletionsUsage {
+                        input_tokens: 100,
+                        output_tokens: 10,
+                        ..Default::default()
+                    }),
+                MockResponse::new("```json\n\"answer\": \"mocked\", \"count\": 3}\n```")
+                    .finish_reason(FinishReason::Stop)
+                    .usage(CompletionsUsage {
+                        input_tokens: 120,
+                        output_tokens: 5,
+                        ..Default::default()
+                    }),
+            ])
+        };
+
+        // The truncated response is missing a required field
+        assert!(Completions::new(model(), "", None, None)
+            .get_answer::<CountedResponse>("Count something")
+            .await
+            .is_err());
+
+        let model = model();
+        let response = Completions::new(model.clone(), "", None, None)
+            .continue_truncated(2)
+            .get_response::<CountedResponse>("Count something")
+            .await
+            .unwrap();
+        assert_eq!(response.data.answer, "mocked");
+        assert_eq!(response.data.count, 3);
+        // The finish reason of the last fragment is reported, with the usage of all requests
+        assert_eq!(response.finish_reason, Some(FinishReason::Stop));
+        let usage = response.usage.unwrap();
+        assert_eq!(usage.input_tokens, 220);
+        assert_eq!(usage.output_tokens, 15);
+        // The model is asked to continue where the response was cut off
+        assert_eq!(model.calls.len(), 2);
+        let continuation_body = model.calls.get(1).unwrap().body;
+        assert!(continuation_body.to_string().contains("was cut off"));
+        // The partial output in the prompt leaves fewer tokens for the continuation
+        let first_body = model.calls.get(0).unwrap().body;
+        assert!(
+            continuation_body["max_tokens"].as_u64().unwrap()
+                < first_body["max_tokens"].as_u64().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_join_continuation() {
+        assert_eq!(
+            join_continuation(r#"{"answer": "Hello, "#, r#"world"}"#),
+            r#"{"answer": "Hello, world"}"#
+        );
+        // Repeated beginning of the continuation is removed
+        assert_eq!(
+            join_continuation(r#"{"answer": "Hello, wor"#, r#""Hello, world"}"#),
+            r#"{"answer": "Hello, world"}"#
+        );
+        // Short overlaps are kept as they are likely part of the value
+        assert_eq!(
+            join_continuation(r#"{"count": 1"#, "1}"),
+            r#"{"count": 11}"#
+        );
+        assert_eq!(
+            join_continuation("```json\n{\"a\": [1, 2,\n```", "```json\n 3]}\n```"),
+            "```json\n{\"a\": [1, 2,3]}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_retries_call_api_again() {
+        let cache = Arc::new(MalformedCache {
+            stored: Mutex::new(Vec::new()),
+        });
+        let model = MockModel::new(r#"{"answer": "mocked"}"#);
+
+        assert!(Completions::new(model.clone(), "", None, None)
+            .cache(cache.clone())
+            .get_answer::<TestResponse>("Say something")
+            .await
+            .is_err());
+
+        let answer = Completions::new(model, "", None, None)
+            .cache(cache.clone())
+            .parse_retries(1)
+            .get_answer::<TestResponse>("Say something")
+            .await
+            .unwrap();
+        assert_eq!(answer.answer, "mocked");
+        // The response of the retry replaces the malformed one in the cache
+        assert_eq!(
+            *cache.stored.lock().unwrap(),
+            vec![r#"{"answer": "mocked"}"#]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_identical_requests_share_cache_key() {
+        let cache = Arc::new(RecordingCache {
+            keys: Mutex::new(Vec::new()),
+        });
+
+        for temperature in [0.0, 0.0, 0.5] {
+            let answer = Completions::new(OpenAIModels::Gpt4o, "", None, None)
+                .temperature_unchecked(temperature)
+                .cache(cache.clone())
+                .get_answer::<TestResponse>("Say something")
+                .await
+                .unwrap();
+            assert_eq!(answer.answer, "cached");
+        }
+
+        let keys = cache.keys.lock().unwrap();
+        assert_eq!(keys.len(), 3);
+        assert_eq!(keys[0], keys[1]);
+        assert_ne!(keys[0], keys[2]);
+    }
+
+    #[tokio::test]
+    async fn test_vertex_and_studio_models_have_distinct_cache_keys() {
+        let cache = Arc::new(RecordingCache {
+            keys: Mutex::new(Vec::new()),
+        });
+
+        //Both variants have the same name and send the same body
+        for model in [GoogleModels::Gemini1_5Pro, GoogleModels::Gemini1_5ProVertex] {
+            let _ = Completions::new(model, "", None, None)
+                .cache(cache.clone())
+                .get_answer::<TestResponse>("Say something")
+                .await;
+        }
+
+        let keys = cache.keys.lock().unwrap();
+        assert_eq!(keys.len(), 2);
+        assert_ne!(keys[0], keys[1]);
+    }
+
+    #[tokio::test]
+    async fn test_lru_cache_miss_hit_and_eviction() {
+        let cache = Arc::new(InMemoryLruCache::new(1));
+        let requests = Arc::new(Mutex::new(0));
+        let counter = requests.clone();
+        let completions =
+            Completions::new(MockModel::new(r#"{"answer": "mocked"}"#), "", None, None)
+                .cache(cache.clone())
+                .on_request(move |_| *counter.lock().unwrap() += 1);
+
+        // Miss, then hit for the identical request
+        for _ in 0..2 {
+            let answer = completions
+                .clone()
+                .get_answer::<TestResponse>("Say something")
+                .await
+                .unwrap();
+            assert_eq!(answer.answer, "mocked");
+        }
+        assert_eq!(*requests.lock().unwrap(), 1);
+        assert_eq!(cache.len(), 1);
+
+        // A different request evicts the first one as the capacity is 1
+        completions
+            .clone()
+            .get_answer::<TestResponse>("Say something else")
+            .await
+            .unwrap();
+        completions
+            .clone()
+            .get_answer::<TestResponse>("Say something")
+            .await
+            .unwrap();
+        assert_eq!(*requests.lock().unwrap(), 3);
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_request_round_trip() {
+        let completions = Completions::new(OpenAIModels::Gpt4oMini, "", Some(2000), None)
+            .temperature_unchecked(0.5)
+            .set_context("input", &vec!["a", "b"])
+            .unwrap();
+        let request = completions
+            .to_request::<TestResponse>("Say something")
+            .unwrap();
+
+        let serialized = serde_json::to_value(&request).unwrap();
+        // Optional fields are kept as null to keep the stored format stable
+        assert!(serialized["options"]["custom_base_instructions"].is_null());
+        assert_eq!(
+            serialized["json_schema"]["properties"]["answer"]["type"],
+            "string"
+        );
+
+        let deserialized: CompletionsRequest =
+            serde_json::from_str(&serialized.to_string()).unwrap();
+        assert_eq!(deserialized, request);
+
+        let replayed = Completions::<OpenAIModels>::from_request(&deserialized, "")
+            .unwrap()
+            .to_request::<TestResponse>(&deserialized.instructions)
+            .unwrap();
+        assert_eq!(replayed, request);
+
+        // Vertex models are restored as such rather than as their AI Studio counterparts
+        let request = Completions::new(GoogleModels::Gemini1_5ProVertex, "", None, None)
+            .to_request::<TestResponse>("Say something")
+            .unwrap();
+        assert_eq!(request.model, "gemini-1.5-pro-vertex");
+        let replayed = Completions::<GoogleModels>::from_request(&request, "").unwrap();
+        assert_eq!(replayed.model, GoogleModels::Gemini1_5ProVertex);
+
+        // Models selected at runtime are stored with their provider
+        let model = LLMModelEnum::from(GoogleModels::Gemini2_0FlashVertex);
+        let request = Completions::new(model.clone(), "", None, None)
+            .to_request::<TestResponse>("Say something")
+            .unwrap();
+        assert_eq!(request.model, "google/gemini-2.0-flash-vertex");
+        let replayed = Completions::<LLMModelEnum>::from_request(&request, "").unwrap();
+        assert_eq!(replayed.model, model);
+    }
+
+    #[test]
+    fn test_options_missing_fields_use_defaults() {
+        // Options stored before a field was added deserialize with its default value
+        let options: CompletionsOptions =
+            serde_json::from_value(json!({"history": [], "n": 2})).unwrap();
+
+        assert!(!options.prompt_caching);
+        assert!(options.custom_base_instructions.is_none());
+        assert_eq!(options.n, Some(2));
+        assert_eq!(
+            serde_json::from_value::<CompletionsOptions>(json!({})).unwrap(),
+            CompletionsOptions::default()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_malformed_json_is_repaired() {
+        let answer = Completions::new(MockModel::new(r#"{"answer": "mocked",}"#), "", None, None)
+            .get_answer::<TestResponse>("Say something")
+            .await
+            .unwrap();
+        assert_eq!(answer.answer, "mocked");
+
+        // Responses cut off by `max_tokens` are completed
+        let answer = Completions::new(MockModel::new(r#"{"answer": "moc"#), "", None, None)
+            .get_answer::<TestResponse>("Say something")
+            .await
+            .unwrap();
+        assert_eq!(answer.answer, "moc");
+    }
+
+    #[tokio::test]
+    async fn test_deserialization_error_includes_raw_response() {
+        let raw_response = r#"{"unexpected": "format"}"#;
+
+        let error = Completions::new(MockModel::new(raw_response), "", None, None)
+            .get_answer::<TestResponse>("Say something")
+            .await
+            .unwrap_err();
+
+        assert!(error
+            .to_string()
+            .contains(r#"{\"unexpected\": \"format\"}"#));
+    }
+
+    #[tokio::test]
+    async fn test_raw_response_only_in_debug_mode() {
+        let raw_response = r#"{"answer": "mocked"}"#;
+
+        let response = Completions::new(MockModel::new(raw_response), "", None, None)
+            .get_response::<TestResponse>("Say something")
+            .await
+            .unwrap();
+        assert!(response.raw_response.is_none());
+
+        let response = Completions::new(MockModel::new(raw_response), "", None, None)
+            .debug()
+            .get_response::<TestResponse>("Say something")
+            .await
+            .unwrap();
+        assert_eq!(response.data.answer, "mocked");
+        assert_eq!(response.raw_response.as_deref(), Some(raw_response));
+    }
+
+    #[tokio::test]
+    async fn test_dry_run() {
+        let requests = Arc::new(Mutex::new(0));
+        let counter = requests.clone();
+        let completions =
+            Completions::new(MockModel::new(r#"{"answer": "mocked"}"#), "", None, None)
+                .dry_run(true)
+                .on_request(move |_| *counter.lock().unwrap() += 1);
+
+        // Every entry point returns the body in a `DryRun` error instead of sending it
+        let errors = vec![
+            completions
+                .clone()
+                .get_answer::<TestResponse>("Say something")
+                .await
+                .unwrap_err(),
+            completions
+                .clone()
+                .get_answers::<TestResponse>("Say something", 2)
+                .await
+                .unwrap_err(),
+            completions
+                .clone()
+                .get_text("Say something")
+                .await
+                .unwrap_err(),
+            completions
+                .clone()
+                .stream_tokens("Say something")
+                .await
+                .err()
+                .unwrap(),
+        ];
+        for error in errors {
+            let dry_run = error.downcast_ref::<DryRun>().unwrap();
+            assert_eq!(dry_run.model, "mock");
+            assert!(dry_run.body.to_string().contains("Say something"));
+        }
+        assert_eq!(*requests.lock().unwrap(), 0);
+
+        // Validation still runs before the body would be sent
+        let answer = Completions::new(MockModel::new(""), "", None, None)
+            .dry_run(true)
+            .logprobs(21)
+            .get_answer::<TestResponse>("Say something")
+            .await;
+        assert!(answer.unwrap_err().downcast_ref::<DryRun>().is_none());
+
+        // The mode is kept in the request snapshot
+        let request = completions
+            .to_request::<TestResponse>("Say something")
+            .unwrap();
+        assert!(request.dry_run);
+        let replayed = Completions::<MockModel>::from_request(&request, "").unwrap();
+        assert!(replayed.dry_run);
+    }
+
+    #[test]
+    fn test_count_tokens_body_uses_prompt() {
+        let completions = Completions::new(AnthropicModels::Claude3_5Sonnet, "", None, None)
+            .set_context("input", &"Rust")
+            .unwrap();
+        let body = completions
+            .get_count_tokens_body::<TestResponse>("Say something")
+            .unwrap();
+
+        // The instructions are wrapped with the input data and the output schema like in `get_answer`
+        let content = body["messages"][0]["content"].to_string();
+        assert!(content.contains("Say something"));
+        assert!(content.contains("Input data"));
+        assert!(content.contains("Rust"));
+        assert!(body.to_string().contains("answer"));
+    }
+
+    #[tokio::test]
+    async fn test_count_tokens_api_unsupported() {
+        let error = Completions::new(MockModel::default(), "", None, None)
+            .count_tokens_api::<TestResponse>("Say something")
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("not supported by mock"));
+    }
+
+    #[tokio::test]
+    async fn test_vertex_config_api_key() {
+        let completions = Completions::new(MockModel::default(), "api-key", None, None);
+        assert_eq!(completions.get_api_key().await.unwrap(), "api-key");
+
+        // The token from the config replaces the API key
+        let completions = completions.vertex_config(GeminiProVertexConfig::new(
+            VertexAuthMethod::RawBearer("vertex-token".to_string()),
+        ));
+        assert_eq!(completions.get_api_key().await.unwrap(), "vertex-token");
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn test_call_api_span_fields() {
+        use std::collections::HashMap;
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::Subscriber;
+        use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+        use tracing_subscriber::Registry;
+
+        // Layer capturing the fields recorded on spans
+        #[derive(Clone, Default)]
+        struct FieldsCapture(Arc<Mutex<HashMap<String, String>>>);
+
+        impl Visit for FieldsCapture {
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .insert(field.name().to_string(), format!("{:?}", value));
+            }
+        }
+
+        impl<S: Subscriber> Layer<S> for FieldsCapture {
+            fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .insert("span".to_string(), attrs.metadata().name().to_string());
+                attrs.record(&mut self.clone());
+            }
+
+            fn on_record(&self, _id: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+                values.record(&mut self.clone());
+            }
+        }
+
+        let capture = FieldsCapture::default();
+        let _guard = tracing::subscriber::set_default(Registry::default().with(capture.clone()));
+
+        // The provider is reported for models selected at runtime too
+        let model: Box<dyn LLMModel> =
+            Box::new(MockModel::new(r#"{"answer": "mocked"}"#).status(200));
+        Completions::new(model, "", None, None)
+            .get_answer::<TestResponse>("Say something")
+            .await
+            .unwrap();
+
+        let fields = capture.0.lock().unwrap().clone();
+        assert_eq!(
+            fields.get("span").map(String::as_str),
+            Some("allms.call_api")
+        );
+        assert_eq!(fields.get("model").map(String::as_str), Some("\"mock\""));
+        assert_eq!(
+            fields.get("provider").map(String::as_str),
+            Some("MockModel")
+        );
+        assert_eq!(fields.get("status").map(String::as_str), Some("200"));
+        assert!(fields.contains_key("duration_ms"));
+        assert!(fields.contains_key("endpoint"));
+        assert!(fields.contains_key("body_size_bytes"));
+
+        // Streamed responses are opened in their own span
+        let capture = FieldsCapture::default();
+        let _guard = tracing::subscriber::set_default(Registry::default().with(capture.clone()));
+
+        let _stream = Completions::new(MockModel::new("Hello"), "", None, None)
+            .stream_tokens("Say hello")
+            .await
+            .unwrap();
+
+        let fields = capture.0.lock().unwrap();
+        assert_eq!(
+            fields.get("span").map(String::as_str),
+            Some("allms.stream_api")
+        );
+        assert_eq!(
+            fields.get("provider").map(String::as_str),
+            Some("MockModel")
+        );
+        assert!(fields.contains_key("duration_ms"));
+        assert!(fields.contains_key("endpoint"));
+        assert!(!fields.contains_key("status"));
+    }
+
+    #[test]
+    fn test_temperature_range_validation() {
+        assert!(Completions::new(OpenAIModels::Gpt4o, "", None, None)
+            .try_temperature(1.5)
+            .is_ok());
+        assert!(Completions::new(OpenAIModels::Gpt4o, "", None, None)
+            .try_temperature(2.5)
+            .is_err());
+
+        assert!(
+            Completions::new(AnthropicModels::Claude3_5Sonnet, "", None, None)
+                .try_temperature(0.7)
+                .is_ok()
+        );
+        assert!(
+            Completions::new(AnthropicModels::Claude3_5Sonnet, "", None, None)
+                .try_temperature(1.5)
+                .is_err()
+        );
+
+        assert!(
+            Completions::new(DeepSeekModels::DeepSeekChat, "", None, None)
+                .try_temperature(1.5)
+                .is_ok()
+        );
+        assert!(Completions::new(PerplexityModels::Sonar, "", None, None)
+            .try_temperature(2.0)
+            .is_err());
+        assert!(
+            Completions::new(GoogleModels::Gemini1_5Flash, "", None, None)
+                .try_temperature(-0.1)
+                .is_err()
+        );
+        assert!(Completions::new(OpenAIModels::Gpt4o, "", None, None)
+            .try_temperature(f32::NAN)
+            .is_err());
+    }
+
+    #[test]
+    fn test_try_temperature_sets_value() {
+        let completions = Completions::new(OpenAIModels::Gpt4oMini, "", None, None)
+            .try_temperature(0.7)
+            .unwrap();
+        let request = completions
+            .to_request::<TestResponse>("Say something")
+            .unwrap();
+
+        assert_eq!(request.temperature, 0.7);
+    }
+
+    #[test]
+    fn test_temperature_policy_per_provider() {
+        let openai =
+            Completions::new(OpenAIModels::Gpt4o, "", None, None).temperature_unchecked(1.5);
+        let anthropic = Completions::new(AnthropicModels::Claude3_5Sonnet, "", None, None)
+            .temperature_unchecked(1.5);
+
+        assert_eq!(
+            openai
+                .temperature_policy(TemperaturePolicy::Error)
+                .get_checked_temperature()
+                .unwrap(),
+            1.5
+        );
+        assert!(anthropic
+            .temperature_policy(TemperaturePolicy::Error)
+            .get_checked_temperature()
+            .is_err());
+
+        let clamped = Completions::new(AnthropicModels::Claude3_5Sonnet, "", None, None)
+            .temperature_unchecked(1.5)
+            .temperature_policy(TemperaturePolicy::Clamp);
+        assert_eq!(clamped.get_checked_temperature().unwrap(), 1.0);
+
+        let unchecked = Completions::new(AnthropicModels::Claude3_5Sonnet, "", None, None)
+            .temperature_unchecked(1.5);
+        assert_eq!(unchecked.get_checked_temperature().unwrap(), 1.5);
+    }
+
+    #[derive(Deserialize, JsonSchema, Debug, PartialEq)]
+    enum Label {
+        Spam,
+        Ham,
+        #[serde(other)]
+        Unknown,
+    }
+
+    #[derive(Deserialize, JsonSchema, Debug)]
+    struct Classification {
+        label: Label,
+        #[schemars(range(min = 1, max = 5))]
+        confidence: u8,
+    }
+
+    #[tokio::test]
+    async fn test_validate_output() {
+        // The hallucinated label is accepted by serde but is not one of the values allowed by the schema
+        let model = MockModel::new(r#"{"label": "Eggs", "confidence": 9}"#);
+
+        let answer = Completions::new(model.clone(), "", None, None)
+            .get_answer::<Classification>("Classify the message")
+            .await
+            .unwrap();
+        assert_eq!(answer.label, Label::Unknown);
+
+        let error = Completions::new(model, "", None, None)
+            .validate_output(true)
+            .get_answer::<Classification>("Classify the message")
+            .await
+            .unwrap_err();
+        let mismatch = error.downcast_ref::<SchemaMismatch>().unwrap();
+        assert_eq!(mismatch.violations.len(), 2);
+        assert!(mismatch
+            .violations
+            .iter()
+            .any(|violation| violation.starts_with("#/label") && violation.contains("Eggs")));
+        assert!(mismatch
+            .violations
+            .iter()
+            .any(|violation| violation.starts_with("#/confidence")));
+
+        let answer = Completions::new(
+            MockModel::new(r#"{"label": "Ham", "confidence": 4}"#),
+            "",
+            None,
+            None,
+        )
+        .validate_output(true)
+        .get_answer::<Classification>("Classify the message")
+        .await
+        .unwrap();
+        assert_eq!(answer.label, Label::Ham);
+        assert_eq!(answer.confidence, 4);
+    }
+
+    #[tokio::test]
+    async fn test_get_answer_as_json_value() {
+        // The schema of `serde_json::Value` accepts any value
+        let answer = Completions::new(MockModel::new(r#"{"answer": 42}"#), "", None, None)
+            .get_answer::<Value>("Say something")
+            .await
+            .unwrap();
+        assert_eq!(answer, json!({"answer": 42}));
+    }
+
+    #[tokio::test]
+    async fn test_get_answers_emulates_multiple_choices() {
+        let model = MockModel::new(r#"{"answer": "mocked"}"#);
+        assert!(!model.supports_multiple_choices());
+
+        let answers = Completions::new(model, "", None, None)
+            .get_answers::<TestResponse>("Say something", 3)
+            .await
+            .unwrap();
+        assert_eq!(answers.len(), 3);
+        assert!(answers.iter().all(|answer| answer.answer == "mocked"));
+    }
+
+    #[tokio::test]
+    async fn test_response_provider() {
+        let model: Box<dyn LLMModel> = Box::new(MockModel::new(r#"{"answer": "mocked"}"#));
+        let response = Completions::new(model, "", None, None)
+            .get_response::<TestResponse>("Say something")
+            .await
+            .unwrap();
+        // Models implemented outside of the known providers are reported by their type name
+        assert_eq!(
+            response.provider,
+            LLMProvider::Other("MockModel".to_string())
+        );
+        assert_eq!(
+            Completions::new(GoogleModels::Gemini1_5Flash, "", None, None)
+                .model
+                .get_provider(),
+            LLMProvider::Google
+        );
+    }
+
+    #[test]
+    fn test_check_logprobs() {
+        assert!(Completions::new(OpenAIModels::Gpt4o, "", None, None)
+            .check_logprobs()
+            .is_ok());
+        assert!(Completions::new(OpenAIModels::Gpt4o, "", None, None)
+            .logprobs(5)
+            .check_logprobs()
+            .is_ok());
+        assert!(Completions::new(OpenAIModels::Gpt4o, "", None, None)
+            .logprobs(21)
+            .check_logprobs()
+            .is_err());
+        assert!(
+            Completions::new(AnthropicModels::Claude3_5Sonnet, "", None, None)
+                .logprobs(5)
+                .check_logprobs()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_check_model_options() {
+        let grounding = GroundingTool {
+            google_search_retrieval: true,
+            ..Default::default()
+        };
+        assert!(
+            Completions::new(GoogleModels::Gemini1_5Flash, "", None, None)
+                .grounding(grounding.clone())
+                .check_model_options(1_000)
+                .is_ok()
+        );
+        assert!(Completions::new(OpenAIModels::Gpt4o, "", None, None)
+            .grounding(grounding.clone())
+            .get_text_body("Who won the last Champions League?")
+            .is_err());
+        // Grounding turned off is accepted by every model
+        assert!(Completions::new(OpenAIModels::Gpt4o, "", None, None)
+            .grounding(GroundingTool::default())
+            .check_model_options(1_000)
+            .is_ok());
+
+        let documents = [Document::pdf(b"%PDF-1.4")];
+        assert!(
+            Completions::new(AnthropicModels::Claude3_5SonnetV2, "", None, None)
+                .documents(&documents)
+                .check_model_options(1_000)
+                .is_ok()
+        );
+        assert!(Completions::new(OpenAIModels::Gpt4o, "", None, None)
+            .documents(&documents)
+            .check_model_options(1_000)
+            .is_err());
+
+        // Thinking budget needs to be within 1024 and the tokens left for the response
+        let thinking = |budget_tokens: u32| {
+            Completions::new(AnthropicModels::Claude3_7Sonnet, "", Some(16_000), None)
+                .thinking_budget_tokens(budget_tokens)
+                .check_model_options(16_000)
+        };
+        assert!(thinking(8_000).is_ok());
+        assert!(thinking(512).is_err());
+        assert!(thinking(16_000).is_err());
+        assert!(
+            Completions::new(AnthropicModels::Claude3_5Sonnet, "", None, None)
+                .thinking_budget_tokens(2_048)
+                .check_model_options(1_000)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_thinking_budget_checked_against_response_tokens() {
+        let completions = Completions::new(AnthropicModels::Claude3_7Sonnet, "", Some(4_000), None)
+            .thinking_budget_tokens(3_000);
+
+        let body = completions.get_text_body("Say something").unwrap();
+        assert!(body["max_tokens"].as_u64().unwrap() > 3_000);
+
+        // The budget fits in the max tokens but not in what the prompt leaves for the response
+        let error = completions
+            .get_text_body(&"lorem ipsum ".repeat(1_000))
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("less than the tokens left for the response"));
+    }
+
+    #[tokio::test]
+    async fn test_out_of_range_temperature_fails_before_sending() {
+        let model = MockModel {
+            should_fail: true,
+            ..MockModel::new(r#"{"answer": "mocked"}"#)
+        };
+
+        let error = Completions::new(model, "", None, None)
+            .temperature_unchecked(1.5)
+            .temperature_policy(TemperaturePolicy::Error)
+            .get_answer::<TestResponse>("Say something")
+            .await
+            .unwrap_err();
+
+        assert!(error
+            .to_string()
+            .contains("Temperature is outside of the range"));
+    }
+
+    #[test]
+    fn test_estimate_cost() {
+        let completions = Completions::new(OpenAIModels::Gpt4o, "", Some(1_000), None);
+        let instructions = "Summarize the following text: lorem ipsum dolor sit amet";
+
+        let prompt_tokens = completions
+            .check_prompt_tokens::<TestResponse>(instructions)
+            .unwrap();
+        let cost = completions
+            .estimate_cost::<TestResponse>(instructions)
+            .unwrap();
+        // $2.50 per 1M input tokens and $10 per 1M output tokens
+        let expected = prompt_tokens as f64 * 0.0000025 + (1_000 - prompt_tokens) as f64 * 0.00001;
+        assert!((cost - expected).abs() < 1e-12);
+        assert!(cost > 0.0025 && cost < 0.01);
+
+        // Prices of custom models are unknown
+        let completions = Completions::new(
+            OpenAIModels::Custom {
+                name: "gpt-custom".to_string(),
+            },
+            "",
+            Some(1_000),
+            None,
+        );
+        assert!(completions
+            .estimate_cost::<TestResponse>(instructions)
+            .is_err());
+
+        // Prompts that do not fit are rejected as they would be when sending
+        let completions = Completions::new(OpenAIModels::Gpt4o, "", Some(100), None);
+        let error = completions
+            .estimate_cost::<TestResponse>(&"lorem ipsum ".repeat(100))
+            .unwrap_err();
+        assert!(error.downcast_ref::<ContextLengthExceeded>().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_context_length_exceeded_fails_before_sending() {
+        let model = MockModel::new(r#"{"answer": "mocked"}"#).max_tokens(1_000);
+        let context = model.context_window_tokens();
+        assert_eq!(context, 1_000);
+
+        let prompt = "lorem ipsum dolor sit amet ".repeat(400);
+        let error = Completions::new(model.clone(), "", None, None)
+            .get_answer::<TestResponse>(&prompt)
+            .await
+            .unwrap_err();
+        let exceeded = error.downcast_ref::<ContextLengthExceeded>().unwrap();
+        assert!(exceeded.prompt > context);
+        assert_eq!(exceeded.max, context);
+        assert_eq!(exceeded.context, context);
+
+        let error = Completions::new(model.clone(), "", None, None)
+            .get_text(&prompt)
+            .await
+            .unwrap_err();
+        assert!(error.downcast_ref::<ContextLengthExceeded>().is_some());
+
+        // Allocating more tokens than the context window fails even for a short prompt
+        let error = Completions::new(model.clone(), "", Some(context + 1), None)
+            .get_answer::<TestResponse>("Say something")
+            .await
+            .unwrap_err();
+        let exceeded = error.downcast_ref::<ContextLengthExceeded>().unwrap();
+        assert_eq!(exceeded.max, context + 1);
+        assert!(model.calls.is_empty());
+    }
+
+    #[test]
+    fn test_prepend_schema_description() {
+        let json_schema = json!({
+            "type": "object",
+            "properties": {"answer": {"type": "string"}},
+            "required": ["answer"],
+        });
+
+        let completions = Completions::new(MockModel::default(), "", None, None);
+        let prompt = completions.get_prompt("Say something", &json_schema);
+        assert!(!prompt.contains("answer (string, required)"));
+
+        let completions = completions.prepend_schema_description(true);
+        let prompt = completions.get_prompt("Say something", &json_schema);
+        assert!(prompt.contains(
+            "Return a JSON object with the following fields:\n- answer (string, required)\n\nSay something"
+        ));
+
+        // The option is kept in persisted requests
+        let request = completions
+            .to_request::<TestResponse>("Say something")
+            .unwrap();
+        assert!(request.options.prepend_schema_description);
+    }
+
+    #[test]
+    fn test_few_shot_examples_in_body() {
+        let history = [Message::user("Hello"), Message::assistant("Hi!")];
+        let completions = Completions::new(MockModel::default(), "", Some(2_000), None)
+            .history(&history)
+            .few_shot_examples(&[("I love it", "positive"), ("I hate it", "negative")]);
+        let body = completions.get_text_body("Classify: it's fine").unwrap();
+        let messages = body["history"].as_array().unwrap();
+        assert_eq!(messages.len(), 6);
+        assert_eq!(messages[0]["role"], "user");
+        assert_eq!(messages[0]["content"], "I love it");
+        assert_eq!(messages[1]["role"], "assistant");
+        assert_eq!(messages[1]["content"], "positive");
+        assert_eq!(messages[4]["content"], "Hello");
+
+        // Examples that do not fit in half of the allocated tokens are dropped
+        let long_example = "lorem ipsum ".repeat(1_000);
+        let completions = Completions::new(MockModel::default(), "", Some(2_000), None)
+            .few_shot_examples(&[("I love it", "positive"), (&long_example, "neutral")]);
+        let body = completions.get_text_body("Classify: it's fine").unwrap();
+        let messages = body["history"].as_array().unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0]["content"], "I love it");
+    }
+
+    #[test]
+    fn test_text_body_without_json_instructions() {
+        let models: Vec<Box<dyn LLMModel>> = vec![
+            Box::new(OpenAIModels::Gpt4o),
+            Box::new(OpenAIModels::TextDavinci003),
+            Box::new(OpenAIModels::O1),
+            Box::new(AnthropicModels::Claude3_5Sonnet),
+            Box::new(AnthropicModels::Claude2),
+            Box::new(GoogleModels::Gemini1_5Flash),
+            Box::new(GoogleModels::Gemini2_0Flash),
+            Box::new(MistralModels::MistralSmall),
+            Box::new(DeepSeekModels::DeepSeekChat),
+            Box::new(PerplexityModels::Sonar),
+        ];
+        for model in models {
+            let model_name = model.as_str().to_string();
+            let mut completions = Completions::new(model, "", None, None);
+            completions.function_call = false;
+            completions.options.custom_base_instructions =
+                Some(PLAIN_TEXT_BASE_INSTRUCTIONS.to_string());
+
+            let body = completions
+                .get_text_body("Write a haiku about Rust")
+                .unwrap()
+                .to_string();
+            assert!(body.contains("Write a haiku about Rust"), "{}", model_name);
+            assert!(!body.contains("Json"), "{}: {}", model_name, body);
+            assert!(!body.contains("schema"), "{}: {}", model_name, body);
+        }
+    }
+
+    #[test]
+    fn test_custom_base_instructions_in_body() {
+        let base_instructions = "Output only JSON, no markdown.";
+
+        // Custom base instructions take precedence over the plain-text ones
+        let mut completions = Completions::new(GoogleModels::Gemini1_5Flash, "", None, None)
+            .base_instructions(base_instructions);
+        completions.set_plain_text();
+        let body = completions.get_text_body("Say something").unwrap();
+        assert_eq!(
+            body["system_instruction"]["parts"][0]["text"],
+            base_instructions
+        );
+
+        let mut completions = Completions::new(OpenAIModels::Gpt4o, "", None, None)
+            .base_instructions(base_instructions);
+        completions.set_plain_text();
+        let body = completions.get_text_body("Say something").unwrap();
+        assert_eq!(body["messages"][0]["content"], base_instructions);
+    }
+
+    #[tokio::test]
+    async fn test_stream_tokens() {
+        let stream = Completions::new(
+            MockModel::new("Hello there, general Kenobi"),
+            "",
+            None,
+            None,
+        )
+        .stream_tokens("Say hello")
+        .await
+        .unwrap();
+        let chunks: Vec<String> = stream.try_collect().await.unwrap();
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks.concat(), "Hello there, general Kenobi");
+
+        assert!(
+            Completions::new(MockModel::default().failing(), "", None, None)
+                .stream_tokens("Say hello")
+                .await
+                .is_err()
+        );
+
+        // Models without streaming support fail before sending the request
+        let error = Completions::new(OpenAIModels::O1, "", None, None)
+            .stream_tokens("Say hello")
+            .await
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("Streaming is not supported"));
+    }
+
+    #[tokio::test]
+    async fn test_truncation_policy() {
+        let model = MockModel::new(r#"{"answer": "mocked"}"#);
+        let log = (0..2_000)
+            .map(|line| format!("line {line}\n"))
+            .collect::<String>();
+        let max_tokens = Some(1_000);
+
+        // Oversized prompts fail unless truncation is enabled
+        let error = Completions::new(model.clone(), "", max_tokens, None)
+            .get_answer::<TestResponse>(&log)
+            .await
+            .unwrap_err();
+        assert!(error.downcast_ref::<ContextLengthExceeded>().is_some());
+
+        // Whether the policy keeps the start of the prompt in addition to its end
+        for (policy, keeps_head) in [
+            (TruncationPolicy::DropStart, false),
+            (TruncationPolicy::DropMiddle, true),
+            (TruncationPolicy::KeepHeadTail { head_tokens: 50 }, true),
+        ] {
+            let completions =
+                Completions::new(model.clone(), "", max_tokens, None).truncation_policy(policy);
+            let truncated = completions
+                .truncate_instructions(
+                    &log,
+                    completions
+                        .check_prompt_tokens::<TestResponse>(&log)
+                        .unwrap(),
+                    completions.count_prompt_tokens::<TestResponse>("").unwrap(),
+                )
+                .unwrap()
+                .unwrap();
+            assert!(
+                completions
+                    .count_prompt_tokens::<TestResponse>(&truncated)
+                    .unwrap()
+                    <= 500
+            );
+            assert!(truncated.ends_with("line 1999\n"));
+            assert_eq!(truncated.starts_with("line 0\nline 1\n"), keeps_head);
+            assert!(!truncated.contains("line 1000\n"));
+
+            let answer = completions
+                .clone()
+                .get_answer::<TestResponse>(&log)
+                .await
+                .unwrap();
+            assert_eq!(answer.answer, "mocked");
+            // The truncated prompt is the one sent to the model
+            let sent = model.calls.all().last().unwrap().body["instructions"]
+                .as_str()
+                .unwrap()
+                .to_string();
+            assert!(sent.contains("line 1999\n"));
+            assert_eq!(sent.contains("line 0\nline 1\n"), keeps_head);
+            assert!(!sent.contains("line 1000\n"));
+            assert_eq!(
+                completions.get_text(&log).await.unwrap(),
+                r#"{"answer": "mocked"}"#
+            );
+        }
+    }
+
+    #[test]
+    fn test_temperature_new() {
+        let openai = OpenAIModels::Gpt4o;
+        let anthropic = AnthropicModels::Claude3_5Sonnet;
+
+        assert_eq!(Temperature::new(1.5, &openai).unwrap().value(), 1.5);
+        assert!(Temperature::new(1.5, &anthropic).is_err());
+        assert!(Temperature::new(100.0, &openai).is_err());
+
+        let temperature = Temperature::new(0.3, &anthropic).unwrap();
+        let request = Completions::new(anthropic, "", None, None)
+            .validated_temperature(temperature)
+            .to_request::<TestResponse>("Say something")
+            .unwrap();
+        assert_eq!(request.temperature, 0.3);
+    }
 }
@@ -1,8 +1,20 @@
 use lazy_static::lazy_static;
+use std::time::Duration;
 
 lazy_static! {
     pub(crate) static ref OPENAI_API_URL: String =
         std::env::var("OPENAI_API_URL").unwrap_or("https://api.openai.com".to_string());
+    //Sent as the `OpenAI-Organization` and `OpenAI-Project` headers, e.g. for billing attribution or project-scoped keys
+    pub(crate) static ref OPENAI_ORG_ID: Option<String> = std::env::var("OPENAI_ORG_ID").ok();
+    pub(crate) static ref OPENAI_PROJECT_ID: Option<String> =
+        std::env::var("OPENAI_PROJECT_ID").ok();
+}
+
+#[cfg(feature = "realtime")]
+lazy_static! {
+    pub(crate) static ref OPENAI_REALTIME_API_URL: String =
+        std::env::var("OPENAI_REALTIME_API_URL")
+            .unwrap_or("wss://api.openai.com/v1/realtime".to_string());
 }
 
 lazy_static! {
@@ -11,11 +23,35 @@ lazy_static! {
     pub(crate) static ref ANTHROPIC_MESSAGES_API_URL: String =
         std::env::var("ANTHROPIC_MESSAGES_API_URL")
             .unwrap_or("https://api.anthropic.com/v1/messages".to_string());
+    pub(crate) static ref ANTHROPIC_MODELS_API_URL: String =
+        std::env::var("ANTHROPIC_MODELS_API_URL")
+            .unwrap_or("https://api.anthropic.com/v1/models".to_string());
+    pub(crate) static ref ANTHROPIC_COUNT_TOKENS_API_URL: String =
+        std::env::var("ANTHROPIC_COUNT_TOKENS_API_URL")
+            .unwrap_or("https://api.anthropic.com/v1/messages/count_tokens".to_string());
+    //Messages API responses are not streamed by default. Set to `true` to stream them, e.g. to avoid gateway timeouts for long outputs
+    pub(crate) static ref ANTHROPIC_STREAMING: bool = std::env::var("ANTHROPIC_STREAMING")
+        .map(|streaming| streaming.to_lowercase() == "true")
+        .unwrap_or(false);
+    //Sent in the `anthropic-version` header of all requests
+    pub(crate) static ref ANTHROPIC_API_VERSION: String =
+        std::env::var("ANTHROPIC_API_VERSION").unwrap_or("2023-06-01".to_string());
+    //Comma-separated beta features sent in the `anthropic-beta` header, e.g. `output-128k-2025-02-19`
+    pub(crate) static ref ANTHROPIC_BETA: Option<String> = std::env::var("ANTHROPIC_BETA").ok();
 }
 
 lazy_static! {
     pub(crate) static ref MISTRAL_API_URL: String = std::env::var("MISTRAL_API_URL")
         .unwrap_or("https://api.mistral.ai/v1/chat/completions".to_string());
+    pub(crate) static ref MISTRAL_MODELS_API_URL: String = std::env::var("MISTRAL_MODELS_API_URL")
+        .unwrap_or("https://api.mistral.ai/v1/models".to_string());
+    //Chat endpoint of local deployments of open-weight models, e.g. served with Ollama
+    pub(crate) static ref MISTRAL_LOCAL_URL: String = std::env::var("MISTRAL_LOCAL_URL")
+        .unwrap_or("http://localhost:11434/v1/chat/completions".to_string());
+    //Prepends the Mistral safety prompt to requests sent to the hosted API
+    pub(crate) static ref MISTRAL_SAFE_PROMPT: bool = std::env::var("MISTRAL_SAFE_PROMPT")
+        .map(|safe_prompt| safe_prompt.to_lowercase() == "true")
+        .unwrap_or(false);
 }
 
 lazy_static! {
@@ -26,11 +62,29 @@ lazy_static! {
         format!("https://{}-aiplatform.googleapis.com/v1/projects/{}/locations/{}/publishers/google/models",
                 region, project_id, region)
     };
+    //Vertex responses are streamed by default. Set to `false` to use the non-streaming `generateContent` endpoint
+    pub(crate) static ref GOOGLE_VERTEX_STREAMING: bool = std::env::var("GOOGLE_VERTEX_STREAMING")
+        .map(|streaming| streaming.to_lowercase() != "false")
+        .unwrap_or(true);
     pub(crate) static ref GOOGLE_GEMINI_API_URL: String = std::env::var("GOOGLE_GEMINI_API_URL")
         .unwrap_or(
             "https://generativelanguage.googleapis.com/v1/models/gemini-pro:generateContent"
                 .to_string()
         );
+    pub(crate) static ref GOOGLE_GEMINI_MODELS_API_URL: String =
+        std::env::var("GOOGLE_GEMINI_MODELS_API_URL")
+            .unwrap_or("https://generativelanguage.googleapis.com/v1beta/models".to_string());
+    pub(crate) static ref GOOGLE_GEMINI_FILES_API_URL: String =
+        std::env::var("GOOGLE_GEMINI_FILES_API_URL")
+            .unwrap_or("https://generativelanguage.googleapis.com/v1beta/files".to_string());
+    pub(crate) static ref GOOGLE_GEMINI_UPLOAD_API_URL: String =
+        std::env::var("GOOGLE_GEMINI_UPLOAD_API_URL")
+            .unwrap_or("https://generativelanguage.googleapis.com/upload/v1beta/files".to_string());
+}
+
+lazy_static! {
+    pub(crate) static ref VOYAGE_API_URL: String = std::env::var("VOYAGE_API_URL")
+        .unwrap_or("https://api.voyageai.com/v1/embeddings".to_string());
 }
 
 lazy_static! {
@@ -56,6 +110,25 @@ lazy_static! {
 lazy_static! {
     pub(crate) static ref DEEPSEEK_API_URL: String = std::env::var("DEEPSEEK_API_URL")
         .unwrap_or("https://api.deepseek.com/chat/completions".to_string());
+    pub(crate) static ref DEEPSEEK_MODELS_API_URL: String =
+        std::env::var("DEEPSEEK_MODELS_API_URL")
+            .unwrap_or("https://api.deepseek.com/models".to_string());
+}
+
+lazy_static! {
+    //Requests rejected with 429 Too Many Requests are sent again after the wait indicated in the response headers, up to this number of times
+    //Not retried by default. Can be overridden per client with `Completions::rate_limit_retries`
+    pub(crate) static ref RATE_LIMIT_RETRIES: usize = std::env::var("RATE_LIMIT_RETRIES")
+        .ok()
+        .and_then(|retries| retries.parse().ok())
+        .unwrap_or(0);
+    //Rate limit errors asking for a longer wait are returned without retrying
+    pub(crate) static ref RATE_LIMIT_MAX_WAIT: Duration = Duration::from_secs(
+        std::env::var("RATE_LIMIT_MAX_WAIT_SECS")
+            .ok()
+            .and_then(|seconds| seconds.parse().ok())
+            .unwrap_or(60)
+    );
 }
 
 //Generic OpenAI instructions
@@ -68,15 +141,37 @@ Step 5: Validate that the Json object matches the 'output Json schema' and corre
 Step 6: Respond ONLY with properly formatted Json object. No other words or text, only valid Json in the answer.
 "#;
 
+//Used with `OutputStrictness::Strict` to demand minified Json without any markdown or explanation
+pub(crate) const OPENAI_STRICT_BASE_INSTRUCTIONS: &str = r#"You are a computer function. You are expected to perform the following tasks:
+Step 1: Review and understand the 'instructions' from the *Instructions* section.
+Step 2: Based on the 'instructions' process the data provided in the *Input data* section using your language model.
+Step 3: Prepare a response by processing the 'input data' as per the 'instructions'.
+Step 4: Convert the response to a Json object. The Json object must match the schema provided in the *Output Json schema* section.
+Step 5: Validate that the Json object matches the 'output Json schema' and correct if needed. If you are not able to generate a valid Json based on the 'input data' and 'instructions' please respond with "Error calculating the answer."
+Step 6: Respond ONLY with the Json object minified to a single line. Do NOT wrap it in markdown code fences and do NOT add any explanation, comments or other text.
+"#;
+
+//Used with `OutputStrictness::Lenient` as markdown code fences are removed before the output is deserialized
+pub(crate) const OPENAI_LENIENT_BASE_INSTRUCTIONS: &str = r#"You are a computer function. You are expected to perform the following tasks:
+Step 1: Review and understand the 'instructions' from the *Instructions* section.
+Step 2: Based on the 'instructions' process the data provided in the *Input data* section using your language model.
+Step 3: Prepare a response by processing the 'input data' as per the 'instructions'.
+Step 4: Convert the response to a Json object. The Json object must match the schema provided in the *Output Json schema* section.
+Step 5: Respond with the Json object. It can be formatted as a markdown code block.
+"#;
+
+//With function calling the output format is enforced by the function definition, so the Json formatting steps are omitted
 pub(crate) const OPENAI_FUNCTION_INSTRUCTIONS: &str = r#"You are a computer function. You are expected to perform the following tasks:
 Step 1: Review and understand the 'instructions' from the *Instructions* section.
 Step 2: Based on the 'instructions' process the data provided in the *Input data* section using your language model.
-Step 3: Prepare a response by processing the 'input data' as per the 'instructions'. 
-Step 4: Convert the response to a Json object. The Json object must match the schema provided in the function definition.
-Step 5: Validate that the Json object matches the function properties and correct if needed. If you are not able to generate a valid Json based on the 'input data' and 'instructions' please respond with "Error calculating the answer."
-Step 6: Respond ONLY with properly formatted Json object. No other words or text, only valid Json in the answer.
+Step 3: Prepare a response by processing the 'input data' as per the 'instructions'.
+Step 4: Return the response by calling the provided function.
 "#;
 
+//Used instead of the Json-specific base instructions for plain-text completions
+pub(crate) const PLAIN_TEXT_BASE_INSTRUCTIONS: &str =
+    "You are a helpful assistant. Respond to the instructions with plain text.";
+
 pub(crate) const OPENAI_ASSISTANT_INSTRUCTIONS: &str = r#"You are a computer function. You are expected to perform the following tasks:
 1: Review and understand the content of user messages passed to you in the thread.
 2: Review and consider any files the user provided attached to the messages.
@@ -300,34 +300,42 @@ impl OpenAIModels {
             OpenAIModels::Gpt3_5Turbo => RateLimit {
                 tpm: 2_000_000,
                 rpm: 10_000,
+                rpd: None,
             },
             OpenAIModels::Gpt3_5Turbo0613 => RateLimit {
                 tpm: 2_000_000,
                 rpm: 10_000,
+                rpd: None,
             },
             OpenAIModels::Gpt3_5Turbo16k => RateLimit {
                 tpm: 2_000_000,
                 rpm: 10_000,
+                rpd: None,
             },
             OpenAIModels::Gpt4 => RateLimit {
                 tpm: 300_000,
                 rpm: 10_000,
+                rpd: None,
             },
             OpenAIModels::Gpt4Turbo => RateLimit {
                 tpm: 2_000_000,
                 rpm: 10_000,
+                rpd: None,
             },
             OpenAIModels::Gpt4_32k => RateLimit {
                 tpm: 300_000,
                 rpm: 10_000,
+                rpd: None,
             },
             OpenAIModels::Gpt4o => RateLimit {
                 tpm: 2_000_000,
                 rpm: 10_000,
+                rpd: None,
             },
             OpenAIModels::TextDavinci003 => RateLimit {
                 tpm: 250_000,
                 rpm: 3_000,
+                rpd: None,
             },
         }
     }
@@ -520,9 +528,7 @@ impl OpenAI {
         if prompt_tokens * 2 >= self.max_tokens {
             warn!(
                 "{} tokens remaining for response: {} allocated, {} used for prompt",
-                response_tokens.to_string(),
-                self.max_tokens.to_string(),
-                prompt_tokens.to_string(),
+                response_tokens, self.max_tokens, prompt_tokens,
             );
         };
 
@@ -540,8 +546,7 @@ impl OpenAI {
             info!("[debug] Model body: {:#?}", model_body);
             info!(
                 "[debug] Prompt accounts for approx {} tokens, leaving {} tokens for answer.",
-                prompt_tokens.to_string(),
-                response_tokens.to_string(),
+                prompt_tokens, response_tokens,
             );
         }
 
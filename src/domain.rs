@@ -1,7 +1,12 @@
+use anyhow::{anyhow, Result};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::time::Duration;
 
 use crate::enums::{OpenAIAssistantRole, OpenAIRunStatus, OpenAIToolTypes};
+use crate::llm_models::LLMModel;
 
 //OpenAI API response type format for Completions API
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -38,6 +43,14 @@ pub struct OpenAPIChatChoices {
     pub message: OpenAPIChatMessage,
     pub index: Option<u32>,
     pub finish_reason: Option<String>,
+    //Only included if requested with `logprobs`
+    #[serde(default)]
+    pub logprobs: Option<OpenAPIChatLogprobs>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OpenAPIChatLogprobs {
+    pub content: Option<Vec<TokenLogprob>>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -45,6 +58,22 @@ pub struct OpenAPIChatMessage {
     pub role: String,
     pub content: Option<String>,
     pub function_call: Option<OpenAPIChatFunctionCall>,
+    //Web sources cited in the content. Only included for search models
+    #[serde(default)]
+    pub annotations: Option<Vec<OpenAPIChatAnnotation>>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OpenAPIChatAnnotation {
+    #[serde(rename(deserialize = "type", serialize = "type"))]
+    pub annotation_type: String,
+    pub url_citation: Option<OpenAPIChatUrlCitation>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OpenAPIChatUrlCitation {
+    pub url: String,
+    pub title: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -64,6 +93,470 @@ pub struct OpenAPIUsage {
 pub struct RateLimit {
     pub tpm: usize, // tokens-per-minute
     pub rpm: usize, // requests-per-minute
+    #[serde(default)]
+    pub rpd: Option<u64>, // requests-per-day (if the API enforces a daily quota)
+}
+
+///Rate limit status reported by the API in the response headers, e.g. `x-ratelimit-remaining-requests` for OpenAI
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct RateLimitStatus {
+    pub limit_requests: Option<u64>,
+    pub remaining_requests: Option<u64>,
+    //Time until the request limit is reset to its initial state
+    pub reset_requests: Option<Duration>,
+    pub limit_tokens: Option<u64>,
+    pub remaining_tokens: Option<u64>,
+    //Time until the token limit is reset to its initial state
+    pub reset_tokens: Option<Duration>,
+}
+
+///Response of an API call with the metadata of the HTTP response
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ApiResponse {
+    pub text: String,
+    //HTTP status code of the response. `None` for models not reporting it, e.g. if the API is called with an SDK
+    pub status: Option<u16>,
+    //Rate limits reported in the response headers, if any
+    pub rate_limit_status: Option<RateLimitStatus>,
+}
+
+impl ApiResponse {
+    ///Returns false if the API returned an HTTP error status
+    pub fn is_success(&self) -> bool {
+        self.status
+            .is_none_or(|status| (200..300).contains(&status))
+    }
+}
+
+///Optional settings of a Completions request that are passed to the model when building the API body
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct CompletionsOptions {
+    ///Marks the static part of the prompt (base instructions & output schema) as cacheable. Currently supported by Anthropic models only.
+    #[serde(default)]
+    pub prompt_caching: bool,
+    ///Replaces the default base (system) instructions of the model
+    #[serde(default)]
+    pub custom_base_instructions: Option<String>,
+    ///Previous turns of the conversation sent before the current instructions, in order
+    #[serde(default)]
+    pub history: Vec<Message>,
+    ///Labelled examples (input, expected output) for in-context learning. They are sent as previous turns of the conversation, ahead of the history.
+    #[serde(default)]
+    pub few_shot_examples: Vec<(String, String)>,
+    ///Requests log probabilities of the response tokens with the given number of most likely alternatives per token. Only supported by models for which `supports_logprobs` returns true.
+    #[serde(default)]
+    pub logprobs: Option<u8>,
+    ///Number of completions generated for the prompt. Only supported by models for which `supports_multiple_choices` returns true.
+    #[serde(default)]
+    pub n: Option<u8>,
+    ///Lets the model ground its answer in live web results. Only supported by models for which `supports_grounding` returns true.
+    #[serde(default)]
+    pub grounding: Option<GroundingTool>,
+    ///Files (e.g. large PDFs uploaded with the Gemini File API) sent to the model along with the instructions. Only supported by models for which `supports_file_data` returns true.
+    #[serde(default)]
+    pub files: Vec<FileData>,
+    ///PDF documents sent inline to the model along with the instructions. Only supported by models for which `supports_pdf_documents` returns true.
+    #[serde(default)]
+    pub documents: Vec<Document>,
+    ///Enables extended thinking with the given budget of tokens the model can use for its reasoning. Only supported by models for which `supports_thinking` returns true.
+    #[serde(default)]
+    pub thinking_budget_tokens: Option<u32>,
+    ///Identifies the end-user on whose behalf the request is sent, e.g. a hashed user ID, to help the provider detect abuse. Currently sent by OpenAI models only.
+    #[serde(default)]
+    pub end_user_id: Option<String>,
+    ///Prepends a natural-language description of the output schema (see `schema_to_description`) to the instructions, helping models follow complex schemas
+    #[serde(default)]
+    pub prepend_schema_description: bool,
+    ///How strictly the base instructions ask the model to format its Json output. Not applied if custom base instructions are set.
+    #[serde(default)]
+    pub output_strictness: OutputStrictness,
+    ///Streams the API response and aggregates it into a single response, e.g. to avoid gateway timeouts for long outputs. Defaults to the `ANTHROPIC_STREAMING` environment variable for Anthropic models and `GOOGLE_VERTEX_STREAMING` for Vertex AI models.
+    #[serde(default)]
+    pub streaming: Option<bool>,
+}
+
+impl CompletionsOptions {
+    ///
+    /// This method returns true if grounding with Google Search was requested
+    ///
+    pub fn google_search_retrieval(&self) -> bool {
+        self.grounding
+            .as_ref()
+            .is_some_and(|grounding| grounding.google_search_retrieval)
+    }
+
+    ///
+    /// This method returns true if grounding with the built-in search tool of the provider was requested. Google Search retrieval is the search tool of Gemini models.
+    ///
+    pub fn web_search(&self) -> bool {
+        self.grounding
+            .as_ref()
+            .is_some_and(|grounding| grounding.web_search || grounding.google_search_retrieval)
+    }
+
+    ///
+    /// This method returns the messages sent ahead of the current instructions: the few-shot examples as user/assistant turns, followed by the history of the conversation
+    ///
+    pub fn get_history(&self) -> Vec<Message> {
+        self.few_shot_examples
+            .iter()
+            .flat_map(|(input, output)| [Message::user(input), Message::assistant(output)])
+            .chain(self.history.iter().cloned())
+            .collect()
+    }
+}
+
+///Tools the model can use to ground its answer in external sources
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroundingTool {
+    ///Retrieves live web results with Google Search. Currently supported by Gemini models only.
+    pub google_search_retrieval: bool,
+    ///Retrieves live web results with the built-in search tool of the provider, e.g. Google Search for Gemini or web search for OpenAI search models
+    #[serde(default)]
+    pub web_search: bool,
+}
+
+///Web source used to ground the answer, as reported in the grounding metadata of the response
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct GroundingCitation {
+    pub uri: String,
+    pub title: Option<String>,
+}
+
+///Reference to a file uploaded to the provider, e.g. with `GeminiFile::upload`
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct FileData {
+    pub mime_type: String,
+    pub file_uri: String,
+}
+
+///Document sent inline as base64-encoded data, e.g. a PDF analyzed by the model without extracting its text first
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Document {
+    pub media_type: String,
+    pub data: String,
+}
+
+impl Document {
+    ///
+    /// This function creates a PDF document from the bytes of the file
+    ///
+    pub fn pdf(bytes: &[u8]) -> Self {
+        Document {
+            media_type: "application/pdf".to_string(),
+            data: base64::encode(bytes),
+        }
+    }
+}
+
+///Author of a message in the conversation history
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageRole {
+    User,
+    Assistant,
+}
+
+impl MessageRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessageRole::User => "user",
+            MessageRole::Assistant => "assistant",
+        }
+    }
+}
+
+///Single turn of a multi-turn conversation
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub role: MessageRole,
+    pub content: String,
+}
+
+impl Message {
+    pub fn user(content: &str) -> Self {
+        Message {
+            role: MessageRole::User,
+            content: content.to_string(),
+        }
+    }
+
+    pub fn assistant(content: &str) -> Self {
+        Message {
+            role: MessageRole::Assistant,
+            content: content.to_string(),
+        }
+    }
+}
+
+///Defines how a temperature outside of the range accepted by the model is handled before the request is sent
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TemperaturePolicy {
+    ///Returns an error without calling the API
+    #[default]
+    Error,
+    ///Clamps the temperature to the closest value accepted by the model
+    Clamp,
+    ///Sends the temperature as-is
+    Unchecked,
+}
+
+///Defines how a prompt exceeding the tokens allocated for it is handled before the request is sent
+///Truncation is applied to the instructions, leaving the base instructions, input data and output schema intact. The truncated instructions take up to half of the allocated tokens so that room remains for the response.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TruncationPolicy {
+    ///Returns a `ContextLengthExceeded` error without calling the API
+    #[default]
+    Error,
+    ///Drops tokens from the start of the instructions, keeping the most recent content (e.g. the latest log lines)
+    DropStart,
+    ///Drops tokens from the middle of the instructions, keeping equally sized head and tail
+    DropMiddle,
+    ///Keeps up to `head_tokens` tokens from the start of the instructions (e.g. a task description) and fills the remaining tokens from the end
+    KeepHeadTail { head_tokens: usize },
+}
+
+///Defines how strictly the base instructions ask the model to format its Json output. Not applied with function calling, where the format is enforced by the function definition.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputStrictness {
+    ///Asks for the Json object only, without other words
+    #[default]
+    Standard,
+    ///Asks for minified Json only, without markdown code fences or any explanation. Pairs well with `validate_output`.
+    Strict,
+    ///Allows the Json object to be wrapped in markdown code fences, which are removed before deserialization
+    Lenient,
+}
+
+///Temperature validated against the range accepted by a model, e.g. 0-2 for OpenAI or 0-1 for Anthropic
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct Temperature(f32);
+
+impl Temperature {
+    pub fn new(value: f32, model: &dyn LLMModel) -> Result<Self> {
+        let (min, max) = model.get_temperature_range();
+        if !(min..=max).contains(&value) {
+            return Err(anyhow!(
+                "Temperature {} is outside of the range accepted by {}: {}-{}",
+                value,
+                model.as_str(),
+                min,
+                max
+            ));
+        }
+        Ok(Temperature(value))
+    }
+
+    pub fn value(&self) -> f32 {
+        self.0
+    }
+}
+
+impl From<Temperature> for f32 {
+    fn from(temperature: Temperature) -> Self {
+        temperature.0
+    }
+}
+
+///Snapshot of a Completions request that can be persisted (e.g. for audit logs or queues) and replayed with `Completions::from_request`
+///Optional fields are always serialized (as `null` when not set) to keep the stored format stable
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct CompletionsRequest {
+    //Model identifier as returned by `LLMModel::id`, e.g. `gemini-1.5-pro-vertex`
+    pub model: String,
+    pub instructions: String,
+    pub json_schema: Value,
+    pub input_json: Option<String>,
+    pub max_tokens: usize,
+    pub temperature: f32,
+    pub function_call: bool,
+    //Only builds and logs the request body instead of sending it. See `Completions::dry_run`
+    #[serde(default)]
+    pub dry_run: bool,
+    pub options: CompletionsOptions,
+}
+
+///Provider serving a model, e.g. to attribute a response when the model is selected at runtime
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LLMProvider {
+    Anthropic,
+    AwsBedrock,
+    DeepSeek,
+    Google,
+    Mistral,
+    OpenAI,
+    Perplexity,
+    //Models implemented outside of the crate, reported with the name of the model type
+    Other(String),
+}
+
+impl fmt::Display for LLMProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LLMProvider::Other(name) => write!(f, "{}", name),
+            provider => write!(f, "{:?}", provider),
+        }
+    }
+}
+
+///Response of the Completions API including the deserialized data and metadata returned by the model
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct CompletionsResponse<T> {
+    pub data: T,
+    pub usage: Option<CompletionsUsage>,
+    //Sources used by search-augmented models (e.g. Perplexity)
+    pub citations: Option<Vec<String>>,
+    //Log probabilities of the response tokens. Only included if requested with `logprobs`
+    pub logprobs: Option<Vec<TokenLogprob>>,
+    //Raw text of the API response. Only included in debug mode
+    pub raw_response: Option<String>,
+    //Number of times the request was sent again because the response could not be deserialized
+    #[serde(default)]
+    pub retries: usize,
+    //Why the model stopped generating, e.g. `FinishReason::Length` if the answer was cut off by the max tokens limit
+    #[serde(default)]
+    pub finish_reason: Option<FinishReason>,
+    //Web sources used to ground the answer. Only included if grounding was requested
+    #[serde(default)]
+    pub grounding_citations: Option<Vec<GroundingCitation>>,
+    //Reasoning of the model before the answer. Only included if extended thinking was requested
+    #[serde(default)]
+    pub thinking: Option<String>,
+    //Rate limits reported in the headers of the response. Not included for cached responses or providers without rate limit headers
+    #[serde(default)]
+    pub rate_limit_status: Option<RateLimitStatus>,
+    //Provider that served the request
+    pub provider: LLMProvider,
+}
+
+///Model available to the user as reported by the provider's models endpoint
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq)]
+pub struct ModelInfo {
+    pub id: String,
+    //Unix timestamp (in seconds) of the model creation. 0 if not reported by the provider
+    #[serde(default)]
+    pub created: u64,
+    #[serde(default)]
+    pub owned_by: String,
+    //Size of the input context window in tokens, if reported by the provider or known for the model
+    #[serde(default, alias = "max_context_length")]
+    pub context_window: Option<usize>,
+}
+
+//Models API response format shared by OpenAI compatible providers
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ModelListResp {
+    pub data: Vec<ModelInfo>,
+}
+
+///Token usage reported by the model API
+#[derive(Deserialize, Serialize, Debug, Clone, Default, Eq, PartialEq)]
+pub struct CompletionsUsage {
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+    //Prompt caching usage (if supported by the model)
+    pub cache_creation_input_tokens: Option<usize>,
+    pub cache_read_input_tokens: Option<usize>,
+}
+
+///Price of a model in USD per 1k tokens
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct TokenCost {
+    pub input: f64,
+    pub output: f64,
+}
+
+impl TokenCost {
+    ///Returns the cost in USD of the token usage at these prices
+    pub fn get_cost(&self, usage: &CompletionsUsage) -> f64 {
+        (usage.input_tokens as f64 * self.input + usage.output_tokens as f64 * self.output) / 1000.0
+    }
+}
+
+//Usage of multiple requests is summed, e.g. to report the total usage of a batch
+impl std::ops::AddAssign for CompletionsUsage {
+    fn add_assign(&mut self, other: Self) {
+        let add = |total: Option<usize>, tokens: Option<usize>| match (total, tokens) {
+            (None, None) => None,
+            (total, tokens) => Some(total.unwrap_or(0) + tokens.unwrap_or(0)),
+        };
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens = add(
+            self.cache_creation_input_tokens,
+            other.cache_creation_input_tokens,
+        );
+        self.cache_read_input_tokens =
+            add(self.cache_read_input_tokens, other.cache_read_input_tokens);
+    }
+}
+
+impl std::iter::Sum for CompletionsUsage {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(CompletionsUsage::default(), |mut total, usage| {
+            total += usage;
+            total
+        })
+    }
+}
+
+///Responses to a batch of prompts with the usage summed across all of them
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct BatchResponse<T> {
+    //Responses in the order of the prompts
+    pub responses: Vec<CompletionsResponse<T>>,
+    //Usage of all requests. Responses for which the model does not report usage are not included
+    pub usage: CompletionsUsage,
+    //Cost of the usage in USD at the prices of the model. None for models without known prices
+    pub cost: Option<f64>,
+}
+
+///Log probability of a token of the response
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct TokenLogprob {
+    pub token: String,
+    pub logprob: f64,
+    //Most likely tokens at this position of the response, as many as requested with `logprobs`
+    #[serde(default)]
+    pub top_logprobs: Vec<TopLogprob>,
+}
+
+///Log probability of one of the most likely tokens at a position of the response
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct TopLogprob {
+    pub token: String,
+    pub logprob: f64,
+}
+
+///Reason the model stopped generating the response, normalized across providers
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub enum FinishReason {
+    //The answer is complete or a stop sequence was reached
+    Stop,
+    //The answer was cut off by the max tokens limit
+    Length,
+    //The model called a tool or function
+    ToolCalls,
+    //The answer was blocked by a content filter or safety settings
+    ContentFilter,
+    //Any other reason, as reported by the provider
+    Other(String),
+}
+
+impl FinishReason {
+    ///
+    /// This function maps the finish reason reported by a provider, e.g. `length` (OpenAI), `max_tokens` (Anthropic) or `MAX_TOKENS` (Google), to the common enum
+    ///
+    pub fn from_provider(reason: &str) -> Self {
+        match reason.to_lowercase().as_str() {
+            "stop" | "end_turn" | "stop_sequence" => FinishReason::Stop,
+            "length" | "max_tokens" | "model_length" => FinishReason::Length,
+            "tool_calls" | "function_call" | "tool_use" => FinishReason::ToolCalls,
+            "content_filter" | "safety" | "recitation" | "blocklist" | "prohibited_content"
+            | "spii" => FinishReason::ContentFilter,
+            _ => FinishReason::Other(reason.to_string()),
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
@@ -82,6 +575,15 @@ pub struct OpenAIAssistantResp {
     pub model: String,
     pub tools: Vec<OpenAITools>,
     pub file_ids: Option<Vec<String>>,
+    //Files and vector stores used by the tools (Assistants API v2)
+    pub tool_resources: Option<Value>,
+}
+
+//Assistants API v1 response type format for files attached to an Assistant
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OpenAIAssistantFileResp {
+    pub id: String,
+    pub assistant_id: String,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -139,6 +641,13 @@ pub struct OpenAIMessageAttachmentTools {
     pub tool_type: OpenAIToolTypes,
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OpenAIDeleteResp {
+    pub id: String,
+    pub object: String,
+    pub deleted: bool,
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct OpenAIRunResp {
     pub id: String,
@@ -147,6 +656,53 @@ pub struct OpenAIRunResp {
     pub status: OpenAIRunStatus,
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OpenAIRunStepListResp {
+    pub data: Vec<OpenAIRunStepResp>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OpenAIRunStepResp {
+    pub id: String,
+    pub step_details: OpenAIRunStepDetails,
+    //Other fields omitted as no use for now
+}
+
+//Details of `message_creation` steps have no tool calls
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OpenAIRunStepDetails {
+    #[serde(rename(deserialize = "type", serialize = "type"))]
+    pub step_type: String,
+    #[serde(default)]
+    pub tool_calls: Vec<OpenAIRunStepToolCall>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OpenAIRunStepToolCall {
+    pub id: String,
+    #[serde(rename(deserialize = "type", serialize = "type"))]
+    pub tool_type: String,
+    pub code_interpreter: Option<OpenAICodeInterpreterCall>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OpenAICodeInterpreterCall {
+    pub input: String,
+    pub outputs: Vec<OpenAICodeInterpreterOutput>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OpenAICodeInterpreterOutput {
+    Logs { logs: String },
+    Image { image: OpenAICodeInterpreterImage },
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OpenAICodeInterpreterImage {
+    pub file_id: String,
+}
+
 //Anthropic API response type format for Text Completions API
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct AnthropicAPICompletionsResponse {
@@ -176,13 +732,100 @@ pub struct AnthropicAPIMessagesResponse {
 pub struct AnthropicAPIMessagesContent {
     #[serde(rename(deserialize = "type", serialize = "type"))]
     pub content_type: String,
+    //Empty for `thinking` blocks
+    #[serde(default)]
     pub text: String,
+    //Only included for `thinking` blocks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct AnthropicAPIMessagesUsage {
     pub input_tokens: i32,
     pub output_tokens: i32,
+    pub cache_creation_input_tokens: Option<i32>,
+    pub cache_read_input_tokens: Option<i32>,
+}
+
+//Anthropic API event format for streamed Messages API responses
+//Docs: https://docs.anthropic.com/en/api/messages-streaming
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnthropicAPIStreamEvent {
+    MessageStart {
+        message: AnthropicAPIMessagesResponse,
+    },
+    ContentBlockDelta {
+        delta: AnthropicAPIStreamDelta,
+    },
+    MessageDelta {
+        delta: AnthropicAPIStreamMessageDelta,
+        usage: Option<AnthropicAPIStreamUsage>,
+    },
+    MessageStop,
+    Error {
+        error: Value,
+    },
+    //E.g. `ping`, `content_block_start` and `content_block_stop`
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AnthropicAPIStreamDelta {
+    //Only included for `text_delta` deltas
+    pub text: Option<String>,
+    //Only included for `thinking_delta` deltas
+    pub thinking: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AnthropicAPIStreamMessageDelta {
+    pub stop_reason: Option<String>,
+    pub stop_sequence: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AnthropicAPIStreamUsage {
+    pub output_tokens: i32,
+}
+
+//Anthropic API response type format for Token Counting API
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AnthropicAPICountTokensResponse {
+    pub input_tokens: usize,
+}
+
+//Anthropic API response type format for Models API
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AnthropicAPIModelsResponse {
+    pub data: Vec<AnthropicAPIModel>,
+    #[serde(default)]
+    pub has_more: bool,
+    //ID of the last model of the page, used as the cursor of the next page
+    pub last_id: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AnthropicAPIModel {
+    pub id: String,
+    pub display_name: Option<String>,
+    //RFC 3339 datetime
+    pub created_at: String,
+}
+
+//Voyage AI (recommended by Anthropic) API response type format for Embeddings API
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct VoyageAPIEmbeddingsResponse {
+    pub data: Vec<VoyageAPIEmbedding>,
+    pub model: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct VoyageAPIEmbedding {
+    pub embedding: Vec<f32>,
+    pub index: usize,
 }
 
 //Mistral API response type format for Chat Completions API
@@ -209,6 +852,23 @@ pub struct MistralAPICompletionsChoices {
 pub struct MistralAPICompletionsMessage {
     pub role: Option<String>,
     pub content: Option<String>,
+    //Only included if the model called a tool
+    #[serde(default)]
+    pub tool_calls: Option<Vec<MistralAPIToolCall>>,
+}
+
+//Mistral API response type format for Chat Completions API
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct MistralAPIToolCall {
+    pub id: Option<String>,
+    pub function: MistralAPIFunctionCall,
+}
+
+//Mistral API response type format for Chat Completions API
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct MistralAPIFunctionCall {
+    pub name: String,
+    pub arguments: String,
 }
 
 //Mistral API response type format for Chat Completions API
@@ -222,6 +882,8 @@ pub struct MistralAPICompletionsUsage {
 ///Google GeminiPro API response deserialization structs
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GoogleGeminiProApiResp {
+    //Not included if the prompt was blocked
+    #[serde(default)]
     pub candidates: Vec<GoogleGeminiProCandidate>,
     #[serde(rename = "usageMetadata")]
     pub usage_metadata: Option<GoogleGeminiProUsageMetadata>,
@@ -229,6 +891,8 @@ pub struct GoogleGeminiProApiResp {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GoogleGeminiProCandidate {
+    //Not included if the answer was blocked by safety settings
+    #[serde(default)]
     pub content: GoogleGeminiProContent,
     #[serde(rename = "finishReason")]
     pub finish_reason: Option<String>,
@@ -236,17 +900,29 @@ pub struct GoogleGeminiProCandidate {
     pub safety_ratings: Option<Vec<GoogleGeminiProSafetyRating>>,
     #[serde(rename = "citationMetadata")]
     pub citation_metadata: Option<GoogleGeminiProCitationMetadata>,
+    #[serde(rename = "groundingMetadata")]
+    pub grounding_metadata: Option<GoogleGeminiProGroundingMetadata>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct GoogleGeminiProContent {
+    #[serde(default)]
     pub parts: Vec<GoogleGeminiProPart>,
     pub role: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GoogleGeminiProPart {
+    #[serde(default)]
     pub text: String,
+    #[serde(rename = "functionCall")]
+    pub function_call: Option<GoogleGeminiProFunctionCall>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoogleGeminiProFunctionCall {
+    pub name: String,
+    pub args: Value,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -274,6 +950,25 @@ pub struct GoogleGeminiProCitation {
     pub publication_date: Option<GoogleGeminiProDate>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoogleGeminiProGroundingMetadata {
+    #[serde(rename = "groundingChunks", default)]
+    pub grounding_chunks: Vec<GoogleGeminiProGroundingChunk>,
+    #[serde(rename = "webSearchQueries", default)]
+    pub web_search_queries: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoogleGeminiProGroundingChunk {
+    pub web: Option<GoogleGeminiProWebSource>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoogleGeminiProWebSource {
+    pub uri: String,
+    pub title: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GoogleGeminiProDate {
     pub year: i32,
@@ -291,6 +986,24 @@ pub struct GoogleGeminiProUsageMetadata {
     pub total_token_count: Option<i32>,
 }
 
+///Google Gemini Models API response deserialization structs
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoogleGeminiModelsResponse {
+    pub models: Vec<GoogleGeminiModel>,
+    #[serde(rename = "nextPageToken")]
+    pub next_page_token: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoogleGeminiModel {
+    //Resource name of the model, e.g. `models/gemini-1.5-pro`
+    pub name: String,
+    #[serde(rename = "inputTokenLimit")]
+    pub input_token_limit: Option<usize>,
+    #[serde(rename = "supportedGenerationMethods", default)]
+    pub supported_generation_methods: Vec<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AllmsError {
     #[serde(rename = "crate")]
@@ -300,6 +1013,179 @@ pub struct AllmsError {
     pub error_detail: String,
 }
 
+/// Error returned when a model name cannot be mapped to any of the provider's models
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct UnknownModelError {
+    pub model: String,
+}
+
+impl UnknownModelError {
+    pub fn new(model: &str) -> Self {
+        UnknownModelError {
+            model: model.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for UnknownModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown model: {}", self.model)
+    }
+}
+
+impl std::error::Error for UnknownModelError {}
+
+///Error returned when validating the API credentials of a provider
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CredentialsError {
+    ///The API rejected the credentials (e.g. 401 Unauthorized or 403 Forbidden)
+    Unauthorized { provider: String, detail: String },
+    ///The API could not be reached
+    Network { provider: String, detail: String },
+    ///The API returned an unexpected error, e.g. 429 Too Many Requests or 500 Internal Server Error
+    Api {
+        provider: String,
+        status: u16,
+        detail: String,
+    },
+}
+
+impl fmt::Display for CredentialsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CredentialsError::Unauthorized { provider, detail } => {
+                write!(f, "[allms][{}] Invalid credentials: {}", provider, detail)
+            }
+            CredentialsError::Network { provider, detail } => {
+                write!(
+                    f,
+                    "[allms][{}] Unable to reach the API: {}",
+                    provider, detail
+                )
+            }
+            CredentialsError::Api {
+                provider,
+                status,
+                detail,
+            } => write!(
+                f,
+                "[allms][{}] API error [{}]: {}",
+                provider, status, detail
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CredentialsError {}
+
+///Error returned when the model output deserializes into the expected type but violates constraints of its JSON schema (e.g. `enum` or `maximum`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaMismatch {
+    ///Violations in the format `<location in the output>: <reason>`
+    pub violations: Vec<String>,
+}
+
+impl fmt::Display for SchemaMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[allms] Output does not match the JSON schema: {}",
+            self.violations.join("; ")
+        )
+    }
+}
+
+impl std::error::Error for SchemaMismatch {}
+
+///Error returned before calling the API when the prompt does not leave room for a response within the allocated tokens or the context window of the model
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextLengthExceeded {
+    ///Estimated number of tokens used by the prompt
+    pub prompt: usize,
+    ///Number of tokens allocated for prompt & response
+    pub max: usize,
+    ///Size of the context window of the model in tokens
+    pub context: usize,
+}
+
+impl fmt::Display for ContextLengthExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[allms] Context length exceeded: the prompt requires approx {} tokens, {} tokens are allocated for prompt & response and the context window of the model is {} tokens",
+            self.prompt, self.max, self.context
+        )
+    }
+}
+
+impl std::error::Error for ContextLengthExceeded {}
+
+///Error returned when the answer of the model is empty or only contains whitespace, e.g. because it was blocked by a content filter or safety settings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmptyResponse {
+    ///Name of the model
+    pub model: String,
+    ///Reason the model stopped generating the response, if reported by the provider
+    pub finish_reason: Option<FinishReason>,
+}
+
+impl fmt::Display for EmptyResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.finish_reason {
+            Some(finish_reason) => write!(
+                f,
+                "[allms] Empty response returned by {} (finish reason: {:?})",
+                self.model, finish_reason
+            ),
+            None => write!(f, "[allms] Empty response returned by {}", self.model),
+        }
+    }
+}
+
+impl std::error::Error for EmptyResponse {}
+
+///Error returned by methods deserializing the answer in dry run mode, as the request is not sent. The body that would have been sent can be inspected.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DryRun {
+    ///Name of the model
+    pub model: String,
+    ///Body of the request built and validated for the model
+    pub body: Value,
+}
+
+impl fmt::Display for DryRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[allms] Dry run of {}: the request was not sent",
+            self.model
+        )
+    }
+}
+
+impl std::error::Error for DryRun {}
+
+///Error returned when a request is not sent because the spend recorded by its `BudgetTracker` reached the budget
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetExceeded {
+    ///Total spend recorded in USD
+    pub spent: f64,
+    ///Budget of the tracker in USD
+    pub budget: f64,
+}
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[allms] Budget exceeded: ${:.4} spent of ${:.4}",
+            self.spent, self.budget
+        )
+    }
+}
+
+impl std::error::Error for BudgetExceeded {}
+
 // Perplexity API response type format for Chat Completions API
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct PerplexityAPICompletionsResponse {
@@ -0,0 +1,169 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::constants::VOYAGE_API_URL;
+use crate::domain::{AllmsError, UnknownModelError, VoyageAPIEmbeddingsResponse};
+use crate::embedding_models::EmbeddingModel;
+use crate::http_client::HttpClient;
+use crate::utils::redact_secrets;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq, Hash)]
+//Anthropic does not offer an embeddings API and recommends Voyage AI instead. The models require a Voyage AI API key.
+//Docs: https://docs.anthropic.com/en/docs/build-with-claude/embeddings
+pub enum AnthropicEmbeddingModels {
+    Voyage3,
+}
+
+#[async_trait(?Send)]
+impl EmbeddingModel for AnthropicEmbeddingModels {
+    fn as_str(&self) -> &str {
+        match self {
+            AnthropicEmbeddingModels::Voyage3 => "voyage-3",
+        }
+    }
+
+    fn try_from_str(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "voyage-3" => Some(AnthropicEmbeddingModels::Voyage3),
+            _ => None,
+        }
+    }
+
+    fn dimensions(&self) -> usize {
+        //Docs: https://docs.voyageai.com/docs/embeddings
+        match self {
+            AnthropicEmbeddingModels::Voyage3 => 1_024,
+        }
+    }
+
+    fn get_endpoint(&self) -> String {
+        VOYAGE_API_URL.to_string()
+    }
+
+    fn get_body(&self, inputs: &[&str]) -> Value {
+        json!({
+            "model": self.as_str(),
+            "input": inputs,
+        })
+    }
+
+    async fn call_api(
+        &self,
+        client: &HttpClient,
+        api_key: &str,
+        body: &Value,
+        debug: bool,
+    ) -> Result<String> {
+        //Get the API url
+        let model_url = self.get_endpoint();
+
+        //Send request
+        let request = client.post_json(model_url, body).bearer_auth(api_key);
+        let response = client.send(request).await?;
+
+        let response_status = response.status();
+        let response_text = response.text().await?;
+
+        if debug {
+            info!(
+                "[debug] Voyage AI Embeddings API response: [{}] {:#?}",
+                &response_status,
+                redact_secrets(&response_text)
+            );
+        }
+
+        Ok(response_text)
+    }
+
+    fn get_data(&self, response_text: &str) -> Result<Vec<Vec<f32>>> {
+        //Convert API response to struct representing expected response format
+        let embeddings_response: VoyageAPIEmbeddingsResponse = serde_json::from_str(response_text)
+            .map_err(|error| {
+                let error = AllmsError {
+                    crate_name: "allms".to_string(),
+                    module: "embedding_models::anthropic".to_string(),
+                    error_message: format!(
+                        "Embeddings API response serialization error: {}",
+                        error
+                    ),
+                    error_detail: response_text.to_string(),
+                };
+                error!("{:?}", error);
+                anyhow!("{:?}", error)
+            })?;
+
+        //Embeddings are returned with the index of the input they were generated for
+        let mut data = embeddings_response.data;
+        data.sort_by_key(|embedding| embedding.index);
+
+        Ok(data
+            .into_iter()
+            .map(|embedding| embedding.embedding)
+            .collect())
+    }
+}
+
+impl fmt::Display for AnthropicEmbeddingModels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for AnthropicEmbeddingModels {
+    type Err = UnknownModelError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        AnthropicEmbeddingModels::try_from_str(name).ok_or_else(|| UnknownModelError::new(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::embedding_models::{AnthropicEmbeddingModels, EmbeddingModel};
+
+    #[test]
+    fn test_voyage_body() {
+        let model = AnthropicEmbeddingModels::Voyage3;
+
+        let body = model.get_body(&["first", "second"]);
+
+        assert_eq!(
+            body,
+            json!({"model": "voyage-3", "input": ["first", "second"]})
+        );
+        assert!(model.get_endpoint().ends_with("/v1/embeddings"));
+        assert_eq!(
+            "voyage-3".parse::<AnthropicEmbeddingModels>(),
+            Ok(AnthropicEmbeddingModels::Voyage3)
+        );
+    }
+
+    #[test]
+    fn test_voyage_get_data_orders_by_index() {
+        let response = json!({
+            "object": "list",
+            "data": [
+                {"object": "embedding", "embedding": [0.3, 0.4], "index": 1},
+                {"object": "embedding", "embedding": [0.1, 0.2], "index": 0}
+            ],
+            "model": "voyage-3",
+            "usage": {"total_tokens": 10}
+        });
+
+        let embeddings = AnthropicEmbeddingModels::Voyage3
+            .get_data(&response.to_string())
+            .unwrap();
+
+        assert_eq!(embeddings, vec![vec![0.1, 0.2], vec![0.3, 0.4]]);
+        assert!(AnthropicEmbeddingModels::Voyage3
+            .get_data(r#"{"detail": "Provided API key is invalid."}"#)
+            .is_err());
+    }
+}
@@ -0,0 +1,32 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::http_client::HttpClient;
+
+///This trait defines functions that need to be implemented for an enum that represents an embedding model from any of the API providers
+#[async_trait(?Send)]
+pub trait EmbeddingModel {
+    ///Converts each item in the model enum into its string representation
+    fn as_str(&self) -> &str;
+    ///Returns an instance of the enum based on the provided string representation of name
+    fn try_from_str(name: &str) -> Option<Self>
+    where
+        Self: Sized;
+    ///Returns the number of dimensions of the embeddings generated by the model
+    fn dimensions(&self) -> usize;
+    ///Returns the url of the endpoint that should be called for each variant of the model enum
+    fn get_endpoint(&self) -> String;
+    ///Constructs the body that should be attached to the API call for the provided inputs
+    fn get_body(&self, inputs: &[&str]) -> Value;
+    ///Makes the call to the correct API for the selected model
+    async fn call_api(
+        &self,
+        client: &HttpClient,
+        api_key: &str,
+        body: &Value,
+        debug: bool,
+    ) -> Result<String>;
+    ///Based on the model type extracts the embeddings (in the order of inputs) from the API response
+    fn get_data(&self, response_text: &str) -> Result<Vec<Vec<f32>>>;
+}
@@ -0,0 +1,5 @@
+pub mod anthropic;
+pub mod embedding_model;
+
+pub use anthropic::AnthropicEmbeddingModels;
+pub use embedding_model::EmbeddingModel;
@@ -0,0 +1,62 @@
+use anyhow::Result;
+use log::info;
+use reqwest::Client;
+
+use crate::embedding_models::EmbeddingModel;
+use crate::http_client::HttpClient;
+
+/// Embeddings APIs convert text into vectors of floating point numbers
+/// that can be used to measure the relatedness of texts, e.g. for search or clustering.
+pub struct Embeddings<T: EmbeddingModel> {
+    model: T,
+    api_key: String,
+    debug: bool,
+    http_client: HttpClient,
+}
+
+impl<T: EmbeddingModel> Embeddings<T> {
+    /// Constructor for the Embeddings API
+    pub fn new(model: T, api_key: &str) -> Self {
+        Embeddings {
+            model,
+            api_key: api_key.to_string(),
+            debug: false,
+            http_client: HttpClient::default(),
+        }
+    }
+
+    ///
+    /// This function turns on debug mode which will info! the request body to log when executing it.
+    ///
+    pub fn debug(mut self) -> Self {
+        self.debug = true;
+        self
+    }
+
+    ///
+    /// This method sets the HTTP client used to send the requests so that its connections are reused across calls
+    /// By default the client shared by all `Completions` is used.
+    ///
+    pub fn http_client(mut self, client: Client) -> Self {
+        self.http_client = self.http_client.client(client);
+        self
+    }
+
+    ///
+    /// This method returns the embeddings of the provided inputs, in the same order as the inputs
+    ///
+    pub async fn get_embeddings(&self, inputs: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let body = self.model.get_body(inputs);
+
+        if self.debug {
+            info!("[debug] Embeddings body: {:#?}", body);
+        }
+
+        let response_text = self
+            .model
+            .call_api(&self.http_client, &self.api_key, &body, self.debug)
+            .await?;
+
+        self.model.get_data(&response_text)
+    }
+}
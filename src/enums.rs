@@ -8,6 +8,8 @@ pub enum OpenAIToolTypes {
     Retrieval,
     #[serde(rename(deserialize = "file_search", serialize = "file_search"))]
     FileSearch,
+    #[serde(rename(deserialize = "function", serialize = "function"))]
+    Function,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq)]
@@ -18,7 +20,7 @@ pub enum OpenAIAssistantRole {
     Assistant,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq)]
 pub enum OpenAIRunStatus {
     #[serde(rename(deserialize = "queued", serialize = "queued"))]
     Queued,
@@ -0,0 +1,434 @@
+use anyhow::{anyhow, Result};
+use log::{error, info};
+use reqwest::{header, Client, Request};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::Path;
+
+use crate::constants::{GOOGLE_GEMINI_FILES_API_URL, GOOGLE_GEMINI_UPLOAD_API_URL};
+use crate::domain::{AllmsError, FileData};
+use crate::utils::redact_secrets;
+
+/// [Google Docs](https://ai.google.dev/gemini-api/docs/document-processing)
+///
+/// Files uploaded with the File API can be referenced in requests to Gemini models with `Completions::files`.
+/// Unlike inline data they are not limited in size, which makes them the only option for documents with hundreds of pages. Uploaded files are deleted by Google after 48 hours.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct GeminiFile {
+    /// Resource name of the file, e.g. `files/abc-123`
+    pub name: Option<String>,
+    debug: bool,
+    api_key: String,
+}
+
+/// File object returned by Gemini File API
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiFileObject {
+    pub name: String,
+    pub display_name: Option<String>,
+    pub mime_type: String,
+    //Reported by the API as a string
+    pub size_bytes: Option<String>,
+    pub create_time: Option<String>,
+    pub expiration_time: Option<String>,
+    pub uri: String,
+    pub state: Option<GeminiFileState>,
+}
+
+/// Processing state of an uploaded file. Only `Active` files can be used in requests.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum GeminiFileState {
+    StateUnspecified,
+    Processing,
+    Active,
+    Failed,
+}
+
+impl GeminiFileObject {
+    ///
+    /// This method returns the reference to the file that can be passed to `Completions::files`
+    ///
+    pub fn file_data(&self) -> FileData {
+        FileData {
+            mime_type: self.mime_type.clone(),
+            file_uri: self.uri.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct GeminiFileResp {
+    file: GeminiFileObject,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct GeminiFileListResp {
+    #[serde(default)]
+    files: Vec<GeminiFileObject>,
+    next_page_token: Option<String>,
+}
+
+impl GeminiFile {
+    /// Constructor
+    pub fn new(name: Option<String>, api_key: &str) -> Self {
+        GeminiFile {
+            name,
+            debug: false,
+            api_key: api_key.to_string(),
+        }
+    }
+
+    ///
+    /// This method can be used to turn on debug mode for the GeminiFile struct
+    ///
+    pub fn debug(mut self) -> Self {
+        self.debug = true;
+        self
+    }
+
+    ///
+    /// This function uploads a file to the Gemini File API using the resumable upload protocol and returns the uploaded file object.
+    /// The first request starts the upload session and returns the URL to which the file is sent in the second request.
+    ///
+    pub async fn upload(
+        &mut self,
+        file_name: &str,
+        file_bytes: Vec<u8>,
+    ) -> Result<GeminiFileObject> {
+        let client = Client::new();
+
+        let start_request = self.get_start_request(
+            &client,
+            file_name,
+            get_mime_type(file_name)?,
+            file_bytes.len(),
+        )?;
+        let response = client
+            .execute(start_request)
+            .await
+            //The URL includes the API key so it's removed from the error
+            .map_err(|error| error.without_url())?;
+
+        let response_status = response.status();
+        let upload_url = response
+            .headers()
+            .get("x-goog-upload-url")
+            .and_then(|upload_url| upload_url.to_str().ok())
+            .map(|upload_url| upload_url.to_string());
+        let upload_url = match upload_url {
+            Some(upload_url) => upload_url,
+            None => {
+                let response_text = response.text().await?;
+                let error = AllmsError {
+                    crate_name: "allms".to_string(),
+                    module: "files::gemini_file".to_string(),
+                    error_message: format!(
+                        "File API did not return an upload URL: [{}]",
+                        response_status
+                    ),
+                    error_detail: redact_secrets(&response_text),
+                };
+                error!("{:?}", error);
+                return Err(anyhow!("{:?}", error));
+            }
+        };
+
+        let upload_request = get_upload_request(&client, &upload_url, file_bytes)?;
+        let response = client
+            .execute(upload_request)
+            .await
+            .map_err(|error| error.without_url())?;
+
+        let response_status = response.status();
+        let response_text = response.text().await?;
+
+        if self.debug {
+            info!(
+                "[debug] Gemini Files upload API response: [{}] {:#?}",
+                &response_status,
+                redact_secrets(&response_text)
+            );
+        }
+
+        let file_object = serde_json::from_str::<GeminiFileResp>(&response_text)
+            .map(|response| response.file)
+            .map_err(|error| parse_error("Files API", error, &response_text))?;
+
+        self.name = Some(file_object.name.clone());
+
+        Ok(file_object)
+    }
+
+    // The upload session is started with the metadata of the file, which is sent in the subsequent request
+    fn get_start_request(
+        &self,
+        client: &Client,
+        file_name: &str,
+        mime_type: &str,
+        content_length: usize,
+    ) -> Result<Request> {
+        let display_name = Path::new(file_name)
+            .file_name()
+            .and_then(std::ffi::OsStr::to_str)
+            .unwrap_or(file_name);
+
+        Ok(client
+            .post(&*GOOGLE_GEMINI_UPLOAD_API_URL)
+            .query(&[("key", &self.api_key)])
+            .header("X-Goog-Upload-Protocol", "resumable")
+            .header("X-Goog-Upload-Command", "start")
+            .header(
+                "X-Goog-Upload-Header-Content-Length",
+                content_length.to_string(),
+            )
+            .header("X-Goog-Upload-Header-Content-Type", mime_type)
+            .json(&json!({
+                "file": {
+                    "display_name": display_name,
+                }
+            }))
+            .build()?)
+    }
+
+    ///
+    /// This function retrieves the details of the file from the Gemini File API
+    ///
+    pub async fn retrieve(&self) -> Result<GeminiFileObject> {
+        let response_text = self
+            .send(
+                Client::new().get(self.get_file_url("retrieve")?),
+                "retrieve",
+            )
+            .await?;
+
+        serde_json::from_str::<GeminiFileObject>(&response_text)
+            .map_err(|error| parse_error("Files API", error, &response_text))
+    }
+
+    ///
+    /// This function lists the files uploaded with the API key
+    ///
+    pub async fn list(&self) -> Result<Vec<GeminiFileObject>> {
+        let mut files = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut request = Client::new()
+                .get(&*GOOGLE_GEMINI_FILES_API_URL)
+                .query(&[("pageSize", "100")]);
+            if let Some(page_token) = &page_token {
+                request = request.query(&[("pageToken", page_token)]);
+            }
+            let response_text = self.send(request, "list").await?;
+
+            let response = serde_json::from_str::<GeminiFileListResp>(&response_text)
+                .map_err(|error| parse_error("Files List API", error, &response_text))?;
+            files.extend(response.files);
+
+            match response.next_page_token {
+                Some(next_page_token) if !next_page_token.is_empty() => {
+                    page_token = Some(next_page_token)
+                }
+                _ => return Ok(files),
+            }
+        }
+    }
+
+    ///
+    /// This function deletes the file from the Gemini File API
+    ///
+    pub async fn delete(&self) -> Result<()> {
+        self.send(Client::new().delete(self.get_file_url("delete")?), "delete")
+            .await
+            .map(|_| ())
+    }
+
+    // The name of the file is `files/{id}`, which is also its path relative to the API version
+    fn get_file_url(&self, action: &str) -> Result<String> {
+        let name = self.name.as_ref().ok_or_else(|| {
+            anyhow!(
+                "[allms][Google][File API] Unable to {} file without a name.",
+                action
+            )
+        })?;
+        let file_id = name.strip_prefix("files/").unwrap_or(name);
+        Ok(format!("{}/{}", *GOOGLE_GEMINI_FILES_API_URL, file_id))
+    }
+
+    // This function sends the request authenticated with the API key and returns the response text if successful
+    async fn send(&self, request: reqwest::RequestBuilder, action: &str) -> Result<String> {
+        let response = request
+            .query(&[("key", &self.api_key)])
+            .send()
+            .await
+            //The URL includes the API key so it's removed from the error
+            .map_err(|error| error.without_url())?;
+
+        let response_status = response.status();
+        let response_text = response.text().await?;
+
+        if self.debug {
+            info!(
+                "[debug] Gemini Files {} API response: [{}] {:#?}",
+                action,
+                &response_status,
+                redact_secrets(&response_text)
+            );
+        }
+
+        if !response_status.is_success() {
+            let error = AllmsError {
+                crate_name: "allms".to_string(),
+                module: "files::gemini_file".to_string(),
+                error_message: format!("Files API {} error: [{}]", action, response_status),
+                error_detail: redact_secrets(&response_text),
+            };
+            error!("{:?}", error);
+            return Err(anyhow!("{:?}", error));
+        }
+
+        Ok(response_text)
+    }
+}
+
+// The file is sent to the URL of the upload session and the upload is finalized in the same request
+fn get_upload_request(client: &Client, upload_url: &str, file_bytes: Vec<u8>) -> Result<Request> {
+    Ok(client
+        .post(upload_url)
+        .header(header::CONTENT_LENGTH, file_bytes.len().to_string())
+        .header("X-Goog-Upload-Offset", "0")
+        .header("X-Goog-Upload-Command", "upload, finalize")
+        .body(file_bytes)
+        .build()?)
+}
+
+// Determine MIME type based on file extension
+// Google documentation: https://ai.google.dev/gemini-api/docs/document-processing#technical-details
+fn get_mime_type(file_name: &str) -> Result<&'static str> {
+    let mime_type = match Path::new(file_name)
+        .extension()
+        .and_then(std::ffi::OsStr::to_str)
+    {
+        Some("pdf") => "application/pdf",
+        Some("txt") => "text/plain",
+        Some("html") => "text/html",
+        Some("md") => "text/md",
+        Some("csv") => "text/csv",
+        Some("xml") => "text/xml",
+        Some("rtf") => "text/rtf",
+        Some("js") => "application/x-javascript",
+        Some("py") => "application/x-python",
+        Some("css") => "text/css",
+        Some("png") => "image/png",
+        Some("jpeg") | Some("jpg") => "image/jpeg",
+        Some("webp") => "image/webp",
+        Some("mp3") => "audio/mp3",
+        Some("wav") => "audio/wav",
+        Some("mp4") => "video/mp4",
+        _ => anyhow::bail!("Unsupported file type"),
+    };
+    Ok(mime_type)
+}
+
+fn parse_error(api: &str, error: serde_json::Error, response_text: &str) -> anyhow::Error {
+    let error = AllmsError {
+        crate_name: "allms".to_string(),
+        module: "files::gemini_file".to_string(),
+        error_message: format!("{} response serialization error: {}", api, error),
+        error_detail: redact_secrets(response_text),
+    };
+    error!("{:?}", error);
+    anyhow!("{:?}", error)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_start_request_headers() {
+        let file = GeminiFile::new(None, "key");
+        let request = file
+            .get_start_request(&Client::new(), "/tmp/contract.pdf", "application/pdf", 1024)
+            .unwrap();
+        let headers = request.headers();
+
+        assert!(request
+            .url()
+            .as_str()
+            .ends_with("/upload/v1beta/files?key=key"));
+        assert_eq!(headers["X-Goog-Upload-Protocol"], "resumable");
+        assert_eq!(headers["X-Goog-Upload-Command"], "start");
+        assert_eq!(headers["X-Goog-Upload-Header-Content-Length"], "1024");
+        assert_eq!(
+            headers["X-Goog-Upload-Header-Content-Type"],
+            "application/pdf"
+        );
+        assert_eq!(headers[header::CONTENT_TYPE], "application/json");
+
+        let body: serde_json::Value =
+            serde_json::from_slice(request.body().unwrap().as_bytes().unwrap()).unwrap();
+        assert_eq!(body, json!({"file": {"display_name": "contract.pdf"}}));
+    }
+
+    #[test]
+    fn test_upload_request_headers() {
+        let request = get_upload_request(
+            &Client::new(),
+            "https://generativelanguage.googleapis.com/upload/v1beta/files?upload_id=abc",
+            vec![0u8; 16],
+        )
+        .unwrap();
+        let headers = request.headers();
+
+        assert_eq!(headers[header::CONTENT_LENGTH], "16");
+        assert_eq!(headers["X-Goog-Upload-Offset"], "0");
+        assert_eq!(headers["X-Goog-Upload-Command"], "upload, finalize");
+    }
+
+    #[test]
+    fn test_get_mime_type() {
+        assert_eq!(get_mime_type("report.pdf").unwrap(), "application/pdf");
+        assert!(get_mime_type("archive.rar").is_err());
+    }
+
+    #[test]
+    fn test_file_data() {
+        let response = json!({
+            "file": {
+                "name": "files/abc-123",
+                "displayName": "contract.pdf",
+                "mimeType": "application/pdf",
+                "sizeBytes": "1024",
+                "createTime": "2025-01-01T00:00:00.000000Z",
+                "expirationTime": "2025-01-03T00:00:00.000000Z",
+                "uri": "https://generativelanguage.googleapis.com/v1beta/files/abc-123",
+                "state": "ACTIVE"
+            }
+        });
+        let file_object = serde_json::from_value::<GeminiFileResp>(response)
+            .unwrap()
+            .file;
+
+        assert_eq!(file_object.state, Some(GeminiFileState::Active));
+        assert_eq!(
+            file_object.file_data(),
+            FileData {
+                mime_type: "application/pdf".to_string(),
+                file_uri: "https://generativelanguage.googleapis.com/v1beta/files/abc-123"
+                    .to_string(),
+            }
+        );
+        assert_eq!(
+            GeminiFile::new(Some(file_object.name), "key")
+                .get_file_url("retrieve")
+                .unwrap(),
+            "https://generativelanguage.googleapis.com/v1beta/files/abc-123"
+        );
+        assert!(GeminiFile::new(None, "key").get_file_url("delete").is_err());
+    }
+}
@@ -0,0 +1,3 @@
+pub mod gemini_file;
+
+pub use gemini_file::{GeminiFile, GeminiFileObject, GeminiFileState};
@@ -0,0 +1,3 @@
+pub mod google;
+
+pub use google::{GeminiFile, GeminiFileObject, GeminiFileState};
@@ -0,0 +1,6 @@
+pub mod openai;
+
+pub use openai::{
+    OpenAIFineTuning, OpenAIFineTuningEvent, OpenAIFineTuningHyperparameters, OpenAIFineTuningJob,
+    OpenAIFineTuningJobRequest, OpenAIFineTuningJobStatus,
+};
@@ -0,0 +1,6 @@
+pub mod openai_fine_tuning;
+
+pub use openai_fine_tuning::{
+    OpenAIFineTuning, OpenAIFineTuningEvent, OpenAIFineTuningHyperparameters, OpenAIFineTuningJob,
+    OpenAIFineTuningJobRequest, OpenAIFineTuningJobStatus,
+};
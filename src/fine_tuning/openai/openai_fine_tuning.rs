@@ -0,0 +1,366 @@
+use anyhow::{anyhow, Result};
+use log::{error, info};
+use reqwest::{header, Client, RequestBuilder};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+use tokio::time::{self, timeout};
+
+use crate::constants::OPENAI_API_URL;
+use crate::domain::AllmsError;
+use crate::http_client::HttpClient;
+use crate::llm_models::OpenAIModels;
+use crate::utils::{get_openai_headers, redact_secrets};
+
+/// [OpenAI Docs](https://platform.openai.com/docs/api-reference/fine-tuning)
+///
+/// The Fine-tuning API manages jobs that tune models to a specific training dataset.
+/// Training files need to be uploaded first using `OpenAIFile` with `fine-tune` purpose.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OpenAIFineTuning {
+    api_key: String,
+    debug: bool,
+}
+
+/// Body of the request creating a fine-tuning job
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct OpenAIFineTuningJobRequest {
+    pub model: String,
+    pub training_file: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suffix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hyperparameters: Option<OpenAIFineTuningHyperparameters>,
+}
+
+/// Hyperparameters of a fine-tuning job. Parameters that are not set are picked automatically by OpenAI.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct OpenAIFineTuningHyperparameters {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n_epochs: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch_size: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub learning_rate_multiplier: Option<f32>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq)]
+pub enum OpenAIFineTuningJobStatus {
+    #[serde(rename(deserialize = "validating_files", serialize = "validating_files"))]
+    ValidatingFiles,
+    #[serde(rename(deserialize = "queued", serialize = "queued"))]
+    Queued,
+    #[serde(rename(deserialize = "running", serialize = "running"))]
+    Running,
+    #[serde(rename(deserialize = "succeeded", serialize = "succeeded"))]
+    Succeeded,
+    #[serde(rename(deserialize = "failed", serialize = "failed"))]
+    Failed,
+    #[serde(rename(deserialize = "cancelled", serialize = "cancelled"))]
+    Cancelled,
+}
+
+impl OpenAIFineTuningJobStatus {
+    /// Returns true if the job will not change its status anymore
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            OpenAIFineTuningJobStatus::Succeeded
+                | OpenAIFineTuningJobStatus::Failed
+                | OpenAIFineTuningJobStatus::Cancelled
+        )
+    }
+}
+
+/// Fine-tuning job object returned by the API
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OpenAIFineTuningJob {
+    pub id: String,
+    pub object: String,
+    pub model: String,
+    pub created_at: u64,
+    pub finished_at: Option<u64>,
+    pub fine_tuned_model: Option<String>,
+    pub status: OpenAIFineTuningJobStatus,
+    pub training_file: String,
+    pub validation_file: Option<String>,
+    pub trained_tokens: Option<u64>,
+    // Hyperparameters may be returned as numbers or `auto`
+    pub hyperparameters: Option<Value>,
+    pub error: Option<Value>,
+}
+
+impl OpenAIFineTuningJob {
+    ///
+    /// This method returns the fine-tuned model that can be used with `Completions` once the job succeeded
+    ///
+    pub fn fine_tuned_model(&self) -> Option<OpenAIModels> {
+        self.fine_tuned_model
+            .as_ref()
+            .map(|name| OpenAIModels::Custom {
+                name: name.to_string(),
+            })
+    }
+}
+
+/// Event logged by a fine-tuning job
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OpenAIFineTuningEvent {
+    pub id: String,
+    pub object: String,
+    pub created_at: u64,
+    pub level: String,
+    pub message: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct OpenAIFineTuningListResp<T> {
+    data: Vec<T>,
+}
+
+impl OpenAIFineTuningJobRequest {
+    /// Constructor
+    pub fn new(model: &str, training_file_id: &str) -> Self {
+        OpenAIFineTuningJobRequest {
+            model: model.to_string(),
+            training_file: training_file_id.to_string(),
+            validation_file: None,
+            suffix: None,
+            hyperparameters: None,
+        }
+    }
+
+    ///
+    /// This method can be used to provide the ID of an uploaded file with validation data
+    ///
+    pub fn validation_file(mut self, validation_file_id: &str) -> Self {
+        self.validation_file = Some(validation_file_id.to_string());
+        self
+    }
+
+    ///
+    /// This method can be used to add a suffix (up to 64 characters) to the fine-tuned model name
+    ///
+    pub fn suffix(mut self, suffix: &str) -> Self {
+        self.suffix = Some(suffix.to_string());
+        self
+    }
+
+    ///
+    /// This method can be used to set the hyperparameters of the job
+    ///
+    pub fn hyperparameters(mut self, hyperparameters: OpenAIFineTuningHyperparameters) -> Self {
+        self.hyperparameters = Some(hyperparameters);
+        self
+    }
+}
+
+impl OpenAIFineTuning {
+    /// Constructor
+    pub fn new(open_ai_key: &str) -> Self {
+        OpenAIFineTuning {
+            api_key: open_ai_key.to_string(),
+            debug: false,
+        }
+    }
+
+    ///
+    /// This method can be used to turn on debug mode
+    ///
+    pub fn debug(mut self) -> Self {
+        self.debug = true;
+        self
+    }
+
+    ///
+    /// This method creates a fine-tuning job
+    ///
+    pub async fn create_job(
+        &self,
+        request: &OpenAIFineTuningJobRequest,
+    ) -> Result<OpenAIFineTuningJob> {
+        let request = Client::new().post(get_jobs_url()).json(request);
+        self.send(request, "Fine-tuning Jobs").await
+    }
+
+    ///
+    /// This method lists fine-tuning jobs of the organization
+    ///
+    pub async fn list_jobs(&self) -> Result<Vec<OpenAIFineTuningJob>> {
+        let request = Client::new().get(get_jobs_url());
+        self.send::<OpenAIFineTuningListResp<OpenAIFineTuningJob>>(request, "Fine-tuning Jobs")
+            .await
+            .map(|response| response.data)
+    }
+
+    ///
+    /// This method retrieves a fine-tuning job
+    ///
+    pub async fn retrieve_job(&self, job_id: &str) -> Result<OpenAIFineTuningJob> {
+        let request = Client::new().get(format!("{}/{}", get_jobs_url(), job_id));
+        self.send(request, "Fine-tuning Jobs").await
+    }
+
+    ///
+    /// This method lists events logged by a fine-tuning job
+    ///
+    pub async fn list_events(&self, job_id: &str) -> Result<Vec<OpenAIFineTuningEvent>> {
+        let request = Client::new().get(format!("{}/{}/events", get_jobs_url(), job_id));
+        self.send::<OpenAIFineTuningListResp<OpenAIFineTuningEvent>>(request, "Fine-tuning Events")
+            .await
+            .map(|response| response.data)
+    }
+
+    ///
+    /// This method polls the status of a fine-tuning job until it is finished or the timeout is reached
+    ///
+    pub async fn wait_for_job(
+        &self,
+        job_id: &str,
+        poll_interval: Duration,
+        operation_timeout: Duration,
+    ) -> Result<OpenAIFineTuningJob> {
+        timeout(operation_timeout, async {
+            let mut interval = time::interval(poll_interval);
+            loop {
+                interval.tick().await;
+                let job = self.retrieve_job(job_id).await?;
+                if job.status.is_finished() {
+                    break Ok(job);
+                }
+            }
+        })
+        .await
+        .map_err(|_| anyhow!("[OpenAI][Fine-tuning] Timed out waiting for job {}", job_id))?
+    }
+
+    // This function sends the request and deserializes the response
+    async fn send<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+        api_name: &str,
+    ) -> Result<T> {
+        let response = request
+            .header(header::CONTENT_TYPE, "application/json")
+            .headers(get_openai_headers(&HttpClient::default()))
+            .bearer_auth(&self.api_key)
+            .send()
+            .await?;
+
+        let response_status = response.status();
+        let response_text = response.text().await?;
+
+        if self.debug {
+            info!(
+                "[allms][OpenAI][Fine-tuning][debug] {} API response: [{}] {:#?}",
+                api_name,
+                &response_status,
+                redact_secrets(&response_text)
+            );
+        }
+
+        parse_response(&response_text, api_name)
+    }
+}
+
+fn get_jobs_url() -> String {
+    format!(
+        "{OPENAI_API_URL}/v1/fine_tuning/jobs",
+        OPENAI_API_URL = *OPENAI_API_URL
+    )
+}
+
+fn parse_response<T: DeserializeOwned>(response_text: &str, api_name: &str) -> Result<T> {
+    serde_json::from_str::<T>(response_text).map_err(|error| {
+        let error = AllmsError {
+            crate_name: "allms".to_string(),
+            module: "fine_tuning::openai_fine_tuning".to_string(),
+            error_message: format!("{} API response serialization error: {}", api_name, error),
+            error_detail: response_text.to_string(),
+        };
+        error!("{:?}", error);
+        anyhow!("{:?}", error)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::llm_models::LLMModel;
+
+    #[test]
+    fn test_create_job_body() {
+        let request = OpenAIFineTuningJobRequest::new("gpt-4o-mini-2024-07-18", "file-abc123")
+            .suffix("custom-name")
+            .hyperparameters(OpenAIFineTuningHyperparameters {
+                n_epochs: Some(3),
+                ..Default::default()
+            });
+
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({
+                "model": "gpt-4o-mini-2024-07-18",
+                "training_file": "file-abc123",
+                "suffix": "custom-name",
+                "hyperparameters": {"n_epochs": 3}
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_job_status() {
+        let response = json!({
+            "object": "fine_tuning.job",
+            "id": "ftjob-abc123",
+            "model": "gpt-4o-mini-2024-07-18",
+            "created_at": 1721764800,
+            "finished_at": 1721765800,
+            "fine_tuned_model": "ft:gpt-4o-mini:my-org:custom_suffix:id",
+            "organization_id": "org-123",
+            "result_files": ["file-abc123"],
+            "status": "succeeded",
+            "validation_file": null,
+            "training_file": "file-abc123",
+            "hyperparameters": {"n_epochs": "auto", "batch_size": 4},
+            "trained_tokens": 5768
+        });
+
+        let job: OpenAIFineTuningJob =
+            parse_response(&response.to_string(), "Fine-tuning Jobs").unwrap();
+
+        assert_eq!(job.status, OpenAIFineTuningJobStatus::Succeeded);
+        assert!(job.status.is_finished());
+        assert_eq!(job.trained_tokens, Some(5768));
+        assert_eq!(
+            job.fine_tuned_model()
+                .map(|model| model.as_str().to_string()),
+            Some("ft:gpt-4o-mini:my-org:custom_suffix:id".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_running_job() {
+        let response = json!({
+            "object": "fine_tuning.job",
+            "id": "ftjob-abc123",
+            "model": "gpt-4o-mini-2024-07-18",
+            "created_at": 1721764800,
+            "fine_tuned_model": null,
+            "status": "running",
+            "training_file": "file-abc123"
+        });
+
+        let job: OpenAIFineTuningJob =
+            parse_response(&response.to_string(), "Fine-tuning Jobs").unwrap();
+
+        assert_eq!(job.status, OpenAIFineTuningJobStatus::Running);
+        assert!(!job.status.is_finished());
+        assert!(job.fine_tuned_model().is_none());
+    }
+}
@@ -0,0 +1,46 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::domain::CompletionsUsage;
+
+///Data passed to the `on_request` callback before each API call
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestEvent {
+    pub model: String,
+    //Size of the serialized request body in bytes
+    pub body_size: usize,
+}
+
+///Data passed to the `on_response` callback after each API call, whether it succeeded or not
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseEvent {
+    pub model: String,
+    //Size of the serialized request body in bytes
+    pub body_size: usize,
+    //HTTP status code of the response, if the model reports it
+    pub status: Option<u16>,
+    //Error of the API call, including errors returned by the API with an HTTP error status. `None` if the call succeeded
+    pub error: Option<String>,
+    pub latency: Duration,
+    //Token usage reported in the response, if any
+    pub usage: Option<CompletionsUsage>,
+}
+
+///Callback invoked before each API call, e.g. to count requests in a metrics sink
+pub type OnRequest = Arc<dyn Fn(&RequestEvent)>;
+
+///Callback invoked after each API call, e.g. to record latency and token usage in a metrics sink
+pub type OnResponse = Arc<dyn Fn(&ResponseEvent)>;
+
+//Callbacks attached to a `Completions` request. Responses read from the cache do not trigger them
+#[derive(Clone, Default)]
+pub(crate) struct RequestHooks {
+    pub(crate) on_request: Option<OnRequest>,
+    pub(crate) on_response: Option<OnResponse>,
+}
+
+impl RequestHooks {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.on_request.is_none() && self.on_response.is_none()
+    }
+}
@@ -0,0 +1,230 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use lazy_static::lazy_static;
+use log::warn;
+use reqwest::{header, Client, IntoUrl, RequestBuilder, Response};
+use serde_json::Value;
+use std::io::Write;
+
+use crate::constants::{
+    ANTHROPIC_API_VERSION, ANTHROPIC_BETA, OPENAI_ORG_ID, OPENAI_PROJECT_ID, RATE_LIMIT_MAX_WAIT,
+    RATE_LIMIT_RETRIES,
+};
+use crate::utils::send_with_retries;
+
+lazy_static! {
+    //Client shared by all requests sent without a client provided by the caller, so its connections are reused
+    static ref DEFAULT_CLIENT: Client = Client::new();
+}
+
+/// HTTP client used by the models to send API requests.
+///
+/// It wraps a `reqwest::Client`, whose connections are reused across requests, with the settings of how request bodies are sent.
+/// The default client is built once and shared by all requests that don't provide their own.
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    client: Client,
+    pub(crate) compress_requests_above_bytes: Option<usize>,
+    pub(crate) rate_limit_retries: usize,
+    pub(crate) anthropic_version: String,
+    pub(crate) anthropic_beta: Option<String>,
+    pub(crate) openai_organization: Option<String>,
+    pub(crate) openai_project: Option<String>,
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        HttpClient::new(DEFAULT_CLIENT.clone())
+    }
+}
+
+impl HttpClient {
+    /// Constructor
+    pub fn new(client: Client) -> Self {
+        HttpClient {
+            client,
+            compress_requests_above_bytes: None,
+            rate_limit_retries: *RATE_LIMIT_RETRIES,
+            anthropic_version: ANTHROPIC_API_VERSION.clone(),
+            anthropic_beta: ANTHROPIC_BETA.clone(),
+            openai_organization: OPENAI_ORG_ID.clone(),
+            openai_project: OPENAI_PROJECT_ID.clone(),
+        }
+    }
+
+    ///
+    /// This method makes request bodies of at least the provided size be compressed with gzip.
+    /// Only applied for providers accepting compressed requests (OpenAI and Anthropic).
+    ///
+    pub fn compress_requests_above_bytes(mut self, bytes: usize) -> Self {
+        self.compress_requests_above_bytes = Some(bytes);
+        self
+    }
+
+    ///
+    /// This method sets how many times requests rejected with 429 Too Many Requests are sent again after the wait indicated by the API.
+    /// Defaults to the `RATE_LIMIT_RETRIES` environment variable, or no retries if it is not set.
+    ///
+    pub fn rate_limit_retries(mut self, retries: usize) -> Self {
+        self.rate_limit_retries = retries;
+        self
+    }
+
+    ///
+    /// This method sets the `anthropic-version` header of Anthropic requests.
+    /// Defaults to the `ANTHROPIC_API_VERSION` environment variable, or `2023-06-01` if it is not set.
+    ///
+    pub fn anthropic_version(mut self, version: &str) -> Self {
+        self.anthropic_version = version.to_string();
+        self
+    }
+
+    ///
+    /// This method sets the `anthropic-beta` header of Anthropic requests enabling beta features, e.g. `output-128k-2025-02-19`.
+    /// Defaults to the `ANTHROPIC_BETA` environment variable.
+    ///
+    pub fn anthropic_beta(mut self, beta: &str) -> Self {
+        self.anthropic_beta = Some(beta.to_string());
+        self
+    }
+
+    ///
+    /// This method sets the organization sent in the `OpenAI-Organization` header of OpenAI requests.
+    /// Defaults to the `OPENAI_ORG_ID` environment variable.
+    ///
+    pub fn openai_organization(mut self, organization: &str) -> Self {
+        self.openai_organization = Some(organization.to_string());
+        self
+    }
+
+    ///
+    /// This method sets the project sent in the `OpenAI-Project` header of OpenAI requests, e.g. for project-scoped keys.
+    /// Defaults to the `OPENAI_PROJECT_ID` environment variable.
+    ///
+    pub fn openai_project(mut self, project: &str) -> Self {
+        self.openai_project = Some(project.to_string());
+        self
+    }
+
+    // This function replaces the underlying client, keeping the settings
+    pub(crate) fn client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    // This function starts a POST request with the underlying client
+    pub(crate) fn post<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    // This function starts a POST request with a Json body, compressed with gzip if it exceeds the configured size
+    pub(crate) fn post_json<U: IntoUrl>(&self, url: U, body: &Value) -> RequestBuilder {
+        let request = self
+            .client
+            .post(url)
+            .header(header::CONTENT_TYPE, "application/json");
+        let body = body.to_string();
+
+        match self.compress_requests_above_bytes {
+            Some(threshold) if body.len() >= threshold => match gzip(body.as_bytes()) {
+                Ok(compressed_body) => request
+                    .header(header::CONTENT_ENCODING, "gzip")
+                    .body(compressed_body),
+                Err(error) => {
+                    warn!(
+                        "[allms] Unable to compress the request body, sending it uncompressed: {}",
+                        error
+                    );
+                    request.body(body)
+                }
+            },
+            _ => request.body(body),
+        }
+    }
+
+    // This function sends the request, retrying rate limit errors as configured
+    pub(crate) async fn send(&self, request: RequestBuilder) -> reqwest::Result<Response> {
+        send_with_retries(request, self.rate_limit_retries, *RATE_LIMIT_MAX_WAIT).await
+    }
+}
+
+fn gzip(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::read::GzDecoder;
+    use serde_json::{json, Value};
+    use std::io::Read;
+    use wiremock::matchers::{body_json, header, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::HttpClient;
+
+    #[tokio::test]
+    async fn test_rate_limit_retries() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after-ms", "10"))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        // Rate limit errors are returned without retrying by default
+        let client = HttpClient::default();
+        let response = client.send(client.post(server.uri())).await.unwrap();
+        assert_eq!(response.status(), 429);
+
+        let client = HttpClient::default().rate_limit_retries(1);
+        let response = client.send(client.post(server.uri())).await.unwrap();
+        assert_eq!(response.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_compress_requests_above_bytes() {
+        let large_body = json!({"prompt": "lorem ipsum ".repeat(1_000)});
+        let small_body = json!({"prompt": "Hi"});
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("Content-Type", "application/json"))
+            .and(header("Content-Encoding", "gzip"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        // Bodies below the threshold are sent as they are
+        Mock::given(method("POST"))
+            .and(header("Content-Type", "application/json"))
+            .and(body_json(&small_body))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = HttpClient::default().compress_requests_above_bytes(4_096);
+        for body in [&large_body, &small_body] {
+            client.post_json(server.uri(), body).send().await.unwrap();
+        }
+
+        let requests = server.received_requests().await.unwrap();
+        let mut decompressed = String::new();
+        GzDecoder::new(requests[0].body.as_slice())
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&decompressed).unwrap(),
+            large_body
+        );
+        assert!(requests[0].body.len() < decompressed.len());
+    }
+}
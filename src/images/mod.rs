@@ -0,0 +1,3 @@
+pub mod openai;
+
+pub use openai::{OpenAIGeneratedImage, OpenAIImage, OpenAIImageModels, OpenAIImageResponseFormat};
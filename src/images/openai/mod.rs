@@ -0,0 +1,5 @@
+pub mod openai_image;
+
+pub use openai_image::{
+    OpenAIGeneratedImage, OpenAIImage, OpenAIImageModels, OpenAIImageResponseFormat,
+};
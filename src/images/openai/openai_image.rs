@@ -0,0 +1,356 @@
+use anyhow::{anyhow, Result};
+use log::{error, info};
+use reqwest::{header, Client};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::constants::OPENAI_API_URL;
+use crate::domain::AllmsError;
+use crate::http_client::HttpClient;
+use crate::utils::{get_openai_headers, redact_secrets};
+
+/// [OpenAI Docs](https://platform.openai.com/docs/api-reference/images/create)
+///
+/// The Images API generates images based on a text prompt.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OpenAIImage {
+    model: OpenAIImageModels,
+    api_key: String,
+    size: Option<String>,
+    quality: Option<String>,
+    style: Option<String>,
+    count: usize,
+    response_format: Option<OpenAIImageResponseFormat>,
+    debug: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq, Hash)]
+pub enum OpenAIImageModels {
+    DallE2,
+    DallE3,
+    GptImage1,
+}
+
+impl OpenAIImageModels {
+    pub fn as_str(&self) -> &str {
+        match self {
+            OpenAIImageModels::DallE2 => "dall-e-2",
+            OpenAIImageModels::DallE3 => "dall-e-3",
+            OpenAIImageModels::GptImage1 => "gpt-image-1",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq)]
+pub enum OpenAIImageResponseFormat {
+    #[serde(rename(deserialize = "url", serialize = "url"))]
+    Url,
+    #[serde(rename(deserialize = "b64_json", serialize = "b64_json"))]
+    B64Json,
+}
+
+/// Generated image returned either as a URL or base64-encoded data depending on the requested format
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OpenAIGeneratedImage {
+    pub url: Option<String>,
+    pub b64_json: Option<String>,
+    // Returned by DALL·E 3 if the prompt was rewritten by the model
+    pub revised_prompt: Option<String>,
+}
+
+impl OpenAIImage {
+    /// Constructor
+    pub fn new(model: OpenAIImageModels, open_ai_key: &str) -> Self {
+        OpenAIImage {
+            model,
+            api_key: open_ai_key.to_string(),
+            size: None,
+            quality: None,
+            style: None,
+            count: 1,
+            response_format: None,
+            debug: false,
+        }
+    }
+
+    ///
+    /// This method can be used to turn on debug mode
+    ///
+    pub fn debug(mut self) -> Self {
+        self.debug = true;
+        self
+    }
+
+    ///
+    /// This method can be used to set the size of generated images, e.g. `1024x1024`
+    ///
+    pub fn size(mut self, size: &str) -> Self {
+        self.size = Some(size.to_string());
+        self
+    }
+
+    ///
+    /// This method can be used to set the quality of generated images, e.g. `standard` or `hd` for DALL·E 3
+    ///
+    pub fn quality(mut self, quality: &str) -> Self {
+        self.quality = Some(quality.to_string());
+        self
+    }
+
+    ///
+    /// This method can be used to set the style of generated images, `vivid` or `natural`. Supported by DALL·E 3 only
+    ///
+    pub fn style(mut self, style: &str) -> Self {
+        self.style = Some(style.to_string());
+        self
+    }
+
+    ///
+    /// This method can be used to set the number of images to generate
+    ///
+    pub fn count(mut self, count: usize) -> Self {
+        self.count = count;
+        self
+    }
+
+    ///
+    /// This method can be used to request images as URLs or base64 data
+    /// `gpt-image-1` always returns base64 data
+    ///
+    pub fn response_format(mut self, response_format: OpenAIImageResponseFormat) -> Self {
+        self.response_format = Some(response_format);
+        self
+    }
+
+    ///
+    /// This method generates images based on the provided prompt
+    /// Settings not supported by the model (e.g. `quality` for DALL·E 2) return an error before the API is called
+    ///
+    pub async fn generate(&self, prompt: &str) -> Result<Vec<OpenAIGeneratedImage>> {
+        self.validate()?;
+
+        let images_url = format!(
+            "{OPENAI_API_URL}/v1/images/generations",
+            OPENAI_API_URL = *OPENAI_API_URL
+        );
+
+        let body = self.get_body(prompt);
+
+        //Make the API call
+        let client = Client::new();
+
+        let response = client
+            .post(images_url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .headers(get_openai_headers(&HttpClient::default()))
+            .bearer_auth(&self.api_key)
+            .json(&body)
+            .send()
+            .await?;
+
+        let response_status = response.status();
+        let response_text = response.text().await?;
+
+        if self.debug {
+            info!(
+                "[allms][OpenAI][Images][debug] Images API response: [{}] {:#?}",
+                &response_status,
+                redact_secrets(&response_text)
+            );
+        }
+
+        parse_images_response(&response_text)
+    }
+
+    // This function checks that the settings are supported by the model
+    // Docs: https://platform.openai.com/docs/api-reference/images/create
+    fn validate(&self) -> Result<()> {
+        let (sizes, max_count): (&[&str], usize) = match self.model {
+            OpenAIImageModels::DallE2 => (&["256x256", "512x512", "1024x1024"], 10),
+            OpenAIImageModels::DallE3 => (&["1024x1024", "1792x1024", "1024x1792"], 1),
+            OpenAIImageModels::GptImage1 => (&["1024x1024", "1536x1024", "1024x1536", "auto"], 10),
+        };
+
+        let error_detail = match &self.model {
+            _ if !(1..=max_count).contains(&self.count) => Some(format!(
+                "Number of images must be between 1 and {}",
+                max_count
+            )),
+            _ if self
+                .size
+                .as_deref()
+                .is_some_and(|size| !sizes.contains(&size)) =>
+            {
+                Some(format!("Size must be one of: {}", sizes.join(", ")))
+            }
+            OpenAIImageModels::DallE2 if self.quality.is_some() => {
+                Some("Quality is not supported".to_string())
+            }
+            OpenAIImageModels::DallE2 | OpenAIImageModels::GptImage1 if self.style.is_some() => {
+                Some("Style is not supported".to_string())
+            }
+            OpenAIImageModels::GptImage1 if self.response_format.is_some() => Some(
+                "Response format is not supported, images are always returned as base64 data"
+                    .to_string(),
+            ),
+            _ => None,
+        };
+
+        match error_detail {
+            None => Ok(()),
+            Some(error_detail) => {
+                let error = AllmsError {
+                    crate_name: "allms".to_string(),
+                    module: "images::openai_image".to_string(),
+                    error_message: format!(
+                        "Images API settings not supported by {}",
+                        self.model.as_str()
+                    ),
+                    error_detail,
+                };
+                error!("{:?}", error);
+                Err(anyhow!("{:?}", error))
+            }
+        }
+    }
+
+    // This function prepares the body of the Images API request
+    fn get_body(&self, prompt: &str) -> serde_json::Value {
+        let mut body = json!({
+            "model": self.model.as_str(),
+            "prompt": prompt,
+            "n": self.count,
+        });
+        if let Some(size) = &self.size {
+            body["size"] = json!(size);
+        }
+        if let Some(quality) = &self.quality {
+            body["quality"] = json!(quality);
+        }
+        if let Some(style) = &self.style {
+            body["style"] = json!(style);
+        }
+        if let Some(response_format) = &self.response_format {
+            body["response_format"] = json!(response_format);
+        }
+        body
+    }
+}
+
+/******************************************************************************************
+*
+* API Response serialization / deserialization structs
+*
+******************************************************************************************/
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct OpenAIImagesResp {
+    created: i64,
+    data: Vec<OpenAIGeneratedImage>,
+}
+
+fn parse_images_response(response_text: &str) -> Result<Vec<OpenAIGeneratedImage>> {
+    serde_json::from_str::<OpenAIImagesResp>(response_text)
+        .map(|response| response.data)
+        .map_err(|error| {
+            let error = AllmsError {
+                crate_name: "allms".to_string(),
+                module: "images::openai_image".to_string(),
+                error_message: format!("Images API response serialization error: {}", error),
+                error_detail: response_text.to_string(),
+            };
+            error!("{:?}", error);
+            anyhow!("{:?}", error)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_images_response_urls() {
+        let response = json!({
+            "created": 1713833628,
+            "data": [
+                {
+                    "url": "https://example.com/image1.png",
+                    "revised_prompt": "A cute baby sea otter floating on its back"
+                },
+                {
+                    "url": "https://example.com/image2.png"
+                }
+            ]
+        });
+
+        let images = parse_images_response(&response.to_string()).unwrap();
+        let urls: Vec<String> = images
+            .iter()
+            .filter_map(|image| image.url.clone())
+            .collect();
+
+        assert_eq!(
+            urls,
+            vec![
+                "https://example.com/image1.png".to_string(),
+                "https://example.com/image2.png".to_string()
+            ]
+        );
+        assert_eq!(
+            images[0].revised_prompt.as_deref(),
+            Some("A cute baby sea otter floating on its back")
+        );
+        assert!(images[1].b64_json.is_none());
+    }
+
+    #[test]
+    fn test_images_body() {
+        let image = OpenAIImage::new(OpenAIImageModels::DallE3, "key")
+            .size("1024x1024")
+            .quality("hd")
+            .response_format(OpenAIImageResponseFormat::B64Json);
+
+        let body = image.get_body("A sea otter");
+
+        assert_eq!(body["model"], "dall-e-3");
+        assert_eq!(body["n"], 1);
+        assert_eq!(body["size"], "1024x1024");
+        assert_eq!(body["quality"], "hd");
+        assert_eq!(body["response_format"], "b64_json");
+    }
+
+    #[test]
+    fn test_images_settings_validation() {
+        assert!(OpenAIImage::new(OpenAIImageModels::DallE3, "key")
+            .size("1792x1024")
+            .quality("hd")
+            .style("natural")
+            .validate()
+            .is_ok());
+        assert!(OpenAIImage::new(OpenAIImageModels::DallE2, "key")
+            .size("512x512")
+            .count(4)
+            .validate()
+            .is_ok());
+
+        assert!(OpenAIImage::new(OpenAIImageModels::DallE2, "key")
+            .quality("hd")
+            .validate()
+            .is_err());
+        assert!(OpenAIImage::new(OpenAIImageModels::DallE2, "key")
+            .style("vivid")
+            .validate()
+            .is_err());
+        assert!(OpenAIImage::new(OpenAIImageModels::DallE3, "key")
+            .count(2)
+            .validate()
+            .is_err());
+        assert!(OpenAIImage::new(OpenAIImageModels::DallE3, "key")
+            .size("512x512")
+            .validate()
+            .is_err());
+        assert!(OpenAIImage::new(OpenAIImageModels::GptImage1, "key")
+            .response_format(OpenAIImageResponseFormat::Url)
+            .validate()
+            .is_err());
+    }
+}
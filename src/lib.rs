@@ -1,17 +1,51 @@
 pub mod assistants;
+pub mod audio;
+mod budget;
+mod cache;
 mod completions;
 mod constants;
 mod domain;
+pub mod embedding_models;
+mod embeddings;
 mod enums;
+pub mod files;
+pub mod fine_tuning;
+mod hooks;
+mod http_client;
+pub mod images;
+mod llm_client;
 pub mod llm_models;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod prompt_template;
+#[cfg(feature = "realtime")]
+pub mod realtime;
 pub use llm_models as llm;
 mod utils;
 
 #[allow(deprecated)]
 mod deprecated;
 
+pub use crate::budget::BudgetTracker;
+pub use crate::cache::{cache_key, InMemoryLruCache, ResponseCache};
 pub use crate::completions::Completions;
 #[allow(deprecated)]
 pub use crate::deprecated::{
     OpenAI, OpenAIAssistant, OpenAIAssistantVersion, OpenAIFile, OpenAIModels,
 };
+pub use crate::domain::{
+    ApiResponse, BatchResponse, BudgetExceeded, CompletionsOptions, CompletionsRequest,
+    CompletionsResponse, CompletionsUsage, ContextLengthExceeded, CredentialsError, Document,
+    DryRun, EmptyResponse, FileData, FinishReason, GroundingCitation, GroundingTool, LLMProvider,
+    Message, MessageRole, ModelInfo, OutputStrictness, RateLimitStatus, SchemaMismatch,
+    Temperature, TemperaturePolicy, TokenCost, TokenLogprob, TopLogprob, TruncationPolicy,
+    UnknownModelError,
+};
+pub use crate::embeddings::Embeddings;
+pub use crate::hooks::{OnRequest, OnResponse, RequestEvent, ResponseEvent};
+pub use crate::http_client::HttpClient;
+pub use crate::llm_client::{ClientConfig, LLMClient};
+#[cfg(feature = "metrics")]
+pub use crate::metrics::register_metrics;
+pub use crate::prompt_template::PromptTemplate;
+pub use crate::utils::{repair_json, schema_for, schema_to_description, validate_json_schema};
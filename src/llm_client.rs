@@ -0,0 +1,737 @@
+use anyhow::{anyhow, Result};
+use futures::{stream, StreamExt, TryStreamExt};
+use reqwest::Client;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::domain::{BatchResponse, CompletionsUsage, Message, MessageRole};
+use crate::hooks::{OnRequest, OnResponse, RequestEvent, ResponseEvent};
+use crate::llm_models::{LLMModel, TextStream};
+use crate::{BudgetTracker, Completions, ResponseCache};
+
+// Responses that cannot be deserialized are retried twice unless configured otherwise
+const DEFAULT_MAX_RETRIES: usize = 2;
+
+/// Settings applied to every request sent by an `LLMClient`
+#[derive(Clone)]
+pub struct ClientConfig {
+    /// Max tokens for prompt & response. Defaults to the max allowed for the model
+    pub max_tokens: Option<usize>,
+    /// Temperature as a percentage of the max temperature of the model. Defaults to the default temperature of the model
+    pub temperature: Option<u32>,
+    /// How many times a request is sent again if the response cannot be deserialized into the requested type
+    pub max_retries: usize,
+    /// Response cache shared by all requests of the client
+    pub cache: Option<Arc<dyn ResponseCache>>,
+    /// Logs the body and response of each request
+    pub debug: bool,
+    /// Replaces the default base (system) instructions of the model, e.g. with wording the model follows more reliably. See `Completions::base_instructions`.
+    pub base_instructions: Option<String>,
+    /// Retries ask the model to correct its invalid output instead of sending the same request again. See `Completions::correct_invalid_json`.
+    pub retry_on_invalid_json: bool,
+    /// Callback invoked before each API call. See `Completions::on_request`.
+    pub on_request: Option<OnRequest>,
+    /// Callback invoked after each API call. See `Completions::on_response`.
+    pub on_response: Option<OnResponse>,
+    /// Sends requests over HTTP/2 without negotiating the protocol first. Only for providers accepting HTTP/2, e.g. OpenAI.
+    pub use_http2: bool,
+    /// Interval of TCP keepalive probes keeping idle connections open between requests
+    pub tcp_keepalive: Option<Duration>,
+    /// Request bodies of at least this size are compressed with gzip. See `Completions::compress_requests_above_bytes`.
+    pub compress_requests_above_bytes: Option<usize>,
+    /// How many times requests rejected with 429 Too Many Requests are sent again. Defaults to the `RATE_LIMIT_RETRIES` environment variable. See `Completions::rate_limit_retries`.
+    pub rate_limit_retries: Option<usize>,
+    /// Anthropic API version. Defaults to the `ANTHROPIC_API_VERSION` environment variable. See `Completions::anthropic_version`.
+    pub anthropic_version: Option<String>,
+    /// Anthropic beta features. Defaults to the `ANTHROPIC_BETA` environment variable. See `Completions::anthropic_beta`.
+    pub anthropic_beta: Option<String>,
+    /// OpenAI organization. Defaults to the `OPENAI_ORG_ID` environment variable. See `Completions::openai_organization`.
+    pub openai_organization: Option<String>,
+    /// OpenAI project. Defaults to the `OPENAI_PROJECT_ID` environment variable. See `Completions::openai_project`.
+    pub openai_project: Option<String>,
+    /// Tracker of the spend of all requests of the client. See `Completions::budget`.
+    pub budget: Option<BudgetTracker>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        ClientConfig {
+            max_tokens: None,
+            temperature: None,
+            max_retries: DEFAULT_MAX_RETRIES,
+            cache: None,
+            debug: false,
+            base_instructions: None,
+            retry_on_invalid_json: false,
+            on_request: None,
+            on_response: None,
+            use_http2: false,
+            tcp_keepalive: None,
+            compress_requests_above_bytes: None,
+            rate_limit_retries: None,
+            anthropic_version: None,
+            anthropic_beta: None,
+            openai_organization: None,
+            openai_project: None,
+            budget: None,
+        }
+    }
+}
+
+/// High-level client returning answers of LLM models as Rust types.
+///
+/// The model, API key and settings are stored once and used for every request. Unlike `Completions`, which is configured for and consumed by a single request, the client can be reused for any number of prompts.
+/// The output schema is derived from the requested type, and responses that cannot be deserialized into it are retried.
+/// Use `LLMModelEnum` to select the model at runtime, or `RateLimitedModel` to enforce rate limits on the client side.
+/// All requests are sent with a single HTTP client so that connections are reused between them.
+/// The model is shared by all requests, so it doesn't need to implement `Clone` and can be a `Box<dyn LLMModel>` chosen at runtime.
+pub struct LLMClient<T: LLMModel> {
+    model: Arc<T>,
+    api_key: String,
+    config: ClientConfig,
+    //Shared by all requests, including those of clients created with `with_api_key`
+    http_client: Client,
+}
+
+//Implemented manually as deriving would require the model to implement `Clone`
+impl<T: LLMModel> Clone for LLMClient<T> {
+    fn clone(&self) -> Self {
+        LLMClient {
+            model: self.model.clone(),
+            api_key: self.api_key.clone(),
+            config: self.config.clone(),
+            http_client: self.http_client.clone(),
+        }
+    }
+}
+
+impl<T: LLMModel> LLMClient<T> {
+    /// Constructor of the client with the default settings
+    pub fn new(model: T, api_key: &str) -> Self {
+        LLMClient {
+            model: Arc::new(model),
+            api_key: api_key.to_string(),
+            config: ClientConfig::default(),
+            http_client: Client::new(),
+        }
+    }
+
+    /// Constructor of the client with the provided settings. Returns an error if the HTTP client cannot be built with the connection settings of the config.
+    pub fn with_config(model: T, api_key: &str, config: ClientConfig) -> Result<Self> {
+        Ok(LLMClient {
+            model: Arc::new(model),
+            api_key: api_key.to_string(),
+            http_client: build_http_client(&config)?,
+            config,
+        })
+    }
+
+    /// Returns the settings applied to every request
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    ///
+    /// This method returns a copy of the client sending requests with the provided API key instead of the one set in the constructor.
+    /// The settings, including the response cache, are shared with the original client. It can be used to serve multiple tenants with their own keys, or to rotate keys without rebuilding the client:
+    /// `client.with_api_key(&tenant_key).complete(prompt).await`
+    ///
+    pub fn with_api_key(&self, api_key: &str) -> Self {
+        LLMClient {
+            model: self.model.clone(),
+            api_key: api_key.to_string(),
+            config: self.config.clone(),
+            http_client: self.http_client.clone(),
+        }
+    }
+
+    ///
+    /// This method sets how many times a request is sent again if the response cannot be deserialized into the requested type
+    ///
+    pub fn max_retries(mut self, max_retries: usize) -> Self {
+        self.config.max_retries = max_retries;
+        self
+    }
+
+    ///
+    /// This method attaches a response cache shared by all requests of the client. See `Completions::cache`.
+    ///
+    pub fn cache(mut self, cache: Arc<dyn ResponseCache>) -> Self {
+        self.config.cache = Some(cache);
+        self
+    }
+
+    ///
+    /// This method replaces the default base (system) instructions of the model for all requests of the client
+    ///
+    pub fn base_instructions(mut self, base_instructions: &str) -> Self {
+        self.config.base_instructions = Some(base_instructions.to_string());
+        self
+    }
+
+    ///
+    /// This method makes retries of responses that cannot be deserialized (see `max_retries`) re-send the prompt with the invalid output and the error, asking the model to correct it
+    ///
+    pub fn retry_on_invalid_json(mut self) -> Self {
+        self.config.retry_on_invalid_json = true;
+        self
+    }
+
+    ///
+    /// This method sets a callback invoked before each API call of the client, e.g. to push metrics to any sink. See `Completions::on_request`.
+    ///
+    pub fn on_request<F: Fn(&RequestEvent) + 'static>(mut self, on_request: F) -> Self {
+        self.config.on_request = Some(Arc::new(on_request));
+        self
+    }
+
+    ///
+    /// This method sets a callback invoked after each API call of the client with its latency, outcome and token usage. See `Completions::on_response`.
+    ///
+    pub fn on_response<F: Fn(&ResponseEvent) + 'static>(mut self, on_response: F) -> Self {
+        self.config.on_response = Some(Arc::new(on_response));
+        self
+    }
+
+    ///
+    /// This method makes the client send all requests over HTTP/2, which saves the protocol negotiation on new connections
+    /// Only use it with providers accepting HTTP/2, e.g. OpenAI. Returns an error if the HTTP client cannot be built with the setting.
+    ///
+    pub fn use_http2(mut self) -> Result<Self> {
+        self.config.use_http2 = true;
+        self.http_client = build_http_client(&self.config)?;
+        Ok(self)
+    }
+
+    ///
+    /// This method sets the interval of TCP keepalive probes so that idle connections stay open between requests
+    /// Returns an error if the HTTP client cannot be built with the setting
+    ///
+    pub fn tcp_keepalive(mut self, interval: Duration) -> Result<Self> {
+        self.config.tcp_keepalive = Some(interval);
+        self.http_client = build_http_client(&self.config)?;
+        Ok(self)
+    }
+
+    ///
+    /// This method makes request bodies of at least the provided size be compressed with gzip. See `Completions::compress_requests_above_bytes`.
+    ///
+    pub fn compress_requests_above_bytes(mut self, bytes: usize) -> Self {
+        self.config.compress_requests_above_bytes = Some(bytes);
+        self
+    }
+
+    ///
+    /// This method sets how many times requests rejected with 429 Too Many Requests are sent again after the wait indicated by the API. See `Completions::rate_limit_retries`.
+    ///
+    pub fn rate_limit_retries(mut self, retries: usize) -> Self {
+        self.config.rate_limit_retries = Some(retries);
+        self
+    }
+
+    ///
+    /// This method sets the Anthropic API version sent with every request of the client. See `Completions::anthropic_version`.
+    ///
+    pub fn anthropic_version(mut self, version: &str) -> Self {
+        self.config.anthropic_version = Some(version.to_string());
+        self
+    }
+
+    ///
+    /// This method enables Anthropic beta features for every request of the client. See `Completions::anthropic_beta`.
+    ///
+    pub fn anthropic_beta(mut self, beta: &str) -> Self {
+        self.config.anthropic_beta = Some(beta.to_string());
+        self
+    }
+
+    ///
+    /// This method sets the OpenAI organization of every request of the client. See `Completions::openai_organization`.
+    ///
+    pub fn openai_organization(mut self, organization: &str) -> Self {
+        self.config.openai_organization = Some(organization.to_string());
+        self
+    }
+
+    ///
+    /// This method sets the OpenAI project of every request of the client. See `Completions::openai_project`.
+    ///
+    pub fn openai_project(mut self, project: &str) -> Self {
+        self.config.openai_project = Some(project.to_string());
+        self
+    }
+
+    ///
+    /// This method sets the tracker recording the spend of all requests of the client. Once its budget is reached, requests fail with a `BudgetExceeded` error. See `Completions::budget`.
+    ///
+    pub fn budget(mut self, budget: BudgetTracker) -> Self {
+        self.config.budget = Some(budget);
+        self
+    }
+
+    ///
+    /// This method turns on debug mode for all requests of the client
+    ///
+    pub fn debug(mut self) -> Self {
+        self.config.debug = true;
+        self
+    }
+
+    ///
+    /// This method sends the prompt to the model and returns the answer deserialized into the requested type.
+    /// The Json schema of the type is derived automatically and included in the request.
+    ///
+    pub async fn complete_as<U: DeserializeOwned + JsonSchema>(&self, prompt: &str) -> Result<U> {
+        self.completions().get_answer::<U>(prompt).await
+    }
+
+    ///
+    /// This method sends the prompts to the model in parallel and returns the answers, in the order of the prompts, with the usage and cost summed across all requests.
+    /// At most as many requests as allowed by the rate limit of the model (see `LLMModel::get_max_requests`) are sent at once. The batch fails if any of the requests fails.
+    ///
+    pub async fn complete_batch_as<U: DeserializeOwned + JsonSchema>(
+        &self,
+        prompts: &[&str],
+    ) -> Result<BatchResponse<U>> {
+        let responses: Vec<_> = stream::iter(prompts)
+            .map(|prompt| self.completions().get_response::<U>(prompt))
+            .buffered(self.model.get_max_requests().max(1))
+            .try_collect()
+            .await?;
+        let usage: CompletionsUsage = responses
+            .iter()
+            .filter_map(|response| response.usage.clone())
+            .sum();
+        let cost = self
+            .model
+            .cost_per_1k_tokens()
+            .map(|prices| prices.get_cost(&usage));
+
+        Ok(BatchResponse {
+            responses,
+            usage,
+            cost,
+        })
+    }
+
+    ///
+    /// This method sends the prompt to the model and returns the plain-text answer, without an output schema
+    ///
+    pub async fn complete(&self, prompt: &str) -> Result<String> {
+        self.completions().get_text(prompt).await
+    }
+
+    ///
+    /// This method continues a multi-turn conversation and returns the plain-text answer of the model.
+    /// The last message is the current prompt and needs to come from the user. All previous messages are sent as the history.
+    ///
+    pub async fn complete_with_history(&self, messages: &[Message]) -> Result<String> {
+        match messages.split_last() {
+            Some((prompt, history)) if prompt.role == MessageRole::User => {
+                self.completions()
+                    .history(history)
+                    .get_text(&prompt.content)
+                    .await
+            }
+            _ => Err(anyhow!(
+                "[allms] The last message of the conversation needs to come from the user"
+            )),
+        }
+    }
+
+    ///
+    /// This method sends the prompt to the model and returns the plain-text answer in chunks as they are generated. See `Completions::stream_tokens`.
+    ///
+    pub async fn stream_tokens(&self, prompt: &str) -> Result<TextStream> {
+        self.completions().stream_tokens(prompt).await
+    }
+
+    // Each request is sent with a new `Completions` configured with the settings of the client
+    fn completions(&self) -> Completions<Arc<T>> {
+        let completions = Completions::new(
+            self.model.clone(),
+            &self.api_key,
+            self.config.max_tokens,
+            self.config.temperature,
+        )
+        .parse_retries(self.config.max_retries)
+        .correct_invalid_json(self.config.retry_on_invalid_json)
+        .http_client(self.http_client.clone());
+        let completions = match &self.config.cache {
+            Some(cache) => completions.cache(cache.clone()),
+            None => completions,
+        };
+        let completions = match self.config.compress_requests_above_bytes {
+            Some(bytes) => completions.compress_requests_above_bytes(bytes),
+            None => completions,
+        };
+        let completions = match self.config.rate_limit_retries {
+            Some(retries) => completions.rate_limit_retries(retries),
+            None => completions,
+        };
+        let completions = match &self.config.anthropic_version {
+            Some(version) => completions.anthropic_version(version),
+            None => completions,
+        };
+        let completions = match &self.config.anthropic_beta {
+            Some(beta) => completions.anthropic_beta(beta),
+            None => completions,
+        };
+        let completions = match &self.config.openai_organization {
+            Some(organization) => completions.openai_organization(organization),
+            None => completions,
+        };
+        let completions = match &self.config.openai_project {
+            Some(project) => completions.openai_project(project),
+            None => completions,
+        };
+        let completions = match &self.config.budget {
+            Some(budget) => completions.budget(budget.clone()),
+            None => completions,
+        };
+        let completions = match &self.config.base_instructions {
+            Some(base_instructions) => completions.base_instructions(base_instructions),
+            None => completions,
+        };
+        let completions = match &self.config.on_request {
+            Some(on_request) => {
+                let on_request = on_request.clone();
+                completions.on_request(move |event| on_request(event))
+            }
+            None => completions,
+        };
+        let completions = match &self.config.on_response {
+            Some(on_response) => {
+                let on_response = on_response.clone();
+                completions.on_response(move |event| on_response(event))
+            }
+            None => completions,
+        };
+        match self.config.debug {
+            true => completions.debug(),
+            false => completions,
+        }
+    }
+}
+
+// The connection settings of the config are applied when the HTTP client is built
+fn build_http_client(config: &ClientConfig) -> Result<Client> {
+    let builder = Client::builder().tcp_keepalive(config.tcp_keepalive);
+    let builder = match config.use_http2 {
+        true => builder.http2_prior_knowledge(),
+        false => builder,
+    };
+    builder.build().map_err(|error| {
+        anyhow!(
+            "[allms] Unable to build the HTTP client with the configured settings: {}",
+            error
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Version;
+    use schemars::JsonSchema;
+    use serde::Deserialize;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::{build_http_client, ClientConfig};
+    use crate::domain::{CompletionsUsage, TokenCost};
+    use crate::llm_models::{GoogleModels, LLMModel, MockModel, MockResponse};
+    use crate::{Completions, LLMClient, Message};
+
+    #[derive(Deserialize, JsonSchema, Debug)]
+    struct TestResponse {
+        answer: String,
+    }
+
+    // Model numbering its responses, with the usage of the n-th response derived from n
+    fn counting_model() -> MockModel {
+        let responses = (1..=3)
+            .map(|n| {
+                MockResponse::new(&format!(r#"{{"answer": "{n}"}}"#)).usage(CompletionsUsage {
+                    input_tokens: n * 10,
+                    output_tokens: n,
+                    cache_creation_input_tokens: None,
+                    cache_read_input_tokens: (n == 2).then_some(5),
+                })
+            })
+            .collect();
+        MockModel::default().responses(responses)
+    }
+
+    #[tokio::test]
+    async fn test_complete_batch_as() {
+        let client = LLMClient::new(counting_model(), "");
+
+        let batch = client
+            .complete_batch_as::<TestResponse>(&["First", "Second", "Third"])
+            .await
+            .unwrap();
+
+        assert_eq!(batch.responses.len(), 3);
+        let per_item_input_tokens: usize = batch
+            .responses
+            .iter()
+            .map(|response| response.usage.as_ref().unwrap().input_tokens)
+            .sum();
+        assert_eq!(per_item_input_tokens, 60);
+        assert_eq!(
+            batch.usage,
+            CompletionsUsage {
+                input_tokens: 60,
+                output_tokens: 6,
+                cache_creation_input_tokens: None,
+                cache_read_input_tokens: Some(5),
+            }
+        );
+
+        // Without prices the cost is unknown
+        assert!(batch.cost.is_none());
+        let client = LLMClient::new(
+            counting_model().prices(TokenCost {
+                input: 1.0,
+                output: 10.0,
+            }),
+            "",
+        );
+        let batch = client
+            .complete_batch_as::<TestResponse>(&["First", "Second", "Third"])
+            .await
+            .unwrap();
+        // 60 input tokens at $1 and 6 output tokens at $10 per 1k tokens
+        assert!((batch.cost.unwrap() - 0.12).abs() < 1e-9);
+
+        // The batch fails with any of its requests
+        let client = LLMClient::new(MockModel::new(r#"{"answer": "mocked"}"#).failing(), "");
+        assert!(client
+            .complete_batch_as::<TestResponse>(&["First", "Second"])
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_complete_as() {
+        let client = LLMClient::new(MockModel::new(r#"{"answer": "mocked"}"#), "");
+
+        for _ in 0..2 {
+            let answer = client
+                .complete_as::<TestResponse>("Say something")
+                .await
+                .unwrap();
+            assert_eq!(answer.answer, "mocked");
+        }
+
+        // Malformed Json fails once the retries are exhausted
+        let client = LLMClient::new(MockModel::new(r#"{"answer": "#), "").max_retries(1);
+        assert!(client
+            .complete_as::<TestResponse>("Say something")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_complete() {
+        // The answer is returned as-is without deserialization
+        let client = LLMClient::new(MockModel::new("Hello there!"), "");
+        assert_eq!(client.complete("Say hello").await.unwrap(), "Hello there!");
+
+        let history = [
+            Message::user("Say hello"),
+            Message::assistant("Hello there!"),
+            Message::user("Say it again"),
+        ];
+        assert_eq!(
+            client.complete_with_history(&history).await.unwrap(),
+            "Hello there!"
+        );
+        assert!(client.complete_with_history(&history[..2]).await.is_err());
+        assert!(client.complete_with_history(&[]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_trait_object_model() {
+        // The model can be selected at runtime
+        let model: Box<dyn LLMModel> = Box::new(MockModel::new("Hello there!"));
+        let client = LLMClient::new(model, "");
+        assert_eq!(client.complete("Say hello").await.unwrap(), "Hello there!");
+        assert_eq!(
+            client
+                .clone()
+                .with_api_key("key")
+                .complete("Say hello")
+                .await
+                .unwrap(),
+            "Hello there!"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_request_callbacks() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let request_events = events.clone();
+        let response_events = events.clone();
+        let client = LLMClient::new(counting_model(), "")
+            .on_request(move |event| request_events.borrow_mut().push(format!("{:?}", event)))
+            .on_response(move |event| {
+                assert_eq!(event.model, "mock");
+                assert!(event.body_size > 0);
+                assert_eq!(event.status, None);
+                assert!(event.error.is_none());
+                assert_eq!(
+                    event.usage,
+                    Some(CompletionsUsage {
+                        input_tokens: 10,
+                        output_tokens: 1,
+                        cache_creation_input_tokens: None,
+                        cache_read_input_tokens: None,
+                    })
+                );
+                response_events.borrow_mut().push("response".to_string());
+            });
+
+        let answer = client
+            .complete_as::<TestResponse>("Say something")
+            .await
+            .unwrap();
+        assert_eq!(answer.answer, "1");
+
+        let events = events.borrow();
+        assert_eq!(events.len(), 2);
+        assert!(events[0].starts_with("RequestEvent { model: \"mock\""));
+        assert_eq!(events[1], "response");
+    }
+
+    #[tokio::test]
+    async fn test_request_callbacks_on_error() {
+        let errors = Rc::new(RefCell::new(Vec::new()));
+        let response_errors = errors.clone();
+        let client = LLMClient::new(MockModel::default().failing(), "").on_response(move |event| {
+            response_errors.borrow_mut().push(event.error.clone());
+        });
+
+        assert!(client.complete("Say something").await.is_err());
+        let errors = errors.borrow();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0]
+            .as_ref()
+            .unwrap()
+            .contains("Simulated API failure"));
+    }
+
+    #[tokio::test]
+    async fn test_request_callbacks_on_error_status() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let response_events = events.clone();
+        let model = MockModel::new(r#"{"error": {"message": "Overloaded"}}"#).status(529);
+        let client = LLMClient::new(model, "").on_response(move |event| {
+            response_events
+                .borrow_mut()
+                .push((event.status, event.error.clone()));
+        });
+
+        assert!(client.complete("Say something").await.is_err());
+        let events = events.borrow();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].0, Some(529));
+        assert!(events[0].1.as_ref().unwrap().contains("Overloaded"));
+    }
+
+    #[test]
+    fn test_client_config() {
+        let client = LLMClient::new(MockModel::default(), "").max_retries(5);
+        assert_eq!(client.config().max_retries, 5);
+        assert_eq!(client.config().max_tokens, None);
+
+        let config = ClientConfig {
+            max_tokens: Some(1_000),
+            ..Default::default()
+        };
+        let client = LLMClient::with_config(MockModel::default(), "", config).unwrap();
+        assert_eq!(client.config().max_tokens, Some(1_000));
+        assert_eq!(client.config().max_retries, 2);
+    }
+
+    #[tokio::test]
+    async fn test_http_client_settings() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let config = ClientConfig {
+            use_http2: true,
+            tcp_keepalive: Some(Duration::from_secs(30)),
+            ..Default::default()
+        };
+        let http_client = build_http_client(&config).unwrap();
+        for _ in 0..2 {
+            let response = http_client.get(server.uri()).send().await.unwrap();
+            assert_eq!(response.version(), Version::HTTP_2);
+        }
+
+        let client = LLMClient::new(MockModel::default(), "")
+            .use_http2()
+            .unwrap()
+            .tcp_keepalive(Duration::from_secs(30))
+            .unwrap();
+        assert!(client.config().use_http2);
+        assert_eq!(client.config().tcp_keepalive, Some(Duration::from_secs(30)));
+    }
+
+    #[tokio::test]
+    async fn test_shared_http_client() {
+        let model = MockModel::new("Hello");
+        let client = LLMClient::new(model.clone(), "").compress_requests_above_bytes(1_000);
+
+        client.complete("Hi").await.unwrap();
+        client.with_api_key("key").complete("Hi").await.unwrap();
+        // Completions use the default client unless one is provided
+        let completions = Completions::new(model.clone(), "", None, None);
+        completions.get_text("Hi").await.unwrap();
+
+        let compress_requests_above_bytes: Vec<Option<usize>> = model
+            .calls
+            .all()
+            .iter()
+            .map(|call| call.http_client.compress_requests_above_bytes)
+            .collect();
+        assert_eq!(
+            compress_requests_above_bytes,
+            vec![Some(1_000), Some(1_000), None]
+        );
+    }
+
+    #[test]
+    fn test_base_instructions() {
+        let base_instructions = "Output only JSON, no markdown.";
+        let client =
+            LLMClient::new(GoogleModels::Gemini1_5Flash, "").base_instructions(base_instructions);
+
+        let request = client
+            .completions()
+            .to_request::<TestResponse>("Say something")
+            .unwrap();
+        let body = GoogleModels::Gemini1_5Flash.get_body(
+            &request.instructions,
+            &request.json_schema,
+            false,
+            &1_000,
+            &0.0,
+            &request.options,
+        );
+        assert_eq!(
+            body["system_instruction"]["parts"][0]["text"],
+            base_instructions
+        );
+    }
+}
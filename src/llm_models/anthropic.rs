@@ -1,23 +1,49 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use log::info;
-use reqwest::{header, Client};
+use futures::stream::StreamExt;
+use log::{error, info};
+use reqwest::header::HeaderMap;
+use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::fmt;
+use std::str::FromStr;
 
-use crate::constants::{ANTHROPIC_API_URL, ANTHROPIC_MESSAGES_API_URL};
-use crate::domain::{AnthropicAPICompletionsResponse, AnthropicAPIMessagesResponse};
-use crate::llm_models::LLMModel;
+use crate::constants::{
+    ANTHROPIC_API_URL, ANTHROPIC_COUNT_TOKENS_API_URL, ANTHROPIC_MESSAGES_API_URL,
+    ANTHROPIC_MODELS_API_URL, ANTHROPIC_STREAMING,
+};
+use crate::domain::{
+    AllmsError, AnthropicAPICompletionsResponse, AnthropicAPICountTokensResponse,
+    AnthropicAPIMessagesContent, AnthropicAPIMessagesResponse, AnthropicAPIModelsResponse,
+    AnthropicAPIStreamEvent, ApiResponse, CompletionsOptions, CompletionsUsage, CredentialsError,
+    FinishReason, LLMProvider, MessageRole, ModelInfo, TokenCost, UnknownModelError,
+};
+use crate::http_client::HttpClient;
+use crate::llm_models::{LLMModel, TextStream};
+use crate::utils::{
+    check_credentials, get_chat_history, get_optional_headers, get_schema_string, get_text_stream,
+    log_request, parse_rfc3339_timestamp, redact_secrets,
+};
 
-#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq, Hash)]
 pub enum AnthropicModels {
     Claude3_5Sonnet,
+    Claude3_5SonnetV2,
+    Claude3_7Sonnet,
     Claude3Opus,
     Claude3Sonnet,
     Claude3Haiku,
     // Legacy
     Claude2,
     ClaudeInstant1_2,
+    // Custom models (e.g. released after the crate was updated). Messages API is used.
+    // Parsed from the `custom:<name>` representation. The limits default to those of the latest models if not set
+    Custom {
+        name: String,
+        max_tokens: Option<usize>,
+        context_window_tokens: Option<usize>,
+    },
 }
 
 #[async_trait(?Send)]
@@ -25,18 +51,34 @@ impl LLMModel for AnthropicModels {
     fn as_str(&self) -> &str {
         match self {
             AnthropicModels::Claude3_5Sonnet => "claude-3-5-sonnet-20240620",
+            AnthropicModels::Claude3_5SonnetV2 => "claude-3-5-sonnet-20241022",
+            AnthropicModels::Claude3_7Sonnet => "claude-3-7-sonnet-20250219",
             AnthropicModels::Claude3Opus => "claude-3-opus-20240229",
             AnthropicModels::Claude3Sonnet => "claude-3-sonnet-20240229",
             AnthropicModels::Claude3Haiku => "claude-3-haiku-20240307",
             // Legacy
             AnthropicModels::Claude2 => "claude-2.1",
             AnthropicModels::ClaudeInstant1_2 => "claude-instant-1.2",
+            AnthropicModels::Custom { name, .. } => name.as_str(),
+        }
+    }
+
+    //Custom models are prefixed so they can't be confused with typos in the names of known models
+    fn id(&self) -> String {
+        match self {
+            AnthropicModels::Custom { name, .. } => format!("custom:{}", name),
+            _ => self.as_str().to_string(),
         }
     }
 
     fn try_from_str(name: &str) -> Option<Self> {
+        if let Some(name) = name.strip_prefix("custom:") {
+            return Some(AnthropicModels::custom(name));
+        }
         match name.to_lowercase().as_str() {
             "claude-3-5-sonnet-20240620" => Some(AnthropicModels::Claude3_5Sonnet),
+            "claude-3-5-sonnet-20241022" => Some(AnthropicModels::Claude3_5SonnetV2),
+            "claude-3-7-sonnet-20250219" => Some(AnthropicModels::Claude3_7Sonnet),
             "claude-3-opus-20240229" => Some(AnthropicModels::Claude3Opus),
             "claude-3-sonnet-20240229" => Some(AnthropicModels::Claude3Sonnet),
             "claude-3-haiku-20240307" => Some(AnthropicModels::Claude3Haiku),
@@ -47,25 +89,94 @@ impl LLMModel for AnthropicModels {
         }
     }
 
+    fn get_provider(&self) -> LLMProvider {
+        LLMProvider::Anthropic
+    }
+
     fn default_max_tokens(&self) -> usize {
         // This is the max tokens allowed for response and not context as per documentation: https://docs.anthropic.com/claude/reference/input-and-output-sizes
         match self {
             AnthropicModels::Claude3_5Sonnet => 4_096, // 8192 output tokens is in beta and requires the header anthropic-beta: max-tokens-3-5-sonnet-2024-07-15. If the header is not specified, the limit is 4096 tokens. (Source: https://docs.anthropic.com/en/docs/about-claude/models)
+            AnthropicModels::Claude3_5SonnetV2 => 8_192,
+            AnthropicModels::Claude3_7Sonnet => 64_000,
             AnthropicModels::Claude3Opus => 4_096,
             AnthropicModels::Claude3Sonnet => 4_096,
             AnthropicModels::Claude3Haiku => 4_096,
             // Legacy
             AnthropicModels::Claude2 => 4_096,
             AnthropicModels::ClaudeInstant1_2 => 4_096,
+            AnthropicModels::Custom { max_tokens, .. } => max_tokens.unwrap_or(4_096),
+        }
+    }
+
+    fn context_window_tokens(&self) -> usize {
+        // Docs: https://docs.anthropic.com/en/docs/about-claude/models
+        match self {
+            AnthropicModels::Claude3_5Sonnet => 200_000,
+            AnthropicModels::Claude3_5SonnetV2 => 200_000,
+            AnthropicModels::Claude3_7Sonnet => 200_000,
+            AnthropicModels::Claude3Opus => 200_000,
+            AnthropicModels::Claude3Sonnet => 200_000,
+            AnthropicModels::Claude3Haiku => 200_000,
+            // Legacy
+            AnthropicModels::Claude2 => 200_000,
+            AnthropicModels::ClaudeInstant1_2 => 100_000,
+            AnthropicModels::Custom {
+                context_window_tokens,
+                ..
+            } => context_window_tokens.unwrap_or(200_000),
+        }
+    }
+
+    fn cost_per_1k_tokens(&self) -> Option<TokenCost> {
+        // Docs: https://www.anthropic.com/pricing#anthropic-api
+        match self {
+            AnthropicModels::Claude3_5Sonnet => Some(TokenCost {
+                input: 0.003,
+                output: 0.015,
+            }),
+            AnthropicModels::Claude3_5SonnetV2 => Some(TokenCost {
+                input: 0.003,
+                output: 0.015,
+            }),
+            AnthropicModels::Claude3_7Sonnet => Some(TokenCost {
+                input: 0.003,
+                output: 0.015,
+            }),
+            AnthropicModels::Claude3Opus => Some(TokenCost {
+                input: 0.015,
+                output: 0.075,
+            }),
+            AnthropicModels::Claude3Sonnet => Some(TokenCost {
+                input: 0.003,
+                output: 0.015,
+            }),
+            AnthropicModels::Claude3Haiku => Some(TokenCost {
+                input: 0.00025,
+                output: 0.00125,
+            }),
+            // Legacy
+            AnthropicModels::Claude2 => Some(TokenCost {
+                input: 0.008,
+                output: 0.024,
+            }),
+            AnthropicModels::ClaudeInstant1_2 => Some(TokenCost {
+                input: 0.0008,
+                output: 0.0024,
+            }),
+            AnthropicModels::Custom { .. } => None,
         }
     }
 
     fn get_endpoint(&self) -> String {
         match self {
             AnthropicModels::Claude3_5Sonnet
+            | AnthropicModels::Claude3_5SonnetV2
+            | AnthropicModels::Claude3_7Sonnet
             | AnthropicModels::Claude3Opus
             | AnthropicModels::Claude3Sonnet
-            | AnthropicModels::Claude3Haiku => ANTHROPIC_MESSAGES_API_URL.to_string(),
+            | AnthropicModels::Claude3Haiku
+            | AnthropicModels::Custom { .. } => ANTHROPIC_MESSAGES_API_URL.to_string(),
             // Legacy
             AnthropicModels::Claude2 | AnthropicModels::ClaudeInstant1_2 => {
                 ANTHROPIC_API_URL.to_string()
@@ -81,9 +192,20 @@ impl LLMModel for AnthropicModels {
         function_call: bool,
         max_tokens: &usize,
         temperature: &f32,
+        options: &CompletionsOptions,
     ) -> serde_json::Value {
-        let schema_string = serde_json::to_string(json_schema).unwrap_or_default();
-        let base_instructions = self.get_base_instructions(Some(function_call));
+        //The output schema is omitted for plain-text requests
+        let schema_string = get_schema_string(json_schema);
+        let base_instructions = self.get_body_base_instructions(function_call, options);
+        //Legacy models take previous turns of the conversation as part of the Human/Assistant transcript
+        let history: String = options
+            .get_history()
+            .iter()
+            .map(|message| match message.role {
+                MessageRole::User => format!("{}\n\nAssistant: ", message.content),
+                MessageRole::Assistant => format!("{}\n\nHuman: ", message.content),
+            })
+            .collect();
 
         let completions_body = json!({
             "model": self.as_str(),
@@ -92,33 +214,105 @@ impl LLMModel for AnthropicModels {
             "prompt": format!(
                 "\n\nHuman:
                 {base_instructions}\n\n
-                Output Json schema:\n
-                {schema_string}\n\n
-                {instructions}
+                {schema_prompt}{history}{instructions}
                 \n\nAssistant:",
+                schema_prompt = schema_string
+                    .as_ref()
+                    .map(|schema_string| format!("Output Json schema:\n\n{schema_string}\n\n"))
+                    .unwrap_or_default(),
             ),
         });
 
-        let message_body = json!({
+        //Messages API takes the system prompt as a top-level field rather than a `system` role message
+        //Docs: https://docs.anthropic.com/en/api/messages
+        //Base instructions and output schema are static across requests so they form the system prompt
+        let system_prompt = match &schema_string {
+            Some(schema_string) => format!(
+                "{base_instructions}\n\n
+                Output Json schema:\n
+                {schema_string}"
+            ),
+            None => base_instructions,
+        };
+        //To be cached the system prompt needs to be sent as a content block marked with `cache_control`
+        //Docs: https://docs.anthropic.com/en/docs/build-with-claude/prompt-caching
+        let system = if options.prompt_caching {
+            json!([{
+                "type": "text",
+                "text": system_prompt,
+                "cache_control": { "type": "ephemeral" },
+            }])
+        } else {
+            json!(system_prompt)
+        };
+
+        //PDF documents are sent as content blocks ahead of the instructions
+        //Docs: https://docs.anthropic.com/en/docs/build-with-claude/pdf-support
+        let content = if options.documents.is_empty() {
+            json!(instructions)
+        } else {
+            let mut content: Vec<Value> = options
+                .documents
+                .iter()
+                .map(|document| {
+                    json!({
+                        "type": "document",
+                        "source": {
+                            "type": "base64",
+                            "media_type": document.media_type,
+                            "data": document.data,
+                        },
+                    })
+                })
+                .collect();
+            content.push(json!({
+                "type": "text",
+                "text": instructions,
+            }));
+            json!(content)
+        };
+
+        let mut messages = get_chat_history(&options.get_history());
+        messages.push(json!({
+            "role": "user",
+            "content": content,
+        }));
+
+        let mut message_body = json!({
             "model": self.as_str(),
             "max_tokens": max_tokens,
             "temperature": temperature,
-            "messages": [{
-                "role": "user",
-                "content": format!(
-                    "{base_instructions}\n\n
-                    Output Json schema:\n
-                    {schema_string}\n\n
-                    {instructions}"
-                )
-            }],
+            "system": system,
+            "messages": messages,
         });
 
+        //Extended thinking is not compatible with modified temperature so the default is used
+        //Docs: https://docs.anthropic.com/en/docs/build-with-claude/extended-thinking
+        if let Some(budget_tokens) = options.thinking_budget_tokens {
+            if self.supports_thinking() {
+                message_body["thinking"] = json!({
+                    "type": "enabled",
+                    "budget_tokens": budget_tokens,
+                });
+                if let Some(body) = message_body.as_object_mut() {
+                    body.remove("temperature");
+                }
+            }
+        }
+
+        //Messages API responses can be streamed as server-sent events
+        if options.streaming.unwrap_or(*ANTHROPIC_STREAMING) {
+            message_body["stream"] = json!(true);
+        }
+
         match self {
             AnthropicModels::Claude3_5Sonnet
+            | AnthropicModels::Claude3_5SonnetV2
+            | AnthropicModels::Claude3_7Sonnet
             | AnthropicModels::Claude3Opus
             | AnthropicModels::Claude3Sonnet
-            | AnthropicModels::Claude3Haiku => message_body,
+            | AnthropicModels::Claude3Haiku
+            | AnthropicModels::Custom { .. } => message_body,
             // Legacy
             AnthropicModels::Claude2 | AnthropicModels::ClaudeInstant1_2 => completions_body,
         }
@@ -130,39 +324,149 @@ impl LLMModel for AnthropicModels {
      */
     async fn call_api(
         &self,
+        client: &HttpClient,
         api_key: &str,
         body: &serde_json::Value,
         debug: bool,
-    ) -> Result<String> {
+    ) -> Result<ApiResponse> {
         //Get the API url
         let model_url = self.get_endpoint();
 
-        //Make the API call
-        let client = Client::new();
+        //Streaming is requested in the body of Messages API requests
+        let streaming = !self.is_legacy() && body["stream"] == json!(true);
 
-        //Send request
-        let response = client
-            .post(model_url)
-            .header(header::CONTENT_TYPE, "application/json")
+        //Send request. Large bodies are compressed if configured as Anthropic accepts gzip-encoded requests
+        let request = client
+            .post_json(model_url, body)
             //Anthropic-specific way of passing API key
             .header("x-api-key", api_key)
             //Required as per documentation
-            .header("anthropic-version", "2023-06-01")
-            .json(&body)
-            .send()
-            .await?;
+            .headers(get_anthropic_headers(client));
+        if debug {
+            log_request("Anthropic", &request, body);
+        }
+        let response = client.send(request).await?;
 
         let response_status = response.status();
-        let response_text = response.text().await?;
+
+        //Errors are returned as a regular Json response even if streaming was requested
+        let response_text = if streaming && response_status.is_success() {
+            //The events are aggregated into a response equivalent to the non-streaming one
+            let mut stream = response.bytes_stream();
+            let mut buffer = AnthropicStreamBuffer::default();
+
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk?;
+
+                if debug {
+                    info!("[allms][Anthropic] Received response chunk: {:?}", chunk);
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(chunk_size_bytes = chunk.len(), "allms.call_api chunk");
+                }
+
+                buffer.push(&chunk)?;
+            }
+            buffer.finish()?
+        } else {
+            response.text().await?
+        };
 
         if debug {
             info!(
                 "[debug] Anthropic API response: [{}] {:#?}",
-                &response_status, &response_text
+                &response_status,
+                redact_secrets(&response_text)
             );
         }
 
-        Ok(response_text)
+        Ok(ApiResponse {
+            text: response_text,
+            status: Some(response_status.as_u16()),
+            rate_limit_status: None,
+        })
+    }
+
+    //Only the Messages API supports streaming
+    fn supports_streaming(&self) -> bool {
+        !self.is_legacy()
+    }
+
+    //Docs: https://docs.anthropic.com/en/api/messages-streaming
+    async fn stream_api(
+        &self,
+        client: &HttpClient,
+        api_key: &str,
+        body: &serde_json::Value,
+        debug: bool,
+    ) -> Result<TextStream> {
+        if !self.supports_streaming() {
+            return Err(anyhow!(
+                "[allms] Streaming is not supported by {}",
+                self.as_str()
+            ));
+        }
+
+        let mut body = body.clone();
+        body["stream"] = json!(true);
+
+        let response = client
+            .send(
+                client
+                    .post_json(self.get_endpoint(), &body)
+                    .header("x-api-key", api_key)
+                    .headers(get_anthropic_headers(client)),
+            )
+            .await?;
+
+        //Errors are returned as a regular Json response
+        let response_status = response.status();
+        if !response_status.is_success() {
+            let response_text = response.text().await?;
+            return Err(anyhow!(
+                "[allms][Anthropic][{}] Response body: {:#?}",
+                response_status,
+                redact_secrets(&response_text)
+            ));
+        }
+
+        let mut buffer = AnthropicStreamBuffer::default();
+        Ok(get_text_stream(response, move |chunk| {
+            if debug {
+                info!("[allms][Anthropic] Received response chunk: {:?}", chunk);
+            }
+            buffer.push(chunk)
+        }))
+    }
+
+    //Only the Messages API supports token counting
+    async fn count_body_tokens_api(
+        &self,
+        client: &HttpClient,
+        api_key: &str,
+        body: &Value,
+    ) -> Result<usize> {
+        if self.is_legacy() {
+            return Err(anyhow!(
+                "[allms][Anthropic] Token counting is not supported for {}",
+                self.as_str()
+            ));
+        }
+
+        let body = get_count_tokens_body(body.clone());
+
+        //Docs: https://docs.anthropic.com/en/api/messages-count-tokens
+        let response = client
+            .send(
+                client
+                    .post_json(&*ANTHROPIC_COUNT_TOKENS_API_URL, &body)
+                    .header("x-api-key", api_key)
+                    .headers(get_anthropic_headers(client)),
+            )
+            .await?;
+
+        let response_text = response.text().await?;
+
+        parse_count_tokens_response(&response_text)
     }
 
     //This method attempts to convert the provided API response text into the expected struct and extracts the data from the response
@@ -170,15 +474,20 @@ impl LLMModel for AnthropicModels {
         //Convert API response to struct representing expected response format
         match self {
             AnthropicModels::Claude3_5Sonnet
+            | AnthropicModels::Claude3_5SonnetV2
+            | AnthropicModels::Claude3_7Sonnet
             | AnthropicModels::Claude3Opus
             | AnthropicModels::Claude3Sonnet
-            | AnthropicModels::Claude3Haiku => {
+            | AnthropicModels::Claude3Haiku
+            | AnthropicModels::Custom { .. } => {
                 let messages_response: AnthropicAPIMessagesResponse =
                     serde_json::from_str(response_text)?;
 
+                //Thinking blocks are not part of the answer
                 let assistant_response = messages_response
                     .content
                     .iter()
+                    .filter(|item| item.content_type == "text")
                     .map(|item| &item.text)
                     .fold(String::new(), |mut acc, text| {
                         acc.push_str(text);
@@ -198,4 +507,880 @@ impl LLMModel for AnthropicModels {
             }
         }
     }
+
+    //This method extracts token usage, including prompt caching usage, from the Messages API response
+    fn get_usage(&self, response_text: &str) -> Option<CompletionsUsage> {
+        match self {
+            AnthropicModels::Claude3_5Sonnet
+            | AnthropicModels::Claude3_5SonnetV2
+            | AnthropicModels::Claude3_7Sonnet
+            | AnthropicModels::Claude3Opus
+            | AnthropicModels::Claude3Sonnet
+            | AnthropicModels::Claude3Haiku
+            | AnthropicModels::Custom { .. } => {
+                let messages_response: AnthropicAPIMessagesResponse =
+                    serde_json::from_str(response_text).ok()?;
+                let usage = messages_response.usage;
+                Some(CompletionsUsage {
+                    input_tokens: usage.input_tokens as usize,
+                    output_tokens: usage.output_tokens as usize,
+                    cache_creation_input_tokens: usage
+                        .cache_creation_input_tokens
+                        .map(|tokens| tokens as usize),
+                    cache_read_input_tokens: usage
+                        .cache_read_input_tokens
+                        .map(|tokens| tokens as usize),
+                })
+            }
+            // Legacy
+            AnthropicModels::Claude2 | AnthropicModels::ClaudeInstant1_2 => None,
+        }
+    }
+
+    //PDF support was introduced with Claude 3.5 Sonnet (October 2024). Custom models are assumed to be newer models supporting it
+    fn supports_pdf_documents(&self) -> bool {
+        matches!(
+            self,
+            AnthropicModels::Claude3_5SonnetV2
+                | AnthropicModels::Claude3_7Sonnet
+                | AnthropicModels::Custom { .. }
+        )
+    }
+
+    //Extended thinking was introduced with Claude 3.7 Sonnet. Custom models are assumed to be newer models supporting it
+    fn supports_thinking(&self) -> bool {
+        matches!(
+            self,
+            AnthropicModels::Claude3_7Sonnet | AnthropicModels::Custom { .. }
+        )
+    }
+
+    //The reasoning is returned in `thinking` content blocks preceding the `text` blocks of the answer
+    fn get_thinking(&self, response_text: &str) -> Option<String> {
+        let thinking: String = serde_json::from_str::<AnthropicAPIMessagesResponse>(response_text)
+            .ok()?
+            .content
+            .into_iter()
+            .filter_map(|item| item.thinking)
+            .collect();
+        (!thinking.is_empty()).then_some(thinking)
+    }
+
+    //The Messages API reports `stop_reason`, e.g. `end_turn` or `max_tokens`
+    fn get_finish_reason(&self, response_text: &str) -> Option<FinishReason> {
+        let stop_reason = match self.is_legacy() {
+            true => {
+                serde_json::from_str::<AnthropicAPICompletionsResponse>(response_text)
+                    .ok()?
+                    .stop_reason
+            }
+            false => {
+                serde_json::from_str::<AnthropicAPIMessagesResponse>(response_text)
+                    .ok()?
+                    .stop_reason?
+            }
+        };
+        Some(FinishReason::from_provider(&stop_reason))
+    }
+}
+
+impl AnthropicModels {
+    ///
+    /// This method returns a custom model with the provided name, e.g. released after the crate was updated.
+    /// The limits default to those of the latest models and can be overridden with the `max_tokens` and `context_window_tokens` fields.
+    ///
+    pub fn custom(name: &str) -> Self {
+        AnthropicModels::Custom {
+            name: name.to_string(),
+            max_tokens: None,
+            context_window_tokens: None,
+        }
+    }
+
+    //Legacy models use the Text Completions API instead of the Messages API
+    fn is_legacy(&self) -> bool {
+        matches!(
+            self,
+            AnthropicModels::Claude2 | AnthropicModels::ClaudeInstant1_2
+        )
+    }
+
+    ///
+    /// This method returns the number of input tokens of a request with the instructions as counted by the Anthropic Token Counting API.
+    /// Unlike the client-side estimate it uses the model's tokenizer. The instructions are counted as given, without the input data and response instructions added by `Completions`.
+    /// Use `Completions::count_tokens_api` to count the prompt sent by `get_answer`, which matches the billed input tokens.
+    /// Only Messages API models are supported.
+    ///
+    pub async fn count_tokens_api(
+        &self,
+        api_key: &str,
+        instructions: &str,
+        json_schema: &Value,
+        options: &CompletionsOptions,
+    ) -> Result<usize> {
+        let body = self.get_body(
+            instructions,
+            json_schema,
+            self.function_call_default(),
+            &self.default_max_tokens(),
+            &0.0,
+            options,
+        );
+        self.count_body_tokens_api(&HttpClient::default(), api_key, &body)
+            .await
+    }
+}
+
+//The Token Counting API takes the same body as the Messages API without the sampling parameters
+fn get_count_tokens_body(mut body: Value) -> Value {
+    if let Some(body) = body.as_object_mut() {
+        body.retain(|key, _| ["model", "system", "messages", "thinking"].contains(&key.as_str()));
+    }
+    body
+}
+
+//Incremental parser of the streamed Messages API response
+//Server-sent events are processed line by line, and the text deltas are combined into a single content block
+#[derive(Debug, Default)]
+struct AnthropicStreamBuffer {
+    buffer: Vec<u8>,
+    message: Option<AnthropicAPIMessagesResponse>,
+    text: String,
+    thinking: String,
+    stopped: bool,
+}
+
+impl AnthropicStreamBuffer {
+    const SSE_DATA_PREFIX: &'static str = "data:";
+
+    //Adds a chunk to the buffer, processes the events completed by it and returns their text deltas
+    fn push(&mut self, chunk: &[u8]) -> Result<Vec<String>> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut deltas = Vec::new();
+        while let Some(position) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=position).collect();
+            deltas.extend(self.process_line(&String::from_utf8_lossy(&line))?);
+        }
+        Ok(deltas)
+    }
+
+    //Only `data` lines are needed as the event type is repeated in the data
+    fn process_line(&mut self, line: &str) -> Result<Option<String>> {
+        let data = match line.trim().strip_prefix(Self::SSE_DATA_PREFIX) {
+            Some(data) => data.trim(),
+            None => return Ok(None),
+        };
+
+        match serde_json::from_str::<AnthropicAPIStreamEvent>(data)? {
+            AnthropicAPIStreamEvent::MessageStart { message } => self.message = Some(message),
+            AnthropicAPIStreamEvent::ContentBlockDelta { delta } => {
+                //Reasoning is collected for the aggregated response but not streamed as part of the answer
+                if let Some(thinking) = delta.thinking {
+                    self.thinking.push_str(&thinking);
+                }
+                if let Some(text) = delta.text {
+                    self.text.push_str(&text);
+                    return Ok(Some(text));
+                }
+            }
+            AnthropicAPIStreamEvent::MessageDelta { delta, usage } => {
+                if let Some(message) = self.message.as_mut() {
+                    message.stop_reason = delta.stop_reason;
+                    message.stop_sequence = delta.stop_sequence;
+                    if let Some(usage) = usage {
+                        message.usage.output_tokens = usage.output_tokens;
+                    }
+                }
+            }
+            AnthropicAPIStreamEvent::MessageStop => self.stopped = true,
+            AnthropicAPIStreamEvent::Error { error } => {
+                return Err(anyhow!("[allms][Anthropic] Streaming error: {}", error))
+            }
+            AnthropicAPIStreamEvent::Other => {}
+        }
+        Ok(None)
+    }
+
+    //Returns the aggregated response serialized in the format of the non-streaming Messages API
+    fn finish(mut self) -> Result<String> {
+        //The last event does not need to be followed by a new line
+        let remainder = String::from_utf8_lossy(&std::mem::take(&mut self.buffer)).to_string();
+        self.process_line(&remainder)?;
+
+        match (self.message, self.stopped) {
+            (Some(mut message), true) => {
+                message.content = vec![AnthropicAPIMessagesContent {
+                    content_type: "text".to_string(),
+                    text: self.text,
+                    thinking: None,
+                }];
+                if !self.thinking.is_empty() {
+                    message.content.insert(
+                        0,
+                        AnthropicAPIMessagesContent {
+                            content_type: "thinking".to_string(),
+                            text: String::new(),
+                            thinking: Some(self.thinking),
+                        },
+                    );
+                }
+                Ok(serde_json::to_string(&message)?)
+            }
+            _ => Err(anyhow!(
+                "[allms][Anthropic] Incomplete streaming response: {}",
+                self.text
+            )),
+        }
+    }
+}
+
+impl fmt::Display for AnthropicModels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.id())
+    }
+}
+
+impl FromStr for AnthropicModels {
+    type Err = UnknownModelError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        AnthropicModels::try_from_str(name).ok_or_else(|| UnknownModelError::new(name))
+    }
+}
+
+///
+/// This function lists models available to the provided API key using the Anthropic Models API
+///
+pub async fn list_models(api_key: &str) -> Result<Vec<ModelInfo>> {
+    list_models_from(&ANTHROPIC_MODELS_API_URL, api_key).await
+}
+
+//The Models API returns the models in pages. Following pages are requested with the ID of the last model as `after_id`
+async fn list_models_from(url: &str, api_key: &str) -> Result<Vec<ModelInfo>> {
+    let client = Client::new();
+    let mut models = Vec::new();
+    let mut after_id: Option<String> = None;
+
+    loop {
+        let mut request = client
+            .get(url)
+            .query(&[("limit", "1000")])
+            .header("x-api-key", api_key)
+            .headers(get_anthropic_headers(&HttpClient::default()));
+        if let Some(after_id) = &after_id {
+            request = request.query(&[("after_id", after_id)]);
+        }
+        let response = request.send().await?;
+
+        let response_text = response.text().await?;
+        let (page_models, last_id) = parse_models_response(&response_text)?;
+        models.extend(page_models);
+
+        match last_id {
+            Some(last_id) => after_id = Some(last_id),
+            None => return Ok(models),
+        }
+    }
+}
+
+///
+/// This function validates the API key by listing models, which does not consume any tokens
+///
+pub async fn validate_credentials(api_key: &str) -> Result<(), CredentialsError> {
+    let request = Client::new()
+        .get(&*ANTHROPIC_MODELS_API_URL)
+        .header("x-api-key", api_key)
+        .headers(get_anthropic_headers(&HttpClient::default()));
+    check_credentials(request, "Anthropic").await
+}
+
+//Headers selecting the API version and beta features set for the client, which default to `ANTHROPIC_API_VERSION` and `ANTHROPIC_BETA`
+fn get_anthropic_headers(client: &HttpClient) -> HeaderMap {
+    get_version_headers(&client.anthropic_version, client.anthropic_beta.as_deref())
+}
+
+fn get_version_headers(version: &str, beta: Option<&str>) -> HeaderMap {
+    get_optional_headers(&[
+        ("anthropic-version", Some(version)),
+        ("anthropic-beta", beta),
+    ])
+}
+
+fn parse_count_tokens_response(response_text: &str) -> Result<usize> {
+    let count_response: AnthropicAPICountTokensResponse = serde_json::from_str(response_text)
+        .map_err(|error| {
+            let error = AllmsError {
+                crate_name: "allms".to_string(),
+                module: "llm_models::anthropic".to_string(),
+                error_message: format!(
+                    "Token Counting API response serialization error: {}",
+                    error
+                ),
+                error_detail: response_text.to_string(),
+            };
+            error!("{:?}", error);
+            anyhow!("{:?}", error)
+        })?;
+
+    Ok(count_response.input_tokens)
+}
+
+//Returns the models of the page and the ID of the last one if more models are available
+fn parse_models_response(response_text: &str) -> Result<(Vec<ModelInfo>, Option<String>)> {
+    let models_response: AnthropicAPIModelsResponse =
+        serde_json::from_str(response_text).map_err(|error| {
+            let error = AllmsError {
+                crate_name: "allms".to_string(),
+                module: "llm_models::anthropic".to_string(),
+                error_message: format!("Models API response serialization error: {}", error),
+                error_detail: response_text.to_string(),
+            };
+            error!("{:?}", error);
+            anyhow!("{:?}", error)
+        })?;
+
+    let models = models_response
+        .data
+        .into_iter()
+        .map(|model| ModelInfo {
+            created: parse_rfc3339_timestamp(&model.created_at).unwrap_or_default(),
+            //The API does not report the context window so it's only provided for models known to the crate
+            context_window: AnthropicModels::try_from_str(&model.id)
+                .map(|known_model| known_model.context_window_tokens()),
+            id: model.id,
+            owned_by: "anthropic".to_string(),
+        })
+        .collect();
+    let last_id = models_response.last_id.filter(|_| models_response.has_more);
+
+    Ok((models, last_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::matchers::{header, method, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::{
+        get_count_tokens_body, get_version_headers, parse_count_tokens_response,
+        AnthropicStreamBuffer,
+    };
+    use crate::domain::{CompletionsOptions, CompletionsUsage, Document, FinishReason, Message};
+    use crate::llm_models::anthropic::{list_models_from, parse_models_response};
+    use crate::llm_models::{AnthropicModels, LLMModel};
+
+    #[test]
+    fn test_pdf_document_content_blocks() {
+        let model = AnthropicModels::Claude3_5SonnetV2;
+        let schema = json!({"type": "object"});
+        let options = CompletionsOptions {
+            documents: vec![Document::pdf(b"%PDF-1.4")],
+            ..Default::default()
+        };
+
+        let body = model.get_body(
+            "Summarize the contract",
+            &schema,
+            false,
+            &1000,
+            &0.0,
+            &options,
+        );
+        assert_eq!(
+            body["messages"][0]["content"],
+            json!([
+                {
+                    "type": "document",
+                    "source": {
+                        "type": "base64",
+                        "media_type": "application/pdf",
+                        "data": "JVBERi0xLjQ=",
+                    },
+                },
+                {"type": "text", "text": "Summarize the contract"},
+            ])
+        );
+
+        // Without documents the instructions are sent as a plain string
+        let body = model.get_body(
+            "Summarize the contract",
+            &schema,
+            false,
+            &1000,
+            &0.0,
+            &CompletionsOptions::default(),
+        );
+        assert_eq!(body["messages"][0]["content"], "Summarize the contract");
+
+        assert!(model.supports_pdf_documents());
+        assert!(!AnthropicModels::Claude3_5Sonnet.supports_pdf_documents());
+    }
+
+    #[test]
+    fn test_version_headers() {
+        let request = reqwest::Client::new()
+            .post("https://api.anthropic.com/v1/messages")
+            .headers(get_version_headers(
+                "2024-10-22",
+                Some("pdfs-2024-09-25,token-counting-2024-11-01"),
+            ))
+            .build()
+            .unwrap();
+        assert_eq!(request.headers()["anthropic-version"], "2024-10-22");
+        assert_eq!(
+            request.headers()["anthropic-beta"],
+            "pdfs-2024-09-25,token-counting-2024-11-01"
+        );
+
+        // Beta header is only sent if set
+        let headers = get_version_headers("2023-06-01", None);
+        assert_eq!(headers["anthropic-version"], "2023-06-01");
+        assert!(headers.get("anthropic-beta").is_none());
+    }
+
+    #[test]
+    fn test_extended_thinking() {
+        let model = AnthropicModels::Claude3_7Sonnet;
+        let schema = json!({"type": "object"});
+        let options = CompletionsOptions {
+            thinking_budget_tokens: Some(2048),
+            ..Default::default()
+        };
+
+        let body = model.get_body("instructions", &schema, false, &4000, &0.2, &options);
+        assert_eq!(
+            body["thinking"],
+            json!({"type": "enabled", "budget_tokens": 2048})
+        );
+        assert!(body.get("temperature").is_none());
+
+        // Models without extended thinking ignore the budget
+        let body = AnthropicModels::Claude3_5SonnetV2.get_body(
+            "instructions",
+            &schema,
+            false,
+            &4000,
+            &0.2,
+            &options,
+        );
+        assert!(body.get("thinking").is_none());
+        assert!(!AnthropicModels::Claude3_5SonnetV2.supports_thinking());
+
+        let response = json!({
+            "id": "msg_01",
+            "type": "message",
+            "role": "assistant",
+            "content": [
+                {"type": "thinking", "thinking": "The user wants Json.", "signature": "EqQBCgIYAhIM"},
+                {"type": "text", "text": "{\"answer\": 42}"}
+            ],
+            "model": "claude-3-7-sonnet-20250219",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 10, "output_tokens": 30}
+        })
+        .to_string();
+        assert_eq!(
+            model.get_data(&response, false).unwrap(),
+            "{\"answer\": 42}"
+        );
+        assert_eq!(
+            model.get_thinking(&response),
+            Some("The user wants Json.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_prompt_caching_marks_system_prompt() {
+        let model = AnthropicModels::Claude3_5Sonnet;
+        let schema = json!({"type": "object"});
+        let options = CompletionsOptions {
+            prompt_caching: true,
+            ..Default::default()
+        };
+
+        let body = model.get_body("instructions", &schema, false, &1000, &0.0, &options);
+        let system = &body["system"];
+
+        assert_eq!(system[0]["type"], "text");
+        assert_eq!(system[0]["cache_control"], json!({ "type": "ephemeral" }));
+        assert_eq!(body["messages"][0]["content"], "instructions");
+    }
+
+    #[test]
+    fn test_streaming_option() {
+        let schema = json!({"type": "object"});
+        let streaming = CompletionsOptions {
+            streaming: Some(true),
+            ..Default::default()
+        };
+        let not_streaming = CompletionsOptions {
+            streaming: Some(false),
+            ..Default::default()
+        };
+
+        let model = AnthropicModels::Claude3_5Sonnet;
+        let body = model.get_body("instructions", &schema, false, &1000, &0.0, &streaming);
+        assert_eq!(body["stream"], true);
+        let body = model.get_body("instructions", &schema, false, &1000, &0.0, &not_streaming);
+        assert!(body.get("stream").is_none());
+
+        //The legacy Text Completions API is never streamed
+        let model = AnthropicModels::Claude2;
+        let body = model.get_body("instructions", &schema, false, &1000, &0.0, &streaming);
+        assert!(body.get("stream").is_none());
+    }
+
+    #[test]
+    fn test_system_prompt_is_top_level_field() {
+        let model = AnthropicModels::Claude3_5Sonnet;
+        let schema = json!({"type": "object"});
+        let options = CompletionsOptions {
+            custom_base_instructions: Some("You are a helpful assistant.".to_string()),
+            ..Default::default()
+        };
+
+        let body = model.get_body("instructions", &schema, false, &1000, &0.0, &options);
+
+        //Without prompt caching the system prompt is a plain string with no cache marker
+        let system = body["system"].as_str().unwrap();
+        assert!(system.starts_with("You are a helpful assistant."));
+        assert!(system.contains(r#"{"type":"object"}"#));
+
+        let messages = body["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 1);
+        assert!(messages.iter().all(|message| message["role"] != "system"));
+        assert_eq!(messages[0]["role"], "user");
+        assert_eq!(messages[0]["content"], "instructions");
+    }
+
+    #[test]
+    fn test_get_usage_parses_cache_fields() {
+        let model = AnthropicModels::Claude3_5Sonnet;
+        let response = json!({
+            "id": "msg_123",
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "text", "text": "{}"}],
+            "model": "claude-3-5-sonnet-20240620",
+            "stop_reason": "end_turn",
+            "stop_sequence": null,
+            "usage": {
+                "input_tokens": 10,
+                "output_tokens": 20,
+                "cache_creation_input_tokens": 1500,
+                "cache_read_input_tokens": 0
+            }
+        });
+
+        let usage = model.get_usage(&response.to_string());
+
+        assert_eq!(
+            usage,
+            Some(CompletionsUsage {
+                input_tokens: 10,
+                output_tokens: 20,
+                cache_creation_input_tokens: Some(1500),
+                cache_read_input_tokens: Some(0),
+            })
+        );
+    }
+
+    #[test]
+    fn test_finish_reason() {
+        let response = |stop_reason: &str| {
+            json!({
+                "id": "msg_123",
+                "type": "message",
+                "role": "assistant",
+                "content": [{"type": "text", "text": "{\"answer\": "}],
+                "model": "claude-3-5-sonnet-20240620",
+                "stop_reason": stop_reason,
+                "stop_sequence": null,
+                "usage": {"input_tokens": 10, "output_tokens": 20}
+            })
+            .to_string()
+        };
+        let model = AnthropicModels::Claude3_5Sonnet;
+
+        assert_eq!(
+            model.get_finish_reason(&response("end_turn")),
+            Some(FinishReason::Stop)
+        );
+        assert_eq!(
+            model.get_finish_reason(&response("max_tokens")),
+            Some(FinishReason::Length)
+        );
+        assert_eq!(
+            model.get_finish_reason(&response("tool_use")),
+            Some(FinishReason::ToolCalls)
+        );
+
+        let legacy_response = json!({
+            "id": "compl_123",
+            "type": "completion",
+            "completion": "{}",
+            "stop_reason": "stop_sequence",
+            "model": "claude-2.1"
+        });
+        assert_eq!(
+            AnthropicModels::Claude2.get_finish_reason(&legacy_response.to_string()),
+            Some(FinishReason::Stop)
+        );
+    }
+
+    #[test]
+    fn test_custom_model_in_body() {
+        let model = AnthropicModels::try_from_str("custom:claude-opus-4-20250514").unwrap();
+        assert_eq!(model, AnthropicModels::custom("claude-opus-4-20250514"));
+        // Unknown names are only accepted as custom models if prefixed
+        assert_eq!(AnthropicModels::try_from_str("claude-3-opus"), None);
+
+        let schema = json!({"type": "object"});
+        let body = model.get_body(
+            "instructions",
+            &schema,
+            false,
+            &1000,
+            &0.0,
+            &CompletionsOptions::default(),
+        );
+
+        assert_eq!(body["model"], "claude-opus-4-20250514");
+        assert_eq!(body["max_tokens"], 1000);
+        assert!(body["messages"].is_array());
+    }
+
+    #[test]
+    fn test_from_str_round_trip() {
+        let models = vec![
+            AnthropicModels::Claude3_5Sonnet,
+            AnthropicModels::Claude3_5SonnetV2,
+            AnthropicModels::Claude3_7Sonnet,
+            AnthropicModels::Claude3Opus,
+            AnthropicModels::Claude3Sonnet,
+            AnthropicModels::Claude3Haiku,
+            AnthropicModels::Claude2,
+            AnthropicModels::ClaudeInstant1_2,
+            AnthropicModels::custom("claude-opus-4-20250514"),
+        ];
+
+        for model in models {
+            assert_eq!(model.to_string().parse::<AnthropicModels>(), Ok(model));
+        }
+    }
+
+    #[test]
+    fn test_custom_model_limits() {
+        let model = AnthropicModels::custom("claude-opus-4-20250514");
+        assert_eq!(model.default_max_tokens(), 4_096);
+        assert_eq!(model.context_window_tokens(), 200_000);
+
+        let model = AnthropicModels::Custom {
+            name: "claude-opus-4-20250514".to_string(),
+            max_tokens: Some(32_000),
+            context_window_tokens: Some(1_000_000),
+        };
+        assert_eq!(model.default_max_tokens(), 32_000);
+        assert_eq!(model.context_window_tokens(), 1_000_000);
+    }
+
+    #[test]
+    fn test_context_window_is_separate_from_output_limit() {
+        let model = AnthropicModels::Claude3Opus;
+
+        assert_eq!(model.default_max_tokens(), 4_096);
+        assert_eq!(model.context_window_tokens(), 200_000);
+        // Default 100M TPM / (200k context * 50%)
+        assert_eq!(model.get_max_requests(), 1_000);
+    }
+
+    #[test]
+    fn test_count_tokens() {
+        assert_eq!(
+            parse_count_tokens_response(r#"{"input_tokens": 2095}"#).unwrap(),
+            2095
+        );
+        let error = parse_count_tokens_response(
+            r#"{"type": "error", "error": {"type": "authentication_error", "message": "invalid x-api-key"}}"#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("invalid x-api-key"));
+
+        // Sampling parameters are not accepted by the Token Counting API
+        let options = CompletionsOptions {
+            thinking_budget_tokens: Some(2048),
+            ..Default::default()
+        };
+        let body = get_count_tokens_body(AnthropicModels::Claude3_7Sonnet.get_body(
+            "Hello",
+            &serde_json::Value::Null,
+            false,
+            &4096,
+            &0.5,
+            &options,
+        ));
+        let mut keys: Vec<&String> = body.as_object().unwrap().keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["messages", "model", "system", "thinking"]);
+        assert_eq!(body["messages"][0]["content"], "Hello");
+    }
+
+    #[test]
+    fn test_parse_models_response() {
+        let response = json!({
+            "data": [{
+                "type": "model",
+                "id": "claude-opus-4-20250514",
+                "display_name": "Claude Opus 4",
+                "created_at": "2025-05-14T00:00:00Z"
+            }, {
+                "type": "model",
+                "id": "claude-3-opus-20240229",
+                "display_name": "Claude 3 Opus",
+                "created_at": "2024-02-29T00:00:00Z"
+            }],
+            "has_more": false,
+            "first_id": "claude-opus-4-20250514",
+            "last_id": "claude-opus-4-20250514"
+        });
+
+        let (models, last_id) = parse_models_response(&response.to_string()).unwrap();
+
+        assert_eq!(models.len(), 2);
+        assert_eq!(last_id, None);
+        assert_eq!(models[0].id, "claude-opus-4-20250514");
+        assert_eq!(models[0].created, 1_747_180_800);
+        assert_eq!(models[0].owned_by, "anthropic");
+        // Context window is only known for models supported by the crate
+        assert_eq!(models[0].context_window, None);
+        assert_eq!(models[1].context_window, Some(200_000));
+    }
+
+    #[tokio::test]
+    async fn test_list_models_pages() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(query_param("after_id", "claude-opus-4-20250514"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [{
+                    "type": "model",
+                    "id": "claude-3-opus-20240229",
+                    "created_at": "2024-02-29T00:00:00Z"
+                }],
+                "has_more": false,
+                "last_id": "claude-3-opus-20240229"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(header("x-api-key", "api-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": [{
+                    "type": "model",
+                    "id": "claude-opus-4-20250514",
+                    "created_at": "2025-05-14T00:00:00Z"
+                }],
+                "has_more": true,
+                "last_id": "claude-opus-4-20250514"
+            })))
+            .mount(&server)
+            .await;
+
+        let models = list_models_from(&server.uri(), "api-key").await.unwrap();
+        let ids: Vec<&str> = models.iter().map(|model| model.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec!["claude-opus-4-20250514", "claude-3-opus-20240229"]
+        );
+    }
+
+    #[test]
+    fn test_history_messages() {
+        let options = CompletionsOptions {
+            history: vec![
+                Message::user("Hi"),
+                Message::assistant("{\"answer\": \"Hello\"}"),
+            ],
+            ..Default::default()
+        };
+
+        let body = AnthropicModels::Claude3_5Sonnet.get_body(
+            "Bye",
+            &json!({}),
+            false,
+            &10,
+            &1.0,
+            &options,
+        );
+        assert!(body["system"].is_string());
+        assert_eq!(
+            body["messages"],
+            json!([
+                {"role": "user", "content": "Hi"},
+                {"role": "assistant", "content": "{\"answer\": \"Hello\"}"},
+                {"role": "user", "content": "Bye"},
+            ])
+        );
+    }
+
+    #[test]
+    fn test_stream_buffer() {
+        let events = [
+            r#"{"type": "message_start", "message": {"id": "msg_1", "type": "message", "role": "assistant", "content": [], "model": "claude-3-5-sonnet-latest", "stop_reason": null, "stop_sequence": null, "usage": {"input_tokens": 25, "output_tokens": 1}}}"#,
+            r#"{"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}"#,
+            r#"{"type": "ping"}"#,
+            r#"{"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "{\"answer\": "}}"#,
+            r#"{"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "\"Hello ☀\"}"}}"#,
+            r#"{"type": "content_block_stop", "index": 0}"#,
+            r#"{"type": "message_delta", "delta": {"stop_reason": "end_turn", "stop_sequence": null}, "usage": {"output_tokens": 15}}"#,
+            r#"{"type": "message_stop"}"#,
+        ];
+        let stream: String = events
+            .iter()
+            .map(|data| {
+                let event_type = serde_json::from_str::<serde_json::Value>(data).unwrap()["type"]
+                    .as_str()
+                    .unwrap()
+                    .to_string();
+                format!("event: {event_type}\ndata: {data}\n\n")
+            })
+            .collect();
+
+        // Chunks can split events and multi-byte characters
+        let mut buffer = AnthropicStreamBuffer::default();
+        let mut deltas = String::new();
+        for chunk in stream.as_bytes().chunks(7) {
+            deltas.extend(buffer.push(chunk).unwrap());
+        }
+        let response = buffer.finish().unwrap();
+        // The text deltas are returned as they are completed
+        assert_eq!(deltas, r#"{"answer": "Hello ☀"}"#);
+
+        let model = AnthropicModels::Claude3_5Sonnet;
+        assert_eq!(
+            model.get_data(&response, false).unwrap(),
+            r#"{"answer": "Hello ☀"}"#
+        );
+        assert_eq!(
+            model.get_usage(&response),
+            Some(CompletionsUsage {
+                input_tokens: 25,
+                output_tokens: 15,
+                cache_creation_input_tokens: None,
+                cache_read_input_tokens: None,
+            })
+        );
+
+        // A stream ending before `message_stop` is incomplete
+        let mut buffer = AnthropicStreamBuffer::default();
+        buffer.push(&stream.as_bytes()[..stream.len() / 2]).unwrap();
+        assert!(buffer.finish().is_err());
+
+        let mut buffer = AnthropicStreamBuffer::default();
+        let error = "event: error\ndata: {\"type\": \"error\", \"error\": {\"type\": \"overloaded_error\", \"message\": \"Overloaded\"}}\n\n";
+        assert!(buffer.push(error.as_bytes()).is_err());
+    }
 }
@@ -8,20 +8,30 @@ use aws_sdk_bedrockruntime::{
 use log::info;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
 
 use crate::constants::{AWS_BEDROCK_API_URL, AWS_REGION};
-use crate::domain::RateLimit;
+use crate::domain::{
+    ApiResponse, CompletionsOptions, LLMProvider, Message as ChatMessage, MessageRole, ModelInfo,
+    OutputStrictness, RateLimit, UnknownModelError,
+};
+use crate::http_client::HttpClient;
 use crate::llm_models::LLMModel;
+use crate::utils::get_schema_string;
 
 #[derive(Serialize, Deserialize)]
 struct AwsBedrockRequestBody {
+    base_instructions: String,
     instructions: String,
     json_schema: Value,
     max_tokens: i32,
     temperature: f32,
+    #[serde(default)]
+    history: Vec<ChatMessage>,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq, Hash)]
 // AWS Bedrock docs: https://docs.aws.amazon.com/bedrock/latest/userguide/models-supported.html
 pub enum AwsBedrockModels {
     NovaPro,
@@ -48,6 +58,10 @@ impl LLMModel for AwsBedrockModels {
         }
     }
 
+    fn get_provider(&self) -> LLMProvider {
+        LLMProvider::AwsBedrock
+    }
+
     fn default_max_tokens(&self) -> usize {
         match self {
             AwsBedrockModels::NovaPro => 5_120,
@@ -56,6 +70,15 @@ impl LLMModel for AwsBedrockModels {
         }
     }
 
+    fn context_window_tokens(&self) -> usize {
+        // Docs: https://docs.aws.amazon.com/nova/latest/userguide/what-is-nova.html
+        match self {
+            AwsBedrockModels::NovaPro => 300_000,
+            AwsBedrockModels::NovaLite => 300_000,
+            AwsBedrockModels::NovaMicro => 128_000,
+        }
+    }
+
     fn get_endpoint(&self) -> String {
         format!("{}/model/{}/converse", &*AWS_BEDROCK_API_URL, self.as_str())
     }
@@ -65,15 +88,18 @@ impl LLMModel for AwsBedrockModels {
         &self,
         instructions: &str,
         json_schema: &Value,
-        _function_call: bool,
+        function_call: bool,
         max_tokens: &usize,
         temperature: &f32,
+        options: &CompletionsOptions,
     ) -> serde_json::Value {
         let body = AwsBedrockRequestBody {
+            base_instructions: self.get_body_base_instructions(function_call, options),
             instructions: instructions.to_string(),
             json_schema: json_schema.clone(),
             max_tokens: *max_tokens as i32,
             temperature: *temperature,
+            history: options.get_history(),
         };
 
         // Return the body serialized as a JSON value
@@ -83,12 +109,14 @@ impl LLMModel for AwsBedrockModels {
     /// This function leverages AWS Bedrock SDK to perform any query as per the provided body.
     async fn call_api(
         &self,
+        // Requests are sent by the AWS Bedrock SDK with its own HTTP client
+        _client: &HttpClient,
         // AWS Bedrock SDK utilizes `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY` environment variables for request authentication
         // Docs: https://docs.aws.amazon.com/sdk-for-rust/latest/dg/credproviders.html
         _api_key: &str,
         body: &serde_json::Value,
         debug: bool,
-    ) -> Result<String> {
+    ) -> Result<ApiResponse> {
         let sdk_config = aws_config::defaults(BehaviorVersion::latest())
             .region(&**AWS_REGION)
             .load()
@@ -98,30 +126,57 @@ impl LLMModel for AwsBedrockModels {
         // Get request info from body
         let request_body_opt: Option<AwsBedrockRequestBody> =
             serde_json::from_value(body.clone()).ok();
-        let (instructions_opt, json_schema_opt, max_tokens_opt, temperature_opt) = request_body_opt
-            .map_or_else(
-                || (None, None, None, None),
-                |request_body| {
-                    (
-                        Some(request_body.instructions),
-                        Some(request_body.json_schema),
-                        Some(request_body.max_tokens),
-                        Some(request_body.temperature),
-                    )
-                },
-            );
+        let (
+            base_instructions_opt,
+            instructions_opt,
+            json_schema_opt,
+            max_tokens_opt,
+            temperature_opt,
+            history,
+        ) = request_body_opt.map_or_else(
+            || (None, None, None, None, None, Vec::new()),
+            |request_body| {
+                (
+                    Some(request_body.base_instructions),
+                    Some(request_body.instructions),
+                    Some(request_body.json_schema),
+                    Some(request_body.max_tokens),
+                    Some(request_body.temperature),
+                    request_body.history,
+                )
+            },
+        );
 
         // Get base instructions
-        let base_instructions = self.get_base_instructions(None);
+        let base_instructions = base_instructions_opt
+            .unwrap_or_else(|| self.get_base_instructions(None, OutputStrictness::default()));
 
         let converse_builder = client
             .converse()
             .model_id(self.as_str())
             .system(SystemContentBlock::Text(base_instructions));
 
+        // Add previous turns of the conversation ahead of the current instructions
+        let converse_builder =
+            history
+                .into_iter()
+                .try_fold(converse_builder, |builder, message| {
+                    let role = match message.role {
+                        MessageRole::User => ConversationRole::User,
+                        MessageRole::Assistant => ConversationRole::Assistant,
+                    };
+                    Message::builder()
+                        .role(role)
+                        .content(ContentBlock::Text(message.content))
+                        .build()
+                        .map(|message| builder.messages(message))
+                        .map_err(|_| anyhow!("failed to build message"))
+                })?;
+
         // Add user instructions including the expected output schema if specifed
         let instructions = instructions_opt.unwrap_or_default();
         let user_instructions = json_schema_opt
+            .and_then(|schema| get_schema_string(&schema))
             .map(|schema| {
                 format!(
                     "Output Json schema:\n
@@ -149,7 +204,10 @@ impl LLMModel for AwsBedrockModels {
             converse_builder
         };
 
-        // Send request
+        // Send request. Credentials are resolved by the SDK so only the body is logged
+        if debug {
+            info!("[debug] AWS Bedrock API request: {:#}", body);
+        }
         let converse_response = converse_builder.send().await?;
 
         if debug {
@@ -171,7 +229,10 @@ impl LLMModel for AwsBedrockModels {
             .as_text()
             .map_err(|_| anyhow!("content is not text"))?
             .to_string();
-        Ok(self.sanitize_json_response(&text))
+        Ok(ApiResponse {
+            text: self.sanitize_json_response(&text),
+            ..Default::default()
+        })
     }
 
     /// AWS Bedrock implementation leverages AWS Bedrock SDK, therefore data extraction is implemented directly in `call_api` method and this method only passes the data on
@@ -186,11 +247,94 @@ impl LLMModel for AwsBedrockModels {
             AwsBedrockModels::NovaPro => RateLimit {
                 tpm: 400_000,
                 rpm: 100,
+                rpd: None,
             },
             AwsBedrockModels::NovaLite | AwsBedrockModels::NovaMicro => RateLimit {
                 tpm: 2_000_000,
                 rpm: 1_000,
+                rpd: None,
             },
         }
     }
 }
+
+impl fmt::Display for AwsBedrockModels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for AwsBedrockModels {
+    type Err = UnknownModelError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        AwsBedrockModels::try_from_str(name).ok_or_else(|| UnknownModelError::new(name))
+    }
+}
+
+///
+/// This function returns the models currently supported by the crate. Access to the models needs to be enabled in the AWS Bedrock console.
+///
+pub async fn list_models(_api_key: &str) -> Result<Vec<ModelInfo>> {
+    Ok([
+        AwsBedrockModels::NovaPro,
+        AwsBedrockModels::NovaLite,
+        AwsBedrockModels::NovaMicro,
+    ]
+    .iter()
+    .map(|model| ModelInfo {
+        id: model.as_str().to_string(),
+        created: 0,
+        owned_by: "amazon".to_string(),
+        context_window: Some(model.context_window_tokens()),
+    })
+    .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::domain::{CompletionsOptions, Message, UnknownModelError};
+    use crate::llm_models::{AwsBedrockModels, LLMModel};
+
+    #[test]
+    fn test_from_str_round_trip() {
+        let models = vec![
+            AwsBedrockModels::NovaPro,
+            AwsBedrockModels::NovaLite,
+            AwsBedrockModels::NovaMicro,
+        ];
+
+        for model in models {
+            assert_eq!(model.to_string().parse::<AwsBedrockModels>(), Ok(model));
+        }
+
+        assert_eq!(
+            "unknown".parse::<AwsBedrockModels>(),
+            Err(UnknownModelError::new("unknown"))
+        );
+    }
+
+    #[test]
+    fn test_history_in_body() {
+        let options = CompletionsOptions {
+            history: vec![
+                Message::user("Hi"),
+                Message::assistant("{\"answer\": \"Hello\"}"),
+            ],
+            ..Default::default()
+        };
+
+        let body =
+            AwsBedrockModels::NovaLite.get_body("Bye", &json!({}), false, &10, &0.5, &options);
+        assert_eq!(
+            body["history"],
+            json!([
+                {"role": "user", "content": "Hi"},
+                {"role": "assistant", "content": "{\"answer\": \"Hello\"}"},
+            ])
+        );
+        assert_eq!(body["instructions"], "Bye");
+    }
+}
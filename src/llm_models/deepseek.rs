@@ -4,13 +4,22 @@ use log::info;
 use reqwest::{header, Client};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::fmt;
+use std::str::FromStr;
 
-use crate::constants::DEEPSEEK_API_URL;
-use crate::domain::{DeepSeekAPICompletionsResponse, RateLimit};
+use crate::constants::{DEEPSEEK_API_URL, DEEPSEEK_MODELS_API_URL};
+use crate::domain::{
+    ApiResponse, CompletionsOptions, CredentialsError, DeepSeekAPICompletionsResponse,
+    FinishReason, LLMProvider, ModelInfo, RateLimit, UnknownModelError,
+};
+use crate::http_client::HttpClient;
 use crate::llm_models::LLMModel;
-use crate::utils::map_to_range_f32;
+use crate::utils::{
+    check_credentials, get_chat_history, get_schema_string, list_openai_compatible_models,
+    log_request, map_to_range_f32, redact_secrets,
+};
 
-#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq, Hash)]
 //DeepSeek docs: https://api-docs.deepseek.com/quick_start/pricing
 pub enum DeepSeekModels {
     DeepSeekChat,
@@ -34,6 +43,10 @@ impl LLMModel for DeepSeekModels {
         }
     }
 
+    fn get_provider(&self) -> LLMProvider {
+        LLMProvider::DeepSeek
+    }
+
     fn default_max_tokens(&self) -> usize {
         match self {
             DeepSeekModels::DeepSeekChat => 8_192,
@@ -41,6 +54,14 @@ impl LLMModel for DeepSeekModels {
         }
     }
 
+    fn context_window_tokens(&self) -> usize {
+        // Docs: https://api-docs.deepseek.com/quick_start/pricing
+        match self {
+            DeepSeekModels::DeepSeekChat => 64_000,
+            DeepSeekModels::DeepSeekReasoner => 64_000,
+        }
+    }
+
     fn get_endpoint(&self) -> String {
         DEEPSEEK_API_URL.to_string()
     }
@@ -53,30 +74,36 @@ impl LLMModel for DeepSeekModels {
         function_call: bool,
         max_tokens: &usize,
         temperature: &f32,
+        options: &CompletionsOptions,
     ) -> serde_json::Value {
         //Prepare the 'messages' part of the body
-        let base_instructions = self.get_base_instructions(Some(function_call));
+        let base_instructions = self.get_body_base_instructions(function_call, options);
         let system_message = json!({
             "role": "system",
             "content": base_instructions,
         });
-        let schema_string = serde_json::to_string(json_schema).unwrap_or_default();
-        let user_message = json!({
-            "role": "user",
-            "content": format!(
+        //The output schema is omitted for plain-text requests
+        let content = match get_schema_string(json_schema) {
+            Some(schema_string) => format!(
                 "Output Json schema:\n
                 {schema_string}\n\n
                 {instructions}"
             ),
+            None => instructions.to_string(),
+        };
+        let user_message = json!({
+            "role": "user",
+            "content": content,
         });
+        //Previous turns of the conversation go between the system message and the current instructions
+        let mut messages = vec![system_message];
+        messages.extend(get_chat_history(&options.get_history()));
+        messages.push(user_message);
         json!({
             "model": self.as_str(),
             "max_tokens": max_tokens,
             "temperature": temperature,
-            "messages": vec![
-                system_message,
-                user_message,
-            ],
+            "messages": messages,
         })
     }
     ///
@@ -86,24 +113,24 @@ impl LLMModel for DeepSeekModels {
     ///
     async fn call_api(
         &self,
+        client: &HttpClient,
         api_key: &str,
         body: &serde_json::Value,
         debug: bool,
-    ) -> Result<String> {
+    ) -> Result<ApiResponse> {
         //Get the API url
         let model_url = self.get_endpoint();
 
-        //Make the API call
-        let client = Client::new();
-
         //Send request
-        let response = client
+        let request = client
             .post(model_url)
             .header(header::CONTENT_TYPE, "application/json")
             .bearer_auth(api_key)
-            .json(&body)
-            .send()
-            .await?;
+            .json(&body);
+        if debug {
+            log_request("DeepSeek", &request, body);
+        }
+        let response = client.send(request).await?;
 
         let response_status = response.status();
         let response_text = response.text().await?;
@@ -111,11 +138,16 @@ impl LLMModel for DeepSeekModels {
         if debug {
             info!(
                 "[debug] DeepSeek API response: [{}] {:#?}",
-                &response_status, &response_text
+                &response_status,
+                redact_secrets(&response_text)
             );
         }
 
-        Ok(response_text)
+        Ok(ApiResponse {
+            text: response_text,
+            status: Some(response_status.as_u16()),
+            rate_limit_status: None,
+        })
     }
 
     ///
@@ -141,6 +173,14 @@ impl LLMModel for DeepSeekModels {
             .ok_or_else(|| anyhow!("Assistant role content not found"))
     }
 
+    fn get_finish_reason(&self, response_text: &str) -> Option<FinishReason> {
+        serde_json::from_str::<DeepSeekAPICompletionsResponse>(response_text)
+            .ok()?
+            .choices
+            .first()
+            .map(|choice| FinishReason::from_provider(&choice.finish_reason))
+    }
+
     // This function allows to check the rate limits for different models
     fn get_rate_limit(&self) -> RateLimit {
         // DeepSeek documentation: https://api-docs.deepseek.com/quick_start/rate_limit
@@ -148,9 +188,15 @@ impl LLMModel for DeepSeekModels {
         RateLimit {
             tpm: 100_000_000, // i.e. very large number
             rpm: 100_000_000,
+            rpd: None,
         }
     }
 
+    fn get_temperature_range(&self) -> (f32, f32) {
+        // Temperature range documentation: https://api-docs.deepseek.com/quick_start/parameter_settings
+        (0f32, 1.5f32)
+    }
+
     // Accepts a [0-100] percentage range and returns the target temperature based on model ranges
     fn get_normalized_temperature(&self, relative_temp: u32) -> f32 {
         // Temperature range documentation: https://api-docs.deepseek.com/quick_start/parameter_settings
@@ -159,3 +205,101 @@ impl LLMModel for DeepSeekModels {
         map_to_range_f32(min, max, relative_temp)
     }
 }
+
+impl fmt::Display for DeepSeekModels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for DeepSeekModels {
+    type Err = UnknownModelError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        DeepSeekModels::try_from_str(name).ok_or_else(|| UnknownModelError::new(name))
+    }
+}
+
+///
+/// This function lists models available to the provided API key using the DeepSeek Models API
+///
+pub async fn list_models(api_key: &str) -> Result<Vec<ModelInfo>> {
+    list_openai_compatible_models(&DEEPSEEK_MODELS_API_URL, api_key, "DeepSeek").await
+}
+
+///
+/// This function validates the API key by listing models, which does not consume any tokens
+///
+pub async fn validate_credentials(api_key: &str) -> Result<(), CredentialsError> {
+    let request = Client::new()
+        .get(&*DEEPSEEK_MODELS_API_URL)
+        .bearer_auth(api_key);
+    check_credentials(request, "DeepSeek").await
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::domain::{CompletionsOptions, Message, UnknownModelError};
+    use crate::llm_models::{DeepSeekModels, LLMModel};
+    use crate::utils::parse_models_response;
+
+    #[test]
+    fn test_from_str_round_trip() {
+        let models = vec![
+            DeepSeekModels::DeepSeekChat,
+            DeepSeekModels::DeepSeekReasoner,
+        ];
+
+        for model in models {
+            assert_eq!(model.to_string().parse::<DeepSeekModels>(), Ok(model));
+        }
+
+        assert_eq!(
+            "unknown".parse::<DeepSeekModels>(),
+            Err(UnknownModelError::new("unknown"))
+        );
+    }
+
+    #[test]
+    fn test_parse_models_response() {
+        let response = r#"{
+            "object": "list",
+            "data": [
+                {"id": "deepseek-chat", "object": "model", "owned_by": "deepseek"},
+                {"id": "deepseek-reasoner", "object": "model", "owned_by": "deepseek"}
+            ]
+        }"#;
+
+        let models = parse_models_response(response, "DeepSeek").unwrap();
+
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[1].id, "deepseek-reasoner");
+        // DeepSeek does not report the creation date
+        assert_eq!(models[1].created, 0);
+        assert_eq!(models[1].owned_by, "deepseek");
+    }
+
+    #[test]
+    fn test_history_messages() {
+        let options = CompletionsOptions {
+            history: vec![
+                Message::user("Hi"),
+                Message::assistant("{\"answer\": \"Hello\"}"),
+            ],
+            ..Default::default()
+        };
+
+        let body =
+            DeepSeekModels::DeepSeekChat.get_body("Bye", &json!({}), false, &10, &0.5, &options);
+        let roles: Vec<&str> = body["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|message| message["role"].as_str().unwrap())
+            .collect();
+        assert_eq!(roles, vec!["system", "user", "assistant", "user"]);
+        assert_eq!(body["messages"][2]["content"], "{\"answer\": \"Hello\"}");
+    }
+}
@@ -3,16 +3,30 @@
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use futures::stream::StreamExt;
-use log::info;
+use log::{error, info, warn};
 use reqwest::{header, Client};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::fmt;
+use std::str::FromStr;
 
-use crate::constants::{GOOGLE_GEMINI_API_URL, GOOGLE_VERTEX_API_URL};
-use crate::domain::{GoogleGeminiProApiResp, RateLimit};
-use crate::llm_models::LLMModel;
+use crate::constants::{
+    GOOGLE_GEMINI_API_URL, GOOGLE_GEMINI_MODELS_API_URL, GOOGLE_VERTEX_API_URL,
+    GOOGLE_VERTEX_STREAMING, OPENAI_FUNCTION_INSTRUCTIONS,
+};
+use crate::domain::{
+    AllmsError, ApiResponse, CompletionsOptions, CredentialsError, FinishReason,
+    GoogleGeminiModelsResponse, GoogleGeminiProApiResp, GoogleGeminiProPart, GroundingCitation,
+    LLMProvider, MessageRole, ModelInfo, OutputStrictness, RateLimit, UnknownModelError,
+};
+use crate::http_client::HttpClient;
+use crate::llm_models::llm_model::get_json_base_instructions;
+use crate::llm_models::{LLMModel, TextStream};
+use crate::utils::{
+    check_credentials, get_schema_string, get_text_stream, log_request, redact_secrets,
+};
 
-#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq, Hash)]
 // Google Docs: https://ai.google.dev/gemini-api/docs/models/gemini
 // Google Vertex Docs: https://cloud.google.com/vertex-ai/docs/generative-ai/model-reference/gemini
 pub enum GoogleModels {
@@ -87,6 +101,26 @@ impl LLMModel for GoogleModels {
         }
     }
 
+    //Vertex models share the API name with their AI Studio counterparts, so they are identified by their `-vertex` name
+    fn id(&self) -> String {
+        match self {
+            GoogleModels::Gemini1_5ProVertex => "gemini-1.5-pro-vertex",
+            GoogleModels::Gemini1_5FlashVertex => "gemini-1.5-flash-vertex",
+            GoogleModels::Gemini1_5Flash8BVertex => "gemini-1.5-flash-8b-vertex",
+            GoogleModels::Gemini2_0FlashVertex => "gemini-2.0-flash-vertex",
+            GoogleModels::Gemini2_0FlashLiteVertex => "gemini-2.0-flash-lite-vertex",
+            GoogleModels::Gemini2_0ProExpVertex => "gemini-2.0-pro-vertex",
+            GoogleModels::Gemini2_0FlashThinkingExpVertex => "gemini-2.0-flash-thinking-vertex",
+            // Legacy
+            #[allow(deprecated)]
+            GoogleModels::GeminiProVertex => "gemini-pro-vertex",
+            #[allow(deprecated)]
+            GoogleModels::Gemini1_0ProVertex => "gemini-1.0-pro-vertex",
+            _ => self.as_str(),
+        }
+        .to_string()
+    }
+
     fn try_from_str(name: &str) -> Option<Self> {
         match name.to_lowercase().as_str() {
             "gemini-1.5-pro" => Some(GoogleModels::Gemini1_5Pro),
@@ -95,15 +129,21 @@ impl LLMModel for GoogleModels {
             "gemini-1.5-flash-vertex" => Some(GoogleModels::Gemini1_5FlashVertex),
             "gemini-1.5-flash-8b" => Some(GoogleModels::Gemini1_5Flash8B),
             "gemini-1.5-flash-8b-vertex" => Some(GoogleModels::Gemini1_5Flash8BVertex),
-            "gemini-2.0-flash" => Some(GoogleModels::Gemini2_0Flash),
+            "gemini-2.0-flash" | "gemini-2.0-flash-001" => Some(GoogleModels::Gemini2_0Flash),
             "gemini-2.0-flash-vertex" => Some(GoogleModels::Gemini2_0FlashVertex),
-            "gemini-2.0-flash-lite" => Some(GoogleModels::Gemini2_0FlashLite),
+            "gemini-2.0-flash-lite" | "gemini-2.0-flash-lite-preview-02-05" => {
+                Some(GoogleModels::Gemini2_0FlashLite)
+            }
             "gemini-2.0-flash-lite-vertex" => Some(GoogleModels::Gemini2_0FlashLiteVertex),
             "gemini-2.0-pro" => Some(GoogleModels::Gemini2_0ProExp),
-            "gemini-2.0-pro-exp" => Some(GoogleModels::Gemini2_0ProExp),
+            "gemini-2.0-pro-exp" | "gemini-2.0-pro-exp-02-05" => {
+                Some(GoogleModels::Gemini2_0ProExp)
+            }
             "gemini-2.0-pro-vertex" => Some(GoogleModels::Gemini2_0ProExpVertex),
             "gemini-2.0-flash-thinking" => Some(GoogleModels::Gemini2_0FlashThinkingExp),
-            "gemini-2.0-flash-thinking-exp" => Some(GoogleModels::Gemini2_0FlashThinkingExp),
+            "gemini-2.0-flash-thinking-exp" | "gemini-2.0-flash-thinking-exp-01-21" => {
+                Some(GoogleModels::Gemini2_0FlashThinkingExp)
+            }
             "gemini-2.0-flash-thinking-vertex" => {
                 Some(GoogleModels::Gemini2_0FlashThinkingExpVertex)
             }
@@ -116,6 +156,10 @@ impl LLMModel for GoogleModels {
         }
     }
 
+    fn get_provider(&self) -> LLMProvider {
+        LLMProvider::Google
+    }
+
     fn default_max_tokens(&self) -> usize {
         // Docs: https://cloud.google.com/vertex-ai/docs/generative-ai/learn/models
         match self {
@@ -136,14 +180,8 @@ impl LLMModel for GoogleModels {
     }
 
     fn get_endpoint(&self) -> String {
-        //The URL requires GOOGLE_REGION and GOOGLE_PROJECT_ID env variables defined to work.
+        //The Vertex URL requires GOOGLE_REGION and GOOGLE_PROJECT_ID env variables defined to work, so it's only built for Vertex models.
         //If not set GOOGLE_REGION will default to 'us-central1' but GOOGLE_PROJECT_ID needs to be defined.
-        let vertex_url = format!(
-            "{}/{}:streamGenerateContent?alt=sse",
-            &*GOOGLE_VERTEX_API_URL,
-            self.as_str()
-        );
-
         match self {
             GoogleModels::Gemini1_5Pro
             | GoogleModels::Gemini1_5Flash
@@ -158,14 +196,57 @@ impl LLMModel for GoogleModels {
             | GoogleModels::Gemini2_0FlashVertex
             | GoogleModels::Gemini2_0FlashLiteVertex
             | GoogleModels::Gemini2_0ProExpVertex
-            | GoogleModels::Gemini2_0FlashThinkingExpVertex => vertex_url,
+            | GoogleModels::Gemini2_0FlashThinkingExpVertex => {
+                self.get_vertex_endpoint(*GOOGLE_VERTEX_STREAMING)
+            }
             // Legacy
             #[allow(deprecated)]
             GoogleModels::GeminiPro | GoogleModels::Gemini1_0Pro => {
                 GOOGLE_GEMINI_API_URL.to_string()
             }
             #[allow(deprecated)]
-            GoogleModels::GeminiProVertex | GoogleModels::Gemini1_0ProVertex => vertex_url,
+            GoogleModels::GeminiProVertex | GoogleModels::Gemini1_0ProVertex => {
+                self.get_vertex_endpoint(*GOOGLE_VERTEX_STREAMING)
+            }
+        }
+    }
+
+    fn get_base_instructions(
+        &self,
+        function_call: Option<bool>,
+        strictness: OutputStrictness,
+    ) -> String {
+        let function_call = function_call.unwrap_or_else(|| self.function_call_default());
+        match function_call {
+            true => OPENAI_FUNCTION_INSTRUCTIONS.to_string(),
+            false => get_json_base_instructions(strictness),
+        }
+    }
+
+    fn function_call_default(&self) -> bool {
+        //Docs: https://ai.google.dev/gemini-api/docs/function-calling
+        match self {
+            GoogleModels::Gemini1_5Pro
+            | GoogleModels::Gemini1_5Flash
+            | GoogleModels::Gemini1_5Flash8B
+            | GoogleModels::Gemini2_0Flash
+            | GoogleModels::Gemini2_0FlashLite
+            | GoogleModels::Gemini2_0ProExp
+            | GoogleModels::Gemini1_5ProVertex
+            | GoogleModels::Gemini1_5FlashVertex
+            | GoogleModels::Gemini1_5Flash8BVertex
+            | GoogleModels::Gemini2_0FlashVertex
+            | GoogleModels::Gemini2_0FlashLiteVertex
+            | GoogleModels::Gemini2_0ProExpVertex => true,
+            // Thinking models do not support function calling
+            GoogleModels::Gemini2_0FlashThinkingExp
+            | GoogleModels::Gemini2_0FlashThinkingExpVertex => false,
+            // Legacy
+            #[allow(deprecated)]
+            GoogleModels::GeminiPro
+            | GoogleModels::Gemini1_0Pro
+            | GoogleModels::GeminiProVertex
+            | GoogleModels::Gemini1_0ProVertex => false,
         }
     }
 
@@ -177,37 +258,172 @@ impl LLMModel for GoogleModels {
         function_call: bool,
         _max_tokens: &usize,
         temperature: &f32,
+        options: &CompletionsOptions,
     ) -> serde_json::Value {
         //Prepare the 'messages' part of the body
         let base_instructions_json = json!({
-            "text": self.get_base_instructions(Some(function_call))
+            "text": self.get_body_base_instructions(function_call, options)
         });
 
-        let schema_string = serde_json::to_string(json_schema).unwrap_or_default();
-        let output_instructions_json =
-            json!({ "text": format!("'Output Json schema': {schema_string}") });
+        //The output schema is omitted for plain-text requests
+        let output_instructions_json = get_schema_string(json_schema).map(
+            |schema_string| json!({ "text": format!("'Output Json schema': {schema_string}") }),
+        );
 
         let user_instructions_json = json!({
             "text": instructions,
         });
 
-        let contents = json!({
-            "role": "user",
-            "parts": vec![
-                base_instructions_json,
-                output_instructions_json,
-                user_instructions_json,
-            ],
-        });
-
         let generation_config = json!({
             "temperature": temperature,
         });
 
-        json!({
-            "contents": contents,
-            "generationConfig": generation_config,
-        })
+        //Previous turns of the conversation precede the current instructions. Gemini refers to the assistant as `model`
+        let history_messages = options.get_history();
+        let history = history_messages.iter().map(|message| {
+            let role = match message.role {
+                MessageRole::User => "user",
+                MessageRole::Assistant => "model",
+            };
+            json!({
+                "role": role,
+                "parts": [{ "text": message.content }],
+            })
+        });
+
+        let mut body = match self {
+            // Gemini 1.0 does not support system instructions so they are sent as part of the user message
+            #[allow(deprecated)]
+            GoogleModels::GeminiPro
+            | GoogleModels::Gemini1_0Pro
+            | GoogleModels::GeminiProVertex
+            | GoogleModels::Gemini1_0ProVertex => {
+                let mut parts = vec![base_instructions_json];
+                parts.extend(output_instructions_json);
+                parts.push(user_instructions_json);
+                let contents: Vec<Value> = history
+                    .chain(std::iter::once(json!({
+                        "role": "user",
+                        "parts": parts,
+                    })))
+                    .collect();
+
+                json!({
+                    "contents": contents,
+                    "generationConfig": generation_config,
+                })
+            }
+            // Gemini 1.5+ accepts system instructions as a separate top-level field
+            // Docs: https://ai.google.dev/gemini-api/docs/text-generation#system-instructions
+            GoogleModels::Gemini1_5Pro
+            | GoogleModels::Gemini1_5Flash
+            | GoogleModels::Gemini1_5Flash8B
+            | GoogleModels::Gemini2_0Flash
+            | GoogleModels::Gemini2_0FlashLite
+            | GoogleModels::Gemini2_0ProExp
+            | GoogleModels::Gemini2_0FlashThinkingExp
+            | GoogleModels::Gemini1_5ProVertex
+            | GoogleModels::Gemini1_5FlashVertex
+            | GoogleModels::Gemini1_5Flash8BVertex
+            | GoogleModels::Gemini2_0FlashVertex
+            | GoogleModels::Gemini2_0FlashLiteVertex
+            | GoogleModels::Gemini2_0ProExpVertex
+            | GoogleModels::Gemini2_0FlashThinkingExpVertex => {
+                //Files are referenced by their URI ahead of the instructions
+                //Docs: https://ai.google.dev/gemini-api/docs/document-processing#large-pdfs
+                let mut user_parts: Vec<Value> = options
+                    .files
+                    .iter()
+                    .map(|file| {
+                        json!({
+                            "fileData": {
+                                "mimeType": file.mime_type,
+                                "fileUri": file.file_uri,
+                            }
+                        })
+                    })
+                    .collect();
+                user_parts.push(user_instructions_json);
+
+                let contents: Vec<Value> = history
+                    .chain(std::iter::once(json!({
+                        "role": "user",
+                        "parts": user_parts,
+                    })))
+                    .collect();
+
+                //Grounding with Google Search is passed as an additional tool. Gemini 2.0 replaced `google_search_retrieval` with `google_search`
+                //Docs: https://ai.google.dev/gemini-api/docs/grounding
+                let grounding_tool = options.web_search().then(|| {
+                    if self.supports_google_search() {
+                        json!({ "google_search": {} })
+                    } else {
+                        json!({ "google_search_retrieval": {} })
+                    }
+                });
+
+                match function_call {
+                    //With function calling the output schema is passed as the parameters of the function which the model is forced to call
+                    //Docs: https://ai.google.dev/gemini-api/docs/function-calling
+                    true => {
+                        let system_instruction = json!({
+                            "parts": vec![base_instructions_json],
+                        });
+
+                        let function = json!({
+                            "name": "analyze_data",
+                            "description": "Use this function to compute the answer based on input data, instructions and your language model. Output should be a fully formed JSON object.",
+                            "parameters": get_function_parameters(json_schema),
+                        });
+
+                        let tool_config = json!({
+                            "functionCallingConfig": {
+                                "mode": "ANY",
+                                "allowedFunctionNames": ["analyze_data"],
+                            }
+                        });
+
+                        //Gemini 2.0 rejects search tools in the same request as function declarations, so grounding is left out
+                        if grounding_tool.is_some() {
+                            warn!("[allms][Google] Grounding is not supported with function calling and was left out of the request. Disable function calling to ground the answer.");
+                        }
+
+                        json!({
+                            "system_instruction": system_instruction,
+                            "contents": contents,
+                            "tools": [{
+                                "functionDeclarations": vec![function],
+                            }],
+                            "toolConfig": tool_config,
+                            "generationConfig": generation_config,
+                        })
+                    }
+                    false => {
+                        let mut parts = vec![base_instructions_json];
+                        parts.extend(output_instructions_json);
+                        let system_instruction = json!({
+                            "parts": parts,
+                        });
+
+                        let mut body = json!({
+                            "system_instruction": system_instruction,
+                            "contents": contents,
+                            "generationConfig": generation_config,
+                        });
+                        if let Some(grounding_tool) = grounding_tool {
+                            body["tools"] = json!([grounding_tool]);
+                        }
+                        body
+                    }
+                }
+            }
+        };
+
+        //Vertex requests select the streaming or non-streaming endpoint. The choice is removed from the body before it is sent
+        if let (true, Some(streaming)) = (self.is_vertex(), options.streaming) {
+            body["stream"] = json!(streaming);
+        }
+        body
     }
     /*
      * This function leverages Mistral API to perform any query as per the provided body.
@@ -216,10 +432,11 @@ impl LLMModel for GoogleModels {
      */
     async fn call_api(
         &self,
+        client: &HttpClient,
         api_key: &str,
         body: &serde_json::Value,
         debug: bool,
-    ) -> Result<String> {
+    ) -> Result<ApiResponse> {
         match &self {
             GoogleModels::Gemini1_5Pro
             | GoogleModels::Gemini1_5Flash
@@ -228,7 +445,7 @@ impl LLMModel for GoogleModels {
             | GoogleModels::Gemini2_0FlashLite
             | GoogleModels::Gemini2_0ProExp
             | GoogleModels::Gemini2_0FlashThinkingExp => {
-                self.call_api_studio(api_key, body, debug).await
+                self.call_api_studio(client, api_key, body, debug).await
             }
             GoogleModels::Gemini1_5ProVertex
             | GoogleModels::Gemini1_5FlashVertex
@@ -237,21 +454,79 @@ impl LLMModel for GoogleModels {
             | GoogleModels::Gemini2_0FlashLiteVertex
             | GoogleModels::Gemini2_0ProExpVertex
             | GoogleModels::Gemini2_0FlashThinkingExpVertex => {
-                self.call_api_vertex(api_key, body, debug).await
+                self.call_api_vertex(client, api_key, body, debug).await
             }
             // Legacy
             #[allow(deprecated)]
             GoogleModels::GeminiPro | GoogleModels::Gemini1_0Pro => {
-                self.call_api_studio(api_key, body, debug).await
+                self.call_api_studio(client, api_key, body, debug).await
             }
             #[allow(deprecated)]
             GoogleModels::GeminiProVertex | GoogleModels::Gemini1_0ProVertex => {
-                self.call_api_vertex(api_key, body, debug).await
+                self.call_api_vertex(client, api_key, body, debug).await
             }
         }
     }
 
-    fn get_data(&self, response_text: &str, _function_call: bool) -> Result<String> {
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+
+    //Both AI Studio and Vertex AI stream the response objects as server-sent events
+    //Docs: https://ai.google.dev/api/generate-content#method:-models.streamgeneratecontent
+    async fn stream_api(
+        &self,
+        client: &HttpClient,
+        api_key: &str,
+        body: &serde_json::Value,
+        debug: bool,
+    ) -> Result<TextStream> {
+        let (_, body) = get_vertex_streaming(body);
+        let request = match self.is_vertex() {
+            true => client
+                .post(self.get_vertex_endpoint(true))
+                .bearer_auth(api_key),
+            false => client.post(format!(
+                "{}?alt=sse&key={}",
+                GOOGLE_GEMINI_API_URL.replace(":generateContent", ":streamGenerateContent"),
+                api_key
+            )),
+        };
+
+        let response = client
+            .send(
+                request
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .json(&body),
+            )
+            .await
+            //The URL can include the API key so it's removed from the error
+            .map_err(|error| error.without_url())?;
+
+        let response_status = response.status();
+        if !response_status.is_success() {
+            let response_text = response.text().await?;
+            return Err(anyhow!(
+                "[allms][Google][{}] Response body: {:#?}",
+                response_status,
+                redact_secrets(&response_text)
+            ));
+        }
+
+        let mut buffer = GeminiStreamBuffer::default();
+        Ok(get_text_stream(response, move |chunk| {
+            if debug {
+                info!("[allms][Google] Received response chunk: {:?}", chunk);
+            }
+            Ok(buffer
+                .push(chunk)?
+                .into_iter()
+                .map(|response| GeminiResponse::from(response).text)
+                .collect())
+        }))
+    }
+
+    fn get_data(&self, response_text: &str, function_call: bool) -> Result<String> {
         match self {
             GoogleModels::Gemini1_5Pro
             | GoogleModels::Gemini1_5Flash
@@ -259,8 +534,10 @@ impl LLMModel for GoogleModels {
             | GoogleModels::Gemini2_0Flash
             | GoogleModels::Gemini2_0FlashLite
             | GoogleModels::Gemini2_0ProExp
-            | GoogleModels::Gemini2_0FlashThinkingExp => self.get_data_studio(response_text),
-            //Because for Vertex we are using streaming the extraction of data/text is handled in call_api method. Here we only pass the input forward
+            | GoogleModels::Gemini2_0FlashThinkingExp => {
+                self.get_data_studio(response_text, function_call)
+            }
+            //Because for Vertex we are using streaming the extraction of data/text (or function call arguments) is handled in call_api method. Here we only pass the input forward
             GoogleModels::Gemini1_5ProVertex
             | GoogleModels::Gemini1_5FlashVertex
             | GoogleModels::Gemini1_5Flash8BVertex
@@ -271,7 +548,7 @@ impl LLMModel for GoogleModels {
             // Legacy
             #[allow(deprecated)]
             GoogleModels::GeminiPro | GoogleModels::Gemini1_0Pro => {
-                self.get_data_studio(response_text)
+                self.get_data_studio(response_text, function_call)
             }
             #[allow(deprecated)]
             GoogleModels::GeminiProVertex | GoogleModels::Gemini1_0ProVertex => {
@@ -280,6 +557,50 @@ impl LLMModel for GoogleModels {
         }
     }
 
+    //For Vertex only the data is passed on by call_api, so the finish reason is not available
+    fn get_finish_reason(&self, response_text: &str) -> Option<FinishReason> {
+        if self.is_vertex() {
+            return None;
+        }
+        serde_json::from_str::<GoogleGeminiProApiResp>(response_text)
+            .map(GeminiResponse::from)
+            .ok()?
+            .finish_reason
+    }
+
+    //Grounding metadata is only available in the responses of Google AI Studio models. For Vertex only the data is passed on by call_api
+    fn supports_grounding(&self) -> bool {
+        matches!(
+            self,
+            GoogleModels::Gemini1_5Pro
+                | GoogleModels::Gemini1_5Flash
+                | GoogleModels::Gemini1_5Flash8B
+                | GoogleModels::Gemini2_0Flash
+                | GoogleModels::Gemini2_0ProExp
+        )
+    }
+
+    //Gemini 1.0 models only accept text. Vertex AI models reference files in Cloud Storage (`gs://` URIs)
+    fn supports_file_data(&self) -> bool {
+        !matches!(
+            self,
+            GoogleModels::GeminiPro
+                | GoogleModels::Gemini1_0Pro
+                | GoogleModels::GeminiProVertex
+                | GoogleModels::Gemini1_0ProVertex
+        )
+    }
+
+    fn get_grounding_citations(&self, response_text: &str) -> Option<Vec<GroundingCitation>> {
+        if !self.supports_grounding() {
+            return None;
+        }
+        serde_json::from_str::<GoogleGeminiProApiResp>(response_text)
+            .map(GeminiResponse::from)
+            .ok()?
+            .grounding_citations
+    }
+
     //This function allows to check the rate limits for different models
     fn get_rate_limit(&self) -> RateLimit {
         //Docs: https://ai.google.dev/gemini-api/docs/models/gemini
@@ -287,56 +608,118 @@ impl LLMModel for GoogleModels {
             GoogleModels::Gemini1_5Flash | GoogleModels::Gemini1_5FlashVertex => RateLimit {
                 tpm: 4_000_000,
                 rpm: 2_000,
+                rpd: None,
             },
             GoogleModels::Gemini1_5Flash8B | GoogleModels::Gemini1_5Flash8BVertex => RateLimit {
                 tpm: 4_000_000,
                 rpm: 4_000,
+                rpd: None,
             },
             GoogleModels::Gemini1_5Pro | GoogleModels::Gemini1_5ProVertex => RateLimit {
                 tpm: 4_000_000,
                 rpm: 1_000,
+                rpd: None,
             },
             GoogleModels::Gemini2_0Flash | GoogleModels::Gemini2_0FlashVertex => RateLimit {
                 tpm: 4_000_000,
                 rpm: 2_000,
+                rpd: None,
             },
             GoogleModels::Gemini2_0FlashLite | GoogleModels::Gemini2_0FlashLiteVertex => {
                 RateLimit {
                     tpm: 4_000_000,
                     rpm: 10,
+                    rpd: None,
                 }
             }
-            // TODO: No rate limits published for experimental models
-            _ => RateLimit {
+            // Experimental models are only available in the free tier which enforces daily quotas
+            GoogleModels::Gemini2_0ProExp | GoogleModels::Gemini2_0ProExpVertex => RateLimit {
+                tpm: 120_000,
+                rpm: 360,
+                rpd: Some(50),
+            },
+            GoogleModels::Gemini2_0FlashThinkingExp
+            | GoogleModels::Gemini2_0FlashThinkingExpVertex => RateLimit {
+                tpm: 120_000,
+                rpm: 360,
+                rpd: Some(1_500),
+            },
+            // Legacy. The free tier of Gemini Pro is limited to 1,500 requests per day
+            #[allow(deprecated)]
+            GoogleModels::GeminiPro
+            | GoogleModels::GeminiProVertex
+            | GoogleModels::Gemini1_0Pro
+            | GoogleModels::Gemini1_0ProVertex => RateLimit {
                 tpm: 120_000,
                 rpm: 360,
+                rpd: Some(1_500),
             },
         }
     }
 }
 
 impl GoogleModels {
+    // Vertex AI models are called with an access token instead of an API key
+    #[allow(deprecated)]
+    fn is_vertex(&self) -> bool {
+        matches!(
+            self,
+            GoogleModels::Gemini1_5ProVertex
+                | GoogleModels::Gemini1_5FlashVertex
+                | GoogleModels::Gemini1_5Flash8BVertex
+                | GoogleModels::Gemini2_0FlashVertex
+                | GoogleModels::Gemini2_0FlashLiteVertex
+                | GoogleModels::Gemini2_0ProExpVertex
+                | GoogleModels::Gemini2_0FlashThinkingExpVertex
+                | GoogleModels::GeminiProVertex
+                | GoogleModels::Gemini1_0ProVertex
+        )
+    }
+
+    // Gemini 2.0 and later models are grounded with the `google_search` tool, earlier ones with `google_search_retrieval`
+    // Only the older models are listed so that models added later use the current tool
+    #[allow(deprecated)]
+    fn supports_google_search(&self) -> bool {
+        !matches!(
+            self,
+            GoogleModels::Gemini1_5Pro
+                | GoogleModels::Gemini1_5Flash
+                | GoogleModels::Gemini1_5Flash8B
+                | GoogleModels::Gemini1_5ProVertex
+                | GoogleModels::Gemini1_5FlashVertex
+                | GoogleModels::Gemini1_5Flash8BVertex
+                | GoogleModels::GeminiPro
+                | GoogleModels::Gemini1_0Pro
+                | GoogleModels::GeminiProVertex
+                | GoogleModels::Gemini1_0ProVertex
+        )
+    }
+
     // Specialized function for calling AI Studio API
     async fn call_api_studio(
         &self,
+        client: &HttpClient,
         api_key: &str,
         body: &serde_json::Value,
         debug: bool,
-    ) -> Result<String> {
+    ) -> Result<ApiResponse> {
         //Get the API url
         let model_url = self.get_endpoint();
 
-        //Make the API call
-        let client = Client::new();
-
         //Send request
         let url_with_key = format!("{}?key={}", model_url, api_key);
-        let response = client
+        let request = client
             .post(url_with_key)
             .header(header::CONTENT_TYPE, "application/json")
-            .json(&body)
-            .send()
-            .await?;
+            .json(&body);
+        if debug {
+            log_request("Google AI Studio", &request, body);
+        }
+        let response = client
+            .send(request)
+            .await
+            //The URL includes the API key so it's removed from the error
+            .map_err(|error| error.without_url())?;
 
         let response_status = response.status();
         let response_text = response.text().await?;
@@ -344,106 +727,1185 @@ impl GoogleModels {
         if debug {
             info!(
                 "[allms][Google AI Studio] API response: [{}] {:#?}",
-                &response_status, &response_text
+                &response_status,
+                redact_secrets(&response_text)
             );
         }
 
-        Ok(response_text)
+        Ok(ApiResponse {
+            text: response_text,
+            status: Some(response_status.as_u16()),
+            rate_limit_status: None,
+        })
     }
 
     // Specialized function for calling Vertex API
     async fn call_api_vertex(
         &self,
+        client: &HttpClient,
         api_key: &str,
         body: &serde_json::Value,
         debug: bool,
-    ) -> Result<String> {
-        //Get the API url
-        let model_url = self.get_endpoint();
+    ) -> Result<ApiResponse> {
+        let (streaming, body) = get_vertex_streaming(body);
 
-        //Make the API call
-        let client = Client::new();
+        //Get the API url
+        let model_url = self.get_vertex_endpoint(streaming);
 
         //Send request
-        let response = client
+        let request = client
             .post(model_url)
             .header(header::CONTENT_TYPE, "application/json")
             .bearer_auth(api_key)
-            .json(&body)
-            .send()
-            .await?;
+            .json(&body);
+        if debug {
+            log_request("Google Vertex AI", &request, &body);
+        }
+        let response = client.send(request).await?;
+        let status = Some(response.status().as_u16());
+
+        //Non-streaming responses are a single JSON object
+        if !streaming && response.status().is_success() {
+            let response_text = response.text().await?;
+
+            if debug {
+                info!(
+                    "[allms][Google Vertex AI] API response: {:#?}",
+                    redact_secrets(&response_text)
+                );
+            }
 
-        //For Vertex we are streaming that data so we need to deserialize each chunk separately
-        // Check if the API uses streaming
+            return self
+                .get_data_vertex(&response_text)
+                .map(|text| ApiResponse {
+                    text,
+                    status,
+                    rate_limit_status: None,
+                });
+        }
+
+        //For Vertex we are streaming that data so we need to deserialize each response object separately
+        //A chunk can contain part of an object or multiple objects, so they are accumulated in a buffer until complete
         if response.status().is_success() {
             let mut stream = response.bytes_stream();
+            let mut buffer = GeminiStreamBuffer::default();
             let mut streamed_response = String::new();
 
             while let Some(chunk) = stream.next().await {
                 let chunk = chunk?;
 
-                // Convert the chunk (Bytes) to a String
-                let mut chunk_str = String::from_utf8(chunk.to_vec()).map_err(|e| anyhow!(e))?;
-
-                // The chunk response starts with "data: " that needs to be remove
-                if chunk_str.starts_with("data: ") {
-                    // Remove the first 6 characters ("data: ")
-                    chunk_str = chunk_str[6..].to_string();
-                }
-
-                //Convert response chunk to struct representing expected response format
-                let gemini_response: GoogleGeminiProApiResp = serde_json::from_str(&chunk_str)?;
-
-                //Extract the data part from the response
-                let part_text = gemini_response
-                    .candidates
-                    .iter()
-                    .filter(|candidate| candidate.content.role.as_deref() == Some("model"))
-                    .flat_map(|candidate| &candidate.content.parts)
-                    .map(|part| &part.text)
-                    .fold(String::new(), |mut acc, text| {
-                        acc.push_str(text);
-                        acc
-                    });
-
-                //Add the chunk response to output string
-                streamed_response.push_str(&part_text);
-
                 // Debug log each chunk if needed
                 if debug {
                     info!(
                         "[allms][Google Vertex AI] Received response chunk: {:?}",
                         chunk
                     );
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(chunk_size_bytes = chunk.len(), "allms.call_api chunk");
+                }
+
+                //Add the data of all complete response objects to output string
+                for gemini_response in buffer.push(&chunk)? {
+                    streamed_response.push_str(&GeminiResponse::from(gemini_response).text);
                 }
             }
-            Ok(self.sanitize_json_response(&streamed_response))
+            buffer.finish()?;
+
+            Ok(ApiResponse {
+                text: self.sanitize_json_response(&streamed_response),
+                status,
+                rate_limit_status: None,
+            })
         } else {
-            let response_status = response.status();
-            let response_txt = response.text().await?;
-            Err(anyhow!(
-                "[allms][Google][{}] Response body: {:#?}",
-                response_status,
-                response_txt
-            ))
+            Ok(ApiResponse {
+                text: response.text().await?,
+                status,
+                rate_limit_status: None,
+            })
         }
     }
 
+    // This function returns the Vertex endpoint using either the streaming or the single response method
+    // Docs: https://cloud.google.com/vertex-ai/generative-ai/docs/model-reference/inference
+    fn get_vertex_endpoint(&self, streaming: bool) -> String {
+        match streaming {
+            true => format!(
+                "{}/{}:streamGenerateContent?alt=sse",
+                &*GOOGLE_VERTEX_API_URL,
+                self.as_str()
+            ),
+            false => format!(
+                "{}/{}:generateContent",
+                &*GOOGLE_VERTEX_API_URL,
+                self.as_str()
+            ),
+        }
+    }
+
+    // Specialized function for parsing the non-streaming response of Vertex API
+    fn get_data_vertex(&self, response_text: &str) -> Result<String> {
+        let gemini_response: GoogleGeminiProApiResp = serde_json::from_str(response_text)?;
+        Ok(self.sanitize_json_response(&GeminiResponse::from(gemini_response).text))
+    }
+
     // Specialized function for parsing response of AI Studio API
-    fn get_data_studio(&self, response_text: &str) -> Result<String> {
+    fn get_data_studio(&self, response_text: &str, function_call: bool) -> Result<String> {
         //Convert response to struct representing expected response format
         let gemini_response: GoogleGeminiProApiResp = serde_json::from_str(response_text)?;
+        let gemini_response = GeminiResponse::from(gemini_response);
+
+        //For function calling the data is in the arguments of the function call, and for regular calls in the text
+        match gemini_response.function_call_args {
+            Some(function_call_args) if function_call => Ok(function_call_args),
+            _ => Ok(gemini_response.text),
+        }
+    }
+}
+
+//Data extracted from a Gemini response object, shared by the streaming and non-streaming paths
+#[derive(Debug, Default, PartialEq)]
+struct GeminiResponse {
+    //Data of all parts generated by the model
+    text: String,
+    //Arguments of the first function call generated by the model
+    function_call_args: Option<String>,
+    finish_reason: Option<FinishReason>,
+    grounding_citations: Option<Vec<GroundingCitation>>,
+}
 
-        //Extract the data part from the response
-        Ok(gemini_response
-            .candidates
+impl From<GoogleGeminiProApiResp> for GeminiResponse {
+    fn from(gemini_response: GoogleGeminiProApiResp) -> Self {
+        let mut response = GeminiResponse::default();
+
+        for (index, candidate) in gemini_response.candidates.into_iter().enumerate() {
+            //Finish reason and grounding metadata are reported for the first candidate
+            if index == 0 {
+                response.finish_reason = candidate
+                    .finish_reason
+                    .as_deref()
+                    .map(FinishReason::from_provider);
+                response.grounding_citations = candidate.grounding_metadata.map(|metadata| {
+                    metadata
+                        .grounding_chunks
+                        .into_iter()
+                        .filter_map(|chunk| chunk.web)
+                        .map(|web| GroundingCitation {
+                            uri: web.uri,
+                            title: web.title,
+                        })
+                        .collect()
+                });
+            }
+
+            if candidate.content.role.as_deref() != Some("model") {
+                continue;
+            }
+            for part in &candidate.content.parts {
+                if response.function_call_args.is_none() {
+                    response.function_call_args = part
+                        .function_call
+                        .as_ref()
+                        .map(|function_call| function_call.args.to_string());
+                }
+                response.text.push_str(&get_part_data(part));
+            }
+        }
+        response
+    }
+}
+
+//Incremental parser of the Gemini streaming response
+//Bytes are accumulated until complete JSON objects are available. Both the SSE format (`data: {...}`) and the JSON array format (`[{...},{...}]`) are supported.
+#[derive(Debug, Default)]
+struct GeminiStreamBuffer {
+    buffer: Vec<u8>,
+}
+
+impl GeminiStreamBuffer {
+    const SSE_PREFIX: &'static [u8] = b"data:";
+
+    //Adds a chunk to the buffer and returns the response objects completed by it
+    fn push(&mut self, chunk: &[u8]) -> Result<Vec<GoogleGeminiProApiResp>> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut responses = Vec::new();
+        loop {
+            self.skip_framing();
+            //Wait for more data if the buffer is empty or ends with an incomplete SSE prefix
+            if self.buffer.is_empty() || Self::SSE_PREFIX.starts_with(&self.buffer) {
+                break;
+            }
+
+            let mut values = serde_json::Deserializer::from_slice(&self.buffer)
+                .into_iter::<GoogleGeminiProApiResp>();
+            match values.next() {
+                Some(Ok(response)) => {
+                    let offset = values.byte_offset();
+                    self.buffer.drain(..offset);
+                    responses.push(response);
+                }
+                //The object is not complete yet
+                Some(Err(error)) if error.is_eof() => break,
+                Some(Err(error)) => return Err(anyhow!(error)),
+                None => break,
+            }
+        }
+        Ok(responses)
+    }
+
+    //Returns an error if the stream ended in the middle of a response object
+    fn finish(&mut self) -> Result<()> {
+        self.skip_framing();
+        if self.buffer.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "[allms][Google] Incomplete streaming response: {}",
+                String::from_utf8_lossy(&self.buffer)
+            ))
+        }
+    }
+
+    //Removes whitespace, SSE prefixes and JSON array delimiters preceding the next object
+    fn skip_framing(&mut self) {
+        loop {
+            if self.buffer.starts_with(Self::SSE_PREFIX) {
+                self.buffer.drain(..Self::SSE_PREFIX.len());
+                continue;
+            }
+            match self.buffer.first() {
+                Some(byte) if byte.is_ascii_whitespace() || matches!(byte, b'[' | b']' | b',') => {
+                    self.buffer.remove(0);
+                }
+                _ => break,
+            }
+        }
+    }
+}
+
+//This function returns whether the Vertex response is streamed, defaulting to `GOOGLE_VERTEX_STREAMING`, and the body without the choice
+fn get_vertex_streaming(body: &Value) -> (bool, Value) {
+    let mut body = body.clone();
+    let streaming = body
+        .as_object_mut()
+        .and_then(|body| body.remove("stream"))
+        .and_then(|streaming| streaming.as_bool())
+        .unwrap_or(*GOOGLE_VERTEX_STREAMING);
+    (streaming, body)
+}
+
+//This function returns the function call arguments of a response part if present, or its text otherwise
+fn get_part_data(part: &GoogleGeminiProPart) -> String {
+    match &part.function_call {
+        Some(function_call) => function_call.args.to_string(),
+        None => part.text.clone(),
+    }
+}
+
+//Gemini function declarations accept a subset of the OpenAPI schema. This function inlines `$ref` definitions,
+//replaces nullable type arrays (e.g. `["string", "null"]`) with the `nullable` flag and removes unsupported keywords
+fn get_function_parameters(json_schema: &Value) -> Value {
+    let definitions = json_schema.get("definitions").cloned().unwrap_or_default();
+    to_gemini_schema(json_schema, &definitions, &mut Vec::new())
+}
+
+//`expanding` holds the definitions being inlined. A recursive type can't be inlined, so a reference back to one
+//of them is replaced with the definition's type and description only
+fn to_gemini_schema<'a>(
+    schema: &Value,
+    definitions: &'a Value,
+    expanding: &mut Vec<&'a str>,
+) -> Value {
+    match schema {
+        Value::Object(object) => {
+            if let Some((name, definition)) = object
+                .get("$ref")
+                .and_then(Value::as_str)
+                .and_then(|reference| reference.strip_prefix("#/definitions/"))
+                .and_then(|name| definitions.as_object()?.get_key_value(name))
+            {
+                if expanding.contains(&name.as_str()) {
+                    let mut gemini_schema = serde_json::Map::new();
+                    for key in ["type", "description"] {
+                        if let Some(value) = definition.get(key) {
+                            gemini_schema.insert(key.to_string(), value.clone());
+                        }
+                    }
+                    return Value::Object(gemini_schema);
+                }
+                expanding.push(name);
+                let gemini_schema = to_gemini_schema(definition, definitions, expanding);
+                expanding.pop();
+                return gemini_schema;
+            }
+
+            let mut gemini_schema = serde_json::Map::new();
+            for (key, value) in object {
+                match key.as_str() {
+                    "definitions" | "$schema" | "title" | "additionalProperties" | "default" => {}
+                    "type" => match value {
+                        Value::Array(types) => {
+                            if let Some(schema_type) =
+                                types.iter().find(|t| t.as_str() != Some("null"))
+                            {
+                                gemini_schema.insert(key.clone(), schema_type.clone());
+                            }
+                            if types.iter().any(|t| t.as_str() == Some("null")) {
+                                gemini_schema.insert("nullable".to_string(), Value::Bool(true));
+                            }
+                        }
+                        _ => {
+                            gemini_schema.insert(key.clone(), value.clone());
+                        }
+                    },
+                    //Keys of 'properties' are field names, so only their schemas are converted
+                    "properties" => {
+                        let properties = value
+                            .as_object()
+                            .map(|properties| {
+                                properties
+                                    .iter()
+                                    .map(|(name, property)| {
+                                        (
+                                            name.clone(),
+                                            to_gemini_schema(property, definitions, expanding),
+                                        )
+                                    })
+                                    .collect()
+                            })
+                            .unwrap_or_default();
+                        gemini_schema.insert(key.clone(), Value::Object(properties));
+                    }
+                    _ => {
+                        gemini_schema
+                            .insert(key.clone(), to_gemini_schema(value, definitions, expanding));
+                    }
+                }
+            }
+            Value::Object(gemini_schema)
+        }
+        Value::Array(values) => Value::Array(
+            values
+                .iter()
+                .map(|value| to_gemini_schema(value, definitions, expanding))
+                .collect(),
+        ),
+        _ => schema.clone(),
+    }
+}
+
+impl fmt::Display for GoogleModels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.id())
+    }
+}
+
+impl FromStr for GoogleModels {
+    type Err = UnknownModelError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        GoogleModels::try_from_str(name).ok_or_else(|| UnknownModelError::new(name))
+    }
+}
+
+///
+/// This function lists models available to the provided AI Studio API key using the Gemini Models API
+/// Vertex AI does not expose a Models API listing Gemini models, so without an API key the Vertex models supported by the crate are returned.
+///
+pub async fn list_models(api_key: &str) -> Result<Vec<ModelInfo>> {
+    if api_key.is_empty() {
+        return Ok(list_vertex_models());
+    }
+    list_models_from(&GOOGLE_GEMINI_MODELS_API_URL, api_key).await
+}
+
+//The Models API returns the models in pages, each pointing to the next one with `nextPageToken`
+async fn list_models_from(url: &str, api_key: &str) -> Result<Vec<ModelInfo>> {
+    let client = Client::new();
+    let mut models = Vec::new();
+    let mut page_token: Option<String> = None;
+
+    loop {
+        let mut request = client
+            .get(url)
+            .query(&[("key", api_key), ("pageSize", "1000")]);
+        if let Some(page_token) = &page_token {
+            request = request.query(&[("pageToken", page_token)]);
+        }
+        let response = request
+            .send()
+            .await
+            //The URL includes the API key so it's removed from the error
+            .map_err(|error| error.without_url())?;
+
+        let response_text = response.text().await?;
+        let (page_models, next_page_token) = parse_models_response(&response_text)?;
+        models.extend(page_models);
+
+        match next_page_token {
+            Some(next_page_token) => page_token = Some(next_page_token),
+            None => return Ok(models),
+        }
+    }
+}
+
+fn list_vertex_models() -> Vec<ModelInfo> {
+    [
+        GoogleModels::Gemini1_5FlashVertex,
+        GoogleModels::Gemini1_5Flash8BVertex,
+        GoogleModels::Gemini1_5ProVertex,
+        GoogleModels::Gemini2_0FlashVertex,
+        GoogleModels::Gemini2_0FlashLiteVertex,
+        GoogleModels::Gemini2_0ProExpVertex,
+        GoogleModels::Gemini2_0FlashThinkingExpVertex,
+    ]
+    .iter()
+    .map(|model| ModelInfo {
+        id: model.id(),
+        created: 0,
+        owned_by: "google".to_string(),
+        context_window: Some(model.default_max_tokens()),
+    })
+    .collect()
+}
+
+///
+/// This function validates the API key of AI Studio by listing models, which does not consume any tokens
+///
+pub async fn validate_credentials(api_key: &str) -> Result<(), CredentialsError> {
+    let request = Client::new()
+        .get(&*GOOGLE_GEMINI_MODELS_API_URL)
+        .query(&[("key", api_key), ("pageSize", "1")]);
+    check_credentials(request, "Google").await
+}
+
+//Returns the models of the page and the token of the next page, if there is one
+fn parse_models_response(response_text: &str) -> Result<(Vec<ModelInfo>, Option<String>)> {
+    let models_response: GoogleGeminiModelsResponse =
+        serde_json::from_str(response_text).map_err(|error| {
+            let error = AllmsError {
+                crate_name: "allms".to_string(),
+                module: "llm_models::google".to_string(),
+                error_message: format!("Models API response serialization error: {}", error),
+                error_detail: response_text.to_string(),
+            };
+            error!("{:?}", error);
+            anyhow!("{:?}", error)
+        })?;
+
+    let models = models_response
+        .models
+        .into_iter()
+        //Only models supporting content generation can be used with Completions
+        .filter(|model| {
+            model
+                .supported_generation_methods
+                .iter()
+                .any(|method| method == "generateContent")
+        })
+        .map(|model| ModelInfo {
+            id: model
+                .name
+                .strip_prefix("models/")
+                .unwrap_or(&model.name)
+                .to_string(),
+            created: 0,
+            owned_by: "google".to_string(),
+            context_window: model.input_token_limit,
+        })
+        .collect();
+    let next_page_token = models_response
+        .next_page_token
+        .filter(|page_token| !page_token.is_empty());
+
+    Ok((models, next_page_token))
+}
+
+#[cfg(test)]
+mod tests {
+    use schemars::{schema_for, JsonSchema};
+    use serde_json::json;
+
+    use wiremock::matchers::{method, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::{
+        get_vertex_streaming, list_models, list_models_from, parse_models_response, GeminiResponse,
+        GeminiStreamBuffer,
+    };
+    use crate::domain::{
+        CompletionsOptions, FileData, FinishReason, GoogleGeminiProApiResp, GroundingCitation,
+        GroundingTool, Message, UnknownModelError,
+    };
+    use crate::llm_models::{GoogleModels, LLMModel};
+
+    #[test]
+    fn test_gemini_1_5_uses_system_instruction() {
+        let model = GoogleModels::Gemini1_5Flash;
+        let schema = json!({"type": "object"});
+        let options = CompletionsOptions {
+            custom_base_instructions: Some("You are a helpful assistant.".to_string()),
+            ..Default::default()
+        };
+
+        let body = model.get_body("instructions", &schema, false, &1000, &0.0, &options);
+
+        let system_parts = body["system_instruction"]["parts"].as_array().unwrap();
+        assert_eq!(system_parts[0]["text"], "You are a helpful assistant.");
+        assert_eq!(system_parts.len(), 2);
+        assert_eq!(
+            body["contents"][0]["parts"],
+            json!([{"text": "instructions"}])
+        );
+    }
+
+    #[test]
+    fn test_gemini_pro_inlines_system_instructions() {
+        let model = GoogleModels::GeminiPro;
+        let schema = json!({"type": "object"});
+        let options = CompletionsOptions {
+            custom_base_instructions: Some("You are a helpful assistant.".to_string()),
+            ..Default::default()
+        };
+
+        let body = model.get_body("instructions", &schema, false, &1000, &0.0, &options);
+
+        assert!(body.get("system_instruction").is_none());
+        let parts = body["contents"][0]["parts"].as_array().unwrap();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0]["text"], "You are a helpful assistant.");
+        assert_eq!(parts[2]["text"], "instructions");
+    }
+
+    #[test]
+    fn test_display_and_from_str() {
+        let model: GoogleModels = "gemini-1.5-pro".parse().unwrap();
+
+        assert_eq!(model, GoogleModels::Gemini1_5Pro);
+        assert_eq!(model.to_string(), "gemini-1.5-pro");
+        assert_eq!(
+            "gemini-unknown".parse::<GoogleModels>(),
+            Err(UnknownModelError::new("gemini-unknown"))
+        );
+    }
+
+    #[test]
+    fn test_from_str_round_trip() {
+        let models = vec![
+            GoogleModels::Gemini1_5Flash,
+            GoogleModels::Gemini1_5Flash8B,
+            GoogleModels::Gemini1_5Pro,
+            GoogleModels::Gemini2_0Flash,
+            GoogleModels::Gemini2_0FlashLite,
+            GoogleModels::Gemini2_0ProExp,
+            GoogleModels::Gemini2_0FlashThinkingExp,
+            GoogleModels::Gemini1_5FlashVertex,
+            GoogleModels::Gemini1_5Flash8BVertex,
+            GoogleModels::Gemini1_5ProVertex,
+            GoogleModels::Gemini2_0FlashVertex,
+            GoogleModels::Gemini2_0FlashLiteVertex,
+            GoogleModels::Gemini2_0ProExpVertex,
+            GoogleModels::Gemini2_0FlashThinkingExpVertex,
+        ];
+
+        for model in models {
+            assert_eq!(model.to_string().parse::<GoogleModels>(), Ok(model));
+        }
+
+        // Vertex models share the API name with their Gemini API counterparts but have a distinct id
+        assert_eq!(
+            GoogleModels::Gemini1_5ProVertex.as_str(),
+            GoogleModels::Gemini1_5Pro.as_str()
+        );
+        assert_eq!(
+            GoogleModels::Gemini1_5ProVertex.id(),
+            "gemini-1.5-pro-vertex"
+        );
+    }
+
+    #[test]
+    fn test_function_call_body() {
+        let model = GoogleModels::Gemini2_0Flash;
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "title": {"type": ["string", "null"]},
+                "author": {"$ref": "#/definitions/Author"}
+            },
+            "required": ["author"],
+            "definitions": {
+                "Author": {
+                    "type": "object",
+                    "properties": {"name": {"type": "string"}}
+                }
+            }
+        });
+
+        let body = model.get_body(
+            "instructions",
+            &schema,
+            model.function_call_default(),
+            &1000,
+            &0.0,
+            &CompletionsOptions::default(),
+        );
+
+        let function = &body["tools"][0]["functionDeclarations"][0];
+        assert_eq!(function["name"], "analyze_data");
+        assert_eq!(
+            function["parameters"],
+            json!({
+                "type": "object",
+                "properties": {
+                    "title": {"type": "string", "nullable": true},
+                    "author": {
+                        "type": "object",
+                        "properties": {"name": {"type": "string"}}
+                    }
+                },
+                "required": ["author"]
+            })
+        );
+        assert_eq!(body["toolConfig"]["functionCallingConfig"]["mode"], "ANY");
+        assert_eq!(
+            body["system_instruction"]["parts"]
+                .as_array()
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_function_call_body_recursive_schema() {
+        #[derive(JsonSchema)]
+        #[allow(dead_code)]
+        struct Node {
+            children: Vec<Node>,
+        }
+
+        let model = GoogleModels::Gemini2_0Flash;
+        let schema = serde_json::to_value(schema_for!(Node)).unwrap();
+
+        let body = model.get_body(
+            "instructions",
+            &schema,
+            true,
+            &1000,
+            &0.0,
+            &CompletionsOptions::default(),
+        );
+
+        // The first reference to `Node` is inlined, while the recursive one is cut to the definition's type
+        assert_eq!(
+            body["tools"][0]["functionDeclarations"][0]["parameters"],
+            json!({
+                "type": "object",
+                "properties": {
+                    "children": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "children": {
+                                    "type": "array",
+                                    "items": {"type": "object"}
+                                }
+                            },
+                            "required": ["children"]
+                        }
+                    }
+                },
+                "required": ["children"]
+            })
+        );
+    }
+
+    #[test]
+    fn test_get_data_from_function_call() {
+        let response = r#"{
+            "candidates": [{
+                "content": {
+                    "role": "model",
+                    "parts": [{
+                        "functionCall": {
+                            "name": "analyze_data",
+                            "args": {"answer": "42", "confidence": 0.9}
+                        }
+                    }]
+                },
+                "finishReason": "STOP"
+            }]
+        }"#;
+
+        let data = GoogleModels::Gemini1_5Pro.get_data(response, true).unwrap();
+        let data: serde_json::Value = serde_json::from_str(&data).unwrap();
+
+        assert_eq!(data, json!({"answer": "42", "confidence": 0.9}));
+    }
+
+    #[test]
+    fn test_gemini_response_from_api_response() {
+        let response = json!({
+            "candidates": [{
+                "content": {
+                    "role": "model",
+                    "parts": [
+                        {"text": "Checking. "},
+                        {"functionCall": {"name": "analyze_data", "args": {"answer": "42"}}}
+                    ]
+                },
+                "finishReason": "MAX_TOKENS",
+                "groundingMetadata": {
+                    "groundingChunks": [{"web": {"uri": "https://example.com", "title": "Example"}}]
+                }
+            }],
+            "usageMetadata": {"promptTokenCount": 10, "candidatesTokenCount": 5, "totalTokenCount": 15}
+        });
+        let response: GeminiResponse = serde_json::from_value::<GoogleGeminiProApiResp>(response)
+            .unwrap()
+            .into();
+
+        assert_eq!(
+            response,
+            GeminiResponse {
+                text: r#"Checking. {"answer":"42"}"#.to_string(),
+                function_call_args: Some(r#"{"answer":"42"}"#.to_string()),
+                finish_reason: Some(FinishReason::Length),
+                grounding_citations: Some(vec![GroundingCitation {
+                    uri: "https://example.com".to_string(),
+                    title: Some("Example".to_string()),
+                }]),
+            }
+        );
+
+        // Parts of other roles are not part of the data
+        let response = json!({
+            "candidates": [{"content": {"role": "user", "parts": [{"text": "Hello"}]}}]
+        });
+        let response = GeminiResponse::from(
+            serde_json::from_value::<GoogleGeminiProApiResp>(response).unwrap(),
+        );
+        assert_eq!(response.text, "");
+        assert_eq!(response.finish_reason, None);
+    }
+
+    #[test]
+    fn test_finish_reason() {
+        let response = |finish_reason: &str| {
+            json!({
+                "candidates": [{
+                    "content": {"role": "model", "parts": [{"text": "{\"answer\": "}]},
+                    "finishReason": finish_reason
+                }]
+            })
+            .to_string()
+        };
+        let model = GoogleModels::Gemini1_5Flash;
+
+        assert_eq!(
+            model.get_finish_reason(&response("STOP")),
+            Some(FinishReason::Stop)
+        );
+        assert_eq!(
+            model.get_finish_reason(&response("MAX_TOKENS")),
+            Some(FinishReason::Length)
+        );
+        assert_eq!(
+            model.get_finish_reason(&response("SAFETY")),
+            Some(FinishReason::ContentFilter)
+        );
+        assert_eq!(
+            model.get_finish_reason(&response("MALFORMED_FUNCTION_CALL")),
+            Some(FinishReason::Other("MALFORMED_FUNCTION_CALL".to_string()))
+        );
+        // Vertex responses are reduced to the data by call_api
+        assert_eq!(
+            GoogleModels::Gemini1_5FlashVertex.get_finish_reason(&response("STOP")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_file_data_parts() {
+        let options = CompletionsOptions {
+            files: vec![FileData {
+                mime_type: "application/pdf".to_string(),
+                file_uri: "https://generativelanguage.googleapis.com/v1beta/files/abc-123"
+                    .to_string(),
+            }],
+            ..Default::default()
+        };
+        let schema = json!({"type": "object"});
+
+        let body = GoogleModels::Gemini1_5Pro.get_body(
+            "Summarize the contract",
+            &schema,
+            false,
+            &1000,
+            &0.0,
+            &options,
+        );
+        assert_eq!(
+            body["contents"][0]["parts"],
+            json!([
+                {
+                    "fileData": {
+                        "mimeType": "application/pdf",
+                        "fileUri": "https://generativelanguage.googleapis.com/v1beta/files/abc-123",
+                    }
+                },
+                {"text": "Summarize the contract"},
+            ])
+        );
+        assert!(GoogleModels::Gemini2_0FlashVertex.supports_file_data());
+        assert!(!GoogleModels::GeminiPro.supports_file_data());
+    }
+
+    #[test]
+    fn test_daily_rate_limits() {
+        assert_eq!(GoogleModels::GeminiPro.get_rate_limit().rpd, Some(1_500));
+        assert_eq!(
+            GoogleModels::Gemini1_0ProVertex.get_rate_limit().rpd,
+            Some(1_500)
+        );
+        assert_eq!(GoogleModels::Gemini1_5Pro.get_rate_limit().rpd, None);
+    }
+
+    #[test]
+    fn test_grounding() {
+        let options = CompletionsOptions {
+            grounding: Some(GroundingTool {
+                google_search_retrieval: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let schema = json!({"type": "object"});
+
+        let body = GoogleModels::Gemini1_5Flash.get_body(
+            "instructions",
+            &schema,
+            false,
+            &1000,
+            &0.0,
+            &options,
+        );
+        assert_eq!(body["tools"], json!([{"google_search_retrieval": {}}]));
+        let body = GoogleModels::Gemini1_5Flash.get_body(
+            "instructions",
+            &schema,
+            true,
+            &1000,
+            &0.0,
+            &options,
+        );
+        // Search tools cannot be combined with function declarations
+        assert_eq!(body["tools"].as_array().unwrap().len(), 1);
+        assert!(body["tools"][0].get("functionDeclarations").is_some());
+        let body = GoogleModels::Gemini2_0Flash.get_body(
+            "instructions",
+            &schema,
+            false,
+            &1000,
+            &0.0,
+            &options,
+        );
+        assert_eq!(body["tools"], json!([{"google_search": {}}]));
+        // All Gemini 2.x models, including Vertex AI ones, use `google_search`
+        for model in [
+            GoogleModels::Gemini2_0FlashLite,
+            GoogleModels::Gemini2_0FlashThinkingExp,
+            GoogleModels::Gemini2_0FlashVertex,
+            GoogleModels::Gemini2_0ProExpVertex,
+        ] {
+            let body = model.get_body("instructions", &schema, false, &1000, &0.0, &options);
+            assert_eq!(body["tools"], json!([{"google_search": {}}]));
+        }
+        let body = GoogleModels::Gemini1_5ProVertex.get_body(
+            "instructions",
+            &schema,
+            false,
+            &1000,
+            &0.0,
+            &options,
+        );
+        assert_eq!(body["tools"], json!([{"google_search_retrieval": {}}]));
+        let body = GoogleModels::Gemini1_5Flash.get_body(
+            "instructions",
+            &schema,
+            false,
+            &1000,
+            &0.0,
+            &CompletionsOptions::default(),
+        );
+        assert!(body.get("tools").is_none());
+        // Google Search is the built-in web search of Gemini models
+        let web_search_options = CompletionsOptions {
+            grounding: Some(GroundingTool {
+                web_search: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let body = GoogleModels::Gemini2_0Flash.get_body(
+            "instructions",
+            &schema,
+            false,
+            &1000,
+            &0.0,
+            &web_search_options,
+        );
+        assert_eq!(body["tools"], json!([{"google_search": {}}]));
+
+        let response = json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [{"text": "{\"winner\": \"Real Madrid\"}"}]},
+                "finishReason": "STOP",
+                "groundingMetadata": {
+                    "webSearchQueries": ["champions league winner"],
+                    "groundingChunks": [
+                        {"web": {"uri": "https://example.com/final", "title": "example.com"}},
+                        {"web": {"uri": "https://example.org/report"}}
+                    ]
+                }
+            }]
+        })
+        .to_string();
+        assert_eq!(
+            GoogleModels::Gemini1_5Flash.get_grounding_citations(&response),
+            Some(vec![
+                GroundingCitation {
+                    uri: "https://example.com/final".to_string(),
+                    title: Some("example.com".to_string()),
+                },
+                GroundingCitation {
+                    uri: "https://example.org/report".to_string(),
+                    title: None,
+                },
+            ])
+        );
+        assert!(!GoogleModels::Gemini1_5FlashVertex.supports_grounding());
+    }
+
+    #[test]
+    fn test_stream_buffer_reconstructs_split_chunks() {
+        let first = r#"{"candidates": [{"content": {"role": "model", "parts": [{"text": "{\"answer\": "}]}}]}"#;
+        let second =
+            r#"{"candidates": [{"content": {"role": "model", "parts": [{"text": "\"42\"}"}]}}]}"#;
+        let stream = format!("data: {first}\r\n\r\ndata: {second}\r\n\r\n");
+
+        //Chunks split mid-object, inside the SSE prefix and with multiple objects in one chunk
+        let chunks = [
+            &stream[..20],
+            &stream[20..first.len() + 8],
+            &stream[first.len() + 8..first.len() + 12],
+            &stream[first.len() + 12..],
+        ];
+
+        let mut buffer = GeminiStreamBuffer::default();
+        let mut streamed_response = String::new();
+        for chunk in chunks {
+            for response in buffer.push(chunk.as_bytes()).unwrap() {
+                streamed_response.push_str(&GeminiResponse::from(response).text);
+            }
+        }
+        buffer.finish().unwrap();
+
+        assert_eq!(streamed_response, r#"{"answer": "42"}"#);
+    }
+
+    #[test]
+    fn test_stream_buffer_json_array_framing() {
+        let stream = r#"[{"candidates": [{"content": {"role": "model", "parts": [{"text": "a"}]}}]}
+,
+{"candidates": [{"content": {"role": "model", "parts": [{"text": "b"}]}}]}
+]"#;
+
+        let mut buffer = GeminiStreamBuffer::default();
+        let mut responses = Vec::new();
+        for chunk in stream.as_bytes().chunks(7) {
+            responses.extend(buffer.push(chunk).unwrap());
+        }
+        buffer.finish().unwrap();
+
+        let data: Vec<String> = responses
+            .into_iter()
+            .map(|response| GeminiResponse::from(response).text)
+            .collect();
+        assert_eq!(data, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_stream_buffer_incomplete_response() {
+        let mut buffer = GeminiStreamBuffer::default();
+
+        assert!(buffer
+            .push(br#"data: {"candidates": [{"content": "#)
+            .unwrap()
+            .is_empty());
+        assert!(buffer.finish().is_err());
+    }
+
+    #[test]
+    fn test_vertex_streaming_option() {
+        let schema = json!({"type": "object"});
+        let options = CompletionsOptions {
+            streaming: Some(false),
+            ..Default::default()
+        };
+
+        let model = GoogleModels::Gemini1_5ProVertex;
+        let body = model.get_body("instructions", &schema, false, &1000, &0.0, &options);
+        let (streaming, sent_body) = get_vertex_streaming(&body);
+        assert!(!streaming);
+        assert!(sent_body.get("stream").is_none());
+        assert_eq!(sent_body["contents"], body["contents"]);
+
+        //Without the option the default of the environment is kept in the body
+        let body = model.get_body(
+            "instructions",
+            &schema,
+            false,
+            &1000,
+            &0.0,
+            &CompletionsOptions::default(),
+        );
+        assert!(body.get("stream").is_none());
+
+        //AI Studio requests are unaffected
+        let model = GoogleModels::Gemini1_5Pro;
+        let body = model.get_body("instructions", &schema, false, &1000, &0.0, &options);
+        assert!(body.get("stream").is_none());
+    }
+
+    #[test]
+    fn test_get_data_vertex_single_response() {
+        let response = r#"{
+            "candidates": [{
+                "content": {
+                    "role": "model",
+                    "parts": [{"text": "```json\n{\"answer\": \"42\"}```"}]
+                },
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": {"promptTokenCount": 10, "candidatesTokenCount": 5, "totalTokenCount": 15}
+        }"#;
+
+        let data = GoogleModels::Gemini1_5ProVertex
+            .get_data_vertex(response)
+            .unwrap();
+
+        assert_eq!(data, r#"{"answer": "42"}"#);
+    }
+
+    #[test]
+    fn test_parse_models_response() {
+        let response = json!({
+            "models": [{
+                "name": "models/gemini-1.5-pro",
+                "displayName": "Gemini 1.5 Pro",
+                "inputTokenLimit": 2000000,
+                "outputTokenLimit": 8192,
+                "supportedGenerationMethods": ["generateContent", "countTokens"]
+            }, {
+                "name": "models/text-embedding-004",
+                "displayName": "Text Embedding 004",
+                "inputTokenLimit": 2048,
+                "supportedGenerationMethods": ["embedContent"]
+            }],
+            "nextPageToken": ""
+        });
+
+        let (models, next_page_token) = parse_models_response(&response.to_string()).unwrap();
+
+        assert_eq!(models.len(), 1);
+        assert_eq!(next_page_token, None);
+        assert_eq!(models[0].id, "gemini-1.5-pro");
+        assert_eq!(models[0].owned_by, "google");
+        assert_eq!(models[0].context_window, Some(2_000_000));
+        assert!(parse_models_response(r#"{"error": {"code": 400}}"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_list_models_pages() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(query_param("pageToken", "page-2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "models": [{
+                    "name": "models/gemini-2.0-flash",
+                    "supportedGenerationMethods": ["generateContent"]
+                }]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "models": [{
+                    "name": "models/gemini-1.5-pro",
+                    "supportedGenerationMethods": ["generateContent"]
+                }],
+                "nextPageToken": "page-2"
+            })))
+            .mount(&server)
+            .await;
+
+        let models = list_models_from(&server.uri(), "api-key").await.unwrap();
+        let ids: Vec<&str> = models.iter().map(|model| model.id.as_str()).collect();
+        assert_eq!(ids, vec!["gemini-1.5-pro", "gemini-2.0-flash"]);
+    }
+
+    #[tokio::test]
+    async fn test_list_vertex_models() {
+        let models = list_models("").await.unwrap();
+
+        // Listed models can be used to build the Vertex variants
+        for model in &models {
+            assert!(GoogleModels::try_from_str(&model.id).unwrap().is_vertex());
+        }
+        assert_eq!(models[0].context_window, Some(1_048_576));
+    }
+
+    #[test]
+    fn test_history_contents() {
+        let options = CompletionsOptions {
+            history: vec![
+                Message::user("Hi"),
+                Message::assistant("{\"answer\": \"Hello\"}"),
+            ],
+            ..Default::default()
+        };
+
+        let body =
+            GoogleModels::Gemini1_5Flash.get_body("Bye", &json!({}), false, &0, &1.0, &options);
+        let contents = body["contents"].as_array().unwrap();
+        let roles: Vec<&str> = contents
             .iter()
-            .filter(|candidate| candidate.content.role.as_deref() == Some("model"))
-            .flat_map(|candidate| &candidate.content.parts)
-            .map(|part| &part.text)
-            .fold(String::new(), |mut acc, text| {
-                acc.push_str(text);
-                acc
-            }))
+            .map(|content| content["role"].as_str().unwrap())
+            .collect();
+        assert_eq!(roles, vec!["user", "model", "user"]);
+        assert_eq!(contents[0]["parts"], json!([{"text": "Hi"}]));
+        assert_eq!(contents[2]["parts"], json!([{"text": "Bye"}]));
+
+        // Without system instructions support the base instructions open the current user turn
+        #[allow(deprecated)]
+        let body = GoogleModels::GeminiPro.get_body(
+            "Bye",
+            &json!({"type": "object"}),
+            false,
+            &0,
+            &1.0,
+            &options,
+        );
+        let contents = body["contents"].as_array().unwrap();
+        assert_eq!(contents.len(), 3);
+        let parts = contents[2]["parts"].as_array().unwrap();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[2], json!({"text": "Bye"}));
+
+        // Plain-text requests are sent without the output schema part
+        let body = GoogleModels::GeminiPro.get_body("Bye", &json!({}), false, &0, &1.0, &options);
+        assert_eq!(body["contents"][2]["parts"].as_array().unwrap().len(), 2);
     }
 }
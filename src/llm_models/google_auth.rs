@@ -0,0 +1,207 @@
+use anyhow::{anyhow, Result};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use yup_oauth2::{
+    authenticator::ApplicationDefaultCredentialsTypes, read_service_account_key, AccessToken,
+    ApplicationDefaultCredentialsAuthenticator, ApplicationDefaultCredentialsFlowOpts,
+    ServiceAccountAuthenticator,
+};
+
+// Scope required by Vertex AI endpoints
+// Docs: https://cloud.google.com/vertex-ai/docs/authentication
+const GOOGLE_CLOUD_PLATFORM_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+
+/// Supported ways of authenticating requests sent to Google Vertex AI
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq)]
+pub enum VertexAuthMethod {
+    /// An OAuth2 access token obtained outside of the crate. The token is used as is and never refreshed.
+    RawBearer(String),
+    /// Path to a GCP service account key file (JSON)
+    ServiceAccountJson(PathBuf),
+    /// Application Default Credentials, i.e. `GOOGLE_APPLICATION_CREDENTIALS` env variable or GCE metadata server
+    ApplicationDefaultCredentials,
+}
+
+/// Configuration of Google Vertex AI access.
+///
+/// The config obtains OAuth2 access tokens using the selected `VertexAuthMethod` and caches them.
+/// Cached tokens are refreshed shortly before they expire. Clones of the config share the same cache.
+#[derive(Debug, Clone)]
+pub struct GeminiProVertexConfig {
+    pub auth: VertexAuthMethod,
+    scopes: Vec<String>,
+    debug: bool,
+    token_cache: Arc<Mutex<Option<AccessToken>>>,
+}
+
+impl GeminiProVertexConfig {
+    /// Constructor
+    pub fn new(auth: VertexAuthMethod) -> Self {
+        GeminiProVertexConfig {
+            auth,
+            scopes: vec![GOOGLE_CLOUD_PLATFORM_SCOPE.to_string()],
+            debug: false,
+            token_cache: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    ///
+    /// This method can be used to turn on debug mode for the config
+    ///
+    pub fn debug(mut self) -> Self {
+        self.debug = true;
+        self
+    }
+
+    ///
+    /// This method can be used to override the OAuth2 scopes requested for the token
+    /// Default is `https://www.googleapis.com/auth/cloud-platform`
+    ///
+    pub fn scopes(mut self, scopes: &[&str]) -> Self {
+        self.scopes = scopes.iter().map(|scope| scope.to_string()).collect();
+        self
+    }
+
+    ///
+    /// This method returns an access token that can be passed to `Completions` as the API key for Vertex models.
+    /// The token is reused until it is about to expire, after which a new one is requested.
+    ///
+    pub async fn access_token(&self) -> Result<String> {
+        if let VertexAuthMethod::RawBearer(token) = &self.auth {
+            return Ok(token.to_string());
+        }
+
+        self.cached_token(|| self.fetch_token()).await
+    }
+
+    // Returns the cached token, or caches a new one obtained with `fetch_token` if it is missing or about to expire
+    async fn cached_token<F, Fut>(&self, fetch_token: F) -> Result<String>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<AccessToken>>,
+    {
+        let mut token_cache = self.token_cache.lock().await;
+
+        // `is_expired` already accounts for a safety margin before the actual expiry time
+        if let Some(token) = token_cache
+            .as_ref()
+            .filter(|token| !token.is_expired())
+            .and_then(|token| token.token())
+        {
+            return Ok(token.to_string());
+        }
+
+        if self.debug {
+            info!("[allms][Google Vertex AI] Requesting new OAuth2 access token");
+        }
+
+        let new_token = fetch_token().await?;
+        let token_str = new_token
+            .token()
+            .ok_or_else(|| anyhow!("[allms][Google Vertex AI] OAuth2 response without a token"))?
+            .to_string();
+        *token_cache = Some(new_token);
+
+        Ok(token_str)
+    }
+
+    // Requests a new token from Google OAuth2 server using the configured method
+    async fn fetch_token(&self) -> Result<AccessToken> {
+        let scopes: Vec<&str> = self.scopes.iter().map(String::as_str).collect();
+
+        match &self.auth {
+            VertexAuthMethod::RawBearer(_) => Err(anyhow!(
+                "[allms][Google Vertex AI] Raw bearer tokens cannot be refreshed"
+            )),
+            VertexAuthMethod::ServiceAccountJson(key_path) => {
+                let service_account_key = read_service_account_key(key_path).await?;
+                let auth = ServiceAccountAuthenticator::builder(service_account_key)
+                    .build()
+                    .await?;
+                Ok(auth.token(&scopes).await?)
+            }
+            VertexAuthMethod::ApplicationDefaultCredentials => {
+                let opts = ApplicationDefaultCredentialsFlowOpts::default();
+                match ApplicationDefaultCredentialsAuthenticator::builder(opts).await {
+                    ApplicationDefaultCredentialsTypes::InstanceMetadata(builder) => {
+                        let auth = builder.build().await?;
+                        Ok(auth.token(&scopes).await?)
+                    }
+                    ApplicationDefaultCredentialsTypes::ServiceAccount(builder) => {
+                        let auth = builder.build().await?;
+                        Ok(auth.token(&scopes).await?)
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use time::{Duration, OffsetDateTime};
+    use yup_oauth2::storage::TokenInfo;
+
+    // Token as returned by the OAuth2 server, expiring after the provided duration
+    fn access_token(expires_in: Duration) -> AccessToken {
+        AccessToken::from(TokenInfo {
+            access_token: Some("test-access-token".to_string()),
+            refresh_token: None,
+            expires_at: Some(OffsetDateTime::now_utc() + expires_in),
+            id_token: None,
+        })
+    }
+
+    // Gets a token from the config, counting how many times a new one is requested
+    async fn get_token(
+        config: &GeminiProVertexConfig,
+        expires_in: Duration,
+        requests: &AtomicUsize,
+    ) -> String {
+        config
+            .cached_token(|| async {
+                requests.fetch_add(1, Ordering::SeqCst);
+                Ok(access_token(expires_in))
+            })
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_raw_bearer_token() {
+        let config = GeminiProVertexConfig::new(VertexAuthMethod::RawBearer("raw-token".into()));
+        assert_eq!(config.access_token().await.unwrap(), "raw-token");
+    }
+
+    #[tokio::test]
+    async fn test_cached_token_reused() {
+        let config = GeminiProVertexConfig::new(VertexAuthMethod::ApplicationDefaultCredentials);
+        let requests = AtomicUsize::new(0);
+
+        let token = get_token(&config, Duration::hours(1), &requests).await;
+        assert_eq!(token, "test-access-token");
+        // Clones share the cache
+        let token = get_token(&config.clone(), Duration::hours(1), &requests).await;
+        assert_eq!(token, "test-access-token");
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_expiring_token_refreshed() {
+        // Tokens within a minute of their expiry are treated as expired
+        let config = GeminiProVertexConfig::new(VertexAuthMethod::ApplicationDefaultCredentials);
+        let requests = AtomicUsize::new(0);
+
+        for _ in 0..2 {
+            let token = get_token(&config, Duration::seconds(30), &requests).await;
+            assert_eq!(token, "test-access-token");
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+}
@@ -1,11 +1,33 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use futures::Stream;
 use serde_json::Value;
+use std::pin::Pin;
+use std::sync::Arc;
 
-use crate::constants::OPENAI_BASE_INSTRUCTIONS;
-use crate::domain::RateLimit;
+use crate::constants::{
+    OPENAI_BASE_INSTRUCTIONS, OPENAI_LENIENT_BASE_INSTRUCTIONS, OPENAI_STRICT_BASE_INSTRUCTIONS,
+};
+use crate::domain::{
+    ApiResponse, CompletionsOptions, CompletionsUsage, FinishReason, GroundingCitation,
+    LLMProvider, OutputStrictness, RateLimit, RateLimitStatus, TokenCost, TokenLogprob,
+};
+use crate::http_client::HttpClient;
+use crate::llm_models::LLMModelEnum;
 use crate::utils::{map_to_range, remove_json_wrapper};
 
+///Text of a response streamed in chunks as they are generated by the model
+pub type TextStream = Pin<Box<dyn Stream<Item = Result<String>>>>;
+
+///Returns the base instructions asking for Json output, worded as per the requested output strictness
+pub(crate) fn get_json_base_instructions(strictness: OutputStrictness) -> String {
+    match strictness {
+        OutputStrictness::Standard => OPENAI_BASE_INSTRUCTIONS.to_string(),
+        OutputStrictness::Strict => OPENAI_STRICT_BASE_INSTRUCTIONS.to_string(),
+        OutputStrictness::Lenient => OPENAI_LENIENT_BASE_INSTRUCTIONS.to_string(),
+    }
+}
+
 ///This trait defines functions that need to be implemented for an enum that represents an LLM Model from any of the API providers
 #[async_trait(?Send)]
 pub trait LLMModel {
@@ -15,13 +37,56 @@ pub trait LLMModel {
     fn try_from_str(name: &str) -> Option<Self>
     where
         Self: Sized;
+    ///Returns the name identifying the model in `try_from_str`, e.g. to persist the model and restore it later
+    ///Defaults to `as_str`. Models sharing the API name with another variant (e.g. Gemini models on Vertex AI) return a distinct name
+    fn id(&self) -> String {
+        self.as_str().to_string()
+    }
+    ///Returns the provider serving the model, e.g. to attribute responses when the model is selected at runtime
+    ///If not explicitly implemented the provider is reported as `Other` with the name of the model type
+    fn get_provider(&self) -> LLMProvider {
+        let type_name = std::any::type_name::<Self>();
+        let type_name = type_name.split('<').next().unwrap_or(type_name);
+        LLMProvider::Other(
+            type_name
+                .rsplit("::")
+                .next()
+                .unwrap_or(type_name)
+                .to_string(),
+        )
+    }
     ///Returns max supported number of tokens for each of the variants of the enum
     fn default_max_tokens(&self) -> usize;
+    ///Returns the size of the input context window of the model in tokens
+    ///If not explicitly implemented it assumes `default_max_tokens` covers both the prompt and the response
+    fn context_window_tokens(&self) -> usize {
+        self.default_max_tokens()
+    }
+    ///Returns the price of input and output tokens in USD per 1k tokens, if known for the model
+    fn cost_per_1k_tokens(&self) -> Option<TokenCost> {
+        None
+    }
     ///Returns the url of the endpoint that should be called for each variant of the LLM Model enum
     fn get_endpoint(&self) -> String;
     ///Provides a list of base instructions that should be added to each prompt when using each of the models
-    fn get_base_instructions(&self, _function_call: Option<bool>) -> String {
-        OPENAI_BASE_INSTRUCTIONS.to_string()
+    ///The wording of the Json formatting steps depends on the requested output strictness
+    fn get_base_instructions(
+        &self,
+        _function_call: Option<bool>,
+        strictness: OutputStrictness,
+    ) -> String {
+        get_json_base_instructions(strictness)
+    }
+    ///Returns base instructions that should be added to the body of the request
+    ///Custom base instructions provided in the request options take precedence over the defaults of the model
+    fn get_body_base_instructions(
+        &self,
+        function_call: bool,
+        options: &CompletionsOptions,
+    ) -> String {
+        options.custom_base_instructions.clone().unwrap_or_else(|| {
+            self.get_base_instructions(Some(function_call), options.output_strictness)
+        })
     }
     ///Returns recommendation if function calling should be used for the specified model
     fn function_call_default(&self) -> bool {
@@ -35,16 +100,112 @@ pub trait LLMModel {
         function_call: bool,
         max_tokens: &usize,
         temperature: &f32,
+        options: &CompletionsOptions,
     ) -> serde_json::Value;
-    ///Makes the call to the correct API for the selected model
+    ///Makes the call to the correct API for the selected model with the provided HTTP client, whose connections are reused across calls
+    ///Returns the response text with the HTTP status and, if reported by the API, its rate limits
     async fn call_api(
         &self,
+        client: &HttpClient,
         api_key: &str,
         body: &serde_json::Value,
         debug: bool,
-    ) -> Result<String>;
+    ) -> Result<ApiResponse>;
+    ///Returns true if the model can stream the text of the response with `stream_api`
+    fn supports_streaming(&self) -> bool {
+        false
+    }
+    ///Sends the request with streaming enabled and returns the text of the response in chunks as they arrive
+    async fn stream_api(
+        &self,
+        _client: &HttpClient,
+        _api_key: &str,
+        _body: &serde_json::Value,
+        _debug: bool,
+    ) -> Result<TextStream> {
+        Err(anyhow!(
+            "[allms] Streaming is not supported by {}",
+            self.as_str()
+        ))
+    }
+    ///Counts the input tokens of the request body with the token counting API of the provider, which matches the billed input tokens
+    ///If not explicitly implemented the model is assumed not to have such an API
+    async fn count_body_tokens_api(
+        &self,
+        _client: &HttpClient,
+        _api_key: &str,
+        _body: &serde_json::Value,
+    ) -> Result<usize> {
+        Err(anyhow!(
+            "[allms] Token counting API is not supported by {}",
+            self.as_str()
+        ))
+    }
     ///Based on the model type extracts the data portion of the API response
     fn get_data(&self, response_text: &str, function_call: bool) -> Result<String>;
+    ///Returns true if the model can generate several completions in one request (the `n` parameter)
+    ///If not explicitly implemented only a single completion is returned per request
+    fn supports_multiple_choices(&self) -> bool {
+        false
+    }
+    ///Based on the model type extracts the data portion of each completion returned in the API response
+    ///If not explicitly implemented the response is assumed to contain a single completion
+    fn get_data_choices(&self, response_text: &str, function_call: bool) -> Result<Vec<String>> {
+        self.get_data(response_text, function_call)
+            .map(|data| vec![data])
+    }
+    ///Based on the model type extracts the token usage reported in the API response
+    ///If not explicitly implemented usage is not reported
+    fn get_usage(&self, _response_text: &str) -> Option<CompletionsUsage> {
+        None
+    }
+    ///Based on the model type extracts the sources cited in the API response (e.g. by search-augmented models)
+    ///If not explicitly implemented citations are not reported
+    fn get_citations(&self, _response_text: &str) -> Option<Vec<String>> {
+        None
+    }
+    ///Returns true if the model can report log probabilities of the response tokens
+    ///If not explicitly implemented log probabilities are not supported
+    fn supports_logprobs(&self) -> bool {
+        false
+    }
+    ///Based on the model type extracts the log probabilities of the response tokens, if they were requested
+    fn get_logprobs(&self, _response_text: &str) -> Option<Vec<TokenLogprob>> {
+        None
+    }
+    ///Based on the model type extracts the reason the model stopped generating the (first) response
+    ///If not explicitly implemented the finish reason is not reported
+    fn get_finish_reason(&self, _response_text: &str) -> Option<FinishReason> {
+        None
+    }
+    ///Returns true if the model can ground its answer in live web results with Google Search
+    ///If not explicitly implemented grounding is not supported
+    fn supports_grounding(&self) -> bool {
+        false
+    }
+    ///Based on the model type extracts the web sources used to ground the answer, if grounding was requested
+    fn get_grounding_citations(&self, _response_text: &str) -> Option<Vec<GroundingCitation>> {
+        None
+    }
+    ///Returns true if files uploaded to the provider can be referenced in the request
+    ///If not explicitly implemented files are not supported
+    fn supports_file_data(&self) -> bool {
+        false
+    }
+    ///Returns true if PDF documents can be sent inline in the request
+    ///If not explicitly implemented documents are not supported
+    fn supports_pdf_documents(&self) -> bool {
+        false
+    }
+    ///Returns true if the model can reason with extended thinking before answering
+    ///If not explicitly implemented extended thinking is not supported
+    fn supports_thinking(&self) -> bool {
+        false
+    }
+    ///Based on the model type extracts the reasoning of the model returned separately from the answer, if extended thinking was requested
+    fn get_thinking(&self, _response_text: &str) -> Option<String> {
+        None
+    }
     /// This function sanitizes the text response from LLMs to clean up common formatting issues.
     /// The default implementation of the function removes the common ```json{}``` wrapper returned by most models
     fn sanitize_json_response(&self, json_response: &str) -> String {
@@ -56,6 +217,7 @@ pub trait LLMModel {
         RateLimit {
             tpm: 100_000_000,
             rpm: 100_000,
+            rpd: None,
         }
     }
     ///Based on the RateLimit for the model calculates how many requests can be send to the API
@@ -68,7 +230,7 @@ pub trait LLMModel {
         //Double check max number of requests based on tpm
         //Assume we will use ~50% of allowed tokens per request (for prompt + response)
         let max_tokens_per_minute = rate_limit.tpm;
-        let tpm_per_request = (self.default_max_tokens() as f64 * 0.5).ceil() as usize;
+        let tpm_per_request = (self.context_window_tokens() as f64 * 0.5).ceil() as usize;
         //Then check how many requests we can process
         let max_requests_from_tpm = max_tokens_per_minute / tpm_per_request;
 
@@ -79,6 +241,11 @@ pub trait LLMModel {
     fn get_default_temperature(&self) -> f32 {
         0f32
     }
+    ///Returns the (inclusive) range of temperature values accepted by the model
+    ///If not explicitly defined it will assume 0-1 range
+    fn get_temperature_range(&self) -> (f32, f32) {
+        (0f32, 1f32)
+    }
     ///Returns the normalized temperature for the model
     //Input should be a 0-100 number representing the percentage of max temp for the model
     fn get_normalized_temperature(&self, relative_temp: u32) -> f32 {
@@ -87,4 +254,479 @@ pub trait LLMModel {
         let max = 1u32;
         map_to_range(min, max, relative_temp)
     }
+    ///Returns the rate limit status reported by the API in the headers of the last successful call made with the model
+    ///If not explicitly implemented the provider does not report it
+    fn last_rate_limit_status(&self) -> Option<RateLimitStatus> {
+        None
+    }
+}
+
+///Boxed models allow selecting the provider at runtime without making the calling code generic
+///The model is created from a `provider/model` string representation, see `LLMModelEnum`
+#[async_trait(?Send)]
+impl LLMModel for Box<dyn LLMModel> {
+    fn as_str(&self) -> &str {
+        self.as_ref().as_str()
+    }
+
+    fn id(&self) -> String {
+        self.as_ref().id()
+    }
+
+    fn try_from_str(name: &str) -> Option<Self> {
+        LLMModelEnum::try_from_str(name).map(|model| Box::new(model) as Box<dyn LLMModel>)
+    }
+
+    fn get_provider(&self) -> LLMProvider {
+        self.as_ref().get_provider()
+    }
+
+    fn default_max_tokens(&self) -> usize {
+        self.as_ref().default_max_tokens()
+    }
+
+    fn context_window_tokens(&self) -> usize {
+        self.as_ref().context_window_tokens()
+    }
+
+    fn cost_per_1k_tokens(&self) -> Option<TokenCost> {
+        self.as_ref().cost_per_1k_tokens()
+    }
+
+    fn get_endpoint(&self) -> String {
+        self.as_ref().get_endpoint()
+    }
+
+    fn get_base_instructions(
+        &self,
+        function_call: Option<bool>,
+        strictness: OutputStrictness,
+    ) -> String {
+        self.as_ref()
+            .get_base_instructions(function_call, strictness)
+    }
+
+    fn get_body_base_instructions(
+        &self,
+        function_call: bool,
+        options: &CompletionsOptions,
+    ) -> String {
+        self.as_ref()
+            .get_body_base_instructions(function_call, options)
+    }
+
+    fn function_call_default(&self) -> bool {
+        self.as_ref().function_call_default()
+    }
+
+    fn get_body(
+        &self,
+        instructions: &str,
+        json_schema: &Value,
+        function_call: bool,
+        max_tokens: &usize,
+        temperature: &f32,
+        options: &CompletionsOptions,
+    ) -> serde_json::Value {
+        self.as_ref().get_body(
+            instructions,
+            json_schema,
+            function_call,
+            max_tokens,
+            temperature,
+            options,
+        )
+    }
+
+    async fn call_api(
+        &self,
+        client: &HttpClient,
+        api_key: &str,
+        body: &serde_json::Value,
+        debug: bool,
+    ) -> Result<ApiResponse> {
+        self.as_ref().call_api(client, api_key, body, debug).await
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.as_ref().supports_streaming()
+    }
+
+    async fn stream_api(
+        &self,
+        client: &HttpClient,
+        api_key: &str,
+        body: &serde_json::Value,
+        debug: bool,
+    ) -> Result<TextStream> {
+        self.as_ref().stream_api(client, api_key, body, debug).await
+    }
+
+    async fn count_body_tokens_api(
+        &self,
+        client: &HttpClient,
+        api_key: &str,
+        body: &serde_json::Value,
+    ) -> Result<usize> {
+        self.as_ref()
+            .count_body_tokens_api(client, api_key, body)
+            .await
+    }
+
+    fn get_data(&self, response_text: &str, function_call: bool) -> Result<String> {
+        self.as_ref().get_data(response_text, function_call)
+    }
+
+    fn supports_multiple_choices(&self) -> bool {
+        self.as_ref().supports_multiple_choices()
+    }
+
+    fn get_data_choices(&self, response_text: &str, function_call: bool) -> Result<Vec<String>> {
+        self.as_ref().get_data_choices(response_text, function_call)
+    }
+
+    fn get_usage(&self, response_text: &str) -> Option<CompletionsUsage> {
+        self.as_ref().get_usage(response_text)
+    }
+
+    fn get_citations(&self, response_text: &str) -> Option<Vec<String>> {
+        self.as_ref().get_citations(response_text)
+    }
+
+    fn supports_logprobs(&self) -> bool {
+        self.as_ref().supports_logprobs()
+    }
+
+    fn get_logprobs(&self, response_text: &str) -> Option<Vec<TokenLogprob>> {
+        self.as_ref().get_logprobs(response_text)
+    }
+
+    fn get_finish_reason(&self, response_text: &str) -> Option<FinishReason> {
+        self.as_ref().get_finish_reason(response_text)
+    }
+
+    fn supports_grounding(&self) -> bool {
+        self.as_ref().supports_grounding()
+    }
+
+    fn get_grounding_citations(&self, response_text: &str) -> Option<Vec<GroundingCitation>> {
+        self.as_ref().get_grounding_citations(response_text)
+    }
+
+    fn supports_file_data(&self) -> bool {
+        self.as_ref().supports_file_data()
+    }
+
+    fn supports_pdf_documents(&self) -> bool {
+        self.as_ref().supports_pdf_documents()
+    }
+
+    fn supports_thinking(&self) -> bool {
+        self.as_ref().supports_thinking()
+    }
+
+    fn get_thinking(&self, response_text: &str) -> Option<String> {
+        self.as_ref().get_thinking(response_text)
+    }
+
+    fn sanitize_json_response(&self, json_response: &str) -> String {
+        self.as_ref().sanitize_json_response(json_response)
+    }
+
+    fn get_rate_limit(&self) -> RateLimit {
+        self.as_ref().get_rate_limit()
+    }
+
+    fn get_max_requests(&self) -> usize {
+        self.as_ref().get_max_requests()
+    }
+
+    fn get_default_temperature(&self) -> f32 {
+        self.as_ref().get_default_temperature()
+    }
+
+    fn get_temperature_range(&self) -> (f32, f32) {
+        self.as_ref().get_temperature_range()
+    }
+
+    fn get_normalized_temperature(&self, relative_temp: u32) -> f32 {
+        self.as_ref().get_normalized_temperature(relative_temp)
+    }
+
+    fn last_rate_limit_status(&self) -> Option<RateLimitStatus> {
+        self.as_ref().last_rate_limit_status()
+    }
+}
+
+///Shared models let clients hand the same model, including boxed trait objects, to every completions request without cloning it
+#[async_trait(?Send)]
+impl<T: LLMModel> LLMModel for Arc<T> {
+    fn as_str(&self) -> &str {
+        self.as_ref().as_str()
+    }
+
+    fn id(&self) -> String {
+        self.as_ref().id()
+    }
+
+    fn try_from_str(name: &str) -> Option<Self> {
+        T::try_from_str(name).map(Arc::new)
+    }
+
+    fn get_provider(&self) -> LLMProvider {
+        self.as_ref().get_provider()
+    }
+
+    fn default_max_tokens(&self) -> usize {
+        self.as_ref().default_max_tokens()
+    }
+
+    fn context_window_tokens(&self) -> usize {
+        self.as_ref().context_window_tokens()
+    }
+
+    fn cost_per_1k_tokens(&self) -> Option<TokenCost> {
+        self.as_ref().cost_per_1k_tokens()
+    }
+
+    fn get_endpoint(&self) -> String {
+        self.as_ref().get_endpoint()
+    }
+
+    fn get_base_instructions(
+        &self,
+        function_call: Option<bool>,
+        strictness: OutputStrictness,
+    ) -> String {
+        self.as_ref()
+            .get_base_instructions(function_call, strictness)
+    }
+
+    fn get_body_base_instructions(
+        &self,
+        function_call: bool,
+        options: &CompletionsOptions,
+    ) -> String {
+        self.as_ref()
+            .get_body_base_instructions(function_call, options)
+    }
+
+    fn function_call_default(&self) -> bool {
+        self.as_ref().function_call_default()
+    }
+
+    fn get_body(
+        &self,
+        instructions: &str,
+        json_schema: &Value,
+        function_call: bool,
+        max_tokens: &usize,
+        temperature: &f32,
+        options: &CompletionsOptions,
+    ) -> serde_json::Value {
+        self.as_ref().get_body(
+            instructions,
+            json_schema,
+            function_call,
+            max_tokens,
+            temperature,
+            options,
+        )
+    }
+
+    async fn call_api(
+        &self,
+        client: &HttpClient,
+        api_key: &str,
+        body: &serde_json::Value,
+        debug: bool,
+    ) -> Result<ApiResponse> {
+        self.as_ref().call_api(client, api_key, body, debug).await
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.as_ref().supports_streaming()
+    }
+
+    async fn stream_api(
+        &self,
+        client: &HttpClient,
+        api_key: &str,
+        body: &serde_json::Value,
+        debug: bool,
+    ) -> Result<TextStream> {
+        self.as_ref().stream_api(client, api_key, body, debug).await
+    }
+
+    async fn count_body_tokens_api(
+        &self,
+        client: &HttpClient,
+        api_key: &str,
+        body: &serde_json::Value,
+    ) -> Result<usize> {
+        self.as_ref()
+            .count_body_tokens_api(client, api_key, body)
+            .await
+    }
+
+    fn get_data(&self, response_text: &str, function_call: bool) -> Result<String> {
+        self.as_ref().get_data(response_text, function_call)
+    }
+
+    fn supports_multiple_choices(&self) -> bool {
+        self.as_ref().supports_multiple_choices()
+    }
+
+    fn get_data_choices(&self, response_text: &str, function_call: bool) -> Result<Vec<String>> {
+        self.as_ref().get_data_choices(response_text, function_call)
+    }
+
+    fn get_usage(&self, response_text: &str) -> Option<CompletionsUsage> {
+        self.as_ref().get_usage(response_text)
+    }
+
+    fn get_citations(&self, response_text: &str) -> Option<Vec<String>> {
+        self.as_ref().get_citations(response_text)
+    }
+
+    fn supports_logprobs(&self) -> bool {
+        self.as_ref().supports_logprobs()
+    }
+
+    fn get_logprobs(&self, response_text: &str) -> Option<Vec<TokenLogprob>> {
+        self.as_ref().get_logprobs(response_text)
+    }
+
+    fn get_finish_reason(&self, response_text: &str) -> Option<FinishReason> {
+        self.as_ref().get_finish_reason(response_text)
+    }
+
+    fn supports_grounding(&self) -> bool {
+        self.as_ref().supports_grounding()
+    }
+
+    fn get_grounding_citations(&self, response_text: &str) -> Option<Vec<GroundingCitation>> {
+        self.as_ref().get_grounding_citations(response_text)
+    }
+
+    fn supports_file_data(&self) -> bool {
+        self.as_ref().supports_file_data()
+    }
+
+    fn supports_pdf_documents(&self) -> bool {
+        self.as_ref().supports_pdf_documents()
+    }
+
+    fn supports_thinking(&self) -> bool {
+        self.as_ref().supports_thinking()
+    }
+
+    fn get_thinking(&self, response_text: &str) -> Option<String> {
+        self.as_ref().get_thinking(response_text)
+    }
+
+    fn sanitize_json_response(&self, json_response: &str) -> String {
+        self.as_ref().sanitize_json_response(json_response)
+    }
+
+    fn get_rate_limit(&self) -> RateLimit {
+        self.as_ref().get_rate_limit()
+    }
+
+    fn get_max_requests(&self) -> usize {
+        self.as_ref().get_max_requests()
+    }
+
+    fn get_default_temperature(&self) -> f32 {
+        self.as_ref().get_default_temperature()
+    }
+
+    fn get_temperature_range(&self) -> (f32, f32) {
+        self.as_ref().get_temperature_range()
+    }
+
+    fn get_normalized_temperature(&self, relative_temp: u32) -> f32 {
+        self.as_ref().get_normalized_temperature(relative_temp)
+    }
+
+    fn last_rate_limit_status(&self) -> Option<RateLimitStatus> {
+        self.as_ref().last_rate_limit_status()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use schemars::JsonSchema;
+    use serde::Deserialize;
+
+    use crate::domain::{CompletionsOptions, OutputStrictness};
+    use crate::llm_models::{
+        AnthropicModels, LLMModel, LLMModelEnum, MistralModels, MockModel, OpenAIModels,
+    };
+    use crate::Completions;
+
+    #[derive(Deserialize, JsonSchema, Debug)]
+    struct TestResponse {
+        answer: String,
+    }
+
+    #[test]
+    fn test_boxed_model_switches_provider_from_config() {
+        for (config, expected) in [
+            ("openai/gpt-4o", LLMModelEnum::OpenAI(OpenAIModels::Gpt4o)),
+            (
+                "mistral:mistral-small",
+                LLMModelEnum::Mistral(MistralModels::MistralSmall),
+            ),
+            (
+                "anthropic/claude-3-haiku-20240307",
+                LLMModelEnum::Anthropic(AnthropicModels::Claude3Haiku),
+            ),
+        ] {
+            let model = <Box<dyn LLMModel>>::try_from_str(config).unwrap();
+
+            assert_eq!(model.as_str(), expected.as_str());
+            assert_eq!(model.get_endpoint(), expected.get_endpoint());
+        }
+        assert!(<Box<dyn LLMModel>>::try_from_str("unknown/model").is_none());
+    }
+
+    #[test]
+    fn test_base_instructions_output_strictness() {
+        let model = OpenAIModels::Gpt4o;
+        let strict = model.get_base_instructions(Some(false), OutputStrictness::Strict);
+        let lenient = model.get_base_instructions(Some(false), OutputStrictness::Lenient);
+
+        assert_ne!(strict, lenient);
+        assert!(strict.contains("minified"));
+        assert!(lenient.contains("markdown code block"));
+        assert_eq!(
+            model.get_base_instructions(Some(false), OutputStrictness::default()),
+            model.get_base_instructions(Some(false), OutputStrictness::Standard)
+        );
+        // Function calling enforces the format so the strictness is not applied
+        assert_eq!(
+            model.get_base_instructions(Some(true), OutputStrictness::Strict),
+            model.get_base_instructions(Some(true), OutputStrictness::Lenient)
+        );
+
+        // The strictness is picked up from the request options
+        let options = CompletionsOptions {
+            output_strictness: OutputStrictness::Strict,
+            ..Default::default()
+        };
+        assert_eq!(model.get_body_base_instructions(false, &options), strict);
+    }
+
+    #[tokio::test]
+    async fn test_completions_with_boxed_model() {
+        let model: Box<dyn LLMModel> = Box::new(MockModel::new(r#"{"answer": "boxed"}"#));
+
+        let answer = Completions::new(model, "", None, None)
+            .get_answer::<TestResponse>("Say something")
+            .await
+            .unwrap();
+
+        assert_eq!(answer.answer, "boxed");
+    }
 }
@@ -0,0 +1,379 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::domain::{
+    ApiResponse, CompletionsOptions, CompletionsUsage, FinishReason, GroundingCitation,
+    LLMProvider, OutputStrictness, RateLimit, RateLimitStatus, TokenCost, TokenLogprob,
+    UnknownModelError,
+};
+use crate::http_client::HttpClient;
+use crate::llm_models::{
+    AnthropicModels, AwsBedrockModels, DeepSeekModels, GoogleModels, LLMModel, MistralModels,
+    OpenAIModels, PerplexityModels, TextStream,
+};
+
+/// Wrapper of the models of all supported providers.
+///
+/// It can be used with `Completions` when the provider is selected at runtime (e.g. from a config file)
+/// and can be parsed from strings in the `provider/model` (or `provider:model`) format, e.g. `openai/gpt-4o` or `google/gemini-1.5-pro`.
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq, Hash)]
+pub enum LLMModelEnum {
+    Anthropic(AnthropicModels),
+    AwsBedrock(AwsBedrockModels),
+    DeepSeek(DeepSeekModels),
+    Google(GoogleModels),
+    Mistral(MistralModels),
+    OpenAI(OpenAIModels),
+    Perplexity(PerplexityModels),
+}
+
+impl LLMModelEnum {
+    ///
+    /// This method returns the name of the provider used in the `provider/model` string representation
+    ///
+    pub fn provider(&self) -> &str {
+        match self {
+            LLMModelEnum::Anthropic(_) => "anthropic",
+            LLMModelEnum::AwsBedrock(_) => "aws",
+            LLMModelEnum::DeepSeek(_) => "deepseek",
+            LLMModelEnum::Google(_) => "google",
+            LLMModelEnum::Mistral(_) => "mistral",
+            LLMModelEnum::OpenAI(_) => "openai",
+            LLMModelEnum::Perplexity(_) => "perplexity",
+        }
+    }
+
+    // All trait methods are delegated to the model of the selected provider
+    fn inner(&self) -> &dyn LLMModel {
+        match self {
+            LLMModelEnum::Anthropic(model) => model,
+            LLMModelEnum::AwsBedrock(model) => model,
+            LLMModelEnum::DeepSeek(model) => model,
+            LLMModelEnum::Google(model) => model,
+            LLMModelEnum::Mistral(model) => model,
+            LLMModelEnum::OpenAI(model) => model,
+            LLMModelEnum::Perplexity(model) => model,
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl LLMModel for LLMModelEnum {
+    fn as_str(&self) -> &str {
+        self.inner().as_str()
+    }
+
+    //The `provider/model` string representation is parsed by `try_from_str`
+    fn id(&self) -> String {
+        self.to_string()
+    }
+
+    //Expects the `provider/model` or `provider:model` format
+    fn try_from_str(name: &str) -> Option<Self> {
+        let (provider, model) = name.split_once('/').or_else(|| name.split_once(':'))?;
+        match provider.to_lowercase().as_str() {
+            "anthropic" => AnthropicModels::try_from_str(model).map(LLMModelEnum::Anthropic),
+            "aws" | "aws-bedrock" => {
+                AwsBedrockModels::try_from_str(model).map(LLMModelEnum::AwsBedrock)
+            }
+            "deepseek" => DeepSeekModels::try_from_str(model).map(LLMModelEnum::DeepSeek),
+            "google" => GoogleModels::try_from_str(model).map(LLMModelEnum::Google),
+            "mistral" => MistralModels::try_from_str(model).map(LLMModelEnum::Mistral),
+            "openai" => OpenAIModels::try_from_str(model).map(LLMModelEnum::OpenAI),
+            "perplexity" => PerplexityModels::try_from_str(model).map(LLMModelEnum::Perplexity),
+            _ => None,
+        }
+    }
+
+    fn get_provider(&self) -> LLMProvider {
+        self.inner().get_provider()
+    }
+
+    fn default_max_tokens(&self) -> usize {
+        self.inner().default_max_tokens()
+    }
+
+    fn context_window_tokens(&self) -> usize {
+        self.inner().context_window_tokens()
+    }
+
+    fn cost_per_1k_tokens(&self) -> Option<TokenCost> {
+        self.inner().cost_per_1k_tokens()
+    }
+
+    fn get_endpoint(&self) -> String {
+        self.inner().get_endpoint()
+    }
+
+    fn get_base_instructions(
+        &self,
+        function_call: Option<bool>,
+        strictness: OutputStrictness,
+    ) -> String {
+        self.inner()
+            .get_base_instructions(function_call, strictness)
+    }
+
+    fn get_body_base_instructions(
+        &self,
+        function_call: bool,
+        options: &CompletionsOptions,
+    ) -> String {
+        self.inner()
+            .get_body_base_instructions(function_call, options)
+    }
+
+    fn function_call_default(&self) -> bool {
+        self.inner().function_call_default()
+    }
+
+    fn get_body(
+        &self,
+        instructions: &str,
+        json_schema: &Value,
+        function_call: bool,
+        max_tokens: &usize,
+        temperature: &f32,
+        options: &CompletionsOptions,
+    ) -> serde_json::Value {
+        self.inner().get_body(
+            instructions,
+            json_schema,
+            function_call,
+            max_tokens,
+            temperature,
+            options,
+        )
+    }
+
+    async fn call_api(
+        &self,
+        client: &HttpClient,
+        api_key: &str,
+        body: &serde_json::Value,
+        debug: bool,
+    ) -> Result<ApiResponse> {
+        self.inner().call_api(client, api_key, body, debug).await
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.inner().supports_streaming()
+    }
+
+    async fn stream_api(
+        &self,
+        client: &HttpClient,
+        api_key: &str,
+        body: &serde_json::Value,
+        debug: bool,
+    ) -> Result<TextStream> {
+        self.inner().stream_api(client, api_key, body, debug).await
+    }
+
+    async fn count_body_tokens_api(
+        &self,
+        client: &HttpClient,
+        api_key: &str,
+        body: &serde_json::Value,
+    ) -> Result<usize> {
+        self.inner()
+            .count_body_tokens_api(client, api_key, body)
+            .await
+    }
+
+    fn get_data(&self, response_text: &str, function_call: bool) -> Result<String> {
+        self.inner().get_data(response_text, function_call)
+    }
+
+    fn supports_multiple_choices(&self) -> bool {
+        self.inner().supports_multiple_choices()
+    }
+
+    fn get_data_choices(&self, response_text: &str, function_call: bool) -> Result<Vec<String>> {
+        self.inner().get_data_choices(response_text, function_call)
+    }
+
+    fn get_usage(&self, response_text: &str) -> Option<CompletionsUsage> {
+        self.inner().get_usage(response_text)
+    }
+
+    fn get_citations(&self, response_text: &str) -> Option<Vec<String>> {
+        self.inner().get_citations(response_text)
+    }
+
+    fn supports_logprobs(&self) -> bool {
+        self.inner().supports_logprobs()
+    }
+
+    fn get_logprobs(&self, response_text: &str) -> Option<Vec<TokenLogprob>> {
+        self.inner().get_logprobs(response_text)
+    }
+
+    fn get_finish_reason(&self, response_text: &str) -> Option<FinishReason> {
+        self.inner().get_finish_reason(response_text)
+    }
+
+    fn supports_grounding(&self) -> bool {
+        self.inner().supports_grounding()
+    }
+
+    fn get_grounding_citations(&self, response_text: &str) -> Option<Vec<GroundingCitation>> {
+        self.inner().get_grounding_citations(response_text)
+    }
+
+    fn supports_file_data(&self) -> bool {
+        self.inner().supports_file_data()
+    }
+
+    fn supports_pdf_documents(&self) -> bool {
+        self.inner().supports_pdf_documents()
+    }
+
+    fn supports_thinking(&self) -> bool {
+        self.inner().supports_thinking()
+    }
+
+    fn get_thinking(&self, response_text: &str) -> Option<String> {
+        self.inner().get_thinking(response_text)
+    }
+
+    fn sanitize_json_response(&self, json_response: &str) -> String {
+        self.inner().sanitize_json_response(json_response)
+    }
+
+    fn get_rate_limit(&self) -> RateLimit {
+        self.inner().get_rate_limit()
+    }
+
+    fn get_max_requests(&self) -> usize {
+        self.inner().get_max_requests()
+    }
+
+    fn get_default_temperature(&self) -> f32 {
+        self.inner().get_default_temperature()
+    }
+
+    fn get_temperature_range(&self) -> (f32, f32) {
+        self.inner().get_temperature_range()
+    }
+
+    fn get_normalized_temperature(&self, relative_temp: u32) -> f32 {
+        self.inner().get_normalized_temperature(relative_temp)
+    }
+
+    fn last_rate_limit_status(&self) -> Option<RateLimitStatus> {
+        self.inner().last_rate_limit_status()
+    }
+}
+
+impl fmt::Display for LLMModelEnum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.provider(), self.inner().id())
+    }
+}
+
+impl FromStr for LLMModelEnum {
+    type Err = UnknownModelError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        LLMModelEnum::try_from_str(name).ok_or_else(|| UnknownModelError::new(name))
+    }
+}
+
+macro_rules! impl_from_model {
+    ($model:ty, $variant:ident) => {
+        impl From<$model> for LLMModelEnum {
+            fn from(model: $model) -> Self {
+                LLMModelEnum::$variant(model)
+            }
+        }
+    };
+}
+
+impl_from_model!(AnthropicModels, Anthropic);
+impl_from_model!(AwsBedrockModels, AwsBedrock);
+impl_from_model!(DeepSeekModels, DeepSeek);
+impl_from_model!(GoogleModels, Google);
+impl_from_model!(MistralModels, Mistral);
+impl_from_model!(OpenAIModels, OpenAI);
+impl_from_model!(PerplexityModels, Perplexity);
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::domain::{CompletionsOptions, LLMProvider, UnknownModelError};
+    use crate::llm_models::{
+        AnthropicModels, GoogleModels, LLMModel, LLMModelEnum, MistralModels, OpenAIModels,
+    };
+
+    #[test]
+    fn test_parse_provider_and_model() {
+        assert_eq!(
+            "openai/gpt-4o".parse::<LLMModelEnum>(),
+            Ok(LLMModelEnum::OpenAI(OpenAIModels::Gpt4o))
+        );
+        assert_eq!(
+            "google/gemini-1.5-pro".parse::<LLMModelEnum>(),
+            Ok(LLMModelEnum::Google(GoogleModels::Gemini1_5Pro))
+        );
+        assert_eq!(
+            "Anthropic/claude-3-opus-20240229".parse::<LLMModelEnum>(),
+            Ok(LLMModelEnum::Anthropic(AnthropicModels::Claude3Opus))
+        );
+        assert_eq!(
+            "openai:ft:gpt-4o-mini:org::abc123".parse::<LLMModelEnum>(),
+            Ok(LLMModelEnum::OpenAI(OpenAIModels::Custom {
+                name: "ft:gpt-4o-mini:org::abc123".to_string()
+            }))
+        );
+        assert_eq!(
+            "gpt-4o".parse::<LLMModelEnum>(),
+            Err(UnknownModelError::new("gpt-4o"))
+        );
+        assert!("cohere/command-r".parse::<LLMModelEnum>().is_err());
+        assert!("google/gemini-unknown".parse::<LLMModelEnum>().is_err());
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let model = LLMModelEnum::from(MistralModels::MistralLarge);
+
+        assert_eq!(model.to_string(), "mistral/mistral-large-latest");
+        assert_eq!(model.to_string().parse::<LLMModelEnum>(), Ok(model));
+
+        let model = LLMModelEnum::from(GoogleModels::Gemini1_5FlashVertex);
+        assert_eq!(model.to_string(), "google/gemini-1.5-flash-vertex");
+        assert_eq!(model.to_string().parse::<LLMModelEnum>(), Ok(model));
+    }
+
+    #[test]
+    fn test_delegates_to_provider_model() {
+        let model = LLMModelEnum::from(AnthropicModels::Claude3_5Sonnet);
+        let options = CompletionsOptions::default();
+
+        let body = model.get_body("instructions", &json!({}), false, &1000, &0.5, &options);
+
+        assert_eq!(model.as_str(), "claude-3-5-sonnet-20240620");
+        assert_eq!(model.get_provider(), LLMProvider::Anthropic);
+        assert_eq!(
+            model.get_endpoint(),
+            AnthropicModels::Claude3_5Sonnet.get_endpoint()
+        );
+        assert_eq!(
+            body,
+            AnthropicModels::Claude3_5Sonnet.get_body(
+                "instructions",
+                &json!({}),
+                false,
+                &1000,
+                &0.5,
+                &options
+            )
+        );
+    }
+}
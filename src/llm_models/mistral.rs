@@ -4,12 +4,32 @@ use log::info;
 use reqwest::{header, Client};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::fmt;
+use std::str::FromStr;
 
-use crate::constants::MISTRAL_API_URL;
-use crate::domain::{MistralAPICompletionsResponse, RateLimit};
+use crate::constants::{
+    MISTRAL_API_URL, MISTRAL_LOCAL_URL, MISTRAL_MODELS_API_URL, MISTRAL_SAFE_PROMPT,
+    OPENAI_FUNCTION_INSTRUCTIONS,
+};
+use crate::domain::{
+    ApiResponse, CompletionsOptions, CredentialsError, FinishReason, LLMProvider,
+    MistralAPICompletionsMessage, MistralAPICompletionsResponse, ModelInfo, OutputStrictness,
+    RateLimit, UnknownModelError,
+};
+use crate::http_client::HttpClient;
+use crate::llm_models::llm_model::get_json_base_instructions;
 use crate::llm_models::LLMModel;
+use crate::utils::{
+    check_credentials, get_chat_history, get_schema_string, list_openai_compatible_models,
+    log_request, redact_secrets,
+};
 
-#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq)]
+/// Models of Mistral AI
+///
+/// All variants except `MistralLocal` target the hosted Mistral API (`MISTRAL_API_URL`), including the `open-*` models whose weights are also released.
+/// The hosted API adds platform features such as the safety prompt enabled with `MISTRAL_SAFE_PROMPT`.
+/// `MistralLocal` targets a local deployment of open weights (e.g. Ollama) at `MISTRAL_LOCAL_URL`, see `is_hosted`.
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq, Hash)]
 //Mistral docs: https://docs.mistral.ai/platform/endpoints
 pub enum MistralModels {
     MistralLarge,
@@ -21,6 +41,17 @@ pub enum MistralModels {
     MistralTiny,
     MistralSmall,
     MistralMedium,
+    // Custom models (e.g. fine-tuned or released after the crate was updated)
+    // Parsed from the `custom:<name>` representation. The token limit defaults to 32k if not set
+    Custom {
+        name: String,
+        max_tokens: Option<usize>,
+    },
+    // Open-weight models served locally under the given name, e.g. `mistral` in Ollama
+    // Parsed from the `local:<name>` representation
+    MistralLocal {
+        name: String,
+    },
 }
 
 #[async_trait(?Send)]
@@ -36,10 +67,30 @@ impl LLMModel for MistralModels {
             MistralModels::MistralTiny => "mistral-tiny",
             MistralModels::MistralSmall => "mistral-small",
             MistralModels::MistralMedium => "mistral-medium",
+            MistralModels::Custom { name, .. } | MistralModels::MistralLocal { name } => {
+                name.as_str()
+            }
+        }
+    }
+
+    //Custom and local models are prefixed so they can't be confused with typos in the names of known models
+    fn id(&self) -> String {
+        match self {
+            MistralModels::Custom { name, .. } => format!("custom:{}", name),
+            MistralModels::MistralLocal { name } => format!("local:{}", name),
+            _ => self.as_str().to_string(),
         }
     }
 
     fn try_from_str(name: &str) -> Option<Self> {
+        if let Some(name) = name.strip_prefix("custom:") {
+            return Some(MistralModels::custom(name));
+        }
+        if let Some(name) = name.strip_prefix("local:") {
+            return Some(MistralModels::MistralLocal {
+                name: name.to_string(),
+            });
+        }
         match name.to_lowercase().as_str() {
             "mistral-large-latest" => Some(MistralModels::MistralLarge),
             "open-mistral-nemo" => Some(MistralModels::MistralNemo),
@@ -54,6 +105,10 @@ impl LLMModel for MistralModels {
         }
     }
 
+    fn get_provider(&self) -> LLMProvider {
+        LLMProvider::Mistral
+    }
+
     fn default_max_tokens(&self) -> usize {
         match self {
             MistralModels::MistralLarge => 128_000,
@@ -65,11 +120,44 @@ impl LLMModel for MistralModels {
             MistralModels::MistralTiny => 32_000,
             MistralModels::MistralSmall => 32_000,
             MistralModels::MistralMedium => 32_000,
+            MistralModels::Custom { max_tokens, .. } => max_tokens.unwrap_or(32_000),
+            MistralModels::MistralLocal { .. } => 32_000,
         }
     }
 
     fn get_endpoint(&self) -> String {
-        MISTRAL_API_URL.to_string()
+        match self.is_hosted() {
+            true => MISTRAL_API_URL.to_string(),
+            false => MISTRAL_LOCAL_URL.to_string(),
+        }
+    }
+
+    fn get_base_instructions(
+        &self,
+        function_call: Option<bool>,
+        strictness: OutputStrictness,
+    ) -> String {
+        let function_call = function_call.unwrap_or_else(|| self.function_call_default());
+        match function_call {
+            true => OPENAI_FUNCTION_INSTRUCTIONS.to_string(),
+            false => get_json_base_instructions(strictness),
+        }
+    }
+
+    fn function_call_default(&self) -> bool {
+        //Mistral documentation: https://docs.mistral.ai/capabilities/function_calling/
+        match self {
+            MistralModels::MistralLarge
+            | MistralModels::MistralNemo
+            | MistralModels::Mixtral8x22B => true,
+            MistralModels::Mistral7B
+            | MistralModels::Mixtral8x7B
+            | MistralModels::MistralTiny
+            | MistralModels::MistralSmall
+            | MistralModels::MistralMedium
+            | MistralModels::Custom { .. }
+            | MistralModels::MistralLocal { .. } => false,
+        }
     }
 
     //This method prepares the body of the API call for different models
@@ -80,31 +168,60 @@ impl LLMModel for MistralModels {
         function_call: bool,
         max_tokens: &usize,
         temperature: &f32,
+        options: &CompletionsOptions,
     ) -> serde_json::Value {
         //Prepare the 'messages' part of the body
-        let base_instructions = self.get_base_instructions(Some(function_call));
+        let base_instructions = self.get_body_base_instructions(function_call, options);
         let system_message = json!({
             "role": "system",
             "content": base_instructions,
         });
-        let schema_string = serde_json::to_string(json_schema).unwrap_or_default();
-        let user_message = json!({
-            "role": "user",
-            "content": format!(
+        //The output schema is omitted for plain-text requests
+        //With function calling it is passed as the parameters of the tool instead of the prompt
+        let content = match get_schema_string(json_schema) {
+            Some(schema_string) if !function_call => format!(
                 "Output Json schema:\n
                 {schema_string}\n\n
                 {instructions}"
             ),
+            _ => instructions.to_string(),
+        };
+        let user_message = json!({
+            "role": "user",
+            "content": content,
         });
-        json!({
+        //Previous turns of the conversation go between the system message and the current instructions
+        let mut messages = vec![system_message];
+        messages.extend(get_chat_history(&options.get_history()));
+        messages.push(user_message);
+        let mut body = json!({
             "model": self.as_str(),
             "max_tokens": max_tokens,
             "temperature": temperature,
-            "messages": vec![
-                system_message,
-                user_message,
-            ],
-        })
+            "messages": messages,
+        });
+        //https://docs.mistral.ai/capabilities/function_calling/
+        if function_call {
+            let function = json!({
+                "name": "analyze_data",
+                "description": "Use this function to compute the answer based on input data, instructions and your language model. Output should be a fully formed JSON object.",
+                "parameters": json_schema,
+            });
+            body["tools"] = json!([{
+                "type": "function",
+                "function": function,
+            }]);
+            //This forces the model to call the only tool available
+            body["tool_choice"] = json!("any");
+        }
+        if let Some(n) = options.n {
+            body["n"] = json!(n);
+        }
+        //https://docs.mistral.ai/capabilities/guardrailing/
+        if *MISTRAL_SAFE_PROMPT && self.is_hosted() {
+            body["safe_prompt"] = json!(true);
+        }
+        body
     }
     /*
      * This function leverages Mistral API to perform any query as per the provided body.
@@ -113,24 +230,24 @@ impl LLMModel for MistralModels {
      */
     async fn call_api(
         &self,
+        client: &HttpClient,
         api_key: &str,
         body: &serde_json::Value,
         debug: bool,
-    ) -> Result<String> {
+    ) -> Result<ApiResponse> {
         //Get the API url
         let model_url = self.get_endpoint();
 
-        //Make the API call
-        let client = Client::new();
-
         //Send request
-        let response = client
+        let request = client
             .post(model_url)
             .header(header::CONTENT_TYPE, "application/json")
             .bearer_auth(api_key)
-            .json(&body)
-            .send()
-            .await?;
+            .json(&body);
+        if debug {
+            log_request("Mistral", &request, body);
+        }
+        let response = client.send(request).await?;
 
         let response_status = response.status();
         let response_text = response.text().await?;
@@ -138,15 +255,20 @@ impl LLMModel for MistralModels {
         if debug {
             info!(
                 "[debug] Mistral API response: [{}] {:#?}",
-                &response_status, &response_text
+                &response_status,
+                redact_secrets(&response_text)
             );
         }
 
-        Ok(response_text)
+        Ok(ApiResponse {
+            text: response_text,
+            status: Some(response_status.as_u16()),
+            rate_limit_status: None,
+        })
     }
 
     //This method attempts to convert the provided API response text into the expected struct and extracts the data from the response
-    fn get_data(&self, response_text: &str, _function_call: bool) -> Result<String> {
+    fn get_data(&self, response_text: &str, function_call: bool) -> Result<String> {
         //Convert API response to struct representing expected response format
         let completions_response: MistralAPICompletionsResponse =
             serde_json::from_str(response_text)?;
@@ -157,21 +279,383 @@ impl LLMModel for MistralModels {
             .iter()
             .filter_map(|choice| choice.message.as_ref())
             .find(|&message| message.role == Some("assistant".to_string()))
-            .and_then(|message| {
-                message
-                    .content
-                    .as_ref()
-                    .map(|content| self.sanitize_json_response(content))
-            })
+            .and_then(|message| self.get_message_data(message, function_call))
             .ok_or_else(|| anyhow!("Assistant role content not found"))
     }
 
+    fn supports_multiple_choices(&self) -> bool {
+        true
+    }
+
+    //With the `n` parameter each of the returned choices is a separate completion
+    fn get_data_choices(&self, response_text: &str, function_call: bool) -> Result<Vec<String>> {
+        let completions_response: MistralAPICompletionsResponse =
+            serde_json::from_str(response_text)?;
+
+        let choices: Vec<String> = completions_response
+            .choices
+            .iter()
+            .filter_map(|choice| choice.message.as_ref())
+            .filter(|&message| message.role == Some("assistant".to_string()))
+            .filter_map(|message| self.get_message_data(message, function_call))
+            .collect();
+
+        if choices.is_empty() {
+            return Err(anyhow!("Assistant role content not found"));
+        }
+        Ok(choices)
+    }
+
+    fn get_finish_reason(&self, response_text: &str) -> Option<FinishReason> {
+        serde_json::from_str::<MistralAPICompletionsResponse>(response_text)
+            .ok()?
+            .choices
+            .first()
+            .map(|choice| FinishReason::from_provider(&choice.finish_reason))
+    }
+
     //This function allows to check the rate limits for different models
     fn get_rate_limit(&self) -> RateLimit {
-        //Mistral documentation: https://docs.mistral.ai/platform/pricing#rate-limits
-        RateLimit {
-            tpm: 2_000_000,
-            rpm: 120, // 2 request per second
+        //Mistral documentation: https://docs.mistral.ai/deployment/laplateforme/tier/
+        //Limits are set per workspace tier and model. The values below are for the free tier, where all models are limited to 1 request per second
+        match self {
+            MistralModels::MistralLarge
+            | MistralModels::MistralMedium
+            | MistralModels::Mixtral8x22B => RateLimit {
+                tpm: 200_000,
+                rpm: 60,
+                rpd: None,
+            },
+            MistralModels::MistralNemo
+            | MistralModels::Mistral7B
+            | MistralModels::Mixtral8x7B
+            | MistralModels::MistralTiny
+            | MistralModels::MistralSmall
+            | MistralModels::Custom { .. } => RateLimit {
+                tpm: 500_000,
+                rpm: 60,
+                rpd: None,
+            },
+            //Local deployments are only limited by the hardware they run on
+            MistralModels::MistralLocal { .. } => RateLimit {
+                tpm: 1_000_000_000,
+                rpm: 1_000_000,
+                rpd: None,
+            },
+        }
+    }
+}
+
+impl MistralModels {
+    ///
+    /// This method returns a custom model with the provided name, e.g. a fine-tuned model.
+    /// The token limit defaults to 32k and can be overridden with the `max_tokens` field.
+    ///
+    pub fn custom(name: &str) -> Self {
+        MistralModels::Custom {
+            name: name.to_string(),
+            max_tokens: None,
+        }
+    }
+
+    ///
+    /// Returns the Mistral usage tier the rate limits returned by `get_rate_limit` correspond to, or `None` for local deployments which are not rate limited.
+    /// Higher tiers (and enterprise agreements) have larger limits which can be checked in the Mistral admin console.
+    ///
+    pub fn rate_limit_tier(&self) -> Option<&'static str> {
+        self.is_hosted().then_some("free")
+    }
+
+    ///
+    /// Returns true if the model is served by the hosted Mistral API, or false for local deployments of open weights (`MistralLocal`)
+    ///
+    pub fn is_hosted(&self) -> bool {
+        !matches!(self, MistralModels::MistralLocal { .. })
+    }
+
+    //For function calls the response is in the arguments of the tool call, and for regular calls in content
+    fn get_message_data(
+        &self,
+        message: &MistralAPICompletionsMessage,
+        function_call: bool,
+    ) -> Option<String> {
+        match function_call {
+            true => message
+                .tool_calls
+                .as_ref()
+                .and_then(|tool_calls| tool_calls.first())
+                .map(|tool_call| self.sanitize_json_response(&tool_call.function.arguments)),
+            false => message
+                .content
+                .as_ref()
+                .map(|content| self.sanitize_json_response(content)),
+        }
+    }
+}
+
+impl fmt::Display for MistralModels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.id())
+    }
+}
+
+impl FromStr for MistralModels {
+    type Err = UnknownModelError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        MistralModels::try_from_str(name).ok_or_else(|| UnknownModelError::new(name))
+    }
+}
+
+///
+/// This function lists models available to the provided API key using the Mistral Models API
+///
+pub async fn list_models(api_key: &str) -> Result<Vec<ModelInfo>> {
+    list_openai_compatible_models(&MISTRAL_MODELS_API_URL, api_key, "Mistral").await
+}
+
+///
+/// This function validates the API key by listing models, which does not consume any tokens
+///
+pub async fn validate_credentials(api_key: &str) -> Result<(), CredentialsError> {
+    let request = Client::new()
+        .get(&*MISTRAL_MODELS_API_URL)
+        .bearer_auth(api_key);
+    check_credentials(request, "Mistral").await
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::domain::{CompletionsOptions, FinishReason, Message};
+    use crate::llm_models::{LLMModel, MistralModels};
+    use crate::utils::parse_models_response;
+
+    #[test]
+    fn test_custom_model_in_body() {
+        let model =
+            MistralModels::try_from_str("custom:ft:open-mistral-7b:587a6b29:20240514:7e773925")
+                .unwrap();
+        assert_eq!(
+            model,
+            MistralModels::custom("ft:open-mistral-7b:587a6b29:20240514:7e773925")
+        );
+        assert_eq!(model.default_max_tokens(), 32_000);
+        // Unknown names are only accepted as custom models if prefixed
+        assert_eq!(MistralModels::try_from_str("mistral-larg"), None);
+
+        let codestral = MistralModels::Custom {
+            name: "codestral-latest".to_string(),
+            max_tokens: Some(256_000),
+        };
+        assert_eq!(codestral.default_max_tokens(), 256_000);
+
+        let schema = json!({"type": "object"});
+        let body = model.get_body(
+            "instructions",
+            &schema,
+            false,
+            &1000,
+            &0.0,
+            &CompletionsOptions::default(),
+        );
+
+        assert_eq!(
+            body["model"],
+            "ft:open-mistral-7b:587a6b29:20240514:7e773925"
+        );
+    }
+
+    #[test]
+    fn test_local_model() {
+        let model = MistralModels::MistralLocal {
+            name: "mistral".to_string(),
+        };
+        assert!(!model.is_hosted());
+        assert!(MistralModels::Mistral7B.is_hosted());
+        assert_eq!(
+            model.get_endpoint(),
+            "http://localhost:11434/v1/chat/completions"
+        );
+        assert_eq!(
+            MistralModels::Mistral7B.get_endpoint(),
+            "https://api.mistral.ai/v1/chat/completions"
+        );
+
+        let body = model.get_body(
+            "instructions",
+            &json!({"type": "object"}),
+            false,
+            &1000,
+            &0.0,
+            &CompletionsOptions::default(),
+        );
+        assert_eq!(body["model"], "mistral");
+        assert!(body.get("safe_prompt").is_none());
+    }
+
+    #[test]
+    fn test_from_str_round_trip() {
+        let models = vec![
+            MistralModels::MistralLarge,
+            MistralModels::MistralNemo,
+            MistralModels::Mistral7B,
+            MistralModels::Mixtral8x7B,
+            MistralModels::Mixtral8x22B,
+            MistralModels::MistralTiny,
+            MistralModels::MistralSmall,
+            MistralModels::MistralMedium,
+            MistralModels::custom("ft:open-mistral-7b:587a6b29:20240514:7e773925"),
+            MistralModels::MistralLocal {
+                name: "mistral".to_string(),
+            },
+        ];
+
+        for model in models {
+            assert_eq!(model.to_string().parse::<MistralModels>(), Ok(model));
         }
     }
+
+    #[test]
+    fn test_free_tier_rate_limit() {
+        let model = MistralModels::MistralSmall;
+        let rate_limit = model.get_rate_limit();
+
+        assert_eq!(model.rate_limit_tier(), Some("free"));
+        assert_eq!(rate_limit.rpm, 60);
+        assert_eq!(rate_limit.tpm, 500_000);
+        // 500k TPM / (32k context * 50%)
+        assert_eq!(model.get_max_requests(), 31);
+
+        // Larger models have lower token limits
+        assert_eq!(MistralModels::MistralLarge.get_rate_limit().tpm, 200_000);
+
+        // Local deployments are not rate limited
+        let model = MistralModels::MistralLocal {
+            name: "mistral".to_string(),
+        };
+        assert_eq!(model.rate_limit_tier(), None);
+        assert_eq!(model.get_rate_limit().rpm, 1_000_000);
+    }
+
+    #[test]
+    fn test_parse_models_response() {
+        let response = r#"{
+            "object": "list",
+            "data": [{
+                "id": "mistral-large-latest",
+                "object": "model",
+                "created": 1739960000,
+                "owned_by": "mistralai",
+                "max_context_length": 131072
+            }]
+        }"#;
+
+        let models = parse_models_response(response, "Mistral").unwrap();
+
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].id, "mistral-large-latest");
+        assert_eq!(models[0].created, 1739960000);
+        assert_eq!(models[0].owned_by, "mistralai");
+        assert_eq!(models[0].context_window, Some(131_072));
+    }
+
+    #[test]
+    fn test_history_messages() {
+        let options = CompletionsOptions {
+            history: vec![
+                Message::user("Hi"),
+                Message::assistant("{\"answer\": \"Hello\"}"),
+            ],
+            ..Default::default()
+        };
+
+        let body =
+            MistralModels::MistralSmall.get_body("Bye", &json!({}), false, &10, &0.5, &options);
+        let roles: Vec<&str> = body["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|message| message["role"].as_str().unwrap())
+            .collect();
+        assert_eq!(roles, vec!["system", "user", "assistant", "user"]);
+        assert_eq!(body["messages"][2]["content"], "{\"answer\": \"Hello\"}");
+    }
+
+    #[test]
+    fn test_function_call() {
+        let model = MistralModels::MistralLarge;
+        assert!(model.function_call_default());
+        assert!(!MistralModels::Mistral7B.function_call_default());
+
+        let schema = json!({
+            "type": "object",
+            "properties": {"answer": {"type": "string"}},
+            "required": ["answer"]
+        });
+        let body = model.get_body(
+            "Say hello",
+            &schema,
+            true,
+            &1000,
+            &0.0,
+            &CompletionsOptions::default(),
+        );
+
+        // The schema is passed as the parameters of the forced tool instead of the prompt
+        assert_eq!(body["tools"][0]["type"], "function");
+        assert_eq!(body["tools"][0]["function"]["name"], "analyze_data");
+        assert_eq!(body["tools"][0]["function"]["parameters"], schema);
+        assert_eq!(body["tool_choice"], "any");
+        assert_eq!(body["messages"][1]["content"], "Say hello");
+
+        let body = model.get_body(
+            "Say hello",
+            &schema,
+            false,
+            &1000,
+            &0.0,
+            &CompletionsOptions::default(),
+        );
+        assert!(body.get("tools").is_none());
+        assert!(body.get("tool_choice").is_none());
+
+        let response = r#"{
+            "id": "cmpl-e5cc70bb28c444948073e77776eb30ef",
+            "object": "chat.completion",
+            "created": 1702256327,
+            "model": "mistral-large-latest",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": "",
+                    "tool_calls": [{
+                        "id": "D681PevKs",
+                        "function": {
+                            "name": "analyze_data",
+                            "arguments": "{\"answer\": \"Hello\"}"
+                        }
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }],
+            "usage": {"prompt_tokens": 90, "completion_tokens": 10, "total_tokens": 100}
+        }"#;
+
+        assert_eq!(
+            model.get_data(response, true).unwrap(),
+            "{\"answer\": \"Hello\"}"
+        );
+        assert_eq!(
+            model.get_data_choices(response, true).unwrap(),
+            vec!["{\"answer\": \"Hello\"}"]
+        );
+        assert!(model.get_data(response, false).unwrap().is_empty());
+        assert_eq!(
+            model.get_finish_reason(response),
+            Some(FinishReason::ToolCalls)
+        );
+    }
 }
@@ -0,0 +1,471 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use futures::stream;
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::domain::{
+    ApiResponse, CompletionsOptions, CompletionsUsage, FinishReason, RateLimit, RateLimitStatus,
+    TokenCost,
+};
+use crate::http_client::HttpClient;
+use crate::llm_models::{LLMModel, TextStream};
+
+/// Test double of an LLM model that returns a canned response instead of calling an API.
+///
+/// It can be used with `Completions` to unit test code relying on the crate without API keys or token costs.
+/// Available with the `testing` feature.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct MockModel {
+    /// Raw text returned as the API response
+    pub response: String,
+    /// If `true` the API call returns an error
+    pub should_fail: bool,
+    /// Simulated latency of the API call
+    pub delay_ms: u64,
+    /// HTTP status code reported with the response, e.g. to simulate an error returned by the API
+    #[serde(default)]
+    pub status: Option<u16>,
+    /// Token prices of the model, e.g. to test budgets. Unknown by default
+    #[serde(default)]
+    pub prices: Option<TokenCost>,
+    /// Rate limit status reported with the response
+    #[serde(default)]
+    pub rate_limit_status: Option<RateLimitStatus>,
+    /// Responses returned for consecutive calls instead of `response`. The last one is returned for any further calls
+    #[serde(default)]
+    pub responses: Vec<MockResponse>,
+    /// Max tokens of the model, 128k by default
+    #[serde(default)]
+    pub max_tokens: Option<usize>,
+    /// Calls received by the model and its clones
+    #[serde(skip)]
+    pub calls: MockCalls,
+}
+
+/// Response of a single call of `MockModel`
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct MockResponse {
+    /// Raw text returned as the API response
+    pub text: String,
+    /// Reason the model stopped generating the response
+    #[serde(default)]
+    pub finish_reason: Option<FinishReason>,
+    /// Token usage reported with the response
+    #[serde(default)]
+    pub usage: Option<CompletionsUsage>,
+}
+
+impl MockResponse {
+    /// Constructor
+    pub fn new(text: &str) -> Self {
+        MockResponse {
+            text: text.to_string(),
+            ..Default::default()
+        }
+    }
+
+    ///
+    /// This method sets the reason the model stopped generating the response, e.g. `FinishReason::Length` to simulate a truncated response
+    ///
+    pub fn finish_reason(mut self, finish_reason: FinishReason) -> Self {
+        self.finish_reason = Some(finish_reason);
+        self
+    }
+
+    ///
+    /// This method sets the token usage reported with the response
+    ///
+    pub fn usage(mut self, usage: CompletionsUsage) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+}
+
+impl From<&str> for MockResponse {
+    fn from(text: &str) -> Self {
+        MockResponse::new(text)
+    }
+}
+
+/// API call received by `MockModel`
+#[derive(Debug, Clone)]
+pub struct MockCall {
+    pub api_key: String,
+    pub body: Value,
+    /// HTTP client the call was made with, e.g. the client shared by `LLMClient`
+    pub http_client: HttpClient,
+}
+
+/// Calls received by `MockModel`, shared between the clones of the model
+#[derive(Debug, Clone, Default)]
+pub struct MockCalls(Arc<Mutex<Vec<MockCall>>>);
+
+impl MockCalls {
+    ///Returns the number of calls received
+    pub fn len(&self) -> usize {
+        self.all().len()
+    }
+
+    ///Returns true if no calls were received
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    ///Returns the n-th call received
+    pub fn get(&self, index: usize) -> Option<MockCall> {
+        self.all().get(index).cloned()
+    }
+
+    ///Returns all calls received, in order
+    pub fn all(&self) -> Vec<MockCall> {
+        self.0.lock().map(|calls| calls.clone()).unwrap_or_default()
+    }
+
+    //Records the call and returns its index
+    fn record(&self, call: MockCall) -> usize {
+        let mut calls = self.0.lock().unwrap_or_else(|error| error.into_inner());
+        calls.push(call);
+        calls.len() - 1
+    }
+}
+
+//Calls of different models are never considered equal, as the records of clones are shared
+impl PartialEq for MockCalls {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl MockModel {
+    /// Constructor of a model returning the provided response
+    pub fn new(response: &str) -> Self {
+        MockModel {
+            response: response.to_string(),
+            ..Default::default()
+        }
+    }
+
+    ///
+    /// This method makes the API call of the model fail
+    ///
+    pub fn failing(mut self) -> Self {
+        self.should_fail = true;
+        self
+    }
+
+    ///
+    /// This method sets the simulated latency of the API call
+    ///
+    pub fn delay_ms(mut self, delay_ms: u64) -> Self {
+        self.delay_ms = delay_ms;
+        self
+    }
+
+    ///
+    /// This method sets the HTTP status code reported with the response
+    ///
+    pub fn status(mut self, status: u16) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    ///
+    /// This method sets the token prices of the model
+    ///
+    pub fn prices(mut self, prices: TokenCost) -> Self {
+        self.prices = Some(prices);
+        self
+    }
+
+    ///
+    /// This method sets the rate limit status reported with the response
+    ///
+    pub fn rate_limit_status(mut self, rate_limit_status: RateLimitStatus) -> Self {
+        self.rate_limit_status = Some(rate_limit_status);
+        self
+    }
+
+    ///
+    /// This method sets the responses returned for consecutive calls. The last one is returned for any further calls
+    ///
+    pub fn responses<R: Into<MockResponse>>(mut self, responses: Vec<R>) -> Self {
+        self.responses = responses.into_iter().map(Into::into).collect();
+        self
+    }
+
+    ///
+    /// This method sets the max tokens of the model
+    ///
+    pub fn max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    // Scripted responses are returned with their index so their metadata is found even if several of them have the same text
+    fn get_scripted_response(&self, response_text: &str) -> Option<&MockResponse> {
+        let envelope: ScriptedResponse = serde_json::from_str(response_text).ok()?;
+        self.responses.get(envelope.mock_response)
+    }
+
+    // Records the call and returns the response scripted for it
+    fn get_response(&self, client: &HttpClient, api_key: &str, body: &Value) -> String {
+        let index = self.calls.record(MockCall {
+            api_key: api_key.to_string(),
+            body: body.clone(),
+            http_client: client.clone(),
+        });
+        if self.responses.is_empty() {
+            return self.response.clone();
+        }
+        let index = index.min(self.responses.len() - 1);
+        serde_json::to_string(&ScriptedResponse {
+            mock_response: index,
+            text: self.responses[index].text.clone(),
+        })
+        .unwrap_or_default()
+    }
+
+    // Returns the text of the response, without the index of scripted responses
+    fn get_response_text<'a>(&'a self, response_text: &'a str) -> &'a str {
+        match self.get_scripted_response(response_text) {
+            Some(response) => &response.text,
+            None => response_text,
+        }
+    }
+}
+
+// Raw text returned for a scripted response
+#[derive(Deserialize, Serialize)]
+struct ScriptedResponse {
+    mock_response: usize,
+    text: String,
+}
+
+#[async_trait(?Send)]
+impl LLMModel for MockModel {
+    fn as_str(&self) -> &str {
+        "mock"
+    }
+
+    fn try_from_str(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "mock" => Some(MockModel::default()),
+            _ => None,
+        }
+    }
+
+    fn default_max_tokens(&self) -> usize {
+        self.max_tokens.unwrap_or(128_000)
+    }
+
+    fn get_endpoint(&self) -> String {
+        "mock://completions".to_string()
+    }
+
+    //The body is not sent anywhere but mirrors a typical request so it can be inspected in tests
+    fn get_body(
+        &self,
+        instructions: &str,
+        json_schema: &Value,
+        function_call: bool,
+        max_tokens: &usize,
+        temperature: &f32,
+        options: &CompletionsOptions,
+    ) -> serde_json::Value {
+        json!({
+            "model": self.as_str(),
+            "system": self.get_body_base_instructions(function_call, options),
+            "instructions": instructions,
+            "history": options.get_history(),
+            "schema": json_schema,
+            "max_tokens": max_tokens,
+            "temperature": temperature,
+        })
+    }
+
+    async fn call_api(
+        &self,
+        client: &HttpClient,
+        api_key: &str,
+        body: &serde_json::Value,
+        debug: bool,
+    ) -> Result<ApiResponse> {
+        let response = self.get_response(client, api_key, body);
+
+        if debug {
+            info!("[debug] Mock model request body: {:#?}", body);
+        }
+
+        if self.delay_ms > 0 {
+            tokio::time::sleep(Duration::from_millis(self.delay_ms)).await;
+        }
+
+        if self.should_fail {
+            return Err(anyhow!("[allms][Mock] Simulated API failure"));
+        }
+
+        Ok(ApiResponse {
+            text: response,
+            status: self.status,
+            rate_limit_status: self.rate_limit_status.clone(),
+        })
+    }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+
+    //The canned response is streamed word by word, with failures and latency simulated as for regular calls
+    async fn stream_api(
+        &self,
+        client: &HttpClient,
+        api_key: &str,
+        body: &serde_json::Value,
+        debug: bool,
+    ) -> Result<TextStream> {
+        let response = self.call_api(client, api_key, body, debug).await?;
+        let chunks: Vec<Result<String>> = self
+            .get_response_text(&response.text)
+            .split_inclusive(' ')
+            .map(|chunk| Ok(chunk.to_string()))
+            .collect();
+        Ok(Box::pin(stream::iter(chunks)))
+    }
+
+    //The canned response is treated as the data portion of the answer
+    fn get_data(&self, response_text: &str, _function_call: bool) -> Result<String> {
+        Ok(self.sanitize_json_response(self.get_response_text(response_text)))
+    }
+
+    fn get_finish_reason(&self, response_text: &str) -> Option<FinishReason> {
+        self.get_scripted_response(response_text)?
+            .finish_reason
+            .clone()
+    }
+
+    fn get_usage(&self, response_text: &str) -> Option<CompletionsUsage> {
+        self.get_scripted_response(response_text)?.usage.clone()
+    }
+
+    fn cost_per_1k_tokens(&self) -> Option<TokenCost> {
+        self.prices
+    }
+
+    fn get_rate_limit(&self) -> RateLimit {
+        RateLimit {
+            tpm: 1_000_000_000,
+            rpm: 1_000_000,
+            rpd: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use schemars::JsonSchema;
+    use serde::Deserialize;
+    use std::time::Instant;
+
+    use crate::domain::FinishReason;
+    use crate::llm_models::{MockModel, MockResponse};
+    use crate::Completions;
+
+    #[derive(Deserialize, JsonSchema, Debug)]
+    struct TestResponse {
+        answer: String,
+    }
+
+    #[tokio::test]
+    async fn test_mock_model_returns_canned_response() {
+        let model = MockModel::new(r#"{"answer": "mocked"}"#).delay_ms(20);
+
+        let start = Instant::now();
+        let answer = Completions::new(model, "", None, None)
+            .get_answer::<TestResponse>("Say something")
+            .await
+            .unwrap();
+
+        assert_eq!(answer.answer, "mocked");
+        assert!(start.elapsed().as_millis() >= 20);
+    }
+
+    #[tokio::test]
+    async fn test_mock_model_scripted_responses() {
+        let model = MockModel::default()
+            .responses(vec![r#"{"answer": "first"}"#, r#"{"answer": "second"}"#]);
+
+        let mut answers = Vec::new();
+        for key in ["key-1", "key-2", "key-3"] {
+            let answer = Completions::new(model.clone(), key, None, None)
+                .get_answer::<TestResponse>("Say something")
+                .await
+                .unwrap();
+            answers.push(answer.answer);
+        }
+
+        // The last response is repeated once the script is exhausted
+        assert_eq!(answers, vec!["first", "second", "second"]);
+        assert_eq!(model.calls.len(), 3);
+        assert_eq!(model.calls.get(2).unwrap().api_key, "key-3");
+        assert!(model.calls.get(0).unwrap().body["instructions"]
+            .to_string()
+            .contains("Say something"));
+    }
+
+    #[tokio::test]
+    async fn test_mock_model_scripted_metadata() {
+        // Responses with the same text are told apart by the call they were returned for
+        let model = MockModel::default().responses(vec![
+            MockResponse::new(r#"{"answer": "mocked"}"#).finish_reason(FinishReason::Length),
+            MockResponse::new(r#"{"answer": "mocked"}"#).finish_reason(FinishReason::Stop),
+        ]);
+
+        let mut finish_reasons = Vec::new();
+        for _ in 0..3 {
+            let response = Completions::new(model.clone(), "", None, None)
+                .get_response::<TestResponse>("Say something")
+                .await
+                .unwrap();
+            assert_eq!(response.data.answer, "mocked");
+            finish_reasons.push(response.finish_reason);
+        }
+
+        assert_eq!(
+            finish_reasons,
+            vec![
+                Some(FinishReason::Length),
+                Some(FinishReason::Stop),
+                Some(FinishReason::Stop)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mock_model_failure() {
+        let model = MockModel::new(r#"{"answer": "mocked"}"#).failing();
+
+        let answer = Completions::new(model, "", None, None)
+            .get_answer::<TestResponse>("Say something")
+            .await;
+
+        assert!(answer.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mock_model_error_status() {
+        let model = MockModel::new(r#"{"error": {"message": "Rate limit exceeded"}}"#).status(429);
+
+        let error = Completions::new(model, "", None, None)
+            .get_answer::<TestResponse>("Say something")
+            .await
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("HTTP status 429"));
+        assert!(error.contains("Rate limit exceeded"));
+    }
+}
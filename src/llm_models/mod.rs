@@ -2,17 +2,28 @@ pub mod anthropic;
 pub mod aws;
 pub mod deepseek;
 pub mod google;
+pub mod google_auth;
 pub mod llm_model;
+pub mod llm_model_enum;
 pub mod mistral;
+#[cfg(any(test, feature = "testing"))]
+pub mod mock;
 pub mod openai;
 pub mod perplexity;
+pub mod rate_limited;
 
 pub use anthropic::AnthropicModels;
 pub use aws::AwsBedrockModels;
 pub use deepseek::DeepSeekModels;
 pub use google::GoogleModels;
+pub use google_auth::{GeminiProVertexConfig, VertexAuthMethod};
 pub use llm_model::LLMModel;
 pub use llm_model::LLMModel as LLM;
+pub use llm_model::TextStream;
+pub use llm_model_enum::LLMModelEnum;
 pub use mistral::MistralModels;
+#[cfg(any(test, feature = "testing"))]
+pub use mock::{MockCall, MockCalls, MockModel, MockResponse};
 pub use openai::OpenAIModels;
 pub use perplexity::PerplexityModels;
+pub use rate_limited::RateLimitedModel;
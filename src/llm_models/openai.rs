@@ -1,18 +1,39 @@
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use lazy_static::lazy_static;
 use log::info;
 use reqwest::{header, Client};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
 
 use crate::{
-    constants::{OPENAI_API_URL, OPENAI_BASE_INSTRUCTIONS, OPENAI_FUNCTION_INSTRUCTIONS},
-    domain::{OpenAPIChatResponse, OpenAPICompletionsResponse, RateLimit},
-    llm_models::LLMModel,
-    utils::map_to_range,
+    constants::{OPENAI_API_URL, OPENAI_FUNCTION_INSTRUCTIONS},
+    domain::{
+        ApiResponse, CompletionsOptions, CredentialsError, FinishReason, GroundingCitation,
+        LLMProvider, ModelInfo, OpenAPIChatChoices, OpenAPIChatResponse,
+        OpenAPICompletionsResponse, OutputStrictness, RateLimit, RateLimitStatus, TokenCost,
+        TokenLogprob, UnknownModelError,
+    },
+    http_client::HttpClient,
+    llm_models::{llm_model::get_json_base_instructions, LLMModel, TextStream},
+    utils::{
+        check_credentials, get_chat_history, get_openai_headers, get_schema_string,
+        get_text_stream, list_openai_compatible_models, log_request, map_to_range,
+        parse_reset_duration, redact_secrets,
+    },
 };
 
-#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq)]
+lazy_static! {
+    // Rate limit status reported in the headers of the last successful API call of each model
+    static ref LAST_RATE_LIMIT_STATUS: Mutex<HashMap<String, RateLimitStatus>> =
+        Mutex::new(HashMap::new());
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq, Hash)]
 pub enum OpenAIModels {
     Gpt3_5Turbo,
     Gpt3_5Turbo0613,
@@ -25,6 +46,8 @@ pub enum OpenAIModels {
     Gpt4o,
     Gpt4o20240806,
     Gpt4oMini,
+    Gpt4oSearchPreview,
+    Gpt4oMiniSearchPreview,
     // Reasoning models
     O1Preview,
     O1Mini,
@@ -49,6 +72,8 @@ impl LLMModel for OpenAIModels {
             OpenAIModels::Gpt4o => "gpt-4o",
             OpenAIModels::Gpt4o20240806 => "gpt-4o-2024-08-06",
             OpenAIModels::Gpt4oMini => "gpt-4o-mini",
+            OpenAIModels::Gpt4oSearchPreview => "gpt-4o-search-preview",
+            OpenAIModels::Gpt4oMiniSearchPreview => "gpt-4o-mini-search-preview",
             OpenAIModels::O1Preview => "o1-preview",
             OpenAIModels::O1Mini => "o1-mini",
             OpenAIModels::O1 => "o1",
@@ -70,6 +95,8 @@ impl LLMModel for OpenAIModels {
             "gpt-4o" => Some(OpenAIModels::Gpt4o),
             "gpt-4o-2024-08-06" => Some(OpenAIModels::Gpt4o20240806),
             "gpt-4o-mini" => Some(OpenAIModels::Gpt4oMini),
+            "gpt-4o-search-preview" => Some(OpenAIModels::Gpt4oSearchPreview),
+            "gpt-4o-mini-search-preview" => Some(OpenAIModels::Gpt4oMiniSearchPreview),
             "o1-preview" => Some(OpenAIModels::O1Preview),
             "o1-mini" => Some(OpenAIModels::O1Mini),
             "o1" => Some(OpenAIModels::O1),
@@ -80,6 +107,10 @@ impl LLMModel for OpenAIModels {
         }
     }
 
+    fn get_provider(&self) -> LLMProvider {
+        LLMProvider::OpenAI
+    }
+
     fn default_max_tokens(&self) -> usize {
         //OpenAI documentation: https://platform.openai.com/docs/models/gpt-3-5
         //This is the max tokens allowed between prompt & response
@@ -95,6 +126,8 @@ impl LLMModel for OpenAIModels {
             OpenAIModels::Gpt4o => 128_000,
             OpenAIModels::Gpt4o20240806 => 128_000,
             OpenAIModels::Gpt4oMini => 128_000,
+            OpenAIModels::Gpt4oSearchPreview => 128_000,
+            OpenAIModels::Gpt4oMiniSearchPreview => 128_000,
             OpenAIModels::O1Preview => 128_000,
             OpenAIModels::O1Mini => 128_000,
             OpenAIModels::O1 => 200_000,
@@ -103,6 +136,81 @@ impl LLMModel for OpenAIModels {
         }
     }
 
+    fn cost_per_1k_tokens(&self) -> Option<TokenCost> {
+        //OpenAI documentation: https://openai.com/api/pricing
+        match self {
+            OpenAIModels::Gpt3_5Turbo => Some(TokenCost {
+                input: 0.0005,
+                output: 0.0015,
+            }),
+            OpenAIModels::Gpt3_5Turbo0613 => Some(TokenCost {
+                input: 0.0015,
+                output: 0.002,
+            }),
+            OpenAIModels::Gpt3_5Turbo16k => Some(TokenCost {
+                input: 0.003,
+                output: 0.004,
+            }),
+            OpenAIModels::Gpt4 => Some(TokenCost {
+                input: 0.03,
+                output: 0.06,
+            }),
+            OpenAIModels::Gpt4_32k => Some(TokenCost {
+                input: 0.06,
+                output: 0.12,
+            }),
+            OpenAIModels::TextDavinci003 => Some(TokenCost {
+                input: 0.02,
+                output: 0.02,
+            }),
+            OpenAIModels::Gpt4Turbo => Some(TokenCost {
+                input: 0.01,
+                output: 0.03,
+            }),
+            OpenAIModels::Gpt4TurboPreview => Some(TokenCost {
+                input: 0.01,
+                output: 0.03,
+            }),
+            OpenAIModels::Gpt4o => Some(TokenCost {
+                input: 0.0025,
+                output: 0.01,
+            }),
+            OpenAIModels::Gpt4o20240806 => Some(TokenCost {
+                input: 0.0025,
+                output: 0.01,
+            }),
+            OpenAIModels::Gpt4oMini => Some(TokenCost {
+                input: 0.00015,
+                output: 0.0006,
+            }),
+            OpenAIModels::Gpt4oSearchPreview => Some(TokenCost {
+                input: 0.0025,
+                output: 0.01,
+            }),
+            OpenAIModels::Gpt4oMiniSearchPreview => Some(TokenCost {
+                input: 0.00015,
+                output: 0.0006,
+            }),
+            OpenAIModels::O1Preview => Some(TokenCost {
+                input: 0.015,
+                output: 0.06,
+            }),
+            OpenAIModels::O1Mini => Some(TokenCost {
+                input: 0.0011,
+                output: 0.0044,
+            }),
+            OpenAIModels::O1 => Some(TokenCost {
+                input: 0.015,
+                output: 0.06,
+            }),
+            OpenAIModels::O3Mini => Some(TokenCost {
+                input: 0.0011,
+                output: 0.0044,
+            }),
+            OpenAIModels::Custom { .. } => None,
+        }
+    }
+
     fn get_endpoint(&self) -> String {
         //OpenAI documentation: https://platform.openai.com/docs/models/model-endpoint-compatibility
         match self {
@@ -115,6 +223,8 @@ impl LLMModel for OpenAIModels {
             | OpenAIModels::Gpt4o
             | OpenAIModels::Gpt4o20240806
             | OpenAIModels::Gpt4oMini
+            | OpenAIModels::Gpt4oSearchPreview
+            | OpenAIModels::Gpt4oMiniSearchPreview
             | OpenAIModels::Gpt4_32k
             | OpenAIModels::O1Preview
             | OpenAIModels::O1Mini
@@ -133,11 +243,15 @@ impl LLMModel for OpenAIModels {
         }
     }
 
-    fn get_base_instructions(&self, function_call: Option<bool>) -> String {
+    fn get_base_instructions(
+        &self,
+        function_call: Option<bool>,
+        strictness: OutputStrictness,
+    ) -> String {
         let function_call = function_call.unwrap_or_else(|| self.function_call_default());
         match function_call {
             true => OPENAI_FUNCTION_INSTRUCTIONS.to_string(),
-            false => OPENAI_BASE_INSTRUCTIONS.to_string(),
+            false => get_json_base_instructions(strictness),
         }
     }
 
@@ -150,7 +264,9 @@ impl LLMModel for OpenAIModels {
             | OpenAIModels::O1Preview
             | OpenAIModels::O1
             | OpenAIModels::O1Mini
-            | OpenAIModels::O3Mini => false,
+            | OpenAIModels::O3Mini
+            | OpenAIModels::Gpt4oSearchPreview
+            | OpenAIModels::Gpt4oMiniSearchPreview => false,
             OpenAIModels::Gpt3_5Turbo0613
             | OpenAIModels::Gpt3_5Turbo16k
             | OpenAIModels::Gpt4
@@ -171,24 +287,37 @@ impl LLMModel for OpenAIModels {
         function_call: bool,
         max_tokens: &usize,
         temperature: &f32,
+        options: &CompletionsOptions,
     ) -> serde_json::Value {
-        match self {
+        //The tokens left for the response are capped at the output limit of the model, which the API would otherwise reject
+        let max_completion_tokens = self
+            .max_output_tokens()
+            .map_or(*max_tokens, |limit| limit.min(*max_tokens));
+        let mut body = match self {
             //https://platform.openai.com/docs/api-reference/completions/create
             //For DaVinci model all text goes into the 'prompt' filed of the body
             OpenAIModels::TextDavinci003 => {
-                let schema_string = serde_json::to_string(json_schema).unwrap_or_default();
-                let base_instructions = self.get_base_instructions(Some(function_call));
-                json!({
+                //The output schema is omitted for plain-text requests
+                let schema_prompt = get_schema_string(json_schema)
+                    .map(|schema_string| format!("Output Json schema:\n\n{schema_string}\n\n"))
+                    .unwrap_or_default();
+                let base_instructions = self.get_body_base_instructions(function_call, options);
+                //Previous turns of the conversation are passed as a transcript ahead of the current instructions
+                let history: String = options
+                    .get_history()
+                    .iter()
+                    .map(|message| format!("{}: {}\n\n", message.role.as_str(), message.content))
+                    .collect();
+                let mut body = json!({
                     "model": self.as_str(),
-                    "max_tokens": max_tokens,
                     "temperature": temperature,
                     "prompt": format!(
                         "{base_instructions}\n\n
-                        Output Json schema:\n
-                        {schema_string}\n\n
-                        {instructions}",
+                        {schema_prompt}{history}{instructions}",
                     ),
-                })
+                });
+                body[self.max_tokens_field()] = json!(max_tokens);
+                body
             }
             OpenAIModels::Gpt3_5Turbo
             | OpenAIModels::Gpt3_5Turbo0613
@@ -201,13 +330,13 @@ impl LLMModel for OpenAIModels {
             | OpenAIModels::Gpt4oMini
             | OpenAIModels::Gpt4_32k
             | OpenAIModels::Custom { .. } => {
-                let base_instructions = self.get_base_instructions(Some(function_call));
+                let base_instructions = self.get_body_base_instructions(function_call, options);
                 let system_message = json!({
                     "role": "system",
                     "content": base_instructions,
                 });
 
-                match function_call {
+                let mut body = match function_call {
                     //If we choose to use function calling
                     //https://platform.openai.com/docs/guides/gpt/function-calling
                     true => {
@@ -215,6 +344,9 @@ impl LLMModel for OpenAIModels {
                             "role": "user",
                             "content": instructions,
                         });
+                        let mut messages = vec![system_message];
+                        messages.extend(get_chat_history(&options.get_history()));
+                        messages.push(user_message);
 
                         let function = json!({
                             "name": "analyze_data",
@@ -226,14 +358,10 @@ impl LLMModel for OpenAIModels {
                             "name": "analyze_data"
                         });
 
-                        //For ChatGPT we ignore max_tokens. It will default to 'inf'
                         json!({
                             "model": self.as_str(),
                             "temperature": temperature,
-                            "messages": vec![
-                                system_message,
-                                user_message,
-                            ],
+                            "messages": messages,
                             "functions": vec![
                                 function,
                             ],
@@ -243,27 +371,40 @@ impl LLMModel for OpenAIModels {
                     }
                     //https://platform.openai.com/docs/guides/chat/introduction
                     false => {
-                        let schema_string = serde_json::to_string(json_schema).unwrap_or_default();
-
-                        let user_message = json!({
-                            "role": "user",
-                            "content": format!(
+                        //The output schema is omitted for plain-text requests
+                        let content = match get_schema_string(json_schema) {
+                            Some(schema_string) => format!(
                                 "Output Json schema:\n
                                 {schema_string}\n\n
                                 {instructions}"
                             ),
+                            None => instructions.to_string(),
+                        };
+                        let user_message = json!({
+                            "role": "user",
+                            "content": content,
                         });
-                        //For ChatGPT we ignore max_tokens. It will default to 'inf'
+                        let mut messages = vec![system_message];
+                        messages.extend(get_chat_history(&options.get_history()));
+                        messages.push(user_message);
                         json!({
                             "model": self.as_str(),
                             "temperature": temperature,
-                            "messages": vec![
-                                system_message,
-                                user_message,
-                            ],
+                            "messages": messages,
                         })
                     }
+                };
+                body[self.max_tokens_field()] = json!(max_completion_tokens);
+
+                //Docs: https://platform.openai.com/docs/api-reference/chat/create#chat-create-logprobs
+                if let Some(top_logprobs) = options.logprobs {
+                    body["logprobs"] = json!(true);
+                    body["top_logprobs"] = json!(top_logprobs);
                 }
+                if let Some(n) = options.n {
+                    body["n"] = json!(n);
+                }
+                body
             }
             // Review https://platform.openai.com/docs/guides/reasoning for beta limitations:
             // - Message types: user and assistant messages only, system messages are not supported.
@@ -274,31 +415,81 @@ impl LLMModel for OpenAIModels {
             | OpenAIModels::O1Mini
             | OpenAIModels::O1
             | OpenAIModels::O3Mini => {
-                let base_instructions = self.get_base_instructions(Some(function_call));
+                let base_instructions = self.get_body_base_instructions(function_call, options);
                 let system_message = json!({
                     "role": "user",
                     "content": base_instructions,
                 });
 
-                let schema_string = serde_json::to_string(json_schema).unwrap_or_default();
-
+                //The output schema is omitted for plain-text requests
+                let content = match get_schema_string(json_schema) {
+                    Some(schema_string) => format!(
+                        "Output Json schema:\n
+                        {schema_string}\n\n
+                        {instructions}"
+                    ),
+                    None => instructions.to_string(),
+                };
                 let user_message = json!({
                     "role": "user",
-                    "content": format!(
+                    "content": content,
+                });
+                let mut messages = vec![system_message];
+                messages.extend(get_chat_history(&options.get_history()));
+                messages.push(user_message);
+                //For reasoning models the limit also covers the reasoning tokens
+                let mut body = json!({
+                    "model": self.as_str(),
+                    "messages": messages,
+                });
+                body[self.max_tokens_field()] = json!(max_completion_tokens);
+                body
+            }
+            // Review https://platform.openai.com/docs/guides/tools-web-search?api-mode=chat for limitations:
+            // - The models search the web before answering. The search is configured with `web_search_options`.
+            // - Tools and function calling are not supported.
+            // - Other: temperature, top_p, n and logprobs are not supported.
+            OpenAIModels::Gpt4oSearchPreview | OpenAIModels::Gpt4oMiniSearchPreview => {
+                let base_instructions = self.get_body_base_instructions(function_call, options);
+                let system_message = json!({
+                    "role": "system",
+                    "content": base_instructions,
+                });
+
+                //The output schema is omitted for plain-text requests
+                let content = match get_schema_string(json_schema) {
+                    Some(schema_string) => format!(
                         "Output Json schema:\n
                         {schema_string}\n\n
                         {instructions}"
                     ),
+                    None => instructions.to_string(),
+                };
+                let user_message = json!({
+                    "role": "user",
+                    "content": content,
                 });
-                json!({
+                let mut messages = vec![system_message];
+                messages.extend(get_chat_history(&options.get_history()));
+                messages.push(user_message);
+                let mut body = json!({
                     "model": self.as_str(),
-                    "messages": vec![
-                        system_message,
-                        user_message,
-                    ],
-                })
+                    "messages": messages,
+                });
+                body[self.max_tokens_field()] = json!(max_completion_tokens);
+                if options.web_search() {
+                    body["web_search_options"] = json!({});
+                }
+                body
             }
+        };
+
+        //Identifies the end-user of multi-tenant applications for OpenAI abuse monitoring
+        //Docs: https://platform.openai.com/docs/guides/safety-best-practices#end-user-ids
+        if let Some(end_user_id) = &options.end_user_id {
+            body["user"] = json!(end_user_id);
         }
+        body
     }
     /*
      * This function leverages OpenAI API to perform any query as per the provided body.
@@ -307,36 +498,110 @@ impl LLMModel for OpenAIModels {
      */
     async fn call_api(
         &self,
+        client: &HttpClient,
         api_key: &str,
         body: &serde_json::Value,
         debug: bool,
-    ) -> Result<String> {
+    ) -> Result<ApiResponse> {
         //Get the API url
         let model_url = self.get_endpoint();
 
-        //Make the API call
-        let client = Client::new();
-
-        //Send request
-        let response = client
-            .post(model_url)
-            .header(header::CONTENT_TYPE, "application/json")
-            .bearer_auth(api_key)
-            .json(&body)
-            .send()
-            .await?;
+        //Send request. Large bodies are compressed if configured as OpenAI accepts gzip-encoded requests
+        let request = client
+            .post_json(model_url, body)
+            .headers(get_openai_headers(client))
+            .bearer_auth(api_key);
+        if debug {
+            log_request("OpenAI", &request, body);
+        }
+        let response = client.send(request).await?;
 
         let response_status = response.status();
+
+        //The rate limit status reported in the headers is returned, and stored for `last_rate_limit_status`, so callers can implement their own throttling
+        let rate_limit_status = get_rate_limit_status(response.headers());
+        if let (true, Some(rate_limit_status)) = (response_status.is_success(), &rate_limit_status)
+        {
+            LAST_RATE_LIMIT_STATUS
+                .lock()
+                .unwrap_or_else(|error| error.into_inner())
+                .insert(self.as_str().to_string(), rate_limit_status.clone());
+        }
+
         let response_text = response.text().await?;
 
         if debug {
             info!(
                 "[debug] OpenAI API response: [{}] {:#?}",
-                &response_status, &response_text
+                &response_status,
+                redact_secrets(&response_text)
             );
         }
 
-        Ok(response_text)
+        Ok(ApiResponse {
+            text: response_text,
+            status: Some(response_status.as_u16()),
+            rate_limit_status,
+        })
+    }
+
+    //Streaming is supported by the Chat API but not by the legacy Completions API or the reasoning models
+    fn supports_streaming(&self) -> bool {
+        !matches!(
+            self,
+            OpenAIModels::TextDavinci003
+                | OpenAIModels::O1Preview
+                | OpenAIModels::O1Mini
+                | OpenAIModels::O1
+                | OpenAIModels::O3Mini
+        )
+    }
+
+    //Docs: https://platform.openai.com/docs/api-reference/chat/streaming
+    async fn stream_api(
+        &self,
+        client: &HttpClient,
+        api_key: &str,
+        body: &serde_json::Value,
+        debug: bool,
+    ) -> Result<TextStream> {
+        if !self.supports_streaming() {
+            return Err(anyhow!(
+                "[allms] Streaming is not supported by {}",
+                self.as_str()
+            ));
+        }
+
+        let mut body = body.clone();
+        body["stream"] = json!(true);
+
+        let response = client
+            .send(
+                client
+                    .post_json(self.get_endpoint(), &body)
+                    .headers(get_openai_headers(client))
+                    .bearer_auth(api_key),
+            )
+            .await?;
+
+        //Errors are returned as a regular Json response
+        let response_status = response.status();
+        if !response_status.is_success() {
+            let response_text = response.text().await?;
+            return Err(anyhow!(
+                "[allms][OpenAI][{}] Response body: {:#?}",
+                response_status,
+                redact_secrets(&response_text)
+            ));
+        }
+
+        let mut buffer = OpenAIStreamBuffer::default();
+        Ok(get_text_stream(response, move |chunk| {
+            if debug {
+                info!("[allms][OpenAI] Received response chunk: {:?}", chunk);
+            }
+            buffer.push(chunk)
+        }))
     }
 
     //This method attempts to convert the provided API response text into the expected struct and extracts the data from the response
@@ -366,6 +631,8 @@ impl LLMModel for OpenAIModels {
             | OpenAIModels::Gpt4o
             | OpenAIModels::Gpt4o20240806
             | OpenAIModels::Gpt4oMini
+            | OpenAIModels::Gpt4oSearchPreview
+            | OpenAIModels::Gpt4oMiniSearchPreview
             | OpenAIModels::Gpt4_32k
             | OpenAIModels::O1Preview
             | OpenAIModels::O1Mini
@@ -379,18 +646,7 @@ impl LLMModel for OpenAIModels {
                 match chat_response.choices {
                     Some(choices) => Ok(choices
                         .into_iter()
-                        .filter_map(|item| {
-                            //For function_call the response is in arguments, and for regular call in content
-                            match function_call {
-                                true => item.message.function_call.map(|function_call| {
-                                    self.sanitize_json_response(&function_call.arguments)
-                                }),
-                                false => item
-                                    .message
-                                    .content
-                                    .map(|content| self.sanitize_json_response(&content)),
-                            }
-                        })
+                        .filter_map(|item| self.get_choice_data(item, function_call))
                         .collect()),
                     None => Err(anyhow!("Unable to retrieve response from OpenAI Chat API")),
                 }
@@ -398,6 +654,38 @@ impl LLMModel for OpenAIModels {
         }
     }
 
+    //Multiple completions are supported by the Chat API. The reasoning and search models only return a single completion
+    fn supports_multiple_choices(&self) -> bool {
+        !matches!(
+            self,
+            OpenAIModels::TextDavinci003
+                | OpenAIModels::O1Preview
+                | OpenAIModels::O1Mini
+                | OpenAIModels::O1
+                | OpenAIModels::O3Mini
+                | OpenAIModels::Gpt4oSearchPreview
+                | OpenAIModels::Gpt4oMiniSearchPreview
+        )
+    }
+
+    //With the `n` parameter each of the returned choices is a separate completion
+    fn get_data_choices(&self, response_text: &str, function_call: bool) -> Result<Vec<String>> {
+        if !self.supports_multiple_choices() {
+            return self
+                .get_data(response_text, function_call)
+                .map(|data| vec![data]);
+        }
+
+        let chat_response: OpenAPIChatResponse = serde_json::from_str(response_text)?;
+        match chat_response.choices {
+            Some(choices) => Ok(choices
+                .into_iter()
+                .filter_map(|item| self.get_choice_data(item, function_call))
+                .collect()),
+            None => Err(anyhow!("Unable to retrieve response from OpenAI Chat API")),
+        }
+    }
+
     /// This function allows to check the rate limits for different models
     /// Rate limit for `Custom` model is assumed based on `GPT-4o` limits
     fn get_rate_limit(&self) -> RateLimit {
@@ -407,66 +695,96 @@ impl LLMModel for OpenAIModels {
             OpenAIModels::Gpt3_5Turbo => RateLimit {
                 tpm: 2_000_000,
                 rpm: 10_000,
+                rpd: None,
             },
             OpenAIModels::Gpt3_5Turbo0613 => RateLimit {
                 tpm: 2_000_000,
                 rpm: 10_000,
+                rpd: None,
             },
             OpenAIModels::Gpt3_5Turbo16k => RateLimit {
                 tpm: 2_000_000,
                 rpm: 10_000,
+                rpd: None,
             },
             OpenAIModels::Gpt4 => RateLimit {
                 tpm: 300_000,
                 rpm: 10_000,
+                rpd: None,
             },
             OpenAIModels::Gpt4Turbo => RateLimit {
                 tpm: 2_000_000,
                 rpm: 10_000,
+                rpd: None,
             },
             OpenAIModels::Gpt4TurboPreview => RateLimit {
                 tpm: 2_000_000,
                 rpm: 10_000,
+                rpd: None,
             },
             OpenAIModels::Gpt4_32k => RateLimit {
                 tpm: 300_000,
                 rpm: 10_000,
+                rpd: None,
             },
             OpenAIModels::Gpt4o | OpenAIModels::Custom { .. } => RateLimit {
                 tpm: 2_000_000,
                 rpm: 10_000,
+                rpd: None,
             },
             OpenAIModels::Gpt4o20240806 => RateLimit {
                 tpm: 2_000_000,
                 rpm: 10_000,
+                rpd: None,
             },
             OpenAIModels::Gpt4oMini => RateLimit {
                 tpm: 1_000_000,
                 rpm: 10_000,
+                rpd: None,
+            },
+            OpenAIModels::Gpt4oSearchPreview => RateLimit {
+                tpm: 3_000_000,
+                rpm: 1_000,
+                rpd: None,
+            },
+            OpenAIModels::Gpt4oMiniSearchPreview => RateLimit {
+                tpm: 3_000_000,
+                rpm: 1_000,
+                rpd: None,
             },
             OpenAIModels::O1Preview => RateLimit {
                 tpm: 30_000_000,
                 rpm: 10_000,
+                rpd: None,
             },
             OpenAIModels::O1Mini => RateLimit {
                 tpm: 150_000_000,
                 rpm: 30_000,
+                rpd: None,
             },
             OpenAIModels::O1 => RateLimit {
                 tpm: 30_000_000,
                 rpm: 1_000,
+                rpd: None,
             },
             OpenAIModels::O3Mini => RateLimit {
                 tpm: 150_000_000,
                 rpm: 30_000,
+                rpd: None,
             },
             OpenAIModels::TextDavinci003 => RateLimit {
                 tpm: 250_000,
                 rpm: 3_000,
+                rpd: None,
             },
         }
     }
 
+    fn get_temperature_range(&self) -> (f32, f32) {
+        // Temperature range documentation: https://platform.openai.com/docs/api-reference/chat/create
+        (0f32, 2f32)
+    }
+
     // Accepts a [0-100] percentage range and returns the target temperature based on model ranges
     fn get_normalized_temperature(&self, relative_temp: u32) -> f32 {
         // Temperature range documentation: https://platform.openai.com/docs/api-reference/chat/create
@@ -474,9 +792,147 @@ impl LLMModel for OpenAIModels {
         let max = 2u32;
         map_to_range(min, max, relative_temp)
     }
+
+    //Log probabilities are supported by the Chat API but not by the legacy Completions API, the reasoning or the search models
+    fn supports_logprobs(&self) -> bool {
+        !matches!(
+            self,
+            OpenAIModels::TextDavinci003
+                | OpenAIModels::O1Preview
+                | OpenAIModels::O1Mini
+                | OpenAIModels::O1
+                | OpenAIModels::O3Mini
+                | OpenAIModels::Gpt4oSearchPreview
+                | OpenAIModels::Gpt4oMiniSearchPreview
+        )
+    }
+
+    //Web search is built into the search models of the Chat API
+    fn supports_grounding(&self) -> bool {
+        matches!(
+            self,
+            OpenAIModels::Gpt4oSearchPreview | OpenAIModels::Gpt4oMiniSearchPreview
+        )
+    }
+
+    //The sources are reported as `url_citation` annotations of the message
+    fn get_grounding_citations(&self, response_text: &str) -> Option<Vec<GroundingCitation>> {
+        if !self.supports_grounding() {
+            return None;
+        }
+        let annotations = serde_json::from_str::<OpenAPIChatResponse>(response_text)
+            .ok()?
+            .choices?
+            .into_iter()
+            .next()?
+            .message
+            .annotations?;
+        Some(
+            annotations
+                .into_iter()
+                .filter_map(|annotation| annotation.url_citation)
+                .map(|citation| GroundingCitation {
+                    uri: citation.url,
+                    title: citation.title,
+                })
+                .collect(),
+        )
+    }
+
+    fn get_logprobs(&self, response_text: &str) -> Option<Vec<TokenLogprob>> {
+        if !self.supports_logprobs() {
+            return None;
+        }
+        serde_json::from_str::<OpenAPIChatResponse>(response_text)
+            .ok()?
+            .choices?
+            .into_iter()
+            .next()?
+            .logprobs?
+            .content
+    }
+
+    fn get_finish_reason(&self, response_text: &str) -> Option<FinishReason> {
+        let finish_reason = match self {
+            OpenAIModels::TextDavinci003 => {
+                serde_json::from_str::<OpenAPICompletionsResponse>(response_text)
+                    .ok()?
+                    .choices?
+                    .into_iter()
+                    .next()?
+                    .finish_reason
+            }
+            _ => {
+                serde_json::from_str::<OpenAPIChatResponse>(response_text)
+                    .ok()?
+                    .choices?
+                    .into_iter()
+                    .next()?
+                    .finish_reason
+            }
+        };
+        finish_reason.as_deref().map(FinishReason::from_provider)
+    }
+
+    fn last_rate_limit_status(&self) -> Option<RateLimitStatus> {
+        LAST_RATE_LIMIT_STATUS
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+            .get(self.as_str())
+            .cloned()
+    }
 }
 
 impl OpenAIModels {
+    //For function_call the response is in arguments, and for regular call in content
+    fn get_choice_data(&self, choice: OpenAPIChatChoices, function_call: bool) -> Option<String> {
+        match function_call {
+            true => choice
+                .message
+                .function_call
+                .map(|function_call| self.sanitize_json_response(&function_call.arguments)),
+            false => choice
+                .message
+                .content
+                .map(|content| self.sanitize_json_response(&content)),
+        }
+    }
+
+    // This function returns the name of the field limiting the tokens of the response
+    // The Chat API deprecated `max_tokens` in favor of `max_completion_tokens`, which is the only one accepted by the o-series models
+    // Docs: https://platform.openai.com/docs/api-reference/chat/create#chat-create-max_completion_tokens
+    pub fn max_tokens_field(&self) -> &'static str {
+        match self {
+            //The legacy Completions API only accepts `max_tokens`
+            OpenAIModels::TextDavinci003 => "max_tokens",
+            _ => "max_completion_tokens",
+        }
+    }
+
+    // This function returns the max number of tokens the model can generate in a single response, if lower than its context window
+    // Docs: https://platform.openai.com/docs/models
+    pub fn max_output_tokens(&self) -> Option<usize> {
+        match self {
+            OpenAIModels::Gpt3_5Turbo
+            | OpenAIModels::Gpt3_5Turbo16k
+            | OpenAIModels::Gpt4Turbo
+            | OpenAIModels::Gpt4TurboPreview => Some(4_096),
+            OpenAIModels::Gpt4o
+            | OpenAIModels::Gpt4o20240806
+            | OpenAIModels::Gpt4oMini
+            | OpenAIModels::Gpt4oSearchPreview
+            | OpenAIModels::Gpt4oMiniSearchPreview => Some(16_384),
+            OpenAIModels::O1Preview => Some(32_768),
+            OpenAIModels::O1Mini => Some(65_536),
+            OpenAIModels::O1 | OpenAIModels::O3Mini => Some(100_000),
+            OpenAIModels::Gpt3_5Turbo0613
+            | OpenAIModels::Gpt4
+            | OpenAIModels::Gpt4_32k
+            | OpenAIModels::TextDavinci003
+            | OpenAIModels::Custom { .. } => None,
+        }
+    }
+
     // This function checks if a model supports tool use in Assistants API (e.g. file_search)
     pub fn tools_support(&self) -> bool {
         matches!(
@@ -504,7 +960,7 @@ impl OpenAIModels {
     }
 
     // This function checks if a model supports use in Assistants API
-    // Reasoning models are NOT currently supported
+    // Reasoning and search models are NOT currently supported
     pub fn assistants_support(&self) -> bool {
         !matches!(
             self,
@@ -512,15 +968,137 @@ impl OpenAIModels {
                 | OpenAIModels::O1Mini
                 | OpenAIModels::O1
                 | OpenAIModels::O3Mini
+                | OpenAIModels::Gpt4oSearchPreview
+                | OpenAIModels::Gpt4oMiniSearchPreview
         )
     }
 }
 
+//Incremental parser of the streamed Chat API response
+//Server-sent events are processed line by line and the content deltas of the first choice are returned
+#[derive(Debug, Default)]
+struct OpenAIStreamBuffer {
+    buffer: Vec<u8>,
+}
+
+impl OpenAIStreamBuffer {
+    const SSE_DATA_PREFIX: &'static str = "data:";
+    const SSE_DONE: &'static str = "[DONE]";
+
+    //Adds a chunk to the buffer and returns the content deltas of the events completed by it
+    fn push(&mut self, chunk: &[u8]) -> Result<Vec<String>> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut deltas = Vec::new();
+        while let Some(position) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=position).collect();
+            let line = String::from_utf8_lossy(&line);
+            let data = match line.trim().strip_prefix(Self::SSE_DATA_PREFIX) {
+                Some(data) => data.trim(),
+                None => continue,
+            };
+            if data == Self::SSE_DONE {
+                continue;
+            }
+
+            let event: Value = serde_json::from_str(data)?;
+            if let Some(error) = event.get("error") {
+                return Err(anyhow!("[allms][OpenAI] Streaming error: {}", error));
+            }
+            if let Some(delta) = event["choices"][0]["delta"]["content"].as_str() {
+                deltas.push(delta.to_string());
+            }
+        }
+        Ok(deltas)
+    }
+}
+
+impl fmt::Display for OpenAIModels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for OpenAIModels {
+    type Err = UnknownModelError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        OpenAIModels::try_from_str(name).ok_or_else(|| UnknownModelError::new(name))
+    }
+}
+
+//This function parses the rate limit headers included by OpenAI in each response
+//Docs: https://platform.openai.com/docs/guides/rate-limits#rate-limits-in-headers
+fn get_rate_limit_status(headers: &header::HeaderMap) -> Option<RateLimitStatus> {
+    let get_header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+    };
+    let get_number = |name: &str| get_header(name).and_then(|value| value.parse().ok());
+    let get_duration = |name: &str| get_header(name).and_then(parse_reset_duration);
+
+    let rate_limit_status = RateLimitStatus {
+        limit_requests: get_number("x-ratelimit-limit-requests"),
+        remaining_requests: get_number("x-ratelimit-remaining-requests"),
+        reset_requests: get_duration("x-ratelimit-reset-requests"),
+        limit_tokens: get_number("x-ratelimit-limit-tokens"),
+        remaining_tokens: get_number("x-ratelimit-remaining-tokens"),
+        reset_tokens: get_duration("x-ratelimit-reset-tokens"),
+    };
+
+    //Headers are not returned e.g. by some OpenAI-compatible proxies
+    match rate_limit_status == RateLimitStatus::default() {
+        true => None,
+        false => Some(rate_limit_status),
+    }
+}
+
+///
+/// This function lists models available to the provided API key using the OpenAI Models API
+///
+pub async fn list_models(api_key: &str) -> Result<Vec<ModelInfo>> {
+    let models_url = format!(
+        "{OPENAI_API_URL}/v1/models",
+        OPENAI_API_URL = *OPENAI_API_URL
+    );
+    list_openai_compatible_models(&models_url, api_key, "OpenAI").await
+}
+
+///
+/// This function validates the API key by listing models, which does not consume any tokens
+///
+pub async fn validate_credentials(api_key: &str) -> Result<(), CredentialsError> {
+    let models_url = format!(
+        "{OPENAI_API_URL}/v1/models",
+        OPENAI_API_URL = *OPENAI_API_URL
+    );
+    let request = Client::new()
+        .get(models_url)
+        .headers(get_openai_headers(&HttpClient::default()))
+        .bearer_auth(api_key);
+    check_credentials(request, "OpenAI").await
+}
+
 #[cfg(test)]
 mod tests {
+    use reqwest::header::{HeaderMap, HeaderValue};
+    use reqwest::Client;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use crate::domain::{
+        CompletionsOptions, FinishReason, GroundingCitation, GroundingTool, Message,
+    };
     use crate::llm_models::llm_model::LLMModel;
     use crate::llm_models::OpenAIModels;
 
+    use super::{get_rate_limit_status, OpenAIStreamBuffer};
+
     // Tests for calculating max requests per model
     #[test]
     fn test_gpt3_5turbo_max_requests() {
@@ -630,4 +1208,508 @@ mod tests {
             })
         );
     }
+
+    #[test]
+    fn test_function_call_omits_json_instructions() {
+        let model = OpenAIModels::Gpt4o;
+        let schema = json!({"type": "object", "properties": {"answer": {"type": "string"}}});
+
+        // The output format is enforced by the function definition
+        let body = model.get_body(
+            "instructions",
+            &schema,
+            true,
+            &1000,
+            &0.0,
+            &CompletionsOptions::default(),
+        );
+        let system_prompt = body["messages"][0]["content"].as_str().unwrap();
+        assert!(!system_prompt.contains("Json"));
+        assert_eq!(body["functions"][0]["parameters"], schema);
+
+        // Without function calling the model is instructed to respond with Json
+        let body = model.get_body(
+            "instructions",
+            &schema,
+            false,
+            &1000,
+            &0.0,
+            &CompletionsOptions::default(),
+        );
+        let system_prompt = body["messages"][0]["content"].as_str().unwrap();
+        assert!(system_prompt.contains("Respond ONLY with properly formatted Json object"));
+    }
+
+    #[test]
+    fn test_web_search() {
+        let options = CompletionsOptions {
+            grounding: Some(GroundingTool {
+                web_search: true,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let model = OpenAIModels::Gpt4oSearchPreview;
+        assert!(model.supports_grounding());
+        assert!(!OpenAIModels::Gpt4o.supports_grounding());
+
+        let body = model.get_body("instructions", &json!({}), false, &1000, &0.0, &options);
+        assert_eq!(body["web_search_options"], json!({}));
+        assert!(body.get("temperature").is_none());
+        let body = model.get_body(
+            "instructions",
+            &json!({}),
+            false,
+            &1000,
+            &0.0,
+            &CompletionsOptions::default(),
+        );
+        assert!(body.get("web_search_options").is_none());
+
+        let response = json!({
+            "choices": [{
+                "index": 0,
+                "finish_reason": "stop",
+                "message": {
+                    "role": "assistant",
+                    "content": "{\"answer\": \"Real Madrid\"}",
+                    "annotations": [{
+                        "type": "url_citation",
+                        "url_citation": {
+                            "start_index": 0,
+                            "end_index": 10,
+                            "url": "https://example.com/final",
+                            "title": "Final"
+                        }
+                    }]
+                }
+            }]
+        })
+        .to_string();
+        assert_eq!(
+            model.get_data(&response, false).unwrap(),
+            "{\"answer\": \"Real Madrid\"}"
+        );
+        assert_eq!(
+            model.get_grounding_citations(&response),
+            Some(vec![GroundingCitation {
+                uri: "https://example.com/final".to_string(),
+                title: Some("Final".to_string()),
+            }])
+        );
+    }
+
+    #[test]
+    fn test_end_user_id() {
+        let options = CompletionsOptions {
+            end_user_id: Some("user-123".to_string()),
+            ..Default::default()
+        };
+
+        for model in [
+            OpenAIModels::Gpt4o,
+            OpenAIModels::O1,
+            OpenAIModels::TextDavinci003,
+        ] {
+            let body = model.get_body("instructions", &json!({}), false, &1000, &0.0, &options);
+            assert_eq!(body["user"], "user-123");
+        }
+
+        let body = OpenAIModels::Gpt4o.get_body(
+            "instructions",
+            &json!({}),
+            false,
+            &1000,
+            &0.0,
+            &CompletionsOptions::default(),
+        );
+        assert!(body.get("user").is_none());
+    }
+
+    #[test]
+    fn test_max_tokens_field() {
+        let options = CompletionsOptions::default();
+
+        let body = OpenAIModels::TextDavinci003.get_body(
+            "instructions",
+            &json!({}),
+            false,
+            &1000,
+            &0.0,
+            &options,
+        );
+        assert_eq!(
+            OpenAIModels::TextDavinci003.max_tokens_field(),
+            "max_tokens"
+        );
+        assert_eq!(body["max_tokens"], 1000);
+
+        // The Chat API receives `max_completion_tokens`, the deprecated `max_tokens` is rejected by the o-series
+        for model in [
+            OpenAIModels::Gpt3_5Turbo,
+            OpenAIModels::Gpt4,
+            OpenAIModels::Gpt4o,
+            OpenAIModels::Gpt4oMini,
+            OpenAIModels::Gpt4oSearchPreview,
+            OpenAIModels::O1Preview,
+            OpenAIModels::O1Mini,
+            OpenAIModels::O1,
+            OpenAIModels::O3Mini,
+            OpenAIModels::try_from_str("ft:gpt-4o-mini:org::abc123").unwrap(),
+        ] {
+            assert_eq!(model.max_tokens_field(), "max_completion_tokens");
+            for function_call in [false, true] {
+                let body = model.get_body(
+                    "instructions",
+                    &json!({}),
+                    function_call,
+                    &1000,
+                    &0.0,
+                    &options,
+                );
+                assert_eq!(body["max_completion_tokens"], 1000, "{}", model.as_str());
+                assert!(body.get("max_tokens").is_none(), "{}", model.as_str());
+            }
+        }
+
+        // The tokens left within the context window are capped at the output limit of the model
+        let body = OpenAIModels::Gpt4o.get_body(
+            "instructions",
+            &json!({}),
+            false,
+            &120_000,
+            &0.0,
+            &options,
+        );
+        assert_eq!(body["max_completion_tokens"], 16_384);
+    }
+
+    #[test]
+    fn test_custom_model_in_body() {
+        let model = OpenAIModels::try_from_str("ft:gpt-4o-mini:org::abc123").unwrap();
+        let schema = json!({"type": "object"});
+        let body = model.get_body(
+            "instructions",
+            &schema,
+            false,
+            &1000,
+            &0.0,
+            &CompletionsOptions::default(),
+        );
+
+        assert_eq!(body["model"], "ft:gpt-4o-mini:org::abc123");
+        assert_eq!(model.default_max_tokens(), 128_000);
+    }
+
+    #[test]
+    fn test_from_str_round_trip() {
+        let models = vec![
+            OpenAIModels::Gpt3_5Turbo,
+            OpenAIModels::Gpt3_5Turbo0613,
+            OpenAIModels::Gpt3_5Turbo16k,
+            OpenAIModels::Gpt4,
+            OpenAIModels::Gpt4_32k,
+            OpenAIModels::TextDavinci003,
+            OpenAIModels::Gpt4Turbo,
+            OpenAIModels::Gpt4TurboPreview,
+            OpenAIModels::Gpt4o,
+            OpenAIModels::Gpt4o20240806,
+            OpenAIModels::Gpt4oMini,
+            OpenAIModels::O1Preview,
+            OpenAIModels::O1Mini,
+            OpenAIModels::O1,
+            OpenAIModels::O3Mini,
+            OpenAIModels::Custom {
+                name: "ft:gpt-4o-mini:org::abc123".to_string(),
+            },
+        ];
+
+        for model in models {
+            assert_eq!(model.to_string().parse::<OpenAIModels>(), Ok(model));
+        }
+
+        // Unknown names are routed to the `Custom` variant
+        assert_eq!(
+            "gpt-5".parse::<OpenAIModels>(),
+            Ok(OpenAIModels::Custom {
+                name: "gpt-5".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_models_as_hash_map_keys() {
+        let mut token_costs: HashMap<OpenAIModels, u64> = HashMap::new();
+        *token_costs.entry(OpenAIModels::Gpt4o).or_default() += 100;
+        *token_costs.entry(OpenAIModels::Gpt4oMini).or_default() += 10;
+        *token_costs.entry(OpenAIModels::Gpt4o).or_default() += 50;
+        *token_costs
+            .entry(OpenAIModels::Custom {
+                name: "ft:gpt-4o-mini:org::abc123".to_string(),
+            })
+            .or_default() += 5;
+
+        assert_eq!(token_costs.len(), 3);
+        assert_eq!(token_costs[&OpenAIModels::Gpt4o], 150);
+        assert_eq!(
+            token_costs[&OpenAIModels::Custom {
+                name: "ft:gpt-4o-mini:org::abc123".to_string()
+            }],
+            5
+        );
+    }
+
+    #[test]
+    fn test_get_rate_limit_status() {
+        let mut headers = HeaderMap::new();
+        for (name, value) in [
+            ("x-ratelimit-limit-requests", "60"),
+            ("x-ratelimit-remaining-requests", "59"),
+            ("x-ratelimit-reset-requests", "1s"),
+            ("x-ratelimit-limit-tokens", "150000"),
+            ("x-ratelimit-remaining-tokens", "149984"),
+            ("x-ratelimit-reset-tokens", "6m0s"),
+        ] {
+            headers.insert(name, HeaderValue::from_static(value));
+        }
+
+        let status = get_rate_limit_status(&headers).unwrap();
+
+        assert_eq!(status.limit_requests, Some(60));
+        assert_eq!(status.remaining_requests, Some(59));
+        assert_eq!(status.reset_requests, Some(Duration::from_secs(1)));
+        assert_eq!(status.limit_tokens, Some(150_000));
+        assert_eq!(status.remaining_tokens, Some(149_984));
+        assert_eq!(status.reset_tokens, Some(Duration::from_secs(360)));
+
+        assert_eq!(get_rate_limit_status(&HeaderMap::new()), None);
+        assert_eq!(OpenAIModels::Gpt4oMini.last_rate_limit_status(), None);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_status_from_response() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("x-ratelimit-remaining-requests", "499")
+                    .insert_header("x-ratelimit-remaining-tokens", "29000")
+                    .insert_header("x-ratelimit-reset-tokens", "120ms")
+                    .set_body_json(json!({"choices": []})),
+            )
+            .mount(&server)
+            .await;
+
+        let response = Client::new().post(server.uri()).send().await.unwrap();
+        let status = get_rate_limit_status(response.headers()).unwrap();
+
+        assert_eq!(status.remaining_requests, Some(499));
+        assert_eq!(status.remaining_tokens, Some(29_000));
+        assert_eq!(status.reset_tokens, Some(Duration::from_millis(120)));
+        assert_eq!(status.limit_requests, None);
+    }
+
+    #[test]
+    fn test_history_messages() {
+        let options = CompletionsOptions {
+            history: vec![
+                Message::user("Hi"),
+                Message::assistant("{\"answer\": \"Hello\"}"),
+            ],
+            ..Default::default()
+        };
+
+        let body = OpenAIModels::Gpt4o.get_body("Bye", &json!({}), false, &0, &1.0, &options);
+        let roles: Vec<&str> = body["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|message| message["role"].as_str().unwrap())
+            .collect();
+        assert_eq!(roles, vec!["system", "user", "assistant", "user"]);
+        assert_eq!(body["messages"][1]["content"], "Hi");
+
+        // Reasoning models take base instructions as the first user message
+        let body = OpenAIModels::O3Mini.get_body("Bye", &json!({}), false, &0, &1.0, &options);
+        let roles: Vec<&str> = body["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|message| message["role"].as_str().unwrap())
+            .collect();
+        assert_eq!(roles, vec!["user", "user", "assistant", "user"]);
+    }
+
+    #[test]
+    fn test_logprobs() {
+        let options = CompletionsOptions {
+            logprobs: Some(2),
+            ..Default::default()
+        };
+        let body = OpenAIModels::Gpt4o.get_body("", &json!({}), false, &0, &1.0, &options);
+        assert_eq!(body["logprobs"], json!(true));
+        assert_eq!(body["top_logprobs"], json!(2));
+        let body = OpenAIModels::Gpt4o.get_body(
+            "",
+            &json!({}),
+            false,
+            &0,
+            &1.0,
+            &CompletionsOptions::default(),
+        );
+        assert!(body.get("logprobs").is_none());
+        assert!(!OpenAIModels::O1.supports_logprobs());
+
+        let response = json!({
+            "id": "chatcmpl-123",
+            "object": "chat.completion",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "{\"label\": \"spam\"}"},
+                "finish_reason": "stop",
+                "logprobs": {
+                    "content": [{
+                        "token": "spam",
+                        "logprob": -0.0123,
+                        "bytes": [115, 112, 97, 109],
+                        "top_logprobs": [
+                            {"token": "spam", "logprob": -0.0123, "bytes": [115, 112, 97, 109]},
+                            {"token": "ham", "logprob": -4.41, "bytes": [104, 97, 109]},
+                        ],
+                    }],
+                    "refusal": null,
+                },
+            }],
+        })
+        .to_string();
+        let logprobs = OpenAIModels::Gpt4o.get_logprobs(&response).unwrap();
+        assert_eq!(logprobs.len(), 1);
+        assert_eq!(logprobs[0].token, "spam");
+        assert_eq!(logprobs[0].logprob, -0.0123);
+        assert_eq!(logprobs[0].top_logprobs[1].token, "ham");
+        assert_eq!(logprobs[0].top_logprobs[1].logprob, -4.41);
+        assert_eq!(
+            OpenAIModels::Gpt4o.get_data(&response, false).unwrap(),
+            "{\"label\": \"spam\"}"
+        );
+
+        // Not reported unless requested
+        let response = json!({
+            "choices": [{"message": {"role": "assistant", "content": "{}"}}],
+        })
+        .to_string();
+        assert_eq!(OpenAIModels::Gpt4o.get_logprobs(&response), None);
+    }
+
+    #[test]
+    fn test_multiple_choices() {
+        #[derive(serde::Deserialize, Debug, PartialEq)]
+        struct Label {
+            label: String,
+        }
+
+        let options = CompletionsOptions {
+            n: Some(3),
+            ..Default::default()
+        };
+        let body = OpenAIModels::Gpt4oMini.get_body("", &json!({}), false, &0, &1.0, &options);
+        assert_eq!(body["n"], json!(3));
+        assert!(OpenAIModels::Gpt4oMini.supports_multiple_choices());
+        assert!(!OpenAIModels::O3Mini.supports_multiple_choices());
+
+        let response = json!({
+            "choices": [
+                {"index": 0, "message": {"role": "assistant", "content": "{\"label\": \"spam\"}"}},
+                {"index": 1, "message": {"role": "assistant", "content": "```json\n{\"label\": \"ham\"}```"}},
+                {"index": 2, "message": {"role": "assistant", "content": "{\"label\": \"spam\"}"}},
+            ],
+        })
+        .to_string();
+        let labels: Vec<Label> = OpenAIModels::Gpt4oMini
+            .get_data_choices(&response, false)
+            .unwrap()
+            .iter()
+            .map(|choice| serde_json::from_str(choice).unwrap())
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                Label {
+                    label: "spam".to_string()
+                },
+                Label {
+                    label: "ham".to_string()
+                },
+                Label {
+                    label: "spam".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_finish_reason() {
+        let response = |finish_reason: &str| {
+            json!({
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "{\"label\": "},
+                    "finish_reason": finish_reason
+                }],
+            })
+            .to_string()
+        };
+        let model = OpenAIModels::Gpt4oMini;
+
+        assert_eq!(
+            model.get_finish_reason(&response("stop")),
+            Some(FinishReason::Stop)
+        );
+        assert_eq!(
+            model.get_finish_reason(&response("length")),
+            Some(FinishReason::Length)
+        );
+        assert_eq!(
+            model.get_finish_reason(&response("function_call")),
+            Some(FinishReason::ToolCalls)
+        );
+        assert_eq!(
+            model.get_finish_reason(&response("content_filter")),
+            Some(FinishReason::ContentFilter)
+        );
+
+        let completions_response = json!({
+            "choices": [{"text": "{}", "index": 0, "logprobs": null, "finish_reason": "length"}],
+        });
+        assert_eq!(
+            OpenAIModels::TextDavinci003.get_finish_reason(&completions_response.to_string()),
+            Some(FinishReason::Length)
+        );
+    }
+
+    #[test]
+    fn test_stream_buffer() {
+        let stream = [
+            r#"{"id":"chatcmpl-1","choices":[{"index":0,"delta":{"role":"assistant","content":""}}]}"#,
+            r#"{"id":"chatcmpl-1","choices":[{"index":0,"delta":{"content":"Hello"}}]}"#,
+            r#"{"id":"chatcmpl-1","choices":[{"index":0,"delta":{"content":" ☀"}}]}"#,
+            r#"{"id":"chatcmpl-1","choices":[{"index":0,"delta":{},"finish_reason":"stop"}]}"#,
+            "[DONE]",
+        ]
+        .iter()
+        .map(|data| format!("data: {data}\n\n"))
+        .collect::<String>();
+
+        // Chunks can split events and multi-byte characters
+        let mut buffer = OpenAIStreamBuffer::default();
+        let mut deltas = Vec::new();
+        for chunk in stream.as_bytes().chunks(5) {
+            deltas.extend(buffer.push(chunk).unwrap());
+        }
+        assert_eq!(deltas.concat(), "Hello ☀");
+
+        let mut buffer = OpenAIStreamBuffer::default();
+        let error = "data: {\"error\": {\"message\": \"Rate limit reached\"}}\n\n";
+        assert!(buffer.push(error.as_bytes()).is_err());
+    }
 }
@@ -3,17 +3,26 @@
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use log::info;
-use reqwest::{header, Client};
+use reqwest::header;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::fmt;
+use std::str::FromStr;
 
 use crate::constants::PERPLEXITY_API_URL;
-use crate::domain::{PerplexityAPICompletionsResponse, RateLimit};
+use crate::domain::{
+    ApiResponse, CompletionsOptions, CompletionsUsage, FinishReason, LLMProvider, ModelInfo,
+    PerplexityAPICompletionsResponse, RateLimit, UnknownModelError,
+};
+use crate::http_client::HttpClient;
 use crate::llm_models::LLMModel;
-use crate::utils::{map_to_range_f32, remove_json_wrapper, remove_think_reasoner_wrapper};
+use crate::utils::{
+    get_chat_history, get_schema_string, log_request, map_to_range_f32, redact_secrets,
+    remove_json_wrapper, remove_think_reasoner_wrapper,
+};
 
 // Perplexity API Docs: https://docs.perplexity.ai/api-reference/chat-completions
-#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq, Hash)]
 pub enum PerplexityModels {
     SonarPro,
     Sonar,
@@ -70,6 +79,10 @@ impl LLMModel for PerplexityModels {
     }
 
     // https://docs.perplexity.ai/guides/model-cards
+    fn get_provider(&self) -> LLMProvider {
+        LLMProvider::Perplexity
+    }
+
     fn default_max_tokens(&self) -> usize {
         match self {
             // Docs: https://docs.perplexity.ai/guides/model-cards
@@ -99,29 +112,35 @@ impl LLMModel for PerplexityModels {
         // If left unspecified, then the model will generate tokens until either it reaches its stop token or the end of its context window.
         _max_tokens: &usize,
         temperature: &f32,
+        options: &CompletionsOptions,
     ) -> serde_json::Value {
         //Prepare the 'messages' part of the body
-        let base_instructions = self.get_base_instructions(Some(function_call));
+        let base_instructions = self.get_body_base_instructions(function_call, options);
         let system_message = json!({
             "role": "system",
             "content": base_instructions,
         });
-        let schema_string = serde_json::to_string(json_schema).unwrap_or_default();
-        let user_message = json!({
-            "role": "user",
-            "content": format!(
+        //The output schema is omitted for plain-text requests
+        let content = match get_schema_string(json_schema) {
+            Some(schema_string) => format!(
                 "Output Json schema:\n
                 {schema_string}\n\n
                 {instructions}"
             ),
+            None => instructions.to_string(),
+        };
+        let user_message = json!({
+            "role": "user",
+            "content": content,
         });
+        //Previous turns of the conversation go between the system message and the current instructions
+        let mut messages = vec![system_message];
+        messages.extend(get_chat_history(&options.get_history()));
+        messages.push(user_message);
         json!({
             "model": self.as_str(),
             "temperature": temperature,
-            "messages": vec![
-                system_message,
-                user_message,
-            ],
+            "messages": messages,
         })
     }
     ///
@@ -131,24 +150,24 @@ impl LLMModel for PerplexityModels {
     ///
     async fn call_api(
         &self,
+        client: &HttpClient,
         api_key: &str,
         body: &serde_json::Value,
         debug: bool,
-    ) -> Result<String> {
+    ) -> Result<ApiResponse> {
         //Get the API url
         let model_url = self.get_endpoint();
 
-        //Make the API call
-        let client = Client::new();
-
         //Send request
-        let response = client
+        let request = client
             .post(model_url)
             .header(header::CONTENT_TYPE, "application/json")
             .bearer_auth(api_key)
-            .json(&body)
-            .send()
-            .await?;
+            .json(&body);
+        if debug {
+            log_request("Perplexity", &request, body);
+        }
+        let response = client.send(request).await?;
 
         let response_status = response.status();
         let response_text = response.text().await?;
@@ -156,11 +175,16 @@ impl LLMModel for PerplexityModels {
         if debug {
             info!(
                 "[debug] Perplexity API response: [{}] {:#?}",
-                &response_status, &response_text
+                &response_status,
+                redact_secrets(&response_text)
             );
         }
 
-        Ok(response_text)
+        Ok(ApiResponse {
+            text: response_text,
+            status: Some(response_status.as_u16()),
+            rate_limit_status: None,
+        })
     }
 
     //This method attempts to convert the provided API response text into the expected struct and extracts the data from the response
@@ -184,6 +208,33 @@ impl LLMModel for PerplexityModels {
             .ok_or_else(|| anyhow!("Assistant role content not found"))
     }
 
+    //This method extracts the token usage reported in the API response
+    fn get_usage(&self, response_text: &str) -> Option<CompletionsUsage> {
+        serde_json::from_str::<PerplexityAPICompletionsResponse>(response_text)
+            .ok()
+            .and_then(|response| response.usage)
+            .map(|usage| CompletionsUsage {
+                input_tokens: usage.prompt_tokens,
+                output_tokens: usage.completion_tokens,
+                ..Default::default()
+            })
+    }
+
+    //This method extracts the URLs of the search results the model used to generate the answer
+    fn get_citations(&self, response_text: &str) -> Option<Vec<String>> {
+        serde_json::from_str::<PerplexityAPICompletionsResponse>(response_text)
+            .ok()
+            .and_then(|response| response.citations)
+    }
+
+    fn get_finish_reason(&self, response_text: &str) -> Option<FinishReason> {
+        serde_json::from_str::<PerplexityAPICompletionsResponse>(response_text)
+            .ok()?
+            .choices
+            .first()
+            .map(|choice| FinishReason::from_provider(&choice.finish_reason))
+    }
+
     /// This function sanitizes the text response from Perplexity models to clean up common formatting issues.
     /// Currently the function checks:
     /// * ```json{}``` wrapper around response
@@ -203,9 +254,15 @@ impl LLMModel for PerplexityModels {
         RateLimit {
             tpm: 50 * 127_072, // 50 requests per minute wit max 127,072 context length
             rpm: 50,           // 50 request per minute
+            rpd: None,
         }
     }
 
+    fn get_temperature_range(&self) -> (f32, f32) {
+        // Temperature range documentation: https://docs.perplexity.ai/api-reference/chat-completions
+        (0f32, 1.99999f32)
+    }
+
     // Accepts a [0-100] percentage range and returns the target temperature based on model ranges
     fn get_normalized_temperature(&self, relative_temp: u32) -> f32 {
         // Temperature range documentation: https://docs.perplexity.ai/api-reference/chat-completions
@@ -215,3 +272,126 @@ impl LLMModel for PerplexityModels {
         map_to_range_f32(min, max, relative_temp)
     }
 }
+
+impl fmt::Display for PerplexityModels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for PerplexityModels {
+    type Err = UnknownModelError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        PerplexityModels::try_from_str(name).ok_or_else(|| UnknownModelError::new(name))
+    }
+}
+
+///
+/// Perplexity does not expose a Models API. This function returns the models currently supported by the crate.
+///
+pub async fn list_models(_api_key: &str) -> Result<Vec<ModelInfo>> {
+    Ok([
+        PerplexityModels::SonarPro,
+        PerplexityModels::Sonar,
+        PerplexityModels::SonarReasoning,
+    ]
+    .iter()
+    .map(|model| ModelInfo {
+        id: model.as_str().to_string(),
+        created: 0,
+        owned_by: "perplexity".to_string(),
+        context_window: Some(model.context_window_tokens()),
+    })
+    .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::domain::{CompletionsOptions, CompletionsUsage, Message};
+    use crate::llm_models::{LLMModel, PerplexityModels};
+
+    #[test]
+    fn test_perplexity_response_citations_and_usage() {
+        let response = json!({
+            "id": "3c90c3cc-0d44-4b50-8888-8dd25736052a",
+            "model": "sonar",
+            "object": "chat.completion",
+            "created": 1724369245,
+            "citations": [
+                "https://www.example.com/article",
+                "https://www.example.org/news"
+            ],
+            "choices": [{
+                "index": 0,
+                "finish_reason": "stop",
+                "message": {"role": "assistant", "content": "{\"answer\": \"42\"}"},
+                "delta": {"role": "assistant", "content": ""}
+            }],
+            "usage": {"prompt_tokens": 14, "completion_tokens": 70, "total_tokens": 84}
+        })
+        .to_string();
+
+        let model = PerplexityModels::Sonar;
+
+        assert_eq!(
+            model.get_data(&response, false).unwrap(),
+            "{\"answer\": \"42\"}"
+        );
+        assert_eq!(
+            model.get_citations(&response),
+            Some(vec![
+                "https://www.example.com/article".to_string(),
+                "https://www.example.org/news".to_string()
+            ])
+        );
+        assert_eq!(
+            model.get_usage(&response),
+            Some(CompletionsUsage {
+                input_tokens: 14,
+                output_tokens: 70,
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_from_str_round_trip() {
+        let models = vec![
+            PerplexityModels::SonarPro,
+            PerplexityModels::Sonar,
+            PerplexityModels::SonarReasoning,
+            PerplexityModels::Llama3_1SonarSmall,
+            PerplexityModels::Llama3_1SonarLarge,
+            PerplexityModels::Llama3_1SonarHuge,
+        ];
+
+        for model in models {
+            assert_eq!(model.to_string().parse::<PerplexityModels>(), Ok(model));
+        }
+    }
+
+    #[test]
+    fn test_history_messages() {
+        let options = CompletionsOptions {
+            history: vec![
+                Message::user("Hi"),
+                Message::assistant("{\"answer\": \"Hello\"}"),
+            ],
+            ..Default::default()
+        };
+
+        let body = PerplexityModels::Sonar.get_body("Bye", &json!({}), false, &10, &0.5, &options);
+        let roles: Vec<&str> = body["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|message| message["role"].as_str().unwrap())
+            .collect();
+        assert_eq!(roles, vec!["system", "user", "assistant", "user"]);
+        assert_eq!(body["messages"][2]["content"], "{\"answer\": \"Hello\"}");
+    }
+}
@@ -0,0 +1,393 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use log::error;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::domain::{
+    AllmsError, ApiResponse, CompletionsOptions, CompletionsUsage, FinishReason, GroundingCitation,
+    LLMProvider, OutputStrictness, RateLimit, RateLimitStatus, TokenCost, TokenLogprob,
+};
+use crate::http_client::HttpClient;
+use crate::llm_models::{LLMModel, TextStream};
+
+const MINUTE: Duration = Duration::from_secs(60);
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Wrapper enforcing the rate limits returned by `get_rate_limit` of the model on the client side.
+///
+/// API calls wait until a slot in the requests-per-minute window is available. If the model has a daily quota
+/// (`RateLimit::rpd`) calls fail once the quota is used up until it resets at UTC midnight.
+/// Clones of the wrapper share the request counters so a single limit can be enforced across many `Completions`.
+#[derive(Debug, Clone)]
+pub struct RateLimitedModel<T: LLMModel> {
+    model: T,
+    state: Arc<Mutex<RateLimitState>>,
+}
+
+#[derive(Debug, Default)]
+struct RateLimitState {
+    minute_requests: VecDeque<Instant>,
+    // Days since the Unix epoch, i.e. changes at UTC midnight
+    day: u64,
+    day_requests: u64,
+}
+
+impl<T: LLMModel> RateLimitedModel<T> {
+    /// Constructor
+    pub fn new(model: T) -> Self {
+        RateLimitedModel {
+            model,
+            state: Arc::new(Mutex::new(RateLimitState::default())),
+        }
+    }
+
+    ///
+    /// This method returns the wrapped model
+    ///
+    pub fn model(&self) -> &T {
+        &self.model
+    }
+
+    ///
+    /// This method returns the number of requests made since UTC midnight
+    ///
+    pub fn requests_today(&self) -> u64 {
+        let state = self.state.lock().unwrap_or_else(|error| error.into_inner());
+        if state.day == get_current_day() {
+            state.day_requests
+        } else {
+            0
+        }
+    }
+
+    // This function reserves a request slot. If the per-minute limit is reached it returns how long to wait before retrying.
+    fn try_acquire(
+        &self,
+        rate_limit: &RateLimit,
+        now: Instant,
+        day: u64,
+    ) -> Result<Option<Duration>> {
+        let mut state = self.state.lock().unwrap_or_else(|error| error.into_inner());
+
+        if state.day != day {
+            state.day = day;
+            state.day_requests = 0;
+        }
+
+        if let Some(rpd) = rate_limit.rpd {
+            if state.day_requests >= rpd {
+                let error = AllmsError {
+                    crate_name: "allms".to_string(),
+                    module: format!("llm_models::rate_limited::{}", self.model.as_str()),
+                    error_message: "Daily request quota exceeded".to_string(),
+                    error_detail: format!(
+                        "{} of {} requests per day used",
+                        state.day_requests, rpd
+                    ),
+                };
+                error!("{:?}", error);
+                return Err(anyhow!("{:?}", error));
+            }
+        }
+
+        while let Some(oldest) = state.minute_requests.front() {
+            if now.duration_since(*oldest) >= MINUTE {
+                state.minute_requests.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        if state.minute_requests.len() >= rate_limit.rpm {
+            let oldest = state.minute_requests.front().copied().unwrap_or(now);
+            return Ok(Some(MINUTE.saturating_sub(now.duration_since(oldest))));
+        }
+
+        state.minute_requests.push_back(now);
+        state.day_requests += 1;
+        Ok(None)
+    }
+}
+
+fn get_current_day() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() / SECONDS_PER_DAY)
+        .unwrap_or_default()
+}
+
+#[async_trait(?Send)]
+impl<T: LLMModel> LLMModel for RateLimitedModel<T> {
+    fn as_str(&self) -> &str {
+        self.model.as_str()
+    }
+
+    fn id(&self) -> String {
+        self.model.id()
+    }
+
+    fn try_from_str(name: &str) -> Option<Self> {
+        T::try_from_str(name).map(RateLimitedModel::new)
+    }
+
+    fn get_provider(&self) -> LLMProvider {
+        self.model.get_provider()
+    }
+
+    fn default_max_tokens(&self) -> usize {
+        self.model.default_max_tokens()
+    }
+
+    fn context_window_tokens(&self) -> usize {
+        self.model.context_window_tokens()
+    }
+
+    fn cost_per_1k_tokens(&self) -> Option<TokenCost> {
+        self.model.cost_per_1k_tokens()
+    }
+
+    fn get_endpoint(&self) -> String {
+        self.model.get_endpoint()
+    }
+
+    fn get_base_instructions(
+        &self,
+        function_call: Option<bool>,
+        strictness: OutputStrictness,
+    ) -> String {
+        self.model.get_base_instructions(function_call, strictness)
+    }
+
+    fn get_body_base_instructions(
+        &self,
+        function_call: bool,
+        options: &CompletionsOptions,
+    ) -> String {
+        self.model
+            .get_body_base_instructions(function_call, options)
+    }
+
+    fn function_call_default(&self) -> bool {
+        self.model.function_call_default()
+    }
+
+    fn get_body(
+        &self,
+        instructions: &str,
+        json_schema: &Value,
+        function_call: bool,
+        max_tokens: &usize,
+        temperature: &f32,
+        options: &CompletionsOptions,
+    ) -> serde_json::Value {
+        self.model.get_body(
+            instructions,
+            json_schema,
+            function_call,
+            max_tokens,
+            temperature,
+            options,
+        )
+    }
+
+    //Waits for a slot within the rate limit before making the call
+    async fn call_api(
+        &self,
+        client: &HttpClient,
+        api_key: &str,
+        body: &serde_json::Value,
+        debug: bool,
+    ) -> Result<ApiResponse> {
+        let rate_limit = self.model.get_rate_limit();
+        while let Some(wait) = self.try_acquire(&rate_limit, Instant::now(), get_current_day())? {
+            tokio::time::sleep(wait).await;
+        }
+
+        self.model.call_api(client, api_key, body, debug).await
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.model.supports_streaming()
+    }
+
+    //Streamed requests count towards the rate limit when they are sent
+    async fn stream_api(
+        &self,
+        client: &HttpClient,
+        api_key: &str,
+        body: &serde_json::Value,
+        debug: bool,
+    ) -> Result<TextStream> {
+        let rate_limit = self.model.get_rate_limit();
+        while let Some(wait) = self.try_acquire(&rate_limit, Instant::now(), get_current_day())? {
+            tokio::time::sleep(wait).await;
+        }
+
+        self.model.stream_api(client, api_key, body, debug).await
+    }
+
+    //Token counting requests are not limited as they are not billed
+    async fn count_body_tokens_api(
+        &self,
+        client: &HttpClient,
+        api_key: &str,
+        body: &serde_json::Value,
+    ) -> Result<usize> {
+        self.model
+            .count_body_tokens_api(client, api_key, body)
+            .await
+    }
+
+    fn get_data(&self, response_text: &str, function_call: bool) -> Result<String> {
+        self.model.get_data(response_text, function_call)
+    }
+
+    fn supports_multiple_choices(&self) -> bool {
+        self.model.supports_multiple_choices()
+    }
+
+    fn get_data_choices(&self, response_text: &str, function_call: bool) -> Result<Vec<String>> {
+        self.model.get_data_choices(response_text, function_call)
+    }
+
+    fn get_usage(&self, response_text: &str) -> Option<CompletionsUsage> {
+        self.model.get_usage(response_text)
+    }
+
+    fn get_citations(&self, response_text: &str) -> Option<Vec<String>> {
+        self.model.get_citations(response_text)
+    }
+
+    fn supports_logprobs(&self) -> bool {
+        self.model.supports_logprobs()
+    }
+
+    fn get_logprobs(&self, response_text: &str) -> Option<Vec<TokenLogprob>> {
+        self.model.get_logprobs(response_text)
+    }
+
+    fn get_finish_reason(&self, response_text: &str) -> Option<FinishReason> {
+        self.model.get_finish_reason(response_text)
+    }
+
+    fn supports_grounding(&self) -> bool {
+        self.model.supports_grounding()
+    }
+
+    fn get_grounding_citations(&self, response_text: &str) -> Option<Vec<GroundingCitation>> {
+        self.model.get_grounding_citations(response_text)
+    }
+
+    fn supports_file_data(&self) -> bool {
+        self.model.supports_file_data()
+    }
+
+    fn supports_pdf_documents(&self) -> bool {
+        self.model.supports_pdf_documents()
+    }
+
+    fn supports_thinking(&self) -> bool {
+        self.model.supports_thinking()
+    }
+
+    fn get_thinking(&self, response_text: &str) -> Option<String> {
+        self.model.get_thinking(response_text)
+    }
+
+    fn sanitize_json_response(&self, json_response: &str) -> String {
+        self.model.sanitize_json_response(json_response)
+    }
+
+    fn get_rate_limit(&self) -> RateLimit {
+        self.model.get_rate_limit()
+    }
+
+    fn get_max_requests(&self) -> usize {
+        self.model.get_max_requests()
+    }
+
+    fn get_default_temperature(&self) -> f32 {
+        self.model.get_default_temperature()
+    }
+
+    fn get_temperature_range(&self) -> (f32, f32) {
+        self.model.get_temperature_range()
+    }
+
+    fn get_normalized_temperature(&self, relative_temp: u32) -> f32 {
+        self.model.get_normalized_temperature(relative_temp)
+    }
+
+    fn last_rate_limit_status(&self) -> Option<RateLimitStatus> {
+        self.model.last_rate_limit_status()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::domain::RateLimit;
+    use crate::llm_models::{GoogleModels, LLMModel, RateLimitedModel};
+
+    #[test]
+    fn test_daily_quota_resets_at_midnight() {
+        let model = RateLimitedModel::new(GoogleModels::Gemini2_0ProExp);
+        let rate_limit = RateLimit {
+            tpm: 1_000_000,
+            rpm: 1_000,
+            rpd: Some(3),
+        };
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert_eq!(model.try_acquire(&rate_limit, now, 20_000).unwrap(), None);
+        }
+        assert!(model.try_acquire(&rate_limit, now, 20_000).is_err());
+
+        // Next UTC day
+        assert_eq!(model.try_acquire(&rate_limit, now, 20_001).unwrap(), None);
+    }
+
+    #[test]
+    fn test_requests_per_minute_wait() {
+        let model = RateLimitedModel::new(GoogleModels::Gemini1_5Pro);
+        let rate_limit = RateLimit {
+            tpm: 1_000_000,
+            rpm: 2,
+            rpd: None,
+        };
+        let start = Instant::now();
+
+        assert_eq!(model.try_acquire(&rate_limit, start, 0).unwrap(), None);
+        assert_eq!(model.try_acquire(&rate_limit, start, 0).unwrap(), None);
+        assert_eq!(
+            model
+                .try_acquire(&rate_limit, start + Duration::from_secs(20), 0)
+                .unwrap(),
+            Some(Duration::from_secs(40))
+        );
+        assert_eq!(
+            model
+                .try_acquire(&rate_limit, start + Duration::from_secs(60), 0)
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_clones_share_counters() {
+        let model = RateLimitedModel::new(GoogleModels::Gemini2_0FlashThinkingExp);
+        let clone = model.clone();
+        let rate_limit = model.get_rate_limit();
+
+        assert_eq!(rate_limit.rpd, Some(1_500));
+        clone
+            .try_acquire(&rate_limit, Instant::now(), super::get_current_day())
+            .unwrap();
+        assert_eq!(model.requests_today(), 1);
+    }
+}
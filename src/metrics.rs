@@ -0,0 +1,146 @@
+use anyhow::Result;
+use lazy_static::lazy_static;
+use prometheus::{HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry};
+use std::time::Duration;
+
+use crate::domain::ApiResponse;
+
+lazy_static! {
+    static ref REQUESTS_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new("allms_requests_total", "Number of API calls sent per model"),
+        &["model"]
+    )
+    .expect("valid allms_requests_total metric");
+    static ref REQUEST_DURATION_SECONDS: HistogramVec = HistogramVec::new(
+        HistogramOpts::new(
+            "allms_request_duration_seconds",
+            "Response latency of API calls per model in seconds"
+        )
+        .buckets(vec![0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0]),
+        &["model"]
+    )
+    .expect("valid allms_request_duration_seconds metric");
+    static ref ERRORS_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "allms_errors_total",
+            "Number of failed API calls per model and status"
+        ),
+        &["model", "status"]
+    )
+    .expect("valid allms_errors_total metric");
+}
+
+///
+/// This function registers the Prometheus metrics of the crate in the provided registry. It needs to be called once at startup.
+/// - `allms_requests_total{model}`: number of API calls (responses served from the cache are not counted)
+/// - `allms_request_duration_seconds{model}`: latency of API calls
+/// - `allms_errors_total{model, status}`: failed API calls. The status is the HTTP status code if known, `network` if the API could not be reached, `invalid_response` if no data could be extracted from a successful response, or `error` otherwise.
+///
+pub fn register_metrics(registry: &Registry) -> Result<()> {
+    registry.register(Box::new(REQUESTS_TOTAL.clone()))?;
+    registry.register(Box::new(REQUEST_DURATION_SECONDS.clone()))?;
+    registry.register(Box::new(ERRORS_TOTAL.clone()))?;
+    Ok(())
+}
+
+// Records an API call and, if it failed, the error. Responses with an HTTP error status are failed calls
+pub(crate) fn observe_request(
+    model: &str,
+    duration: Duration,
+    response: Result<&ApiResponse, &anyhow::Error>,
+) {
+    REQUESTS_TOTAL.with_label_values(&[model]).inc();
+    REQUEST_DURATION_SECONDS
+        .with_label_values(&[model])
+        .observe(duration.as_secs_f64());
+
+    let status = match response {
+        Ok(response) if response.is_success() => return,
+        Ok(response) => response
+            .status
+            .map(|status| status.to_string())
+            .unwrap_or_else(|| "error".to_string()),
+        Err(error) => match error.downcast_ref::<reqwest::Error>() {
+            Some(error) => error
+                .status()
+                .map(|status| status.as_u16().to_string())
+                .unwrap_or_else(|| "network".to_string()),
+            None => "error".to_string(),
+        },
+    };
+    observe_error(model, &status);
+}
+
+// Records a failed API call
+pub(crate) fn observe_error(model: &str, status: &str) {
+    ERRORS_TOTAL.with_label_values(&[model, status]).inc();
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+    use prometheus::Registry;
+    use std::time::Duration;
+
+    use super::{observe_error, observe_request, register_metrics};
+    use crate::domain::ApiResponse;
+
+    #[test]
+    fn test_register_metrics() {
+        let registry = Registry::new();
+        register_metrics(&registry).unwrap();
+        // Metrics can only be registered once per registry
+        assert!(register_metrics(&registry).is_err());
+
+        let model = "metrics-test";
+        let ok_response = ApiResponse {
+            status: Some(200),
+            ..Default::default()
+        };
+        let rate_limited_response = ApiResponse {
+            status: Some(429),
+            ..Default::default()
+        };
+        observe_request(model, Duration::from_millis(300), Ok(&ok_response));
+        observe_request(model, Duration::from_secs(3), Err(&anyhow!("failed")));
+        observe_request(model, Duration::from_secs(1), Ok(&rate_limited_response));
+        observe_error(model, "invalid_response");
+
+        let metric_value = |name: &str, status: Option<&str>| {
+            registry
+                .gather()
+                .into_iter()
+                .find(|family| family.get_name() == name)
+                .unwrap()
+                .get_metric()
+                .iter()
+                .find(|metric| {
+                    let labels = metric.get_label();
+                    labels
+                        .iter()
+                        .any(|label| label.get_name() == "model" && label.get_value() == model)
+                        && status.is_none_or(|status| {
+                            labels.iter().any(|label| {
+                                label.get_name() == "status" && label.get_value() == status
+                            })
+                        })
+                })
+                .map(|metric| match name {
+                    "allms_request_duration_seconds" => {
+                        metric.get_histogram().get_sample_count() as f64
+                    }
+                    _ => metric.get_counter().get_value(),
+                })
+                .unwrap()
+        };
+
+        assert_eq!(metric_value("allms_requests_total", None), 3.0);
+        assert_eq!(metric_value("allms_request_duration_seconds", None), 3.0);
+        assert_eq!(metric_value("allms_errors_total", Some("error")), 1.0);
+        assert_eq!(metric_value("allms_errors_total", Some("429")), 1.0);
+        assert_eq!(
+            metric_value("allms_errors_total", Some("invalid_response")),
+            1.0
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+lazy_static! {
+    // `{{name}}` or `{{name | default("fallback")}}`, with optional whitespace
+    static ref PLACEHOLDER: Regex = Regex::new(
+        r#"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*(?:\|\s*default\(\s*"([^"]*)"\s*\)\s*)?\}\}"#
+    )
+    .expect("valid placeholder regex");
+}
+
+/// Instructions with `{{variable_name}}` placeholders replaced by values when the template is rendered.
+/// A fallback used when no value is provided can be set with `{{variable_name | default("fallback")}}`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct PromptTemplate {
+    pub template: String,
+    strict: bool,
+}
+
+impl PromptTemplate {
+    /// Constructor
+    pub fn new(template: &str) -> Self {
+        PromptTemplate {
+            template: template.to_string(),
+            strict: false,
+        }
+    }
+
+    ///
+    /// This method makes `render` fail if a placeholder has neither a value nor a default. By default such placeholders are left in the output as-is.
+    ///
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    ///
+    /// This method returns the names of the variables used in the template, in order of first appearance
+    ///
+    pub fn variables(&self) -> Vec<&str> {
+        let mut variables: Vec<&str> = Vec::new();
+        for captures in PLACEHOLDER.captures_iter(&self.template) {
+            let name = captures.get(1).map_or("", |name| name.as_str());
+            if !variables.contains(&name) {
+                variables.push(name);
+            }
+        }
+        variables
+    }
+
+    ///
+    /// This method replaces the placeholders of the template with the provided values
+    ///
+    pub fn render(&self, vars: &HashMap<&str, &str>) -> Result<String> {
+        let mut missing: Vec<String> = Vec::new();
+        let rendered = PLACEHOLDER.replace_all(&self.template, |captures: &Captures| {
+            let name = &captures[1];
+            match (vars.get(name), captures.get(2)) {
+                (Some(value), _) => value.to_string(),
+                (None, Some(default)) => default.as_str().to_string(),
+                (None, None) => {
+                    if !missing.iter().any(|missing_name| missing_name == name) {
+                        missing.push(name.to_string());
+                    }
+                    captures[0].to_string()
+                }
+            }
+        });
+
+        if self.strict && !missing.is_empty() {
+            return Err(anyhow!(
+                "[allms] No value provided for template variables: {}",
+                missing.join(", ")
+            ));
+        }
+        Ok(rendered.into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::PromptTemplate;
+
+    #[test]
+    fn test_render() {
+        let template = PromptTemplate::new(
+            "Translate {{ text }} into {{language | default(\"English\")}}. Keep {{text}} short.",
+        );
+        assert_eq!(template.variables(), vec!["text", "language"]);
+
+        let vars = HashMap::from([("text", "Bonjour"), ("language", "German")]);
+        assert_eq!(
+            template.render(&vars).unwrap(),
+            "Translate Bonjour into German. Keep Bonjour short."
+        );
+
+        // Defaults are used for missing values
+        let vars = HashMap::from([("text", "Bonjour")]);
+        assert_eq!(
+            template.render(&vars).unwrap(),
+            "Translate Bonjour into English. Keep Bonjour short."
+        );
+
+        // Placeholders without value are kept unless the template is strict
+        let vars = HashMap::from([("language", "German")]);
+        assert_eq!(
+            template.render(&vars).unwrap(),
+            "Translate {{ text }} into German. Keep {{text}} short."
+        );
+        let error = template.strict().render(&vars).unwrap_err();
+        assert!(error.to_string().contains("text"));
+    }
+}
@@ -0,0 +1,9 @@
+pub mod openai;
+
+pub use openai::{
+    OpenAIRealtimeClient, OpenAIRealtimeModels, OpenAIRealtimeSession, RealtimeClientEvent,
+    RealtimeContentPart, RealtimeContentType, RealtimeConversation, RealtimeError, RealtimeEvent,
+    RealtimeItem, RealtimeItemType, RealtimeModality, RealtimeRateLimit, RealtimeResponse,
+    RealtimeResponseConfig, RealtimeRole, RealtimeSession, RealtimeTool, RealtimeTranscription,
+    RealtimeTurnDetection, RealtimeUsage,
+};
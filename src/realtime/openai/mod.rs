@@ -0,0 +1,10 @@
+pub mod openai_realtime;
+pub mod openai_realtime_events;
+
+pub use openai_realtime::{OpenAIRealtimeClient, OpenAIRealtimeModels, OpenAIRealtimeSession};
+pub use openai_realtime_events::{
+    RealtimeClientEvent, RealtimeContentPart, RealtimeContentType, RealtimeConversation,
+    RealtimeError, RealtimeEvent, RealtimeItem, RealtimeItemType, RealtimeModality,
+    RealtimeRateLimit, RealtimeResponse, RealtimeResponseConfig, RealtimeRole, RealtimeSession,
+    RealtimeTool, RealtimeTranscription, RealtimeTurnDetection, RealtimeUsage,
+};
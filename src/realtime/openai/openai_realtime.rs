@@ -0,0 +1,287 @@
+use anyhow::{anyhow, Result};
+use futures::{SinkExt, Stream, StreamExt};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::{HeaderValue, Request};
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+use crate::constants::OPENAI_REALTIME_API_URL;
+use crate::domain::AllmsError;
+use crate::realtime::openai::openai_realtime_events::{
+    RealtimeClientEvent, RealtimeEvent, RealtimeItem, RealtimeModality, RealtimeSession,
+};
+
+/// [OpenAI Docs](https://platform.openai.com/docs/guides/realtime)
+///
+/// The Realtime API exchanges events with the model over a WebSocket connection. The client configures the session and opens the connection.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct OpenAIRealtimeClient {
+    model: OpenAIRealtimeModels,
+    api_key: String,
+    session: RealtimeSession,
+    debug: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, PartialEq, Hash)]
+pub enum OpenAIRealtimeModels {
+    Gpt4oRealtimePreview,
+    Gpt4oMiniRealtimePreview,
+}
+
+impl OpenAIRealtimeModels {
+    pub fn as_str(&self) -> &str {
+        match self {
+            OpenAIRealtimeModels::Gpt4oRealtimePreview => "gpt-4o-realtime-preview",
+            OpenAIRealtimeModels::Gpt4oMiniRealtimePreview => "gpt-4o-mini-realtime-preview",
+        }
+    }
+}
+
+impl OpenAIRealtimeClient {
+    /// Constructor
+    pub fn new(model: OpenAIRealtimeModels, open_ai_key: &str) -> Self {
+        OpenAIRealtimeClient {
+            model,
+            api_key: open_ai_key.to_string(),
+            session: RealtimeSession::default(),
+            debug: false,
+        }
+    }
+
+    ///
+    /// This method can be used to turn on debug mode
+    ///
+    pub fn debug(mut self) -> Self {
+        self.debug = true;
+        self
+    }
+
+    ///
+    /// This method can be used to set the system instructions of the session
+    ///
+    pub fn instructions(mut self, instructions: &str) -> Self {
+        self.session.instructions = Some(instructions.to_string());
+        self
+    }
+
+    ///
+    /// This method can be used to set the modalities of the responses, e.g. `[RealtimeModality::Text]` for text-only answers
+    /// Default is text and audio
+    ///
+    pub fn modalities(mut self, modalities: &[RealtimeModality]) -> Self {
+        self.session.modalities = Some(modalities.to_vec());
+        self
+    }
+
+    ///
+    /// This method can be used to set the voice of audio responses, e.g. `alloy`, `echo` or `shimmer`
+    ///
+    pub fn voice(mut self, voice: &str) -> Self {
+        self.session.voice = Some(voice.to_string());
+        self
+    }
+
+    ///
+    /// This method can be used to set the sampling temperature of the session
+    ///
+    pub fn temperature(mut self, temperature: f32) -> Self {
+        self.session.temperature = Some(temperature);
+        self
+    }
+
+    ///
+    /// This method can be used to provide the complete session configuration, e.g. to set tools or turn detection
+    ///
+    pub fn session(mut self, session: RealtimeSession) -> Self {
+        self.session = session;
+        self
+    }
+
+    ///
+    /// This method opens the WebSocket connection and configures the session.
+    /// It waits for the `session.created` event and, if the session was configured, for the `session.updated` event confirming the configuration.
+    ///
+    pub async fn connect(&self) -> Result<OpenAIRealtimeSession> {
+        let request = self.build_request()?;
+        let (socket, _) = connect_async(request).await?;
+
+        let mut session = OpenAIRealtimeSession {
+            socket,
+            session: RealtimeSession::default(),
+            debug: self.debug,
+        };
+
+        session.session = session.wait_for_session().await?;
+
+        if self.session != RealtimeSession::default() {
+            session
+                .send(&RealtimeClientEvent::SessionUpdate {
+                    event_id: None,
+                    session: self.session.clone(),
+                })
+                .await?;
+            session.session = session.wait_for_session().await?;
+        }
+
+        if self.debug {
+            info!(
+                "[allms][OpenAI][Realtime][debug] Session: {:?}",
+                session.session
+            );
+        }
+
+        Ok(session)
+    }
+
+    // The model and API key are passed in the URL and headers of the handshake
+    fn build_request(&self) -> Result<Request<()>> {
+        let url = format!("{}?model={}", *OPENAI_REALTIME_API_URL, self.model.as_str());
+        let mut request = url.into_client_request()?;
+        let headers = request.headers_mut();
+        headers.insert(
+            "Authorization",
+            HeaderValue::from_str(&format!("Bearer {}", self.api_key))?,
+        );
+        headers.insert("OpenAI-Beta", HeaderValue::from_static("realtime=v1"));
+        Ok(request)
+    }
+}
+
+/// Open connection to the Realtime API. Events sent by the API are read by polling the session as a `Stream`.
+pub struct OpenAIRealtimeSession {
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    session: RealtimeSession,
+    debug: bool,
+}
+
+impl OpenAIRealtimeSession {
+    ///
+    /// This method returns the session configuration confirmed by the API
+    ///
+    pub fn session(&self) -> &RealtimeSession {
+        &self.session
+    }
+
+    ///
+    /// This method sends a client event to the API
+    ///
+    pub async fn send(&mut self, event: &RealtimeClientEvent) -> Result<()> {
+        let event_str = serde_json::to_string(event)?;
+
+        if self.debug {
+            info!(
+                "[allms][OpenAI][Realtime][debug] Client event: {}",
+                event_str
+            );
+        }
+
+        self.socket.send(WsMessage::Text(event_str)).await?;
+        Ok(())
+    }
+
+    ///
+    /// This method adds a user message to the conversation and asks the model to respond.
+    /// The response is streamed as `response.text.delta` events, followed by `response.done`.
+    ///
+    pub async fn send_text(&mut self, text: &str) -> Result<()> {
+        self.send(&RealtimeClientEvent::ConversationItemCreate {
+            event_id: None,
+            previous_item_id: None,
+            item: RealtimeItem::user_text(text),
+        })
+        .await?;
+        self.send(&RealtimeClientEvent::ResponseCreate {
+            event_id: None,
+            response: None,
+        })
+        .await
+    }
+
+    ///
+    /// This method closes the connection
+    ///
+    pub async fn close(mut self) -> Result<()> {
+        self.socket.close(None).await?;
+        Ok(())
+    }
+
+    // Reads events until the API confirms the session configuration
+    async fn wait_for_session(&mut self) -> Result<RealtimeSession> {
+        while let Some(event) = self.next().await {
+            match event? {
+                RealtimeEvent::SessionCreated { session, .. }
+                | RealtimeEvent::SessionUpdated { session, .. } => return Ok(session),
+                RealtimeEvent::Error { error, .. } => {
+                    let error = AllmsError {
+                        crate_name: "allms".to_string(),
+                        module: "realtime::openai_realtime".to_string(),
+                        error_message: format!("Realtime API error: {}", error.error_type),
+                        error_detail: error.message,
+                    };
+                    error!("{:?}", error);
+                    return Err(anyhow!("{:?}", error));
+                }
+                _ => {}
+            }
+        }
+        Err(anyhow!(
+            "[allms] The Realtime API closed the connection before the session was configured"
+        ))
+    }
+}
+
+impl Stream for OpenAIRealtimeSession {
+    type Item = Result<RealtimeEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let message = match self.socket.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(message))) => message,
+                Poll::Ready(Some(Err(error))) => return Poll::Ready(Some(Err(error.into()))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+
+            match message {
+                WsMessage::Text(text) => {
+                    if self.debug {
+                        info!("[allms][OpenAI][Realtime][debug] Server event: {}", text);
+                    }
+                    let event = serde_json::from_str::<RealtimeEvent>(&text).map_err(|error| {
+                        anyhow!("[allms] Unable to parse Realtime API event: {}", error)
+                    });
+                    return Poll::Ready(Some(event));
+                }
+                WsMessage::Close(_) => return Poll::Ready(None),
+                // Pings are answered by the WebSocket library. Other frames are not used by the API.
+                _ => continue,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OpenAIRealtimeClient, OpenAIRealtimeModels};
+    use crate::realtime::RealtimeModality;
+
+    #[test]
+    fn test_build_request() {
+        let client =
+            OpenAIRealtimeClient::new(OpenAIRealtimeModels::Gpt4oMiniRealtimePreview, "sk-test")
+                .modalities(&[RealtimeModality::Text]);
+        let request = client.build_request().unwrap();
+
+        assert_eq!(
+            request.uri().to_string(),
+            "wss://api.openai.com/v1/realtime?model=gpt-4o-mini-realtime-preview"
+        );
+        assert_eq!(request.headers()["Authorization"], "Bearer sk-test");
+        assert_eq!(request.headers()["OpenAI-Beta"], "realtime=v1");
+    }
+}
@@ -0,0 +1,543 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// [OpenAI Docs](https://platform.openai.com/docs/api-reference/realtime-client-events)
+///
+/// Events sent by the client to the Realtime API
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum RealtimeClientEvent {
+    #[serde(rename = "session.update")]
+    SessionUpdate {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        event_id: Option<String>,
+        session: RealtimeSession,
+    },
+    #[serde(rename = "input_audio_buffer.append")]
+    InputAudioBufferAppend {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        event_id: Option<String>,
+        /// Base64-encoded audio bytes in the input audio format of the session
+        audio: String,
+    },
+    #[serde(rename = "input_audio_buffer.commit")]
+    InputAudioBufferCommit {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        event_id: Option<String>,
+    },
+    #[serde(rename = "input_audio_buffer.clear")]
+    InputAudioBufferClear {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        event_id: Option<String>,
+    },
+    #[serde(rename = "conversation.item.create")]
+    ConversationItemCreate {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        event_id: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        previous_item_id: Option<String>,
+        item: RealtimeItem,
+    },
+    #[serde(rename = "conversation.item.truncate")]
+    ConversationItemTruncate {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        event_id: Option<String>,
+        item_id: String,
+        content_index: usize,
+        audio_end_ms: u64,
+    },
+    #[serde(rename = "conversation.item.delete")]
+    ConversationItemDelete {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        event_id: Option<String>,
+        item_id: String,
+    },
+    #[serde(rename = "response.create")]
+    ResponseCreate {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        event_id: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        response: Option<RealtimeResponseConfig>,
+    },
+    #[serde(rename = "response.cancel")]
+    ResponseCancel {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        event_id: Option<String>,
+    },
+}
+
+/// [OpenAI Docs](https://platform.openai.com/docs/api-reference/realtime-server-events)
+///
+/// Events sent by the Realtime API to the client. Event types added to the API after this version of the crate are returned as `Unknown`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum RealtimeEvent {
+    #[serde(rename = "error")]
+    Error {
+        event_id: String,
+        error: RealtimeError,
+    },
+    #[serde(rename = "session.created")]
+    SessionCreated {
+        event_id: String,
+        session: RealtimeSession,
+    },
+    #[serde(rename = "session.updated")]
+    SessionUpdated {
+        event_id: String,
+        session: RealtimeSession,
+    },
+    #[serde(rename = "conversation.created")]
+    ConversationCreated {
+        event_id: String,
+        conversation: RealtimeConversation,
+    },
+    #[serde(rename = "conversation.item.created")]
+    ConversationItemCreated {
+        event_id: String,
+        previous_item_id: Option<String>,
+        item: RealtimeItem,
+    },
+    #[serde(rename = "conversation.item.input_audio_transcription.completed")]
+    ConversationItemInputAudioTranscriptionCompleted {
+        event_id: String,
+        item_id: String,
+        content_index: usize,
+        transcript: String,
+    },
+    #[serde(rename = "conversation.item.input_audio_transcription.failed")]
+    ConversationItemInputAudioTranscriptionFailed {
+        event_id: String,
+        item_id: String,
+        content_index: usize,
+        error: RealtimeError,
+    },
+    #[serde(rename = "conversation.item.truncated")]
+    ConversationItemTruncated {
+        event_id: String,
+        item_id: String,
+        content_index: usize,
+        audio_end_ms: u64,
+    },
+    #[serde(rename = "conversation.item.deleted")]
+    ConversationItemDeleted { event_id: String, item_id: String },
+    #[serde(rename = "input_audio_buffer.committed")]
+    InputAudioBufferCommitted {
+        event_id: String,
+        previous_item_id: Option<String>,
+        item_id: String,
+    },
+    #[serde(rename = "input_audio_buffer.cleared")]
+    InputAudioBufferCleared { event_id: String },
+    #[serde(rename = "input_audio_buffer.speech_started")]
+    InputAudioBufferSpeechStarted {
+        event_id: String,
+        audio_start_ms: u64,
+        item_id: String,
+    },
+    #[serde(rename = "input_audio_buffer.speech_stopped")]
+    InputAudioBufferSpeechStopped {
+        event_id: String,
+        audio_end_ms: u64,
+        item_id: String,
+    },
+    #[serde(rename = "response.created")]
+    ResponseCreated {
+        event_id: String,
+        response: RealtimeResponse,
+    },
+    #[serde(rename = "response.done")]
+    ResponseDone {
+        event_id: String,
+        response: RealtimeResponse,
+    },
+    #[serde(rename = "response.output_item.added")]
+    ResponseOutputItemAdded {
+        event_id: String,
+        response_id: String,
+        output_index: usize,
+        item: RealtimeItem,
+    },
+    #[serde(rename = "response.output_item.done")]
+    ResponseOutputItemDone {
+        event_id: String,
+        response_id: String,
+        output_index: usize,
+        item: RealtimeItem,
+    },
+    #[serde(rename = "response.content_part.added")]
+    ResponseContentPartAdded {
+        event_id: String,
+        response_id: String,
+        item_id: String,
+        output_index: usize,
+        content_index: usize,
+        part: RealtimeContentPart,
+    },
+    #[serde(rename = "response.content_part.done")]
+    ResponseContentPartDone {
+        event_id: String,
+        response_id: String,
+        item_id: String,
+        output_index: usize,
+        content_index: usize,
+        part: RealtimeContentPart,
+    },
+    #[serde(rename = "response.text.delta")]
+    ResponseTextDelta {
+        event_id: String,
+        response_id: String,
+        item_id: String,
+        output_index: usize,
+        content_index: usize,
+        delta: String,
+    },
+    #[serde(rename = "response.text.done")]
+    ResponseTextDone {
+        event_id: String,
+        response_id: String,
+        item_id: String,
+        output_index: usize,
+        content_index: usize,
+        text: String,
+    },
+    #[serde(rename = "response.audio_transcript.delta")]
+    ResponseAudioTranscriptDelta {
+        event_id: String,
+        response_id: String,
+        item_id: String,
+        output_index: usize,
+        content_index: usize,
+        delta: String,
+    },
+    #[serde(rename = "response.audio_transcript.done")]
+    ResponseAudioTranscriptDone {
+        event_id: String,
+        response_id: String,
+        item_id: String,
+        output_index: usize,
+        content_index: usize,
+        transcript: String,
+    },
+    #[serde(rename = "response.audio.delta")]
+    ResponseAudioDelta {
+        event_id: String,
+        response_id: String,
+        item_id: String,
+        output_index: usize,
+        content_index: usize,
+        /// Base64-encoded audio bytes in the output audio format of the session
+        delta: String,
+    },
+    #[serde(rename = "response.audio.done")]
+    ResponseAudioDone {
+        event_id: String,
+        response_id: String,
+        item_id: String,
+        output_index: usize,
+        content_index: usize,
+    },
+    #[serde(rename = "response.function_call_arguments.delta")]
+    ResponseFunctionCallArgumentsDelta {
+        event_id: String,
+        response_id: String,
+        item_id: String,
+        output_index: usize,
+        call_id: String,
+        delta: String,
+    },
+    #[serde(rename = "response.function_call_arguments.done")]
+    ResponseFunctionCallArgumentsDone {
+        event_id: String,
+        response_id: String,
+        item_id: String,
+        output_index: usize,
+        call_id: String,
+        arguments: String,
+    },
+    #[serde(rename = "rate_limits.updated")]
+    RateLimitsUpdated {
+        event_id: String,
+        rate_limits: Vec<RealtimeRateLimit>,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+impl RealtimeEvent {
+    ///
+    /// This method returns the text chunk carried by `response.text.delta` events
+    ///
+    pub fn text_delta(&self) -> Option<&str> {
+        match self {
+            RealtimeEvent::ResponseTextDelta { delta, .. } => Some(delta),
+            _ => None,
+        }
+    }
+}
+
+/// Configuration of a Realtime session. Fields left empty keep the value set by the API.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct RealtimeSession {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modalities: Option<Vec<RealtimeModality>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voice: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_audio_format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_audio_format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_audio_transcription: Option<RealtimeTranscription>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub turn_detection: Option<RealtimeTurnDetection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<RealtimeTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_response_output_tokens: Option<Value>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RealtimeModality {
+    Text,
+    Audio,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct RealtimeTranscription {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+/// Voice activity detection settings. The API turns it off when the session is updated with `null`.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct RealtimeTurnDetection {
+    #[serde(rename = "type")]
+    pub detection_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prefix_padding_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub silence_duration_ms: Option<u64>,
+}
+
+/// Function the model can call during the session
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct RealtimeTool {
+    #[serde(rename = "type")]
+    pub tool_type: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub parameters: Value,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct RealtimeConversation {
+    pub id: String,
+    pub object: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RealtimeItemType {
+    Message,
+    FunctionCall,
+    FunctionCallOutput,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RealtimeRole {
+    User,
+    Assistant,
+    System,
+}
+
+/// Item of a Realtime conversation: a message, a function call or the output of a function call
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct RealtimeItem {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(rename = "type")]
+    pub item_type: RealtimeItemType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<RealtimeRole>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub content: Vec<RealtimeContentPart>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub call_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+}
+
+impl RealtimeItem {
+    /// Constructor of a user message with text content
+    pub fn user_text(text: &str) -> Self {
+        RealtimeItem {
+            id: None,
+            item_type: RealtimeItemType::Message,
+            status: None,
+            role: Some(RealtimeRole::User),
+            content: vec![RealtimeContentPart {
+                content_type: RealtimeContentType::InputText,
+                text: Some(text.to_string()),
+                audio: None,
+                transcript: None,
+            }],
+            call_id: None,
+            name: None,
+            arguments: None,
+            output: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RealtimeContentType {
+    InputText,
+    InputAudio,
+    Text,
+    Audio,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct RealtimeContentPart {
+    #[serde(rename = "type")]
+    pub content_type: RealtimeContentType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transcript: Option<String>,
+}
+
+/// Settings of a single response. They override the session configuration for that response only.
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct RealtimeResponseConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modalities: Option<Vec<RealtimeModality>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voice: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_audio_format: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<RealtimeTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<Value>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct RealtimeResponse {
+    pub id: String,
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_details: Option<Value>,
+    #[serde(default)]
+    pub output: Vec<RealtimeItem>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<RealtimeUsage>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct RealtimeUsage {
+    pub total_tokens: usize,
+    pub input_tokens: usize,
+    pub output_tokens: usize,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct RealtimeError {
+    #[serde(rename = "type")]
+    pub error_type: String,
+    #[serde(default)]
+    pub code: Option<String>,
+    pub message: String,
+    #[serde(default)]
+    pub param: Option<String>,
+    #[serde(default)]
+    pub event_id: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct RealtimeRateLimit {
+    pub name: String,
+    pub limit: u64,
+    pub remaining: u64,
+    pub reset_seconds: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{RealtimeClientEvent, RealtimeEvent, RealtimeItem};
+
+    #[test]
+    fn test_server_events() {
+        let event: RealtimeEvent = serde_json::from_value(json!({
+            "type": "response.text.delta",
+            "event_id": "event_1",
+            "response_id": "resp_1",
+            "item_id": "item_1",
+            "output_index": 0,
+            "content_index": 0,
+            "delta": "Hello"
+        }))
+        .unwrap();
+        assert_eq!(event.text_delta(), Some("Hello"));
+
+        // Event types unknown to the crate do not break the stream
+        let event: RealtimeEvent =
+            serde_json::from_value(json!({"type": "response.new_event", "event_id": "event_2"}))
+                .unwrap();
+        assert_eq!(event, RealtimeEvent::Unknown);
+        assert_eq!(event.text_delta(), None);
+    }
+
+    #[test]
+    fn test_client_events() {
+        let event = RealtimeClientEvent::ConversationItemCreate {
+            event_id: None,
+            previous_item_id: None,
+            item: RealtimeItem::user_text("Hello"),
+        };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            json!({
+                "type": "conversation.item.create",
+                "item": {
+                    "type": "message",
+                    "role": "user",
+                    "content": [{"type": "input_text", "text": "Hello"}]
+                }
+            })
+        );
+    }
+}
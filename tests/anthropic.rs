@@ -0,0 +1,90 @@
+//! Runs `AnthropicModels` through the HTTP layer against a stubbed Messages API.
+//! Each test binary holds a single test because API urls are read from env variables only once.
+
+use allms::llm::{AnthropicModels, LLMModel};
+use allms::{CompletionsOptions, HttpClient};
+use serde_json::{json, Value};
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_anthropic_call_api() {
+    let server = MockServer::start().await;
+    std::env::set_var(
+        "ANTHROPIC_MESSAGES_API_URL",
+        format!("{}/v1/messages", server.uri()),
+    );
+
+    let response = json!({
+        "id": "msg_013Zva2CMHLNnXjNJJKqJ2EF",
+        "type": "message",
+        "role": "assistant",
+        "content": [{"type": "text", "text": "{\"answer\": \"42\"}"}],
+        "model": "claude-3-5-sonnet-20240620",
+        "stop_reason": "end_turn",
+        "stop_sequence": null,
+        "usage": {"input_tokens": 10, "output_tokens": 25}
+    });
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(header("x-api-key", "test-key"))
+        .and(header("anthropic-version", "2023-06-01"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&response))
+        .expect(1)
+        .mount(&server)
+        .await;
+    //Version and beta headers set for the client
+    Mock::given(method("POST"))
+        .and(path("/v1/messages"))
+        .and(header("anthropic-version", "2024-10-22"))
+        .and(header("anthropic-beta", "output-128k-2025-02-19"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&response))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let model = AnthropicModels::Claude3_5Sonnet;
+    let schema = json!({"type": "object"});
+    let body = model.get_body(
+        "instructions",
+        &schema,
+        false,
+        &1000,
+        &0.0,
+        &CompletionsOptions::default(),
+    );
+
+    let response_text = model
+        .call_api(&HttpClient::default(), "test-key", &body, false)
+        .await
+        .unwrap()
+        .text;
+    let data = model.get_data(&response_text, false).unwrap();
+    assert_eq!(data, "{\"answer\": \"42\"}");
+
+    let usage = model.get_usage(&response_text).unwrap();
+    assert_eq!(usage.input_tokens, 10);
+    assert_eq!(usage.output_tokens, 25);
+
+    let requests = server.received_requests().await.unwrap();
+    let request_body: Value = requests[0].body_json().unwrap();
+    assert_eq!(request_body["model"], "claude-3-5-sonnet-20240620");
+    assert_eq!(request_body["max_tokens"], 1000);
+    assert!(request_body["system"].is_string());
+    assert_eq!(
+        request_body["messages"],
+        json!([{"role": "user", "content": "instructions"}])
+    );
+    assert!(requests[0]
+        .headers
+        .keys()
+        .all(|name| name.as_str() != "anthropic-beta"));
+
+    let client = HttpClient::default()
+        .anthropic_version("2024-10-22")
+        .anthropic_beta("output-128k-2025-02-19");
+    model
+        .call_api(&client, "test-key", &body, false)
+        .await
+        .unwrap();
+}
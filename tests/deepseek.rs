@@ -0,0 +1,67 @@
+//! Runs `DeepSeekModels` through the HTTP layer against a stubbed Chat Completions API.
+//! Each test binary holds a single test because API urls are read from env variables only once.
+
+use allms::llm::{DeepSeekModels, LLMModel};
+use allms::{CompletionsOptions, HttpClient};
+use serde_json::{json, Value};
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_deepseek_call_api() {
+    let server = MockServer::start().await;
+    std::env::set_var(
+        "DEEPSEEK_API_URL",
+        format!("{}/chat/completions", server.uri()),
+    );
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(header("authorization", "Bearer test-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "930c60df-bf64-41c9-a88e-3ec75f81e00e",
+            "object": "chat.completion",
+            "created": 1705651092,
+            "model": "deepseek-chat",
+            "system_fingerprint": "fp_a49d71b8a1",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "{\"answer\": \"42\"}"},
+                "finish_reason": "stop"
+            }],
+            "usage": {
+                "completion_tokens": 10,
+                "prompt_tokens": 16,
+                "prompt_cache_hit_tokens": 0,
+                "prompt_cache_miss_tokens": 16,
+                "total_tokens": 26
+            }
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let model = DeepSeekModels::DeepSeekChat;
+    let schema = json!({"type": "object"});
+    let body = model.get_body(
+        "instructions",
+        &schema,
+        false,
+        &1000,
+        &0.0,
+        &CompletionsOptions::default(),
+    );
+
+    let response_text = model
+        .call_api(&HttpClient::default(), "test-key", &body, false)
+        .await
+        .unwrap()
+        .text;
+    let data = model.get_data(&response_text, false).unwrap();
+    assert_eq!(data, "{\"answer\": \"42\"}");
+
+    let requests = server.received_requests().await.unwrap();
+    let request_body: Value = requests[0].body_json().unwrap();
+    assert_eq!(request_body["model"], "deepseek-chat");
+    assert!(request_body["messages"].is_array());
+}
@@ -0,0 +1,67 @@
+//! Runs `GoogleModels` through the HTTP layer against a stubbed Google AI Studio API.
+//! Each test binary holds a single test because API urls are read from env variables only once.
+//! Vertex AI models are not covered as their url is always built for `aiplatform.googleapis.com`.
+
+use allms::llm::{GoogleModels, LLMModel};
+use allms::{CompletionsOptions, HttpClient};
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_google_studio_call_api() {
+    let server = MockServer::start().await;
+    std::env::set_var(
+        "GOOGLE_GEMINI_API_URL",
+        format!(
+            "{}/v1/models/gemini-1.5-flash:generateContent",
+            server.uri()
+        ),
+    );
+
+    Mock::given(method("POST"))
+        .and(path("/v1/models/gemini-1.5-flash:generateContent"))
+        .and(query_param("key", "test-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [{
+                "content": {
+                    "parts": [{"text": "{\"answer\": \"42\"}"}],
+                    "role": "model"
+                },
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": {
+                "promptTokenCount": 12,
+                "candidatesTokenCount": 8,
+                "totalTokenCount": 20
+            }
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let model = GoogleModels::Gemini1_5Flash;
+    let schema = json!({"type": "object"});
+    let body = model.get_body(
+        "instructions",
+        &schema,
+        false,
+        &1000,
+        &0.0,
+        &CompletionsOptions::default(),
+    );
+
+    let response_text = model
+        .call_api(&HttpClient::default(), "test-key", &body, false)
+        .await
+        .unwrap()
+        .text;
+    let data = model.get_data(&response_text, false).unwrap();
+    assert_eq!(data, "{\"answer\": \"42\"}");
+
+    let requests = server.received_requests().await.unwrap();
+    let request_body: Value = requests[0].body_json().unwrap();
+    assert!(request_body["system_instruction"]["parts"].is_array());
+    assert_eq!(request_body["contents"][0]["role"], "user");
+    assert_eq!(request_body["generationConfig"]["temperature"], 0.0);
+}
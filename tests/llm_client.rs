@@ -0,0 +1,47 @@
+//! Sends requests of `LLMClient` through the HTTP layer against a stubbed Chat Completions API.
+//! Each test binary holds a single test because API urls are read from env variables only once.
+
+use allms::llm::OpenAIModels;
+use allms::LLMClient;
+use serde_json::json;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_with_api_key() {
+    let server = MockServer::start().await;
+    std::env::set_var("OPENAI_API_URL", server.uri());
+
+    // Each key is answered with its own name so the key used for a request can be told from the answer
+    for api_key in ["default-key", "tenant-key"] {
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .and(header(
+                "authorization",
+                format!("Bearer {}", api_key).as_str(),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "chatcmpl-123",
+                "object": "chat.completion",
+                "created": 1677652288,
+                "model": "gpt-4o",
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": api_key},
+                    "finish_reason": "stop"
+                }],
+                "usage": {"prompt_tokens": 9, "completion_tokens": 2, "total_tokens": 11}
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+    }
+
+    let client = LLMClient::new(OpenAIModels::Gpt4o, "default-key").max_retries(5);
+    let tenant_client = client.with_api_key("tenant-key");
+
+    assert_eq!(tenant_client.complete("Hi").await.unwrap(), "tenant-key");
+    assert_eq!(tenant_client.config().max_retries, 5);
+    // The original client keeps its key
+    assert_eq!(client.complete("Hi").await.unwrap(), "default-key");
+}
@@ -0,0 +1,62 @@
+//! Runs `MistralModels` through the HTTP layer against a stubbed Chat Completions API.
+//! Each test binary holds a single test because API urls are read from env variables only once.
+
+use allms::llm::{LLMModel, MistralModels};
+use allms::{CompletionsOptions, HttpClient};
+use serde_json::{json, Value};
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_mistral_call_api() {
+    let server = MockServer::start().await;
+    std::env::set_var(
+        "MISTRAL_API_URL",
+        format!("{}/v1/chat/completions", server.uri()),
+    );
+
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(header("authorization", "Bearer test-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "cmpl-e5cc70bb28c444948073e77776eb30ef",
+            "object": "chat.completion",
+            "created": 1702256327,
+            "model": "mistral-large-latest",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "```json\n{\"answer\": \"42\"}```"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 16, "completion_tokens": 34, "total_tokens": 50}
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let model = MistralModels::MistralLarge;
+    let schema = json!({"type": "object"});
+    let body = model.get_body(
+        "instructions",
+        &schema,
+        false,
+        &1000,
+        &0.0,
+        &CompletionsOptions::default(),
+    );
+
+    let response_text = model
+        .call_api(&HttpClient::default(), "test-key", &body, false)
+        .await
+        .unwrap()
+        .text;
+    let data = model.get_data(&response_text, false).unwrap();
+    assert_eq!(data, "{\"answer\": \"42\"}");
+
+    let requests = server.received_requests().await.unwrap();
+    let request_body: Value = requests[0].body_json().unwrap();
+    assert_eq!(request_body["model"], model.as_str());
+    let messages = request_body["messages"].as_array().unwrap();
+    assert_eq!(messages[0]["role"], "system");
+    assert_eq!(messages[1]["role"], "user");
+}
@@ -0,0 +1,79 @@
+//! Runs `OpenAIModels` through the HTTP layer against a stubbed Chat Completions API.
+//! Each test binary holds a single test because API urls are read from env variables only once.
+
+use allms::llm::{LLMModel, OpenAIModels};
+use allms::{CompletionsOptions, HttpClient};
+use serde_json::{json, Value};
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_openai_call_api() {
+    let server = MockServer::start().await;
+    std::env::set_var("OPENAI_API_URL", server.uri());
+
+    let response = json!({
+        "id": "chatcmpl-123",
+        "object": "chat.completion",
+        "created": 1677652288,
+        "model": "gpt-4o",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": "{\"answer\": \"42\"}"},
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 9, "completion_tokens": 12, "total_tokens": 21}
+    });
+    //Organization and project headers set for the client
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(header("OpenAI-Organization", "org-abc123"))
+        .and(header("OpenAI-Project", "proj_abc123"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&response))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(header("authorization", "Bearer test-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&response))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let model = OpenAIModels::Gpt4o;
+    let schema = json!({"type": "object"});
+    let body = model.get_body(
+        "instructions",
+        &schema,
+        false,
+        &1000,
+        &0.0,
+        &CompletionsOptions::default(),
+    );
+
+    let response_text = model
+        .call_api(&HttpClient::default(), "test-key", &body, false)
+        .await
+        .unwrap()
+        .text;
+    let data = model.get_data(&response_text, false).unwrap();
+    assert_eq!(data, "{\"answer\": \"42\"}");
+
+    let requests = server.received_requests().await.unwrap();
+    let request_body: Value = requests[0].body_json().unwrap();
+    assert_eq!(request_body["model"], "gpt-4o");
+    assert_eq!(request_body["temperature"], 0.0);
+    assert!(request_body.get("functions").is_none());
+    let messages = request_body["messages"].as_array().unwrap();
+    assert_eq!(messages[0]["role"], "system");
+    assert_eq!(messages.last().unwrap()["role"], "user");
+
+    let client = HttpClient::default()
+        .openai_organization("org-abc123")
+        .openai_project("proj_abc123");
+    model
+        .call_api(&client, "test-key", &body, false)
+        .await
+        .unwrap();
+}
@@ -0,0 +1,68 @@
+//! Runs `PerplexityModels` through the HTTP layer against a stubbed Chat Completions API.
+//! Each test binary holds a single test because API urls are read from env variables only once.
+
+use allms::llm::{LLMModel, PerplexityModels};
+use allms::{CompletionsOptions, HttpClient};
+use serde_json::{json, Value};
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_perplexity_call_api() {
+    let server = MockServer::start().await;
+    std::env::set_var(
+        "PERPLEXITY_API_URL",
+        format!("{}/chat/completions", server.uri()),
+    );
+
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(header("authorization", "Bearer test-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "3c90c3cc-0d44-4b50-8888-8dd25736052a",
+            "model": "sonar",
+            "object": "chat.completion",
+            "created": 1724369245,
+            "citations": ["https://www.example.com/article"],
+            "choices": [{
+                "index": 0,
+                "finish_reason": "stop",
+                "message": {"role": "assistant", "content": "{\"answer\": \"42\"}"},
+                "delta": {"role": "assistant", "content": ""}
+            }],
+            "usage": {"prompt_tokens": 14, "completion_tokens": 70, "total_tokens": 84}
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let model = PerplexityModels::Sonar;
+    let schema = json!({"type": "object"});
+    let body = model.get_body(
+        "instructions",
+        &schema,
+        false,
+        &1000,
+        &0.0,
+        &CompletionsOptions::default(),
+    );
+
+    let response_text = model
+        .call_api(&HttpClient::default(), "test-key", &body, false)
+        .await
+        .unwrap()
+        .text;
+    let data = model.get_data(&response_text, false).unwrap();
+    assert_eq!(data, "{\"answer\": \"42\"}");
+    assert_eq!(
+        model.get_citations(&response_text),
+        Some(vec!["https://www.example.com/article".to_string()])
+    );
+
+    let requests = server.received_requests().await.unwrap();
+    let request_body: Value = requests[0].body_json().unwrap();
+    assert_eq!(request_body["model"], "sonar");
+    let messages = request_body["messages"].as_array().unwrap();
+    assert_eq!(messages[0]["role"], "system");
+    assert_eq!(messages[1]["role"], "user");
+}